@@ -1,8 +1,9 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use serde_json::json;
 
 use crate::config::{OutputType, OutputConfig};
+use crate::storage::S3Config;
 
 /// Command line arguments for crabwalk
 #[derive(Parser, Debug)]
@@ -22,7 +23,7 @@ pub struct Cli {
     #[arg(short, long, default_value = "transform")]
     schema: String,
 
-    /// Output type (table, view, parquet, csv, json)
+    /// Output type (table, view, parquet, csv, json, ndjson, arrow, xlsx, delta, iceberg)
     #[arg(short, long, default_value = "table")]
     output: OutputType,
 
@@ -30,7 +31,7 @@ pub struct Cli {
     #[arg(long)]
     output_dir: Option<String>,
 
-    /// Keep temporary tables when generating files
+    /// Also create a table named after the model for file outputs
     #[arg(short, long)]
     keep_tables: bool,
     
@@ -50,6 +51,18 @@ pub struct Cli {
     #[arg(short, long)]
     force: bool,
     
+    /// Run only these models: model names or `tag:<name>`, comma-separated or repeated
+    #[arg(long, value_delimiter = ',')]
+    select: Vec<String>,
+    
+    /// Leave out these models: model names or `tag:<name>`, comma-separated or repeated
+    #[arg(long, value_delimiter = ',')]
+    exclude: Vec<String>,
+    
+    /// Set a variable for `{var.name}` in output locations, as `name=value`; may be repeated
+    #[arg(long = "var", value_parser = parse_var)]
+    vars: Vec<(String, String)>,
+    
     /// Subcommand to execute
     #[command(subcommand)]
     command: Option<Command>,
@@ -59,7 +72,7 @@ pub struct Cli {
 enum Command {
     /// Run the jaffle shop example
     Jaffle {
-        /// Output type (table, view, parquet, csv, json)
+        /// Output type (table, view, parquet, csv, json, ndjson, arrow, xlsx, delta, iceberg)
         #[arg(short, long, default_value = "table")]
         output: OutputType,
         
@@ -70,7 +83,7 @@ enum Command {
     
     /// Run the simple example
     Simple {
-        /// Output type (table, view, parquet, csv, json)
+        /// Output type (table, view, parquet, csv, json, ndjson, arrow, xlsx, delta, iceberg)
         #[arg(short, long, default_value = "table")]
         output: OutputType,
     },
@@ -101,6 +114,71 @@ enum Command {
         columns: bool,
     },
     
+    /// Generate documentation for the models
+    Docs {
+        #[command(subcommand)]
+        command: DocsCommand,
+    },
+    
+    /// Print the JSON Schema for `@config` comments
+    ConfigSchema,
+    
+    /// Check SQL models for style and project hygiene problems
+    Lint {
+        /// SQL file or directory to process
+        #[arg(help = "SQL file or directory to process")]
+        path: Option<String>,
+    },
+    
+    /// Back up the database to a directory, object store URL or S3 bucket as a new version
+    #[command(args_conflicts_with_subcommands = true)]
+    Backup {
+        #[command(subcommand)]
+        command: Option<BackupCommand>,
+        
+        /// Directory or URL to back up to, e.g. `/mnt/nas/crabwalk`, `s3://bucket/db` or `gs://bucket/db`;
+        /// `--bucket` and `--prefix` when not given
+        target: Option<String>,
+        
+        /// Keep only this many of the newest versions, removing older ones after the backup
+        #[arg(long)]
+        keep: Option<usize>,
+        
+        /// Back up only these tables and views: model names, `schema.model` or `schema:<name>`,
+        /// comma-separated or repeated
+        #[arg(long, value_delimiter = ',')]
+        select: Vec<String>,
+        
+        #[command(flatten)]
+        s3: S3Args,
+    },
+    
+    /// Restore the database from a backup
+    Restore {
+        /// Directory or URL of the backups; `--bucket` and `--prefix` when not given
+        source: Option<String>,
+        
+        /// Backup version to restore, as shown by `crabwalk backup list`; the newest by default
+        #[arg(long)]
+        version: Option<String>,
+        
+        /// Restore only these tables and views into the existing database: model names,
+        /// `schema.model` or `schema:<name>`, comma-separated or repeated
+        #[arg(long, value_delimiter = ',')]
+        select: Vec<String>,
+        
+        /// Restore the tables into this schema, next to the current ones, keeping the database
+        #[arg(long)]
+        into_schema: Option<String>,
+        
+        /// Replace the database file if it already exists, or the tables with `--select` or `--into-schema`
+        #[arg(long)]
+        overwrite: bool,
+        
+        #[command(flatten)]
+        s3: S3Args,
+    },
+    
     /// Launch the web application for visualizing Crabwalk projects
     App {
         /// Port to use for the web server
@@ -113,6 +191,79 @@ enum Command {
     },
 }
 
+#[derive(Subcommand, Debug)]
+enum BackupCommand {
+    /// List the backup versions, from oldest to newest
+    List {
+        /// Directory or URL of the backups; `--bucket` and `--prefix` when not given
+        source: Option<String>,
+        
+        #[command(flatten)]
+        s3: S3Args,
+    },
+}
+
+/// S3 settings for `backup` and `restore`, used for `s3://` URLs and when no location is given
+#[derive(Args, Debug)]
+struct S3Args {
+    /// S3 bucket holding the backups
+    #[arg(long, env = "CRABWALK_S3_BUCKET")]
+    bucket: Option<String>,
+    
+    /// Folder in the bucket holding the backups
+    #[arg(long, env = "CRABWALK_S3_PREFIX", default_value = "db")]
+    prefix: String,
+    
+    /// S3 endpoint URL, e.g. `http://localhost:9000` for MinIO
+    #[arg(long, env = "AWS_ENDPOINT_URL")]
+    endpoint: Option<String>,
+    
+    /// S3 region
+    #[arg(long, env = "AWS_REGION")]
+    region: Option<String>,
+    
+    /// S3 access key; the default AWS credential chain, which reads `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY` and `AWS_SESSION_TOKEN`, is used when unset
+    #[arg(long)]
+    access_key: Option<String>,
+    
+    /// S3 secret key
+    #[arg(long)]
+    secret_key: Option<String>,
+}
+
+impl S3Args {
+    /// S3 configuration of the arguments
+    fn into_config(self) -> Result<S3Config> {
+        if self.access_key.is_some() != self.secret_key.is_some() {
+            anyhow::bail!("--access-key and --secret-key must be given together");
+        }
+
+        Ok(S3Config {
+            bucket: self.bucket.unwrap_or_default(),
+            access_key: self.access_key,
+            secret_key: self.secret_key,
+            endpoint_url: self.endpoint,
+            region_name: self.region,
+            db_folder_name: self.prefix,
+        })
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum DocsCommand {
+    /// Generate a static documentation site
+    Generate {
+        /// SQL file or directory to process
+        #[arg(help = "SQL file or directory to process")]
+        path: Option<String>,
+        
+        /// Directory to write the site to
+        #[arg(short = 'O', long, default_value = "crabwalk_docs")]
+        output: String,
+    },
+}
+
 /// Improved CLI implementation
 pub fn run() -> Result<()> {
     // Parse command line arguments
@@ -127,11 +278,7 @@ pub fn run() -> Result<()> {
                     "./examples/jaffle_shop".to_string(),
                     "duckdb".to_string(),
                     "transform".to_string(),
-                    Some(OutputConfig {
-                        output_type: output,
-                        location: cli.output_dir,
-                        keep_table: cli.keep_tables,
-                    }),
+                    Some(OutputConfig::new(output, cli.output_dir, cli.keep_tables)),
                     None,
                 );
                 println!("Running jaffle shop example...");
@@ -152,11 +299,7 @@ pub fn run() -> Result<()> {
                     "./examples/simple".to_string(),
                     "duckdb".to_string(),
                     "transform".to_string(),
-                    Some(OutputConfig {
-                        output_type: output,
-                        location: cli.output_dir,
-                        keep_table: cli.keep_tables,
-                    }),
+                    Some(OutputConfig::new(output, cli.output_dir, cli.keep_tables)),
                     None,
                 );
                 println!("Running simple example...");
@@ -188,6 +331,105 @@ pub fn run() -> Result<()> {
                 println!("Visualization completed successfully!");
                 return Ok(());
             },
+            Command::Docs { command: DocsCommand::Generate { path, output } } => {
+                let sql_path = path.unwrap_or_else(|| "./examples/simple".to_string());
+                let crabwalk = crate::Crabwalk::new(
+                    cli.database,
+                    sql_path,
+                    "duckdb".to_string(),
+                    cli.schema,
+                    None,
+                    None,
+                );
+                
+                println!("Generating documentation...");
+                let index_path = crabwalk.generate_docs(&output)?;
+                println!("Documentation written to {}", index_path.display());
+                return Ok(());
+            },
+            Command::ConfigSchema => {
+                println!("{}", serde_json::to_string_pretty(&crate::config::model_config_schema())?);
+                return Ok(());
+            },
+            Command::Lint { path } => {
+                let sql_path = path.unwrap_or_else(|| "./examples/simple".to_string());
+                let crabwalk = crate::Crabwalk::new(
+                    cli.database,
+                    sql_path,
+                    "duckdb".to_string(),
+                    cli.schema,
+                    None,
+                    None,
+                );
+                
+                let issues = crabwalk.lint()?;
+                for issue in &issues {
+                    println!("{}", issue);
+                }
+                
+                let errors = issues.iter().filter(|i| i.severity == crate::lint::Severity::Error).count();
+                println!("{} error(s), {} warning(s)", errors, issues.len() - errors);
+                
+                if errors > 0 {
+                    return Err(anyhow::anyhow!("Lint found {} error(s)", errors));
+                }
+                return Ok(());
+            },
+            Command::Backup { command: Some(BackupCommand::List { source, s3 }), .. } => {
+                let crabwalk = crate::Crabwalk::for_database(cli.database, Some(s3.into_config()?));
+                
+                let manifests = crabwalk.list_backups(source.as_deref())?;
+                if manifests.is_empty() {
+                    println!("No backups found");
+                }
+                for manifest in &manifests {
+                    println!(
+                        "{}  {}  {} file(s), {} bytes  DuckDB {}  schemas: {}",
+                        manifest.version,
+                        manifest.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                        manifest.files.len(),
+                        manifest.size(),
+                        manifest.duckdb_version,
+                        manifest.schemas.join(", ")
+                    );
+                    if !manifest.selected.is_empty() {
+                        println!("    selected: {}", manifest.selected.join(", "));
+                    }
+                }
+                return Ok(());
+            },
+            Command::Backup { command: None, target, keep, select, s3 } => {
+                let selection = crate::storage::BackupSelection::parse(&select, &cli.schema)?;
+                let crabwalk = crate::Crabwalk::for_database(cli.database, Some(s3.into_config()?));
+                
+                println!("Backing up the database...");
+                let manifest = crabwalk.backup(target.as_deref(), keep, &selection)?;
+                println!("Backup {} completed successfully!", manifest.version);
+                return Ok(());
+            },
+            Command::Restore { source, version, select, into_schema, overwrite, s3 } => {
+                let selection = crate::storage::BackupSelection::parse(&select, &cli.schema)?;
+                let crabwalk = crate::Crabwalk::for_database(cli.database, Some(s3.into_config()?));
+                
+                if selection.is_empty() && into_schema.is_none() {
+                    println!("Restoring the database...");
+                    crabwalk.restore(source.as_deref(), version.as_deref(), overwrite)?;
+                } else {
+                    println!("Restoring tables into the database...");
+                    let restored = crabwalk.restore_selected(
+                        source.as_deref(),
+                        version.as_deref(),
+                        &selection,
+                        into_schema.as_deref(),
+                        overwrite,
+                    )?;
+                    for relation in &restored {
+                        println!("  {}", relation);
+                    }
+                }
+                println!("Restore completed successfully!");
+                return Ok(());
+            },
             Command::App { port, open } => {
                 // Launch the web application
                 println!("Starting Crabwalk Web Visualizer on port {}", port);
@@ -305,13 +547,10 @@ pub fn run() -> Result<()> {
         sql_path,
         "duckdb".to_string(),
         cli.schema,
-        Some(OutputConfig {
-            output_type: cli.output,
-            location: cli.output_dir,
-            keep_table: cli.keep_tables,
-        }),
+        Some(OutputConfig::new(cli.output, cli.output_dir, cli.keep_tables)),
         None,
-    );
+    ).with_selection(crate::parser::selection::Selection::parse(&cli.select, &cli.exclude)?)
+    .with_vars(cli.vars.into_iter().collect());
     
     // Check if lineage-only mode
     if cli.lineage_only {
@@ -340,6 +579,14 @@ pub fn run() -> Result<()> {
     Ok(())
 }
 
+/// Parse a `--var name=value` argument
+fn parse_var(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.to_string())),
+        _ => Err(format!("expected name=value, got `{}`", arg)),
+    }
+}
+
 /// Print instructions for LLMs to help create a Crabwalk project
 fn print_llm_instructions(format: &str) {
     if format == "json" {
@@ -369,31 +616,7 @@ fn print_llm_instructions(format: &str) {
                         "description": "SQL transformation files using DuckDB syntax",
                         "config_format": "SQL comments with @config: {...} JSON format",
                         "example": "-- @config: {output: {type: \"parquet\", location: \"./output/example.parquet\"}}",
-                        "config_schema": {
-                            "type": "object",
-                            "properties": {
-                                "output": {
-                                    "type": "object",
-                                    "properties": {
-                                        "type": {
-                                            "type": "string",
-                                            "enum": ["table", "view", "parquet", "csv", "json"],
-                                            "description": "Output type for the SQL transformation"
-                                        },
-                                        "location": {
-                                            "type": "string",
-                                            "description": "Output file path (required for file-based outputs like parquet, csv, json)"
-                                        },
-                                        "keep_table": {
-                                            "type": "boolean",
-                                            "description": "Keep temporary tables when generating file outputs",
-                                            "default": false
-                                        }
-                                    },
-                                    "required": ["type"]
-                                }
-                            }
-                        }
+                        "config_schema": crate::config::model_config_schema()
                     },
                     {
                         "extension": ".mmd",
@@ -477,32 +700,9 @@ fn print_llm_instructions(format: &str) {
         println!("- `json`: Export as JSON file\n");
         
         println!("## Configuration JSON Schema\n");
+        println!("Unknown keys and invalid values in `@config` are errors.\n");
         println!("```json");
-        println!("{{");
-        println!("  \"type\": \"object\",");
-        println!("  \"properties\": {{");
-        println!("    \"output\": {{");
-        println!("      \"type\": \"object\",");
-        println!("      \"properties\": {{");
-        println!("        \"type\": {{");
-        println!("          \"type\": \"string\",");
-        println!("          \"enum\": [\"table\", \"view\", \"parquet\", \"csv\", \"json\"],");
-        println!("          \"description\": \"Output type for the SQL transformation\"");
-        println!("        }},");
-        println!("        \"location\": {{");
-        println!("          \"type\": \"string\",");
-        println!("          \"description\": \"Output file path (required for file-based outputs)\"");
-        println!("        }},");
-        println!("        \"keep_table\": {{");
-        println!("          \"type\": \"boolean\",");
-        println!("          \"description\": \"Keep temporary tables when generating file outputs\",");
-        println!("          \"default\": false");
-        println!("        }}");
-        println!("      }},");
-        println!("      \"required\": [\"type\"]");
-        println!("    }}");
-        println!("  }}");
-        println!("}}");
+        println!("{}", serde_json::to_string_pretty(&crate::config::model_config_schema()).unwrap());
         println!("```\n");
         
         println!("## Dependency Management\n");
@@ -0,0 +1,46 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// Foreign key declared on a model column
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ForeignKey {
+    /// Column of this model holding the key
+    pub column: String,
+    /// Referenced column in `table.column` form
+    pub references: String,
+}
+
+impl ForeignKey {
+    /// Split `references` into the referenced table and column
+    ///
+    /// # Returns
+    ///
+    /// * `Option<(&str, &str)>` - Table and column, or None if `references` has no column part
+    pub fn referenced(&self) -> Option<(&str, &str)> {
+        let (table, column) = self.references.rsplit_once('.')?;
+        if table.is_empty() || column.is_empty() {
+            return None;
+        }
+        Some((table, column))
+    }
+}
+
+/// A single column name or a list of columns
+#[derive(Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub(crate) enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// Deserialize a key given either as a single column name or a list of columns
+pub(crate) fn one_or_many<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(column) => vec![column],
+        OneOrMany::Many(columns) => columns,
+    })
+}
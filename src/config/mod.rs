@@ -1,19 +1,145 @@
+mod columns;
+mod keys;
 mod output;
+mod project;
+mod template;
 
+pub use columns::ColumnConfig;
+pub use keys::ForeignKey;
 pub use output::OutputConfig;
+pub use output::clashing_relation;
 pub use output::OutputType;
+pub use output::is_remote_location;
+pub use output::WriteMode;
+pub use project::{AttachConfig, DatabaseType, ProjectConfig, SecretConfig, SecretType};
+pub use template::{check_template, render_template, RunInfo};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 /// Model configuration settings
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(deny_unknown_fields)]
 pub struct ModelConfig {
+    /// What the model contains, shown in generated documentation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
     /// Output configuration for the model
     #[serde(default)]
     pub output: Option<OutputConfig>,
+    /// Several outputs produced from one evaluation of the model, instead of `output`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<OutputConfig>,
+    /// Primary key column(s), given as a single name or a list
+    #[serde(default, deserialize_with = "keys::one_or_many", skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "keys::OneOrMany")]
+    pub primary_key: Vec<String>,
+    /// Foreign keys from columns of this model to other models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub foreign_keys: Vec<ForeignKey>,
+    /// External tables the model reads that are not crabwalk models
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// Labels used to select subsets of models at run time, e.g. `hourly` or `finance`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Arbitrary key/value metadata carried into the schema, docs and run results
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, serde_json::Value>,
+    /// Documentation for the model's columns, keyed by column name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub columns: BTreeMap<String, ColumnConfig>,
     // Can be extended with additional configuration options
 }
 
+impl ModelConfig {
+    /// Check values that deserialize but cannot be used
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Description of the first invalid value
+    pub fn validate(&self) -> Result<(), String> {
+        for foreign_key in &self.foreign_keys {
+            if foreign_key.referenced().is_none() {
+                return Err(format!(
+                    "foreign key on {} references `{}`; expected `table.column`",
+                    foreign_key.column, foreign_key.references
+                ));
+            }
+        }
+
+        if self.primary_key.iter().any(|column| column.trim().is_empty()) {
+            return Err("primary_key contains an empty column name".to_string());
+        }
+
+        if self.output.is_some() && !self.outputs.is_empty() {
+            return Err("use either output or outputs, not both".to_string());
+        }
+        for output in self.output_configs() {
+            output.validate()?;
+        }
+        let relations = self.output_configs().iter().filter(|output| !output.is_export()).count();
+        if relations > 1 {
+            return Err("outputs can hold only one table or view named after the model; give the others a target".to_string());
+        }
+        if let Some(relation) = clashing_relation(self.output_configs(), "{schema}", "{table_name}") {
+            return Err(format!("more than one output creates {}; give the kept_table another name", relation));
+        }
+        let mut locations = std::collections::HashSet::new();
+        for location in self.outputs.iter().filter_map(|output| output.location.as_ref().or(output.target.as_ref())) {
+            if !locations.insert(location) {
+                return Err(format!("outputs write to {} more than once", location));
+            }
+        }
+
+        if self.tags.iter().any(|tag| tag.trim().is_empty()) {
+            return Err("tags contains an empty tag".to_string());
+        }
+
+        if self.columns.keys().any(|column| column.trim().is_empty()) {
+            return Err("columns contains an empty column name".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Outputs declared for the model, from either `output` or `outputs`
+    pub fn output_configs(&self) -> Vec<&OutputConfig> {
+        self.output.iter().chain(self.outputs.iter()).collect()
+    }
+
+    /// Whether the model carries a tag, compared case-insensitively
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag))
+    }
+
+    /// Documentation declared for a column, matched case-insensitively
+    ///
+    /// # Arguments
+    ///
+    /// * `column` - Column name as it appears in the model's output
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&ColumnConfig>` - Declared documentation, if any
+    pub fn column(&self, column: &str) -> Option<&ColumnConfig> {
+        self.columns.iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(column))
+            .map(|(_, config)| config)
+    }
+}
+
+/// JSON Schema for `@config` comments, generated from [`ModelConfig`]
+///
+/// # Returns
+///
+/// * `serde_json::Value` - Draft-07 JSON Schema document
+pub fn model_config_schema() -> serde_json::Value {
+    let schema = schemars::schema_for!(ModelConfig);
+    serde_json::to_value(schema).unwrap_or_default()
+}
+
 /// Command line arguments for the crabwalk CLI
 #[derive(Debug, Clone)]
 pub struct CliArgs {
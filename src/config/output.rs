@@ -1,8 +1,9 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// Output type for the model
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum OutputType {
     /// Create a DuckDB table
@@ -13,8 +14,18 @@ pub enum OutputType {
     Parquet,
     /// Export to CSV file
     Csv,
-    /// Export to JSON file
+    /// Export to a JSON file holding an array of objects
     Json,
+    /// Export to a newline-delimited JSON file, one object per line
+    Ndjson,
+    /// Export to an Arrow IPC file (Feather v2)
+    Arrow,
+    /// Export to an Excel spreadsheet
+    Xlsx,
+    /// Write a Delta Lake table
+    Delta,
+    /// Write an Iceberg table in a directory
+    Iceberg,
 }
 
 impl Default for OutputType {
@@ -31,6 +42,11 @@ impl fmt::Display for OutputType {
             OutputType::Parquet => write!(f, "parquet"),
             OutputType::Csv => write!(f, "csv"),
             OutputType::Json => write!(f, "json"),
+            OutputType::Ndjson => write!(f, "ndjson"),
+            OutputType::Arrow => write!(f, "arrow"),
+            OutputType::Xlsx => write!(f, "xlsx"),
+            OutputType::Delta => write!(f, "delta"),
+            OutputType::Iceberg => write!(f, "iceberg"),
         }
     }
 }
@@ -45,23 +61,82 @@ impl std::str::FromStr for OutputType {
             "parquet" => Ok(OutputType::Parquet),
             "csv" => Ok(OutputType::Csv),
             "json" => Ok(OutputType::Json),
+            "ndjson" => Ok(OutputType::Ndjson),
+            "arrow" => Ok(OutputType::Arrow),
+            "xlsx" => Ok(OutputType::Xlsx),
+            "delta" => Ok(OutputType::Delta),
+            "iceberg" => Ok(OutputType::Iceberg),
             _ => Err(format!("Unknown output type: {}", s)),
         }
     }
 }
 
+/// How a table output treats data from earlier runs
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WriteMode {
+    /// Replace the table's contents
+    #[default]
+    Overwrite,
+    /// Add the new rows to the table
+    Append,
+}
+
+impl fmt::Display for WriteMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteMode::Overwrite => write!(f, "overwrite"),
+            WriteMode::Append => write!(f, "append"),
+        }
+    }
+}
+
 /// Output configuration for a model
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
 pub struct OutputConfig {
-    /// Type of output (table, view, parquet, csv, json)
-    #[serde(default)]
-    #[serde(alias = "type")]
+    /// Type of output (table, view, parquet, csv, json, ndjson, arrow, xlsx, delta, iceberg)
+    #[serde(default, rename = "type", alias = "output_type")]
     pub output_type: OutputType,
-    /// Location for file outputs (parquet, csv, json)
+    /// Location for file, Delta and Iceberg outputs;
+    /// may use `{table_name}`, `{schema}`, `{run_id}`, `{run_started_at:%Y/%m/%d}`, `{var.name}` and `{env.NAME}`
+    #[serde(default)]
     pub location: Option<String>,
-    /// Whether to keep temporary tables for file outputs
+    /// Relation a table or view output is created as, e.g. `serving.public.{table_name}` in an
+    /// attached database; defaults to the model name in the target schema
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// Whether to also create a table in the target schema for file outputs
     #[serde(default)]
     pub keep_table: bool,
+    /// Name of the table kept for a file output, defaulting to the model name; `{table_name}` is replaced by the model name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kept_table: Option<String>,
+    /// Columns to partition file outputs by, written as Hive-style `column=value` directories
+    #[serde(default, deserialize_with = "super::keys::one_or_many", skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "super::keys::OneOrMany")]
+    pub partition_by: Vec<String>,
+    /// Compression codec for file outputs, e.g. `zstd` or `snappy` for Parquet, `gzip` for CSV/JSON
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compression: Option<String>,
+    /// Number of rows per Parquet row group
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_group_size: Option<u64>,
+    /// Write one file per DuckDB thread into the output directory
+    #[serde(default)]
+    pub per_thread_output: bool,
+    /// Write into an output directory that already has files, leaving existing files in place
+    #[serde(default)]
+    pub overwrite_or_ignore: bool,
+    /// Whether CSV and Excel outputs start with a header row (default true)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub header: Option<bool>,
+    /// Worksheet name for Excel outputs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sheet: Option<String>,
+    /// Whether Delta and Iceberg outputs replace or append to the table (default overwrite)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<WriteMode>,
 }
 
 impl Default for OutputConfig {
@@ -69,7 +144,17 @@ impl Default for OutputConfig {
         Self {
             output_type: OutputType::default(),
             location: None,
+            target: None,
             keep_table: false,
+            kept_table: None,
+            partition_by: Vec::new(),
+            compression: None,
+            row_group_size: None,
+            per_thread_output: false,
+            overwrite_or_ignore: false,
+            header: None,
+            sheet: None,
+            mode: None,
         }
     }
 }
@@ -81,6 +166,7 @@ impl OutputConfig {
             output_type,
             location,
             keep_table,
+            ..Self::default()
         }
     }
 
@@ -90,21 +176,232 @@ impl OutputConfig {
         if other.location.is_some() {
             self.location = other.location.clone();
         }
+        if other.target.is_some() {
+            self.target = other.target.clone();
+        }
         self.keep_table = other.keep_table;
+        if other.kept_table.is_some() {
+            self.kept_table = other.kept_table.clone();
+        }
+        if !other.partition_by.is_empty() {
+            self.partition_by = other.partition_by.clone();
+        }
+        if other.compression.is_some() {
+            self.compression = other.compression.clone();
+        }
+        if other.row_group_size.is_some() {
+            self.row_group_size = other.row_group_size;
+        }
+        self.per_thread_output = other.per_thread_output;
+        self.overwrite_or_ignore = other.overwrite_or_ignore;
+        if other.header.is_some() {
+            self.header = other.header;
+        }
+        if other.sheet.is_some() {
+            self.sheet = other.sheet.clone();
+        }
+        if other.mode.is_some() {
+            self.mode = other.mode;
+        }
+    }
+
+    /// Whether the output is written as files rather than a DuckDB relation
+    pub fn is_file(&self) -> bool {
+        matches!(
+            self.output_type,
+            OutputType::Parquet | OutputType::Csv | OutputType::Json | OutputType::Ndjson
+                | OutputType::Arrow | OutputType::Xlsx | OutputType::Delta | OutputType::Iceberg
+        )
     }
 
-    /// Get the location, replacing {table_name} placeholder if present
-    pub fn get_location(&self, table_name: &str) -> Option<String> {
-        self.location.as_ref().map(|loc| loc.replace("{table_name}", table_name))
+    /// Whether the output leaves the model's relation, as files, an external table or another target
+    pub fn is_export(&self) -> bool {
+        !matches!(self.output_type, OutputType::Table | OutputType::View) || self.target.is_some()
+    }
+
+    /// Whether the output is written as a directory of files rather than a single file
+    pub fn writes_directory(&self) -> bool {
+        matches!(self.output_type, OutputType::Delta | OutputType::Iceberg)
+            || !self.partition_by.is_empty()
+            || self.per_thread_output
+    }
+
+    /// Check output options that do not apply to the output type or that DuckDB would reject
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Description of the first invalid option
+    pub fn validate(&self) -> Result<(), String> {
+        use OutputType::*;
+
+        const COPY_FORMATS: &[OutputType] = &[Parquet, Csv, Json, Ndjson];
+        let options: [(&str, bool, &[OutputType]); 10] = [
+            ("target", self.target.is_some(), &[Table, View]),
+            ("partition_by", !self.partition_by.is_empty(), &[Parquet, Csv, Json, Ndjson, Delta, Iceberg]),
+            ("compression", self.compression.is_some(), &[Parquet, Csv, Json, Ndjson, Delta, Iceberg]),
+            ("row_group_size", self.row_group_size.is_some(), &[Parquet, Delta, Iceberg]),
+            ("per_thread_output", self.per_thread_output, COPY_FORMATS),
+            ("overwrite_or_ignore", self.overwrite_or_ignore, COPY_FORMATS),
+            ("header", self.header.is_some(), &[Csv, Xlsx]),
+            ("sheet", self.sheet.is_some(), &[Xlsx]),
+            ("mode", self.mode.is_some(), &[Delta, Iceberg]),
+            ("keep_table", self.keep_table, &[Parquet, Csv, Json, Ndjson, Arrow, Xlsx, Delta, Iceberg]),
+        ];
+        for (name, is_set, applies_to) in options {
+            if is_set && !applies_to.contains(&self.output_type) {
+                let types: Vec<String> = applies_to.iter().map(|t| t.to_string()).collect();
+                return Err(format!("{} only applies to {} outputs, not {}", name, types.join(", "), self.output_type));
+            }
+        }
+
+        if self.kept_table.is_some() && !self.keep_table {
+            return Err("kept_table requires keep_table: true".to_string());
+        }
+        if self.kept_table.as_ref().is_some_and(|name| name.trim().is_empty()) {
+            return Err("kept_table is empty".to_string());
+        }
+
+        if self.partition_by.iter().any(|column| column.trim().is_empty()) {
+            return Err("partition_by contains an empty column name".to_string());
+        }
+        if !self.partition_by.is_empty() && self.per_thread_output {
+            return Err("per_thread_output cannot be combined with partition_by".to_string());
+        }
+        if self.row_group_size == Some(0) {
+            return Err("row_group_size must be greater than 0".to_string());
+        }
+        if let Some(location) = &self.location {
+            super::check_template(location).map_err(|message| format!("location: {}", message))?;
+        }
+        if let Some(target) = &self.target {
+            let parts = target.split('.').collect::<Vec<_>>();
+            if parts.len() > 3 || parts.iter().any(|part| part.trim().is_empty()) {
+                return Err(format!("target `{}` should be `table`, `schema.table` or `database.schema.table`", target));
+            }
+        }
+        if self.sheet.as_ref().is_some_and(|sheet| sheet.trim().is_empty()) {
+            return Err("sheet is empty".to_string());
+        }
+
+        if let Some(compression) = &self.compression {
+            let codecs: &[&str] = match self.output_type {
+                Parquet | Delta | Iceberg => &["uncompressed", "snappy", "gzip", "zstd", "brotli", "lz4", "lz4_raw"],
+                _ => &["none", "auto", "gzip", "zstd"],
+            };
+            if !codecs.contains(&compression.to_lowercase().as_str()) {
+                return Err(format!(
+                    "unknown {} compression `{}`; expected one of {}",
+                    self.output_type,
+                    compression,
+                    codecs.join(", ")
+                ));
+            }
+        }
+
+        if matches!(self.output_type, Arrow | Delta | Iceberg) && self.location.as_deref().is_some_and(is_remote_location) {
+            return Err(format!("{} outputs can only be written to local paths", self.output_type));
+        }
+
+        Ok(())
+    }
+
+    /// Relation a table or view output is created as
+    ///
+    /// # Arguments
+    ///
+    /// * `schema` - Target schema of the run, used when no `target` is set
+    /// * `table_name` - Name of the model
+    ///
+    /// # Returns
+    ///
+    /// * `String` - `target` with `{table_name}` replaced, or `schema.table_name`
+    pub fn relation(&self, schema: &str, table_name: &str) -> String {
+        self.target.as_ref()
+            .map(|target| target.replace("{table_name}", table_name))
+            .unwrap_or_else(|| format!("{}.{}", schema, table_name))
+    }
+
+    /// Get the location with its placeholders filled in
+    ///
+    /// # Arguments
+    ///
+    /// * `table_name` - Name of the model
+    /// * `schema` - Target schema of the run
+    /// * `run` - Run id, start time and variables
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<String>, String>` - Location, None if unset, or the first placeholder that does not resolve
+    pub fn get_location(&self, table_name: &str, schema: &str, run: &super::RunInfo) -> Result<Option<String>, String> {
+        self.location.as_ref()
+            .map(|location| super::render_template(location, table_name, schema, run))
+            .transpose()
+    }
+
+    /// Name of the table kept for a file output, without the schema
+    pub fn kept_table_name(&self, table_name: &str) -> String {
+        self.kept_table.as_ref()
+            .map(|name| name.replace("{table_name}", table_name))
+            .unwrap_or_else(|| table_name.to_string())
+    }
+
+    /// Relation of the table kept for a file output, None without `keep_table`
+    pub fn kept_relation(&self, schema: &str, table_name: &str) -> Option<String> {
+        self.keep_table.then(|| format!("{}.{}", schema, self.kept_table_name(table_name)))
     }
 
     /// Get default location for a given output type and table name
+    ///
+    /// Delta and Iceberg tables, partitioned and per-thread outputs default to a directory named after the model.
     pub fn default_location(&self, table_name: &str) -> String {
+        if self.is_file() && self.writes_directory() {
+            return format!("./output/{}", table_name);
+        }
+
         match self.output_type {
             OutputType::Parquet => format!("./output/{}.parquet", table_name),
             OutputType::Csv => format!("./output/{}.csv", table_name),
             OutputType::Json => format!("./output/{}.json", table_name),
+            OutputType::Ndjson => format!("./output/{}.ndjson", table_name),
+            OutputType::Arrow => format!("./output/{}.arrow", table_name),
+            OutputType::Xlsx => format!("./output/{}.xlsx", table_name),
             _ => String::new(),
         }
     }
-}
\ No newline at end of file
+}
+
+/// First relation created by more than one output, e.g. a kept table named like the model's table
+///
+/// # Arguments
+///
+/// * `outputs` - Outputs of one model
+/// * `schema` - Target schema of the run
+/// * `table_name` - Name of the model
+///
+/// # Returns
+///
+/// * `Option<String>` - Relation created twice, compared case-insensitively like DuckDB identifiers
+pub fn clashing_relation<'a>(
+    outputs: impl IntoIterator<Item = &'a OutputConfig>,
+    schema: &str,
+    table_name: &str,
+) -> Option<String> {
+    let mut relations = std::collections::HashSet::new();
+    for output in outputs {
+        let relation = match output.output_type {
+            OutputType::Table | OutputType::View => Some(output.relation(schema, table_name)),
+            _ => output.kept_relation(schema, table_name),
+        };
+        if let Some(relation) = relation.filter(|relation| !relations.insert(relation.to_lowercase())) {
+            return Some(relation);
+        }
+    }
+    None
+}
+
+/// Whether a location is a URI on a remote store, such as `s3://bucket/key`, rather than a local path
+pub fn is_remote_location(location: &str) -> bool {
+    location.split_once("://").is_some_and(|(scheme, _)| {
+        scheme.len() > 1 && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    })
+}
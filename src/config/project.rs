@@ -0,0 +1,282 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// File names the project config is read from, in the model folder
+const PROJECT_FILES: [&str; 2] = ["crabwalk.yml", "crabwalk.yaml"];
+
+/// Project-wide settings, read from `crabwalk.yml` in the model folder
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// External databases attached before the models run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attach: Vec<AttachConfig>,
+    /// Credentials for cloud object storage, created as DuckDB secrets before the models run
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secrets: Vec<SecretConfig>,
+    /// Values for `{var.name}` in output locations, overridden by `--var name=value`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub vars: BTreeMap<String, serde_json::Value>,
+}
+
+/// Kind of database attached through a DuckDB scanner extension
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseType {
+    /// Another DuckDB database file
+    Duckdb,
+    /// SQLite database file
+    Sqlite,
+    /// PostgreSQL server, given as a libpq connection string
+    Postgres,
+    /// MySQL server, given as a connection string
+    Mysql,
+}
+
+impl fmt::Display for DatabaseType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatabaseType::Duckdb => write!(f, "duckdb"),
+            DatabaseType::Sqlite => write!(f, "sqlite"),
+            DatabaseType::Postgres => write!(f, "postgres"),
+            DatabaseType::Mysql => write!(f, "mysql"),
+        }
+    }
+}
+
+impl DatabaseType {
+    /// DuckDB extension that reads and writes the database, if one is needed
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            DatabaseType::Duckdb => None,
+            DatabaseType::Sqlite => Some("sqlite"),
+            DatabaseType::Postgres => Some("postgres"),
+            DatabaseType::Mysql => Some("mysql"),
+        }
+    }
+}
+
+/// An external database attached under an alias, so models can read `alias.schema.table`
+/// and outputs can target it
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AttachConfig {
+    /// Name the database is attached as
+    pub alias: String,
+    /// Kind of database
+    #[serde(rename = "type")]
+    pub database_type: DatabaseType,
+    /// File path or connection string; `{{VAR_NAME}}` is replaced by the environment variable
+    pub path: String,
+    /// Attach the database read-only
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+impl AttachConfig {
+    /// Build the `ATTACH` statement for the database
+    ///
+    /// # Returns
+    ///
+    /// * `String` - Statement that attaches the database unless the alias is already in use
+    pub fn attach_sql(&self) -> String {
+        let mut options = Vec::new();
+        if self.database_type != DatabaseType::Duckdb {
+            options.push(format!("TYPE {}", self.database_type));
+        }
+        if self.read_only {
+            options.push("READ_ONLY".to_string());
+        }
+
+        let mut sql = format!(
+            "ATTACH IF NOT EXISTS '{}' AS \"{}\"",
+            self.path.replace('\'', "''"),
+            self.alias.replace('"', "\"\"")
+        );
+        if !options.is_empty() {
+            sql.push_str(&format!(" ({})", options.join(", ")));
+        }
+        sql
+    }
+}
+
+/// Object store a secret holds credentials for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretType {
+    /// Amazon S3 and S3-compatible stores such as MinIO, for `s3://` URIs
+    S3,
+    /// Google Cloud Storage, for `gs://` and `gcs://` URIs
+    Gcs,
+    /// Cloudflare R2, for `r2://` URIs
+    R2,
+    /// Azure Blob Storage and ADLS, for `az://`, `azure://` and `abfss://` URIs
+    Azure,
+}
+
+impl fmt::Display for SecretType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretType::S3 => write!(f, "s3"),
+            SecretType::Gcs => write!(f, "gcs"),
+            SecretType::R2 => write!(f, "r2"),
+            SecretType::Azure => write!(f, "azure"),
+        }
+    }
+}
+
+impl SecretType {
+    /// DuckDB extension that reads and writes the object store
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SecretType::Azure => "azure",
+            _ => "httpfs",
+        }
+    }
+}
+
+/// Credentials for an object store, turned into a DuckDB `CREATE SECRET` statement
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct SecretConfig {
+    /// Name of the secret
+    pub name: String,
+    /// Object store the secret is for
+    #[serde(rename = "type")]
+    pub secret_type: SecretType,
+    /// How DuckDB finds the credentials, e.g. `config` (the default) or `credential_chain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    /// URI prefix the secret applies to, e.g. `s3://lake`; every URI of the type when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Secret parameters such as `key_id`, `secret`, `region`, `endpoint`, `url_style` or `use_ssl`;
+    /// `{{VAR_NAME}}` in a value is replaced by the environment variable
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub options: BTreeMap<String, serde_json::Value>,
+}
+
+impl SecretConfig {
+    /// Build the `CREATE SECRET` statement for the credentials
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String, String>` - Statement creating a temporary secret, or the first option that is not a string, number or boolean
+    pub fn create_secret_sql(&self) -> Result<String, String> {
+        let mut parameters = vec![format!("TYPE {}", self.secret_type)];
+        if let Some(provider) = &self.provider {
+            parameters.push(format!("PROVIDER {}", provider));
+        }
+        if let Some(scope) = &self.scope {
+            parameters.push(format!("SCOPE '{}'", scope.replace('\'', "''")));
+        }
+
+        for (key, value) in &self.options {
+            let value = match value {
+                serde_json::Value::String(text) => format!("'{}'", text.replace('\'', "''")),
+                serde_json::Value::Bool(flag) => flag.to_string(),
+                serde_json::Value::Number(number) => number.to_string(),
+                _ => return Err(format!("secret {} option {} should be a string, number or boolean", self.name, key)),
+            };
+            parameters.push(format!("{} {}", key.to_uppercase(), value));
+        }
+
+        Ok(format!(
+            "CREATE OR REPLACE SECRET \"{}\" ({})",
+            self.name.replace('"', "\"\""),
+            parameters.join(", ")
+        ))
+    }
+}
+
+impl ProjectConfig {
+    /// Read the project config of a model folder
+    ///
+    /// # Arguments
+    ///
+    /// * `sql_folder` - Model folder, or a single model file whose folder is used
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ProjectConfig>` - Project config, or the default when the folder has none
+    pub fn load(sql_folder: &str) -> Result<Self> {
+        let Some(path) = Self::path(sql_folder) else {
+            return Ok(Self::default());
+        };
+
+        let yaml = std::fs::read_to_string(&path)
+            .context(format!("Failed to read project config: {}", path.display()))?;
+        let config: ProjectConfig = serde_yaml::from_str(&yaml)
+            .context(format!("Invalid project config: {}", path.display()))?;
+        config.validate()
+            .map_err(|message| anyhow::anyhow!("Invalid project config {}: {}", path.display(), message))?;
+
+        tracing::info!("Loaded project config from {}", path.display());
+
+        Ok(config)
+    }
+
+    /// Variables as the text substituted into locations
+    pub fn vars(&self) -> BTreeMap<String, String> {
+        self.vars.iter()
+            .map(|(name, value)| {
+                let text = match value {
+                    serde_json::Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                };
+                (name.clone(), text)
+            })
+            .collect()
+    }
+
+    /// Path of the project config file of a model folder, if it has one
+    pub fn path(sql_folder: &str) -> Option<PathBuf> {
+        let folder = Path::new(sql_folder);
+        let folder = if folder.is_file() { folder.parent()? } else { folder };
+
+        PROJECT_FILES.iter()
+            .map(|name| folder.join(name))
+            .find(|candidate| candidate.is_file())
+    }
+
+    /// Check values that deserialize but cannot be used
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(), String>` - Description of the first invalid value
+    pub fn validate(&self) -> Result<(), String> {
+        for (index, database) in self.attach.iter().enumerate() {
+            if database.alias.trim().is_empty() {
+                return Err("attach contains an empty alias".to_string());
+            }
+            if database.path.trim().is_empty() {
+                return Err(format!("attached database {} has no path", database.alias));
+            }
+            if self.attach[..index].iter().any(|other| other.alias.eq_ignore_ascii_case(&database.alias)) {
+                return Err(format!("database alias {} is attached more than once", database.alias));
+            }
+        }
+
+        for (index, secret) in self.secrets.iter().enumerate() {
+            if secret.name.trim().is_empty() {
+                return Err("secrets contains an empty name".to_string());
+            }
+            if self.secrets[..index].iter().any(|other| other.name.eq_ignore_ascii_case(&secret.name)) {
+                return Err(format!("secret {} is defined more than once", secret.name));
+            }
+            if secret.provider.as_ref().is_some_and(|provider| !provider.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')) {
+                return Err(format!("secret {} has an invalid provider", secret.name));
+            }
+            if secret.options.keys().any(|key| key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')) {
+                return Err(format!("secret {} has an invalid option name", secret.name));
+            }
+            secret.create_secret_sql()?;
+        }
+
+        Ok(())
+    }
+}
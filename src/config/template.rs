@@ -0,0 +1,139 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+
+/// Format of `{run_started_at}` when no format is given
+const DEFAULT_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Values that stay the same for every model in a run, used to fill in location templates
+#[derive(Debug, Clone)]
+pub struct RunInfo {
+    /// Id of the run, also written to `run_results.json`
+    pub run_id: String,
+    /// When the run started, in UTC
+    pub started_at: DateTime<Utc>,
+    /// Variables from `crabwalk.yml` and `--var`, read by `{var.name}`
+    pub vars: BTreeMap<String, String>,
+}
+
+impl Default for RunInfo {
+    fn default() -> Self {
+        Self::new(BTreeMap::new())
+    }
+}
+
+impl RunInfo {
+    /// Start a run with a new id and the current time
+    pub fn new(vars: BTreeMap<String, String>) -> Self {
+        Self {
+            run_id: uuid::Uuid::new_v4().simple().to_string(),
+            started_at: Utc::now(),
+            vars,
+        }
+    }
+}
+
+/// A placeholder in a location template
+enum Placeholder<'a> {
+    TableName,
+    Schema,
+    RunId,
+    RunStartedAt(&'a str),
+    Var(&'a str),
+    Env(&'a str),
+}
+
+/// Parse the text between braces, e.g. `run_started_at:%Y/%m/%d` or `var.region`
+fn parse_placeholder(text: &str) -> Result<Placeholder<'_>, String> {
+    let is_name = |name: &str| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    match text {
+        "table_name" => return Ok(Placeholder::TableName),
+        "schema" => return Ok(Placeholder::Schema),
+        "run_id" => return Ok(Placeholder::RunId),
+        "run_started_at" => return Ok(Placeholder::RunStartedAt(DEFAULT_TIMESTAMP_FORMAT)),
+        _ => {}
+    }
+
+    if let Some(format) = text.strip_prefix("run_started_at:") {
+        if format.is_empty() || StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+            return Err(format!("invalid date format in {{{}}}", text));
+        }
+        return Ok(Placeholder::RunStartedAt(format));
+    }
+    if let Some(name) = text.strip_prefix("var.").filter(|name| is_name(name)) {
+        return Ok(Placeholder::Var(name));
+    }
+    if let Some(name) = text.strip_prefix("env.").filter(|name| is_name(name)) {
+        return Ok(Placeholder::Env(name));
+    }
+
+    Err(format!(
+        "unknown placeholder {{{}}}; expected table_name, schema, run_id, run_started_at[:format], var.<name> or env.<NAME>",
+        text
+    ))
+}
+
+/// Split a template into literal text and placeholders, calling `replace` for each placeholder
+fn expand<F>(template: &str, mut replace: F) -> Result<String, String>
+where
+    F: FnMut(Placeholder<'_>) -> Result<String, String>,
+{
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        if rest[start..].starts_with('}') {
+            return Err(format!("unmatched `}}` in `{}`", template));
+        }
+        let end = rest[start..].find('}')
+            .map(|end| start + end)
+            .ok_or_else(|| format!("unclosed `{{` in `{}`", template))?;
+
+        result.push_str(&rest[..start]);
+        result.push_str(&replace(parse_placeholder(&rest[start + 1..end])?)?);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+/// Check that every placeholder in a template is known and well-formed
+///
+/// # Arguments
+///
+/// * `template` - Location template, e.g. `./snapshots/{run_started_at:%Y/%m/%d}/{table_name}.parquet`
+///
+/// # Returns
+///
+/// * `Result<(), String>` - Description of the first invalid placeholder
+pub fn check_template(template: &str) -> Result<(), String> {
+    expand(template, |_| Ok(String::new())).map(|_| ())
+}
+
+/// Fill in the placeholders of a location template
+///
+/// # Arguments
+///
+/// * `template` - Location template
+/// * `table_name` - Name of the model, for `{table_name}`
+/// * `schema` - Target schema, for `{schema}`
+/// * `run` - Run id, start time and variables
+///
+/// # Returns
+///
+/// * `Result<String, String>` - Location, or a description of the first placeholder that does not resolve
+pub fn render_template(template: &str, table_name: &str, schema: &str, run: &RunInfo) -> Result<String, String> {
+    expand(template, |placeholder| match placeholder {
+        Placeholder::TableName => Ok(table_name.to_string()),
+        Placeholder::Schema => Ok(schema.to_string()),
+        Placeholder::RunId => Ok(run.run_id.clone()),
+        Placeholder::RunStartedAt(format) => Ok(run.started_at.format(format).to_string()),
+        Placeholder::Var(name) => run.vars.get(name)
+            .cloned()
+            .ok_or_else(|| format!("variable {} is not set; define it in crabwalk.yml or pass --var {}=<value>", name, name)),
+        Placeholder::Env(name) => std::env::var(name)
+            .map_err(|_| format!("environment variable {} is not set", name)),
+    })
+}
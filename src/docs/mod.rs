@@ -0,0 +1,335 @@
+use anyhow::{Context, Result};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::ColumnConfig;
+use crate::parser::dependencies::Dependency;
+use crate::schema::{escape_markup, meta_text};
+use crate::schema::relationships::primary_key_columns;
+
+const STYLESHEET: &str = "\
+body { font-family: Arial, sans-serif; margin: 0; color: #222; }
+header { background-color: #2d3e50; color: #fff; padding: 12px 20px; }
+header a { color: #fff; text-decoration: none; font-weight: bold; }
+main { padding: 20px; max-width: 1100px; }
+h1 { margin-top: 0; }
+a { color: #1d5fa8; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 20px; }
+th, td { border-bottom: 1px solid #ddd; padding: 6px 8px; text-align: left; vertical-align: top; }
+th { background-color: #f0f0f0; }
+.description { color: #444; margin-bottom: 16px; white-space: pre-wrap; }
+.muted { color: #888; }
+.tag { display: inline-block; padding: 0 6px; margin: 0 4px 2px 0; border-radius: 3px; background-color: #eef; font-size: 0.85em; }
+.meta { font-size: 0.85em; color: #555; }
+.lineage { font-size: 0.9em; }
+.lineage .computed { background-color: #fde8c8; }
+.facts { margin-bottom: 20px; }
+.facts div { margin-bottom: 4px; }
+pre { background-color: #f7f7f7; border: 1px solid #ddd; padding: 12px; overflow-x: auto; }
+#filter { padding: 6px; width: 300px; margin-bottom: 12px; }
+";
+
+/// Generate a static documentation site for the models
+///
+/// Writes an `index.html` listing every model and one page per model under `models/`
+/// combining the documented descriptions, tags and metadata from `@config` with the
+/// columns, column lineage and SQL found by parsing the model.
+///
+/// # Arguments
+///
+/// * `dependencies` - Map of model names to their dependencies
+/// * `output_dir` - Directory to write the site to
+///
+/// # Returns
+///
+/// * `Result<PathBuf>` - Path of the generated index page
+pub fn generate_docs(dependencies: &HashMap<String, Dependency>, output_dir: &str) -> Result<PathBuf> {
+    let output_dir = Path::new(output_dir);
+    let models_dir = output_dir.join("models");
+    fs::create_dir_all(&models_dir)
+        .context(format!("Failed to create docs directory: {}", models_dir.display()))?;
+
+    fs::write(output_dir.join("style.css"), STYLESHEET)?;
+
+    // Sort models by name for consistent output
+    let mut sorted_deps: Vec<(&String, &Dependency)> = dependencies.iter().collect();
+    sorted_deps.sort_by_key(|a| a.0);
+
+    let downstream = downstream_models(dependencies);
+
+    for (model_name, dependency) in &sorted_deps {
+        let used_by = downstream.get(model_name.as_str()).map(Vec::as_slice).unwrap_or_default();
+        let html = model_page(model_name, dependency, dependencies, used_by);
+        let page_path = models_dir.join(page_name(model_name));
+        fs::write(&page_path, html).context(format!("Failed to write docs page: {}", page_path.display()))?;
+    }
+
+    let index_path = output_dir.join("index.html");
+    fs::write(&index_path, index_page(&sorted_deps))
+        .context(format!("Failed to write docs index: {}", index_path.display()))?;
+
+    tracing::info!("Generated documentation for {} models in {}", sorted_deps.len(), output_dir.display());
+
+    Ok(index_path)
+}
+
+/// Render the index page listing every model
+fn index_page(sorted_deps: &[(&String, &Dependency)]) -> String {
+    let mut html = page_header("Crabwalk Models", "");
+    html.push_str("  <h1>Models</h1>\n");
+    html.push_str("  <input id=\"filter\" type=\"search\" placeholder=\"Filter models\" oninput=\"filterModels(this.value)\">\n");
+    html.push_str("  <table id=\"models\">\n");
+    html.push_str("    <tr><th>Model</th><th>Output</th><th>Tags</th><th>Columns</th><th>Description</th></tr>\n");
+
+    for (model_name, dependency) in sorted_deps {
+        let config = dependency.config.as_ref();
+        let outputs: Vec<String> = config
+            .map(|config| config.output_configs().iter().map(|output| output.output_type.to_string()).collect())
+            .unwrap_or_default();
+        let output = if outputs.is_empty() { "default".to_string() } else { outputs.join(", ") };
+        let description = config.and_then(|config| config.description.as_deref()).unwrap_or("");
+
+        html.push_str("    <tr>");
+        html.push_str(&format!("<td><a href=\"models/{}\">{}</a></td>", page_name(model_name), escape_markup(model_name)));
+        html.push_str(&format!("<td>{}</td>", output));
+        html.push_str(&format!("<td>{}</td>", config.map(|config| tag_list(&config.tags)).unwrap_or_default()));
+        html.push_str(&format!("<td>{}</td>", dependency.columns.len()));
+        html.push_str(&format!("<td>{}</td>", escape_markup(first_line(description))));
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("  </table>\n");
+    html.push_str("  <script>\n");
+    html.push_str("    function filterModels(text) {\n");
+    html.push_str("      const query = text.toLowerCase();\n");
+    html.push_str("      document.querySelectorAll('#models tr').forEach((row, index) => {\n");
+    html.push_str("        if (index > 0) row.style.display = row.textContent.toLowerCase().includes(query) ? '' : 'none';\n");
+    html.push_str("      });\n");
+    html.push_str("    }\n");
+    html.push_str("  </script>\n");
+    html.push_str(&page_footer());
+    html
+}
+
+/// Render the page for a single model
+fn model_page(
+    model_name: &str,
+    dependency: &Dependency,
+    dependencies: &HashMap<String, Dependency>,
+    used_by: &[String],
+) -> String {
+    let config = dependency.config.as_ref();
+
+    let mut html = page_header(model_name, "../");
+    html.push_str(&format!("  <h1>{}</h1>\n", escape_markup(model_name)));
+
+    match config.and_then(|config| config.description.as_deref()) {
+        Some(description) => html.push_str(&format!("  <div class=\"description\">{}</div>\n", escape_markup(description))),
+        None => html.push_str("  <div class=\"description muted\">No description</div>\n"),
+    }
+
+    // Summary of where the model comes from and how it is materialized
+    html.push_str("  <div class=\"facts\">\n");
+    html.push_str(&format!("    <div><strong>File:</strong> {}</div>\n", escape_markup(&dependency.filename)));
+    for output in config.map(|config| config.output_configs()).unwrap_or_default() {
+        html.push_str(&format!("    <div><strong>Output:</strong> {}", output.output_type));
+        if let Some(location) = output.location.as_ref().or(output.target.as_ref()) {
+            html.push_str(&format!(" ({})", escape_markup(location)));
+        }
+        html.push_str("</div>\n");
+    }
+    if let Some(config) = config.filter(|config| !config.tags.is_empty()) {
+        html.push_str(&format!("    <div><strong>Tags:</strong> {}</div>\n", tag_list(&config.tags)));
+    }
+    for (key, value) in config.map(|config| &config.meta).into_iter().flatten() {
+        html.push_str(&format!("    <div><strong>{}:</strong> {}</div>\n", escape_markup(key), escape_markup(&meta_text(value))));
+    }
+    let primary_key = primary_key_columns(dependency);
+    if !primary_key.is_empty() {
+        html.push_str(&format!("    <div><strong>Primary key:</strong> {}</div>\n", escape_markup(&primary_key.join(", "))));
+    }
+
+    let mut depends_on: Vec<String> = dependency.deps.iter()
+        .map(|dep| match resolve_model(dep, dependencies) {
+            Some(model) => model_link(model),
+            None => escape_markup(dep),
+        })
+        .collect();
+    depends_on.sort();
+    if !depends_on.is_empty() {
+        html.push_str(&format!("    <div><strong>Depends on:</strong> {}</div>\n", depends_on.join(", ")));
+    }
+    if !used_by.is_empty() {
+        let links: Vec<String> = used_by.iter().map(|model| model_link(model)).collect();
+        html.push_str(&format!("    <div><strong>Used by:</strong> {}</div>\n", links.join(", ")));
+    }
+    html.push_str("  </div>\n");
+
+    // Columns found in the SQL, followed by documented columns the SQL does not show
+    html.push_str("  <h2>Columns</h2>\n");
+    let documented: BTreeMap<&str, &ColumnConfig> = config
+        .map(|config| config.columns.iter().map(|(name, column)| (name.as_str(), column)).collect())
+        .unwrap_or_default();
+
+    if dependency.columns.is_empty() && documented.is_empty() {
+        html.push_str("  <p class=\"muted\">Columns could not be determined from the SQL</p>\n");
+    } else {
+        html.push_str("  <table>\n");
+        html.push_str("    <tr><th>Column</th><th>Type</th><th>Description</th><th>Lineage</th></tr>\n");
+
+        for column in &dependency.columns {
+            let column_config = config.and_then(|config| config.column(&column.name));
+            let mut lineage = Vec::new();
+
+            if let (Some(table), Some(source)) = (&column.source_table, &column.source_column) {
+                lineage.push(format!("<div>from {}.{}</div>", table_link(table, dependencies), escape_markup(source)));
+            }
+            for relationship in dependency.column_lineage.iter()
+                .filter(|r| r.target_column.eq_ignore_ascii_case(&column.name))
+            {
+                let mut entry = format!(
+                    "<div><span class=\"tag{}\">{}</span>{}.{}",
+                    if relationship.transformation.is_computed() { " computed" } else { "" },
+                    relationship.transformation,
+                    table_link(&relationship.source_table, dependencies),
+                    escape_markup(&relationship.source_column)
+                );
+                if let Some(expression) = &relationship.expression {
+                    entry.push_str(&format!(" <code>{}</code>", escape_markup(expression)));
+                }
+                entry.push_str("</div>");
+                lineage.push(entry);
+            }
+            if lineage.is_empty() {
+                if let Some(expression) = &column.expression {
+                    lineage.push(format!("<code>{}</code>", escape_markup(expression)));
+                }
+            }
+
+            html.push_str(&column_row(&column.name, &column.data_type, column_config, &lineage.join("")));
+        }
+
+        for (name, column_config) in &documented {
+            if !dependency.columns.iter().any(|column| column.name.eq_ignore_ascii_case(name)) {
+                html.push_str(&column_row(
+                    name,
+                    "",
+                    Some(column_config),
+                    "<span class=\"muted\">documented but not found in the SQL</span>",
+                ));
+            }
+        }
+
+        html.push_str("  </table>\n");
+    }
+
+    html.push_str("  <h2>SQL</h2>\n");
+    html.push_str(&format!("  <pre><code>{}</code></pre>\n", escape_markup(dependency.sql.trim_end())));
+    html.push_str(&page_footer());
+    html
+}
+
+/// Render one row of a model's column table
+fn column_row(name: &str, data_type: &str, config: Option<&ColumnConfig>, lineage: &str) -> String {
+    let mut description = String::new();
+    if let Some(config) = config {
+        if let Some(text) = &config.description {
+            description.push_str(&format!("<div class=\"description\">{}</div>", escape_markup(text)));
+        }
+        description.push_str(&tag_list(&config.tags));
+        for (key, value) in &config.meta {
+            description.push_str(&format!("<div class=\"meta\">{}: {}</div>", escape_markup(key), escape_markup(&meta_text(value))));
+        }
+    }
+
+    format!(
+        "    <tr><td><strong>{}</strong></td><td>{}</td><td>{}</td><td class=\"lineage\">{}</td></tr>\n",
+        escape_markup(name),
+        escape_markup(data_type),
+        description,
+        lineage
+    )
+}
+
+/// Render tags as labels
+fn tag_list(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("<span class=\"tag\">{}</span>", escape_markup(tag)))
+        .collect()
+}
+
+/// Models that read each model, keyed by the model they read
+fn downstream_models(dependencies: &HashMap<String, Dependency>) -> HashMap<&str, Vec<String>> {
+    let mut downstream: HashMap<&str, Vec<String>> = HashMap::new();
+
+    for (model_name, dependency) in dependencies {
+        for dep in &dependency.deps {
+            if let Some(upstream) = resolve_model(dep, dependencies) {
+                if upstream != model_name {
+                    downstream.entry(upstream).or_default().push(model_name.clone());
+                }
+            }
+        }
+    }
+
+    for models in downstream.values_mut() {
+        models.sort();
+        models.dedup();
+    }
+
+    downstream
+}
+
+/// Find the model a table reference points at, ignoring any schema qualifier
+fn resolve_model<'a>(table: &str, dependencies: &'a HashMap<String, Dependency>) -> Option<&'a str> {
+    let base_name = table.rsplit('.').next().unwrap_or(table);
+    dependencies.keys()
+        .find(|name| name.as_str() == table || name.as_str() == base_name)
+        .map(String::as_str)
+}
+
+/// Link to a table's page if it is a model, or its escaped name otherwise
+fn table_link(table: &str, dependencies: &HashMap<String, Dependency>) -> String {
+    match resolve_model(table, dependencies) {
+        Some(model) => model_link(model),
+        None => escape_markup(table),
+    }
+}
+
+/// Link from one model page to another
+fn model_link(model_name: &str) -> String {
+    format!("<a href=\"{}\">{}</a>", page_name(model_name), escape_markup(model_name))
+}
+
+/// File name of a model's page
+fn page_name(model_name: &str) -> String {
+    let name: String = model_name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}.html", name)
+}
+
+fn first_line(text: &str) -> &str {
+    text.lines().next().unwrap_or("")
+}
+
+fn page_header(title: &str, root: &str) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n");
+    html.push_str("<html lang=\"en\">\n");
+    html.push_str("<head>\n");
+    html.push_str("  <meta charset=\"UTF-8\">\n");
+    html.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">\n");
+    html.push_str(&format!("  <title>{}</title>\n", escape_markup(title)));
+    html.push_str(&format!("  <link rel=\"stylesheet\" href=\"{}style.css\">\n", root));
+    html.push_str("</head>\n");
+    html.push_str("<body>\n");
+    html.push_str(&format!("<header><a href=\"{}index.html\">Crabwalk docs</a></header>\n", root));
+    html.push_str("<main>\n");
+    html
+}
+
+fn page_footer() -> String {
+    "</main>\n</body>\n</html>\n".to_string()
+}
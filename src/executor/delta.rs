@@ -0,0 +1,87 @@
+use anyhow::{Context, Result};
+use arrow::datatypes::Schema as ArrowSchema;
+use arrow::record_batch::RecordBatch;
+use deltalake::kernel::engine::arrow_conversion::TryFromArrow;
+use deltalake::kernel::StructField;
+use deltalake::operations::write::SchemaMode;
+use deltalake::protocol::SaveMode;
+use deltalake::DeltaOps;
+use std::fs;
+use std::path::Path;
+use tokio::runtime::Runtime;
+
+use crate::config::{OutputConfig, WriteMode};
+use crate::executor::output::{partition_columns, writer_properties};
+use crate::executor::RunContext;
+
+/// Write the result of a query as a new version of a Delta Lake table
+///
+/// The rows are read from DuckDB as Arrow batches and written with the `deltalake` crate,
+/// which writes the Parquet data files and commits them to the table's `_delta_log`.
+/// Overwrites replace the table's schema and files; the old files stay on disk until the
+/// table is vacuumed.
+///
+/// # Arguments
+///
+/// * `source_query` - Query producing the rows to write
+/// * `output_config` - Output configuration with the mode, partitioning and Parquet options
+/// * `table_dir` - Directory of the Delta table
+/// * `context` - RunContext for SQL execution
+///
+/// # Returns
+///
+/// * `Result<i64>` - Version of the new commit
+pub fn write_delta_table(
+    source_query: &str,
+    output_config: &OutputConfig,
+    table_dir: &Path,
+    context: &RunContext,
+) -> Result<i64> {
+    let (schema, mut batches) = context.query_table_arrow(source_query)?;
+    check_column_types(&schema)?;
+    let partition_by = partition_columns(&schema, &output_config.partition_by)?;
+
+    // An empty result still writes a version, with the query's schema and no rows
+    if batches.is_empty() {
+        batches.push(RecordBatch::new_empty(schema.clone()));
+    }
+
+    fs::create_dir_all(table_dir)
+        .context(format!("Failed to create directory: {}", table_dir.display()))?;
+    let table_uri = fs::canonicalize(table_dir)
+        .context(format!("Failed to resolve directory: {}", table_dir.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let properties = writer_properties(output_config)?;
+    let runtime = Runtime::new().context("Failed to create async runtime for Delta output")?;
+    let table = runtime.block_on(async {
+        let write = DeltaOps::try_from_uri(&table_uri).await?
+            .write(batches)
+            .with_partition_columns(partition_by)
+            .with_writer_properties(properties);
+        let write = match output_config.mode.unwrap_or_default() {
+            WriteMode::Overwrite => write.with_save_mode(SaveMode::Overwrite).with_schema_mode(SchemaMode::Overwrite),
+            WriteMode::Append => write.with_save_mode(SaveMode::Append),
+        };
+        anyhow::Ok(write.await?)
+    }).context(format!("Failed to write Delta table {}", table_dir.display()))?;
+
+    let version = table.version().unwrap_or_default();
+    tracing::info!("Committed version {} of Delta table {}", version, table_dir.display());
+
+    Ok(version)
+}
+
+/// Refuse columns whose type has no Delta equivalent, naming the first one
+fn check_column_types(schema: &ArrowSchema) -> Result<()> {
+    for field in schema.fields() {
+        if let Err(e) = StructField::try_from_arrow(field.as_ref()) {
+            anyhow::bail!(
+                "Column {} has type {}, which Delta outputs do not support; cast it in the model: {}",
+                field.name(), field.data_type(), e
+            );
+        }
+    }
+    Ok(())
+}
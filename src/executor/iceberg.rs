@@ -0,0 +1,419 @@
+use anyhow::{Context, Result};
+use arrow::array::ArrayRef;
+use arrow::compute::{cast, concat_batches, lexsort_to_indices, partition, take_record_batch, SortColumn};
+use arrow::datatypes::{DataType, Field, Fields, Schema as ArrowSchema};
+use arrow::record_batch::RecordBatch;
+use async_trait::async_trait;
+use iceberg::arrow::{arrow_primitive_to_literal, arrow_schema_to_schema, arrow_type_to_type, schema_to_arrow_schema};
+use iceberg::io::{FileIO, FileIOBuilder};
+use iceberg::spec::{
+    DataFile, DataFileFormat, FormatVersion, PartitionKey, Schema, SortOrder, Struct, TableMetadata,
+    TableMetadataBuilder, Transform, UnboundPartitionSpec, MAIN_BRANCH,
+};
+use iceberg::table::Table;
+use iceberg::transaction::{ApplyTransactionAction, Transaction};
+use iceberg::writer::base_writer::data_file_writer::DataFileWriterBuilder;
+use iceberg::writer::file_writer::location_generator::{DefaultFileNameGenerator, DefaultLocationGenerator};
+use iceberg::writer::file_writer::ParquetWriterBuilder;
+use iceberg::writer::{IcebergWriter, IcebergWriterBuilder};
+use iceberg::{Catalog, ErrorKind, Namespace, NamespaceIdent, TableCommit, TableCreation, TableIdent};
+use parquet::arrow::PARQUET_FIELD_ID_META_KEY;
+use parquet::file::properties::WriterProperties;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+
+use crate::config::{OutputConfig, WriteMode};
+use crate::executor::output::{partition_columns, writer_properties};
+use crate::executor::RunContext;
+
+/// Snapshot property recording the run that wrote a snapshot
+const RUN_ID_PROPERTY: &str = "crabwalk.run-id";
+
+/// Write the result of a query as a new snapshot of an Iceberg table in a directory
+///
+/// The table uses the layout of Iceberg's Hadoop catalog: `metadata/version-hint.text` holds the
+/// number of the current `metadata/v<N>.metadata.json`, so DuckDB's `iceberg_scan`, Spark and
+/// PyIceberg can read it from the directory alone. Data files are written with the `iceberg`
+/// crate under `data/`, in one directory per value of the `partition_by` columns.
+///
+/// Appends must keep the table's columns and partitioning. Overwrites replace both, and start
+/// the table's history over from a snapshot holding only the new files; earlier snapshots stay in
+/// the metadata, and their files on disk.
+///
+/// # Arguments
+///
+/// * `source_query` - Query producing the rows to write
+/// * `output_config` - Output configuration with the mode, partitioning and Parquet options
+/// * `table_dir` - Directory of the Iceberg table
+/// * `context` - RunContext for SQL execution
+///
+/// # Returns
+///
+/// * `Result<i32>` - Version of the new metadata file
+pub fn write_iceberg_table(
+    source_query: &str,
+    output_config: &OutputConfig,
+    table_dir: &Path,
+    context: &RunContext,
+) -> Result<i32> {
+    let (arrow_schema, batches) = context.query_table_arrow(source_query)?;
+    let partition_by = partition_columns(&arrow_schema, &output_config.partition_by)?;
+    // Columns Iceberg cannot store are refused before anything is written
+    iceberg_schema(&arrow_schema, 1)?;
+    let rows = concat_batches(&arrow_schema, &batches).context("Failed to collect the query result")?;
+
+    fs::create_dir_all(table_dir)
+        .context(format!("Failed to create directory: {}", table_dir.display()))?;
+    let location = fs::canonicalize(table_dir)
+        .context(format!("Failed to resolve directory: {}", table_dir.display()))?
+        .to_string_lossy()
+        .to_string();
+
+    let properties = writer_properties(output_config)?;
+    let mode = output_config.mode.unwrap_or_default();
+    let run_id = context.run_info().run_id.clone();
+    let runtime = Runtime::new().context("Failed to create async runtime for Iceberg output")?;
+    let version = runtime.block_on(async {
+        let file_io = FileIOBuilder::new_fs_io().build()?;
+        let current = read_current_metadata(&file_io, &location).await?;
+        let version = current.as_ref().map_or(0, |(version, _)| *version);
+
+        let metadata = match (current, mode) {
+            (None, _) => {
+                let schema = iceberg_schema(&arrow_schema, 1)?;
+                let spec = partition_spec(&schema, &partition_by)?;
+                TableMetadataBuilder::new(
+                    schema, spec, SortOrder::unsorted_order(), location.clone(), FormatVersion::V2, HashMap::new(),
+                )?.build()?.metadata
+            }
+            (Some((_, current)), WriteMode::Append) => {
+                let schema = iceberg_schema(&arrow_schema, 1)?;
+                if !same_columns(&schema, current.current_schema())? {
+                    anyhow::bail!("the query's columns differ from the table's; write it with mode overwrite to replace them");
+                }
+                let table_partition_by = identity_partition_columns(&current);
+                if table_partition_by.as_ref() != Some(&partition_by) {
+                    anyhow::bail!(
+                        "the table is partitioned by [{}]; write it with mode overwrite to change the partitioning",
+                        table_partition_by.map(|columns| columns.join(", ")).unwrap_or_else(|| "non-identity transforms".to_string())
+                    );
+                }
+                current
+            }
+            (Some((_, current)), WriteMode::Overwrite) => {
+                // New field ids, so snapshots written with the old columns keep reading them
+                let schema = iceberg_schema(&arrow_schema, current.last_column_id() + 1)?;
+                let spec = partition_spec(&schema, &partition_by)?;
+                current.into_builder(None)
+                    .add_current_schema(schema)?
+                    .add_default_partition_spec(spec)?
+                    .remove_ref(MAIN_BRANCH)
+                    .build()?
+                    .metadata
+            }
+        };
+
+        let mut table = Table::builder()
+            .file_io(file_io.clone())
+            .metadata(metadata)
+            .identifier(TableIdent::from_strs(["crabwalk", table_name(&location)])?);
+        if version > 0 {
+            table = table.metadata_location(metadata_file(&location, version));
+        }
+        let table = table.build()?;
+        let data_files = write_data_files(&table, &location, rows, &partition_by, properties).await?;
+
+        let catalog = DirectoryCatalog { location: location.clone(), state: Mutex::new((version, table.clone())) };
+        let transaction = Transaction::new(&table);
+        let transaction = transaction.fast_append()
+            .add_data_files(data_files)
+            .set_snapshot_properties(HashMap::from([(RUN_ID_PROPERTY.to_string(), run_id)]))
+            .apply(transaction)?;
+        transaction.commit(&catalog).await?;
+
+        let (version, _) = *catalog.state.lock().await;
+        anyhow::Ok(version)
+    }).context(format!("Failed to write Iceberg table {}", table_dir.display()))?;
+
+    tracing::info!("Committed version {} of Iceberg table {}", version, table_dir.display());
+
+    Ok(version)
+}
+
+/// Iceberg schema of a query result, numbering field ids from `first_id`
+///
+/// Refuses columns whose type has no Iceberg equivalent, naming the first one.
+fn iceberg_schema(arrow_schema: &ArrowSchema, first_id: i32) -> Result<Schema> {
+    let mut next_id = first_id;
+    let fields = with_field_ids(arrow_schema.fields(), &mut next_id);
+    for (field, original) in fields.iter().zip(arrow_schema.fields()) {
+        if let Err(e) = arrow_type_to_type(field.data_type()) {
+            anyhow::bail!(
+                "Column {} has type {}, which Iceberg outputs do not support; cast it in the model: {}",
+                original.name(), original.data_type(), e
+            );
+        }
+    }
+
+    Ok(arrow_schema_to_schema(&ArrowSchema::new(fields))?)
+}
+
+/// Give every field, nested ones included, a Parquet field id, numbering from `next_id`
+fn with_field_ids(fields: &Fields, next_id: &mut i32) -> Fields {
+    fields.iter().map(|field| Arc::new(with_field_id(field, next_id))).collect()
+}
+
+fn with_field_id(field: &Field, next_id: &mut i32) -> Field {
+    let id = *next_id;
+    *next_id += 1;
+
+    let data_type = match field.data_type() {
+        DataType::Struct(children) => DataType::Struct(with_field_ids(children, next_id)),
+        DataType::List(element) => DataType::List(Arc::new(with_field_id(element, next_id))),
+        DataType::LargeList(element) => DataType::LargeList(Arc::new(with_field_id(element, next_id))),
+        DataType::FixedSizeList(element, size) => DataType::FixedSizeList(Arc::new(with_field_id(element, next_id)), *size),
+        DataType::Map(entries, sorted) => DataType::Map(Arc::new(with_field_id(entries, next_id)), *sorted),
+        other => other.clone(),
+    };
+    let mut metadata = field.metadata().clone();
+    metadata.insert(PARQUET_FIELD_ID_META_KEY.to_string(), id.to_string());
+
+    field.clone().with_data_type(data_type).with_metadata(metadata)
+}
+
+/// Whether two schemas have the same columns, comparing names, types and nullability but not field ids
+fn same_columns(left: &Schema, right: &Schema) -> Result<bool> {
+    let renumbered = |schema: &Schema| -> Result<Schema> { iceberg_schema(&schema_to_arrow_schema(schema)?, 1) };
+    Ok(renumbered(left)?.as_struct() == renumbered(right)?.as_struct())
+}
+
+/// Identity partitioning on the `partition_by` columns
+fn partition_spec(schema: &Schema, partition_by: &[String]) -> Result<UnboundPartitionSpec> {
+    let mut spec = UnboundPartitionSpec::builder();
+    for column in partition_by {
+        let field = schema.field_by_name(column)
+            .ok_or_else(|| anyhow::anyhow!("Partition column {} is not in the query result", column))?;
+        spec = spec.add_partition_field(field.id, column, Transform::Identity)?;
+    }
+    Ok(spec.build())
+}
+
+/// Columns of the table's partitioning, None if it uses transforms other than identity
+fn identity_partition_columns(metadata: &TableMetadata) -> Option<Vec<String>> {
+    let schema = metadata.current_schema();
+    metadata.default_partition_spec().fields().iter()
+        .map(|field| match field.transform {
+            Transform::Identity => schema.name_by_field_id(field.source_id).map(str::to_string),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Write the rows as Parquet data files of the table, one file per partition
+async fn write_data_files(
+    table: &Table,
+    location: &str,
+    rows: RecordBatch,
+    partition_by: &[String],
+    properties: WriterProperties,
+) -> Result<Vec<DataFile>> {
+    let metadata = table.metadata();
+    let schema = metadata.current_schema().clone();
+    let spec = metadata.default_partition_spec().clone();
+
+    // The table's arrow schema carries the field ids the Parquet writer records
+    let arrow_schema = Arc::new(schema_to_arrow_schema(&schema)?);
+    let columns = rows.columns().iter()
+        .zip(arrow_schema.fields())
+        .map(|(column, field)| cast(column, field.data_type()))
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let rows = RecordBatch::try_new(arrow_schema, columns)?;
+
+    let mut parts = Vec::new();
+    if partition_by.is_empty() {
+        parts.push((None, rows));
+    } else {
+        let sort_columns = partition_by.iter()
+            .map(|column| SortColumn { values: rows.column_by_name(column).unwrap().clone(), options: None })
+            .collect::<Vec<_>>();
+        let rows = take_record_batch(&rows, &lexsort_to_indices(&sort_columns, None)?)?;
+        let keys: Vec<ArrayRef> = partition_by.iter().map(|column| rows.column_by_name(column).unwrap().clone()).collect();
+        for range in partition(&keys)?.ranges() {
+            let part = rows.slice(range.start, range.end - range.start);
+            let mut values = Vec::new();
+            for (column, field) in partition_by.iter().zip(spec.fields()) {
+                let value = part.column_by_name(column).unwrap().slice(0, 1);
+                let source = schema.field_by_id(field.source_id).unwrap();
+                values.push(arrow_primitive_to_literal(&value, &source.field_type)?.pop().flatten());
+            }
+            parts.push((Some(values.into_iter().collect::<Struct>()), part));
+        }
+    }
+
+    let locations = DefaultLocationGenerator::with_data_location(format!("{}/data", location));
+    let file_names = DefaultFileNameGenerator::new(uuid::Uuid::new_v4().to_string(), None, DataFileFormat::Parquet);
+    let mut data_files = Vec::new();
+    for (partition_value, part) in parts.into_iter().filter(|(_, part)| part.num_rows() > 0) {
+        let partition_key = partition_value.clone()
+            .map(|value| PartitionKey::new(spec.as_ref().clone(), schema.clone(), value));
+        let parquet_writer = ParquetWriterBuilder::new(
+            properties.clone(), schema.clone(), partition_key, table.file_io().clone(), locations.clone(), file_names.clone(),
+        );
+        let mut writer = DataFileWriterBuilder::new(parquet_writer, partition_value, spec.spec_id()).build().await?;
+        writer.write(part).await?;
+        data_files.extend(writer.close().await?);
+    }
+
+    Ok(data_files)
+}
+
+/// Path of a metadata file of a table
+fn metadata_file(location: &str, version: i32) -> String {
+    format!("{}/metadata/v{}.metadata.json", location, version)
+}
+
+/// Path of the file holding the current metadata version of a table
+fn version_hint_file(location: &str) -> String {
+    format!("{}/metadata/version-hint.text", location)
+}
+
+/// Name of the table, taken from its directory
+fn table_name(location: &str) -> &str {
+    location.rsplit('/').next().unwrap_or(location)
+}
+
+/// Current metadata version and metadata of the table in a directory, None if there is no table yet
+async fn read_current_metadata(file_io: &FileIO, location: &str) -> Result<Option<(i32, TableMetadata)>> {
+    let version_hint = version_hint_file(location);
+    if !file_io.exists(&version_hint).await? {
+        return Ok(None);
+    }
+
+    let hint = file_io.new_input(&version_hint)?.read().await?;
+    let version: i32 = String::from_utf8_lossy(&hint).trim().parse()
+        .context(format!("Invalid Iceberg version hint in {}", version_hint))?;
+    let metadata = TableMetadata::read_from(file_io, metadata_file(location, version)).await?;
+
+    Ok(Some((version, metadata)))
+}
+
+/// Catalog of the one table in a directory, which commits by writing the next metadata file
+///
+/// Only loading and updating the table are supported, which is all a transaction needs.
+#[derive(Debug)]
+struct DirectoryCatalog {
+    /// Directory of the table
+    location: String,
+    /// Metadata version and table the next commit applies to
+    state: Mutex<(i32, Table)>,
+}
+
+fn unsupported(operation: &str) -> iceberg::Error {
+    iceberg::Error::new(ErrorKind::FeatureUnsupported, format!("{} is not supported for a table directory", operation))
+}
+
+#[async_trait]
+impl Catalog for DirectoryCatalog {
+    async fn list_namespaces(&self, _parent: Option<&NamespaceIdent>) -> iceberg::Result<Vec<NamespaceIdent>> {
+        Err(unsupported("Listing namespaces"))
+    }
+
+    async fn create_namespace(
+        &self,
+        _namespace: &NamespaceIdent,
+        _properties: HashMap<String, String>,
+    ) -> iceberg::Result<Namespace> {
+        Err(unsupported("Creating namespaces"))
+    }
+
+    async fn get_namespace(&self, _namespace: &NamespaceIdent) -> iceberg::Result<Namespace> {
+        Err(unsupported("Reading namespaces"))
+    }
+
+    async fn namespace_exists(&self, _namespace: &NamespaceIdent) -> iceberg::Result<bool> {
+        Err(unsupported("Reading namespaces"))
+    }
+
+    async fn update_namespace(
+        &self,
+        _namespace: &NamespaceIdent,
+        _properties: HashMap<String, String>,
+    ) -> iceberg::Result<()> {
+        Err(unsupported("Updating namespaces"))
+    }
+
+    async fn drop_namespace(&self, _namespace: &NamespaceIdent) -> iceberg::Result<()> {
+        Err(unsupported("Dropping namespaces"))
+    }
+
+    async fn list_tables(&self, _namespace: &NamespaceIdent) -> iceberg::Result<Vec<TableIdent>> {
+        Err(unsupported("Listing tables"))
+    }
+
+    async fn create_table(&self, _namespace: &NamespaceIdent, _creation: TableCreation) -> iceberg::Result<Table> {
+        Err(unsupported("Creating tables"))
+    }
+
+    async fn load_table(&self, _table: &TableIdent) -> iceberg::Result<Table> {
+        Ok(self.state.lock().await.1.clone())
+    }
+
+    async fn drop_table(&self, _table: &TableIdent) -> iceberg::Result<()> {
+        Err(unsupported("Dropping tables"))
+    }
+
+    async fn table_exists(&self, _table: &TableIdent) -> iceberg::Result<bool> {
+        Ok(true)
+    }
+
+    async fn rename_table(&self, _src: &TableIdent, _dest: &TableIdent) -> iceberg::Result<()> {
+        Err(unsupported("Renaming tables"))
+    }
+
+    async fn register_table(&self, _table: &TableIdent, _metadata_location: String) -> iceberg::Result<Table> {
+        Err(unsupported("Registering tables"))
+    }
+
+    async fn update_table(&self, mut commit: TableCommit) -> iceberg::Result<Table> {
+        let mut state = self.state.lock().await;
+        let (version, table) = &mut *state;
+
+        for requirement in commit.take_requirements() {
+            requirement.check(Some(table.metadata()))?;
+        }
+        let previous = (*version > 0).then(|| metadata_file(&self.location, *version));
+        let mut builder = table.metadata().clone().into_builder(previous);
+        for update in commit.take_updates() {
+            builder = update.apply(builder)?;
+        }
+        let metadata = builder.build()?.metadata;
+
+        // Another writer committing the same version first makes this commit fail
+        let next_version = *version + 1;
+        let metadata_location = metadata_file(&self.location, next_version);
+        if table.file_io().exists(&metadata_location).await? {
+            return Err(iceberg::Error::new(
+                ErrorKind::CatalogCommitConflicts,
+                format!("{} was written by another commit", metadata_location),
+            ));
+        }
+        metadata.write_to(table.file_io(), &metadata_location).await?;
+        table.file_io().new_output(version_hint_file(&self.location))?
+            .write(next_version.to_string().into())
+            .await?;
+
+        *table = Table::builder()
+            .file_io(table.file_io().clone())
+            .metadata_location(metadata_location)
+            .metadata(metadata)
+            .identifier(table.identifier().clone())
+            .build()?;
+        *version = next_version;
+
+        Ok(table.clone())
+    }
+}
@@ -1,9 +1,16 @@
+pub mod delta;
+pub mod iceberg;
 pub mod output;
+pub mod results;
 
 use anyhow::{Context, Result};
+use duckdb::arrow::datatypes::SchemaRef;
+use duckdb::arrow::record_batch::RecordBatch;
 use duckdb::Connection;
 use std::path::Path;
 
+use crate::config::RunInfo;
+
 /// Connect to DuckDB database
 ///
 /// # Arguments
@@ -35,12 +42,25 @@ pub fn connect_to_duckdb(database_path: &str) -> Result<Connection> {
 pub struct RunContext {
     /// DuckDB connection
     conn: Connection,
+    /// Run id, start time and variables for output locations
+    run: RunInfo,
 }
 
 impl RunContext {
     /// Create a new run context
     pub fn new(conn: Connection) -> Self {
-        Self { conn }
+        Self { conn, run: RunInfo::default() }
+    }
+
+    /// Use the given run id, start time and variables for output locations
+    pub fn with_run_info(mut self, run: RunInfo) -> Self {
+        self.run = run;
+        self
+    }
+
+    /// Run id, start time and variables of the run
+    pub fn run_info(&self) -> &RunInfo {
+        &self.run
     }
     
     /// Execute a SQL statement with environment variable replacement
@@ -57,6 +77,148 @@ impl RunContext {
         Ok(())
     }
     
+    /// Describe the columns of a table, view or SELECT query
+    ///
+    /// # Arguments
+    ///
+    /// * `relation` - Qualified table/view name or SELECT statement
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<(String, String)>>` - Column names and DuckDB types, in order
+    pub fn describe(&self, relation: &str) -> Result<Vec<(String, String)>> {
+        let sql = replace_env_vars(&format!("DESCRIBE {}", relation))?;
+
+        let mut stmt = self.conn.prepare(&sql)
+            .context(format!("Failed to prepare SQL: {}", sql))?;
+        let columns = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .context(format!("Failed to execute SQL: {}", sql))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(columns)
+    }
+    
+    /// Run a query and collect its result as Arrow record batches
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SELECT statement, with environment variables in the format {{VAR_NAME}}
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(SchemaRef, Vec<RecordBatch>)>` - Result schema and batches, in order
+    pub fn query_arrow(&self, sql: &str) -> Result<(SchemaRef, Vec<RecordBatch>)> {
+        let sql = replace_env_vars(sql)?;
+
+        let mut stmt = self.conn.prepare(&sql)
+            .context(format!("Failed to prepare SQL: {}", sql))?;
+        let batches = stmt.query_arrow([])
+            .context(format!("Failed to execute SQL: {}", sql))?;
+        let schema = batches.get_schema();
+
+        Ok((schema, batches.collect()))
+    }
+
+    /// Run a query and collect its result as batches of the arrow version used by the table writers
+    ///
+    /// The Delta and Iceberg writers build on a newer arrow than DuckDB, so the batches are
+    /// handed over as an Arrow IPC stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SELECT statement, with environment variables in the format {{VAR_NAME}}
+    ///
+    /// # Returns
+    ///
+    /// * `Result<(arrow::datatypes::SchemaRef, Vec<arrow::record_batch::RecordBatch>)>` - Result schema and batches, in order
+    pub fn query_table_arrow(&self, sql: &str) -> Result<(arrow::datatypes::SchemaRef, Vec<arrow::record_batch::RecordBatch>)> {
+        let (schema, batches) = self.query_arrow(sql)?;
+
+        let mut stream = Vec::new();
+        let mut writer = arrow_ipc::writer::StreamWriter::try_new(&mut stream, &schema)
+            .context("Failed to start Arrow stream")?;
+        for batch in &batches {
+            writer.write(batch).context("Failed to write Arrow stream")?;
+        }
+        writer.finish().context("Failed to finish Arrow stream")?;
+        drop(writer);
+
+        let reader = arrow::ipc::reader::StreamReader::try_new(stream.as_slice(), None)
+            .context("Failed to read Arrow stream")?;
+        let schema = reader.schema();
+        let batches = reader.collect::<std::result::Result<Vec<_>, _>>()
+            .context("Failed to read Arrow stream")?;
+
+        Ok((schema, batches))
+    }
+
+    /// Load a DuckDB extension, installing it first if it is not available locally
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - Extension name, e.g. `excel` or `postgres`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success, or an error if the extension cannot be installed
+    pub fn ensure_extension(&self, extension: &str) -> Result<()> {
+        if self.execute(&format!("LOAD {}", extension)).is_ok() {
+            return Ok(());
+        }
+
+        tracing::info!("Installing DuckDB extension {}", extension);
+        self.execute(&format!("INSTALL {}", extension))
+            .context(format!("Failed to install the DuckDB {} extension", extension))?;
+        self.execute(&format!("LOAD {}", extension))
+    }
+
+    /// Attach an external database under its alias
+    ///
+    /// # Arguments
+    ///
+    /// * `database` - Database to attach, with its type and connection string
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or error
+    pub fn attach(&self, database: &crate::config::AttachConfig) -> Result<()> {
+        if let Some(extension) = database.database_type.extension() {
+            self.ensure_extension(extension)?;
+        }
+
+        // The path may hold credentials, so errors name the alias rather than the statement
+        let sql = replace_env_vars(&database.attach_sql())?;
+        self.conn.execute(&sql, [])
+            .context(format!("Failed to attach {} database {}", database.database_type, database.alias))?;
+
+        tracing::info!("Attached {} database {}", database.database_type, database.alias);
+
+        Ok(())
+    }
+
+    /// Create a temporary DuckDB secret with object store credentials
+    ///
+    /// # Arguments
+    ///
+    /// * `secret` - Secret to create, with its type, scope and parameters
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or error
+    pub fn create_secret(&self, secret: &crate::config::SecretConfig) -> Result<()> {
+        self.ensure_extension(secret.secret_type.extension())?;
+
+        // The statement holds credentials, so errors name the secret rather than the statement
+        let sql = secret.create_secret_sql().map_err(|message| anyhow::anyhow!(message))?;
+        let sql = replace_env_vars(&sql)?;
+        self.conn.execute(&sql, [])
+            .context(format!("Failed to create {} secret {}", secret.secret_type, secret.name))?;
+
+        tracing::info!("Created {} secret {}", secret.secret_type, secret.name);
+
+        Ok(())
+    }
+
     /// Get the DuckDB connection
     pub fn get_connection(&self) -> &Connection {
         &self.conn
@@ -1,9 +1,14 @@
 use anyhow::{Context, Result};
+use arrow::datatypes::Schema as ArrowSchema;
+use arrow_ipc::writer::FileWriter;
+use parquet::basic::{BrotliLevel, Compression, GzipLevel, ZstdLevel};
+use parquet::file::properties::WriterProperties;
 use std::fs;
+use std::io::BufWriter;
 use std::path::Path;
 
-use crate::config::{OutputConfig, OutputType};
-use crate::executor::RunContext;
+use crate::config::{clashing_relation, is_remote_location, OutputConfig, OutputType};
+use crate::executor::{delta, iceberg, RunContext};
 
 /// Handle different output types based on configuration
 ///
@@ -31,12 +36,12 @@ pub fn handle_output(
     match output_config.output_type {
         OutputType::Table => {
             // Default behavior - create a table
-            let create_table_sql = format!("CREATE OR REPLACE TABLE {}.{} AS {}", _schema, table_name, sql_query);
+            let create_table_sql = format!("CREATE OR REPLACE TABLE {} AS {}", output_config.relation(_schema, table_name), sql_query);
             context.execute(&create_table_sql)?;
         }
         OutputType::View => {
             // Create a view instead of a table
-            let create_view_sql = format!("CREATE OR REPLACE VIEW {}.{} AS {}", _schema, table_name, sql_query);
+            let create_view_sql = format!("CREATE OR REPLACE VIEW {} AS {}", output_config.relation(_schema, table_name), sql_query);
             context.execute(&create_view_sql)?;
         }
         OutputType::Parquet => {
@@ -52,27 +57,197 @@ pub fn handle_output(
             // Write to a JSON file
             handle_file_output(table_name, sql_query, output_config, _schema, context, "json")?;
         }
+        OutputType::Ndjson => {
+            // Write to a newline-delimited JSON file
+            handle_file_output(table_name, sql_query, output_config, _schema, context, "ndjson")?;
+        }
+        OutputType::Xlsx => {
+            // Excel files are written by DuckDB's excel extension
+            context.ensure_extension("excel")?;
+            handle_file_output(table_name, sql_query, output_config, _schema, context, "xlsx")?;
+        }
+        OutputType::Arrow => {
+            handle_arrow_output(table_name, sql_query, output_config, _schema, context)?;
+        }
+        OutputType::Delta => {
+            let location = local_output_location(table_name, output_config, _schema, context)?;
+            let source_query = output_source(table_name, sql_query, output_config, _schema, context)?;
+            delta::write_delta_table(&source_query, output_config, Path::new(&location), context)?;
+        }
+        OutputType::Iceberg => {
+            let location = local_output_location(table_name, output_config, _schema, context)?;
+            let source_query = output_source(table_name, sql_query, output_config, _schema, context)?;
+            iceberg::write_iceberg_table(&source_query, output_config, Path::new(&location), context)?;
+        }
     }
     
     Ok(())
 }
 
-/// Handle file outputs (Parquet, CSV, JSON)
+/// Produce every output of a model from one evaluation of its query
+///
+/// With more than one output that evaluates the query, the result is created once, as the
+/// model's table if one of the outputs is a table or else as a temporary table, and the
+/// other outputs are written from it. Views keep the model query, since they store no rows.
+///
+/// # Arguments
+///
+/// * `table_name` - Name of the model
+/// * `sql_query` - SQL query string
+/// * `output_configs` - Outputs to produce, in order
+/// * `schema` - Database schema
+/// * `context` - RunContext for SQL execution
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or the first output error
+pub fn handle_outputs(
+    table_name: &str,
+    sql_query: &str,
+    output_configs: &[OutputConfig],
+    schema: &str,
+    context: &RunContext,
+) -> Result<()> {
+    // Outputs without a location, or sharing --output-dir, can still land on the same path
+    let mut locations = std::collections::HashSet::new();
+    for output_config in output_configs.iter().filter(|output| output.is_file()) {
+        let location = resolve_output_location(table_name, output_config, schema, context)?;
+        if !locations.insert(location.clone()) {
+            anyhow::bail!("More than one output of {} writes to {}", table_name, location);
+        }
+    }
+
+    // A kept table named like the model's table would replace it with a copy of itself
+    if let Some(relation) = clashing_relation(output_configs, schema, table_name) {
+        anyhow::bail!("More than one output of {} creates {}; give the kept_table another name", table_name, relation);
+    }
+
+    let evaluated = output_configs.iter()
+        .filter(|output| output.output_type != OutputType::View)
+        .count();
+    if evaluated <= 1 {
+        for output_config in output_configs {
+            handle_output(table_name, sql_query, output_config, schema, context)?;
+        }
+        return Ok(());
+    }
+
+    let table_index = output_configs.iter().position(|output| output.output_type == OutputType::Table);
+    let source = match table_index {
+        Some(index) => {
+            handle_output(table_name, sql_query, &output_configs[index], schema, context)?;
+            output_configs[index].relation(schema, table_name)
+        }
+        None => {
+            let temp_table = format!("\"__crabwalk_{}\"", table_name.replace('"', "\"\""));
+            context.execute(&format!("CREATE OR REPLACE TEMP TABLE {} AS {}", temp_table, sql_query))?;
+            temp_table
+        }
+    };
+    tracing::info!("Writing {} outputs of {} from {}", output_configs.len(), table_name, source);
+
+    let source_query = format!("SELECT * FROM {}", source);
+    let result = output_configs.iter()
+        .enumerate()
+        .filter(|(index, _)| Some(*index) != table_index)
+        .try_for_each(|(_, output_config)| {
+            let query = if output_config.output_type == OutputType::View { sql_query } else { &source_query };
+            handle_output(table_name, query, output_config, schema, context)
+        });
+
+    if table_index.is_none() {
+        context.execute(&format!("DROP TABLE IF EXISTS {}", source))?;
+    }
+
+    result
+}
+
+/// Handle file outputs written with `COPY` (Parquet, CSV, JSON, NDJSON, XLSX)
+///
+/// The query is copied straight to the file. With `keep_table` the result is first
+/// created as a table in the target schema and the file is written from that table.
 fn handle_file_output(
     table_name: &str,
     sql_query: &str,
     output_config: &OutputConfig,
-    _schema: &str,
+    schema: &str,
     context: &RunContext,
     format: &str,
 ) -> Result<()> {
+    let location = output_location(table_name, output_config, schema, context)?;
+    let source_query = output_source(table_name, sql_query, output_config, schema, context)?;
+    
+    // Then export to file
+    let format_options = copy_options(output_config, format);
+    
+    // The query goes on its own lines so a trailing `--` comment cannot swallow the parenthesis
+    let export_sql = format!("COPY (\n{}\n) TO '{}' {}", source_query, location.replace('\'', "''"), format_options);
+    tracing::info!("Export SQL: {}", export_sql);
+    let result = context.execute(&export_sql);
+    
+    if let Err(ref e) = result {
+        tracing::error!("Error exporting data: {}", e);
+    }
+    
+    result?;
+    
+    tracing::info!("Wrote {} file to {}", format, location);
+    
+    Ok(())
+}
+
+/// Write an Arrow IPC file (Feather v2) from the query result
+///
+/// DuckDB has no Arrow file writer, so the result is fetched as record batches and
+/// written with the `arrow-ipc` crate.
+fn handle_arrow_output(
+    table_name: &str,
+    sql_query: &str,
+    output_config: &OutputConfig,
+    schema: &str,
+    context: &RunContext,
+) -> Result<()> {
+    let location = local_output_location(table_name, output_config, schema, context)?;
+    let source_query = output_source(table_name, sql_query, output_config, schema, context)?;
+
+    let (arrow_schema, batches) = context.query_arrow(&source_query)?;
+
+    let file = fs::File::create(&location)
+        .context(format!("Failed to create Arrow file: {}", location))?;
+    let mut writer = FileWriter::try_new(BufWriter::new(file), &arrow_schema)
+        .context(format!("Failed to start Arrow file: {}", location))?;
+    for batch in &batches {
+        writer.write(batch)
+            .context(format!("Failed to write Arrow file: {}", location))?;
+    }
+    writer.finish()
+        .context(format!("Failed to finish Arrow file: {}", location))?;
+
+    tracing::info!("Wrote arrow file to {}", location);
+
+    Ok(())
+}
+
+/// Location an output writes to: its location with the placeholders filled in, or the default
+fn resolve_output_location(table_name: &str, output_config: &OutputConfig, schema: &str, context: &RunContext) -> Result<String> {
+    Ok(output_config
+        .get_location(table_name, schema, context.run_info())
+        .map_err(|message| anyhow::anyhow!("Invalid location for {}: {}", table_name, message))?
+        .unwrap_or_else(|| output_config.default_location(table_name)))
+}
+
+/// Resolve the location of a file output and create its parent directory
+fn output_location(table_name: &str, output_config: &OutputConfig, schema: &str, context: &RunContext) -> Result<String> {
     // Get location, with fallback to default
-    let location = output_config
-        .get_location(table_name)
-        .unwrap_or_else(|| output_config.default_location(table_name));
+    let location = resolve_output_location(table_name, output_config, schema, context)?;
     
     tracing::info!("File output location: {}", location);
     
+    // Object stores have no directories to create
+    if is_remote_location(&location) {
+        return Ok(location);
+    }
+    
     // Ensure output directory exists
     if let Some(parent) = Path::new(&location).parent() {
         if !parent.exists() {
@@ -81,38 +256,120 @@ fn handle_file_output(
                 .context(format!("Failed to create directory: {}", parent.display()))?;
         }
     }
-    
-    // First create a temporary table
-    let temp_table = format!("temp_{}", table_name);
-    let create_temp_table_sql = format!("CREATE OR REPLACE TABLE {} AS {}", temp_table, sql_query);
-    tracing::info!("Creating temp table with SQL: {}", create_temp_table_sql);
-    context.execute(&create_temp_table_sql)?;
-    
-    // Then export to file
-    let format_options = match format {
-        "csv" => "(FORMAT CSV, HEADER)",
-        "json" => "(FORMAT JSON)",
-        "parquet" => "(FORMAT PARQUET)",
-        _ => "(FORMAT PARQUET)",
+
+    Ok(location)
+}
+
+/// Resolve the location of an output written by crabwalk itself, which cannot write to object stores
+fn local_output_location(table_name: &str, output_config: &OutputConfig, schema: &str, context: &RunContext) -> Result<String> {
+    let location = output_location(table_name, output_config, schema, context)?;
+    if is_remote_location(&location) {
+        anyhow::bail!("{} outputs can only be written to local paths, not {}", output_config.output_type, location);
+    }
+
+    Ok(location)
+}
+
+/// Query an export reads from: the model query, or the kept table when `keep_table` is set
+fn output_source(
+    table_name: &str,
+    sql_query: &str,
+    output_config: &OutputConfig,
+    schema: &str,
+    context: &RunContext,
+) -> Result<String> {
+    let Some(kept_table) = output_config.kept_relation(schema, table_name) else {
+        return Ok(copy_source(sql_query));
     };
+
+    // Materialize the kept table and export from it, so the query runs only once
+    let create_table_sql = format!("CREATE OR REPLACE TABLE {} AS {}", kept_table, sql_query);
+    tracing::info!("Creating kept table with SQL: {}", create_table_sql);
+    context.execute(&create_table_sql)?;
+
+    Ok(format!("SELECT * FROM {}", kept_table))
+}
+
+/// Model query without the trailing semicolon, which is not allowed inside `COPY (...)`
+fn copy_source(sql_query: &str) -> String {
+    sql_query.trim_end().trim_end_matches(';').trim_end().to_string()
+}
+
+/// Build the options clause of the `COPY ... TO` statement for a file output
+///
+/// # Arguments
+///
+/// * `output_config` - Output configuration with the partitioning and compression options
+/// * `format` - File format (parquet, csv, json, ndjson, xlsx)
+///
+/// # Returns
+///
+/// * `String` - Options in parentheses, e.g. `(FORMAT PARQUET, PARTITION_BY ("year", "month"))`
+pub fn copy_options(output_config: &OutputConfig, format: &str) -> String {
+    let header = output_config.header.unwrap_or(true);
+    let mut options = vec![match format {
+        "csv" if header => "FORMAT CSV, HEADER".to_string(),
+        "csv" => "FORMAT CSV, HEADER false".to_string(),
+        "json" => "FORMAT JSON, ARRAY true".to_string(),
+        "ndjson" => "FORMAT JSON".to_string(),
+        "xlsx" => format!("FORMAT XLSX, HEADER {}", header),
+        _ => "FORMAT PARQUET".to_string(),
+    }];
     
-    let export_sql = format!("COPY (SELECT * FROM {}) TO '{}' {}", temp_table, location, format_options);
-    tracing::info!("Export SQL: {}", export_sql);
-    let result = context.execute(&export_sql);
-    
-    if let Err(ref e) = result {
-        tracing::error!("Error exporting data: {}", e);
+    if let Some(sheet) = &output_config.sheet {
+        options.push(format!("SHEET '{}'", sheet.replace('\'', "''")));
     }
-    
-    result?;
-    
-    // Clean up the temporary table if not keeping it
-    if !output_config.keep_table {
-        let drop_sql = format!("DROP TABLE IF EXISTS {}", temp_table);
-        context.execute(&drop_sql)?;
+    if !output_config.partition_by.is_empty() {
+        let columns: Vec<String> = output_config.partition_by.iter()
+            .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+            .collect();
+        options.push(format!("PARTITION_BY ({})", columns.join(", ")));
+    }
+    if let Some(compression) = &output_config.compression {
+        options.push(format!("COMPRESSION '{}'", compression.replace('\'', "''")));
+    }
+    if let Some(row_group_size) = output_config.row_group_size {
+        options.push(format!("ROW_GROUP_SIZE {}", row_group_size));
+    }
+    if output_config.per_thread_output {
+        options.push("PER_THREAD_OUTPUT".to_string());
+    }
+    if output_config.overwrite_or_ignore {
+        options.push("OVERWRITE_OR_IGNORE".to_string());
     }
     
-    tracing::info!("Wrote {} file to {}", format, location);
-    
-    Ok(())
-}
\ No newline at end of file
+    format!("({})", options.join(", "))
+}
+
+/// Parquet writer properties for the compression and row group size of a Delta or Iceberg output
+pub(crate) fn writer_properties(output_config: &OutputConfig) -> Result<WriterProperties> {
+    let compression = match output_config.compression.as_deref().map(str::to_lowercase).as_deref() {
+        None | Some("snappy") => Compression::SNAPPY,
+        Some("uncompressed") => Compression::UNCOMPRESSED,
+        Some("gzip") => Compression::GZIP(GzipLevel::default()),
+        Some("zstd") => Compression::ZSTD(ZstdLevel::default()),
+        Some("brotli") => Compression::BROTLI(BrotliLevel::default()),
+        Some("lz4") => Compression::LZ4,
+        Some("lz4_raw") => Compression::LZ4_RAW,
+        Some(other) => anyhow::bail!("Unknown {} compression: {}", output_config.output_type, other),
+    };
+
+    let mut builder = WriterProperties::builder().set_compression(compression);
+    if let Some(row_group_size) = output_config.row_group_size {
+        builder = builder.set_max_row_group_size(row_group_size as usize);
+    }
+
+    Ok(builder.build())
+}
+
+/// Partition columns of a Delta or Iceberg output, spelled as in the query result
+pub(crate) fn partition_columns(schema: &ArrowSchema, partition_by: &[String]) -> Result<Vec<String>> {
+    partition_by.iter()
+        .map(|column| {
+            schema.fields().iter()
+                .find(|field| field.name().eq_ignore_ascii_case(column))
+                .map(|field| field.name().clone())
+                .ok_or_else(|| anyhow::anyhow!("Partition column {} is not in the query result", column))
+        })
+        .collect()
+}
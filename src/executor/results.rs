@@ -0,0 +1,99 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::config::{ModelConfig, RunInfo};
+
+/// What happened to a model during a run
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// The model ran and its output was written
+    Success,
+    /// The model failed
+    Error,
+    /// The model was left out by `--select`/`--exclude`
+    Skipped,
+}
+
+/// Outcome of one model in a run, written to `run_results.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelRun {
+    /// Model name
+    pub name: String,
+    /// What happened to the model
+    pub status: RunStatus,
+    /// Output types the model was written as, comma-separated
+    pub output: String,
+    /// Tags from the model config
+    pub tags: Vec<String>,
+    /// Metadata from the model config
+    pub meta: BTreeMap<String, serde_json::Value>,
+    /// Time spent running the model, in milliseconds
+    pub duration_ms: u128,
+    /// Error message if the model failed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ModelRun {
+    /// Record the outcome of a model
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Model name
+    /// * `config` - Model configuration, for its tags and metadata
+    /// * `output` - Output types the model was written as
+    /// * `status` - What happened to the model
+    /// * `duration` - Time spent running the model
+    ///
+    /// # Returns
+    ///
+    /// * `ModelRun` - Run record without an error message
+    pub fn new(name: &str, config: Option<&ModelConfig>, output: String, status: RunStatus, duration: Duration) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            output,
+            tags: config.map(|c| c.tags.clone()).unwrap_or_default(),
+            meta: config.map(|c| c.meta.clone()).unwrap_or_default(),
+            duration_ms: duration.as_millis(),
+            error: None,
+        }
+    }
+}
+
+/// Write the outcome of every model in a run as JSON
+///
+/// # Arguments
+///
+/// * `runs` - Outcomes in execution order
+/// * `run` - Id and start time of the run
+/// * `output_path` - Path of the JSON file
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or error
+pub fn write_run_results(runs: &[ModelRun], run: &RunInfo, output_path: &str) -> Result<()> {
+    let results = serde_json::json!({
+        "run_id": run.run_id,
+        "run_started_at": run.started_at.to_rfc3339(),
+        "generated_at": std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        "models": runs,
+    });
+
+    let output_dir = Path::new(output_path).parent().unwrap_or(Path::new("."));
+    fs::create_dir_all(output_dir)?;
+    fs::write(output_path, serde_json::to_string_pretty(&results)?)
+        .context(format!("Failed to write run results: {}", output_path))?;
+
+    tracing::info!("Wrote run results to {}", output_path);
+
+    Ok(())
+}
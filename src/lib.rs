@@ -1,11 +1,13 @@
 pub mod cli;
 pub mod config;
+pub mod docs;
 pub mod executor;
+pub mod lint;
 pub mod parser;
 pub mod schema;
 pub mod storage;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use parser::dependencies::Dependency;
 
 /// Crabwalk is the main struct for the SQL transformation orchestrator
@@ -22,6 +24,10 @@ pub struct Crabwalk {
     default_output: config::OutputConfig,
     /// S3 configuration for backup/restore (optional)
     s3_config: Option<storage::S3Config>,
+    /// Models to run, from `--select` and `--exclude`
+    selection: parser::selection::Selection,
+    /// Variables for output locations from `--var`, overriding those in crabwalk.yml
+    vars: std::collections::BTreeMap<String, String>,
 }
 
 impl Crabwalk {
@@ -41,9 +47,33 @@ impl Crabwalk {
             schema,
             default_output: default_output.unwrap_or_default(),
             s3_config,
+            selection: parser::selection::Selection::default(),
+            vars: std::collections::BTreeMap::new(),
         }
     }
 
+    /// Create an instance for backing up and restoring a database, without a model folder
+    ///
+    /// # Arguments
+    ///
+    /// * `database_path` - Path to the DuckDB database file
+    /// * `s3_config` - S3 configuration used when no backup location is given
+    pub fn for_database(database_path: String, s3_config: Option<storage::S3Config>) -> Self {
+        Self::new(database_path, String::new(), "duckdb".to_string(), String::new(), None, s3_config)
+    }
+
+    /// Run only the models chosen by `selection`
+    pub fn with_selection(mut self, selection: parser::selection::Selection) -> Self {
+        self.selection = selection;
+        self
+    }
+
+    /// Set variables for `{var.name}` in output locations, overriding those in crabwalk.yml
+    pub fn with_vars(mut self, vars: std::collections::BTreeMap<String, String>) -> Self {
+        self.vars = vars;
+        self
+    }
+
     /// Run the transformation pipeline
     pub fn run(&self) -> Result<()> {
         // Initialize tracing for logging
@@ -53,25 +83,37 @@ impl Crabwalk {
         let conn = executor::connect_to_duckdb(&self.database_path)?;
         
         // Create context
-        let context = executor::RunContext::new(conn);
+        let (context, project) = self.start_run(conn)?;
         
         // Get dependencies
-        let dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        let mut dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
         
         // Get execution order
         let execution_order = parser::dependencies::get_execution_order(&dependencies)?;
+        let selected = self.selection.apply(&dependencies)?;
+        
+        // Fail before running anything if an output location cannot be filled in
+        self.check_locations(&dependencies, &selected, context.run_info())?;
         
         // Run pre-queries (create schema)
-        self.run_pre_queries(&context)?;
+        self.run_pre_queries(&context, &project)?;
+        
+        // Run objects in order, recording the outcome of each even if one fails
+        let mut runs = Vec::new();
+        let result = self.run_objects(execution_order, &dependencies, &selected, &context, &mut runs);
+        if let Err(e) = executor::results::write_run_results(&runs, context.run_info(), &self.artifact_path("run_results.json")) {
+            tracing::warn!("Could not write run results: {}", e);
+        }
+        result?;
         
-        // Run objects in order
-        self.run_objects(execution_order, &dependencies, &context)?;
+        // Replace inferred column types with the types DuckDB reports
+        schema::introspect::apply_column_types(&mut dependencies, &context, &self.schema);
         
         // Generate lineage diagram
-        parser::lineage::generate_mermaid_diagram(&self.sql_folder, &dependencies)?;
+        parser::lineage::generate_mermaid_diagram(&self.artifact_folder(), &dependencies)?;
         
         // Generate database schema XML
-        schema::generate_database_schema(&dependencies, &format!("{}/database_schema.xml", self.sql_folder))?;
+        schema::generate_database_schema(&dependencies, &self.artifact_path("database_schema.xml"))?;
         
         tracing::info!("Crabwalk transformation pipeline completed successfully");
         
@@ -87,16 +129,21 @@ impl Crabwalk {
         let conn = executor::connect_to_duckdb(&self.database_path)?;
         
         // Create context
-        let context = executor::RunContext::new(conn);
+        let (context, project) = self.start_run(conn)?;
         
         // Get dependencies
-        let dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        let mut dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        let selected = self.selection.apply(&dependencies)?;
+        
+        // Fail before running anything if an output location cannot be filled in
+        self.check_locations(&dependencies, &selected, context.run_info())?;
         
         // Run pre-queries (create schema)
-        self.run_pre_queries(&context)?;
+        self.run_pre_queries(&context, &project)?;
         
         // In force mode, we run each file directly without worrying about dependencies
         let mut file_count = 0;
+        let mut runs = Vec::new();
         
         // Check if this is a single file or a directory
         if self.sql_folder.ends_with(".sql") {
@@ -107,16 +154,20 @@ impl Crabwalk {
                 .and_then(|s| s.to_str())
                 .unwrap_or("unknown");
                 
-            tracing::info!("Running single SQL file in force mode: {}", file_path);
-            
-            // Run the SQL file directly
-            match self.run_sql_query(file_path, file_name, &context, None) {
-                Ok(_) => {
-                    file_count += 1;
-                    tracing::info!("Successfully executed: {}", file_path);
-                },
-                Err(e) => {
-                    tracing::error!("Error executing {}: {}", file_path, e);
+            if !selected.contains(file_name) {
+                self.record_skipped(file_name, &dependencies, &mut runs);
+            } else {
+                tracing::info!("Running single SQL file in force mode: {}", file_path);
+                
+                // Run the SQL file directly
+                match self.run_recorded(file_name, &dependencies, &context, &mut runs) {
+                    Ok(_) => {
+                        file_count += 1;
+                        tracing::info!("Successfully executed: {}", file_path);
+                    },
+                    Err(e) => {
+                        tracing::error!("Error executing {}: {}", file_path, e);
+                    }
                 }
             }
         } else {
@@ -126,10 +177,15 @@ impl Crabwalk {
                     let path = entry.path();
                     if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("sql") {
                         if let Some(file_name) = path.file_stem().and_then(|s| s.to_str()) {
+                            if !selected.contains(file_name) {
+                                self.record_skipped(file_name, &dependencies, &mut runs);
+                                continue;
+                            }
+                            
                             tracing::info!("Running SQL file in force mode: {}", path.display());
                             
                             // Run the SQL file directly
-                            match self.run_sql_query(&path.to_string_lossy(), file_name, &context, None) {
+                            match self.run_recorded(file_name, &dependencies, &context, &mut runs) {
                                 Ok(_) => {
                                     file_count += 1;
                                     tracing::info!("Successfully executed: {}", path.display());
@@ -144,13 +200,21 @@ impl Crabwalk {
             }
         }
         
+        // Replace inferred column types with the types DuckDB reports
+        schema::introspect::apply_column_types(&mut dependencies, &context, &self.schema);
+        
+        // Write run results if possible
+        if let Err(e) = executor::results::write_run_results(&runs, context.run_info(), &self.artifact_path("run_results.json")) {
+            tracing::warn!("Could not write run results: {}", e);
+        }
+        
         // Generate lineage diagram if possible
-        if let Err(e) = parser::lineage::generate_mermaid_diagram(&self.sql_folder, &dependencies) {
+        if let Err(e) = parser::lineage::generate_mermaid_diagram(&self.artifact_folder(), &dependencies) {
             tracing::warn!("Could not generate lineage diagram: {}", e);
         }
         
         // Generate database schema XML if possible
-        if let Err(e) = schema::generate_database_schema(&dependencies, &format!("{}/database_schema.xml", self.sql_folder)) {
+        if let Err(e) = schema::generate_database_schema(&dependencies, &self.artifact_path("database_schema.xml")) {
             tracing::warn!("Could not generate database schema: {}", e);
         }
         
@@ -175,7 +239,8 @@ impl Crabwalk {
     /// Generate database schema XML
     pub fn generate_schema(&self, output_path: Option<&str>) -> Result<()> {
         // Get dependencies from SQL files
-        let dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        let mut dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        self.apply_database_column_types(&mut dependencies);
         
         // Determine output path
         let schema_path = match output_path {
@@ -194,7 +259,8 @@ impl Crabwalk {
     /// Generate schema visualization
     pub fn visualize_schema(&self, format: &str, output_path: Option<&str>, include_columns: bool) -> Result<()> {
         // Get dependencies from SQL files
-        let dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        let mut dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        self.apply_database_column_types(&mut dependencies);
         
         // Determine output path if not provided
         let viz_path = output_path.map(|p| p.to_string());
@@ -207,8 +273,100 @@ impl Crabwalk {
         Ok(())
     }
 
+    /// Generate a static documentation site for the models
+    pub fn generate_docs(&self, output_dir: &str) -> Result<std::path::PathBuf> {
+        // Get dependencies from SQL files
+        let mut dependencies = parser::dependencies::get_dependencies(&self.sql_folder, &self.dialect)?;
+        self.apply_database_column_types(&mut dependencies);
+        
+        let index_path = docs::generate_docs(&dependencies, output_dir)?;
+        
+        tracing::info!("Documentation generation completed");
+        
+        Ok(index_path)
+    }
+
+    /// Resolve column types from the database if the models have already been run
+    fn apply_database_column_types(&self, dependencies: &mut std::collections::HashMap<String, Dependency>) {
+        if !std::path::Path::new(&self.database_path).exists() {
+            tracing::debug!("Database {} does not exist, keeping inferred column types", self.database_path);
+            return;
+        }
+        
+        match executor::connect_to_duckdb(&self.database_path) {
+            Ok(conn) => {
+                let context = executor::RunContext::new(conn);
+                schema::introspect::apply_column_types(dependencies, &context, &self.schema);
+            },
+            Err(e) => tracing::warn!("Could not open database to resolve column types: {}", e),
+        }
+    }
+
+    /// Folder the run writes its results, lineage and schema to: the SQL folder, or the
+    /// directory of the SQL file when a single model is run
+    fn artifact_folder(&self) -> String {
+        let path = std::path::Path::new(&self.sql_folder);
+        if !path.is_file() {
+            return self.sql_folder.clone();
+        }
+        
+        match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.display().to_string(),
+            _ => ".".to_string(),
+        }
+    }
+
+    /// Path of a file written next to the models by a run
+    fn artifact_path(&self, file_name: &str) -> String {
+        format!("{}/{}", self.artifact_folder(), file_name)
+    }
+
+    /// Read crabwalk.yml and create the context of a run, with its id, start time and variables
+    fn start_run(&self, conn: duckdb::Connection) -> Result<(executor::RunContext, config::ProjectConfig)> {
+        let project = config::ProjectConfig::load(&self.sql_folder)?;
+        
+        let mut vars = project.vars();
+        vars.extend(self.vars.clone());
+        let run = config::RunInfo::new(vars);
+        tracing::info!("Starting run {}", run.run_id);
+        
+        Ok((executor::RunContext::new(conn).with_run_info(run), project))
+    }
+
+    /// Check that the output locations of the selected models can be filled in
+    fn check_locations(
+        &self,
+        dependencies: &std::collections::HashMap<String, Dependency>,
+        selected: &std::collections::HashSet<String>,
+        run: &config::RunInfo,
+    ) -> Result<()> {
+        let mut errors = Vec::new();
+        for (name, dependency) in dependencies.iter().filter(|(name, _)| selected.contains(*name)) {
+            for output in self.get_output_configs(dependency.config.as_ref()) {
+                if let Err(message) = output.get_location(name, &self.schema, run) {
+                    errors.push(format!("{}: {}", name, message));
+                }
+            }
+        }
+        
+        if !errors.is_empty() {
+            errors.sort();
+            anyhow::bail!("Invalid output locations:\n  {}", errors.join("\n  "));
+        }
+        
+        Ok(())
+    }
+
     /// Run pre-queries to set up the environment
-    fn run_pre_queries(&self, context: &executor::RunContext) -> Result<()> {
+    fn run_pre_queries(&self, context: &executor::RunContext, project: &config::ProjectConfig) -> Result<()> {
+        // Create secrets and attach the databases from crabwalk.yml before anything can refer to them
+        for secret in &project.secrets {
+            context.create_secret(secret)?;
+        }
+        for database in &project.attach {
+            context.attach(database)?;
+        }
+        
         // Create schema if it doesn't exist
         context.execute(&format!("CREATE SCHEMA IF NOT EXISTS {}", self.schema))?;
         // Set schema as default
@@ -217,17 +375,29 @@ impl Crabwalk {
         Ok(())
     }
 
-    /// Run all objects in the execution order
-    fn run_objects(&self, execution_order: Vec<String>, dependencies: &std::collections::HashMap<String, Dependency>, context: &executor::RunContext) -> Result<()> {
+    /// Run all selected objects in the execution order, recording the outcome of each in `runs`
+    fn run_objects(
+        &self,
+        execution_order: Vec<String>,
+        dependencies: &std::collections::HashMap<String, Dependency>,
+        selected: &std::collections::HashSet<String>,
+        context: &executor::RunContext,
+        runs: &mut Vec<executor::results::ModelRun>,
+    ) -> Result<()> {
         tracing::info!("Running {} objects", execution_order.len());
         tracing::info!("Execution order: {:?}", execution_order);
         
         for object_name in execution_order {
             if let Some(dependency) = dependencies.get(&object_name) {
+                if !selected.contains(&object_name) {
+                    self.record_skipped(&object_name, dependencies, runs);
+                    continue;
+                }
+                
                 let filename = &dependency.filename;
                 if filename.ends_with(".sql") {
                     tracing::info!("Running SQL {}", object_name);
-                    self.run_sql_query(filename, &object_name, context, dependency.config.as_ref())?;
+                    self.run_recorded(&object_name, dependencies, context, runs)?;
                     tracing::info!("{} completed", object_name);
                 } else if filename.ends_with(".py") {
                     // Python execution will be handled differently in Rust, possibly via subprocess
@@ -241,74 +411,220 @@ impl Crabwalk {
         Ok(())
     }
 
-    /// Run a SQL query and handle the output based on configuration
-    fn run_sql_query(&self, filename: &str, table_name: &str, context: &executor::RunContext, model_config: Option<&config::ModelConfig>) -> Result<()> {
-        // Read SQL file
-        let sql = std::fs::read_to_string(filename)?;
-        
-        // Extract config from SQL comments
-        let sql_config = parser::config::extract_config_from_sql(&sql)?;
+    /// Run a model parsed by `get_dependencies` and record its outcome in `runs`
+    fn run_recorded(
+        &self,
+        table_name: &str,
+        dependencies: &std::collections::HashMap<String, Dependency>,
+        context: &executor::RunContext,
+        runs: &mut Vec<executor::results::ModelRun>,
+    ) -> Result<()> {
+        let config = dependencies.get(table_name).and_then(|dep| dep.config.as_ref());
+        let output = self.output_types(config);
+        
+        let started = std::time::Instant::now();
+        let result = self.run_parsed_model(table_name, dependencies, context);
+        let status = if result.is_ok() { executor::results::RunStatus::Success } else { executor::results::RunStatus::Error };
+        
+        let mut run = executor::results::ModelRun::new(table_name, config, output, status, started.elapsed());
+        run.error = result.as_ref().err().map(|e| format!("{:#}", e));
+        runs.push(run);
+        
+        result
+    }
+
+    /// Record a model left out by the selection
+    fn record_skipped(
+        &self,
+        table_name: &str,
+        dependencies: &std::collections::HashMap<String, Dependency>,
+        runs: &mut Vec<executor::results::ModelRun>,
+    ) {
+        tracing::info!("Skipping {} (not selected)", table_name);
+        
+        let config = dependencies.get(table_name).and_then(|dep| dep.config.as_ref());
+        let output = self.output_types(config);
+        runs.push(executor::results::ModelRun::new(
+            table_name,
+            config,
+            output,
+            executor::results::RunStatus::Skipped,
+            std::time::Duration::ZERO,
+        ));
+    }
+
+    /// Run a model parsed by `get_dependencies`, looked up by name
+    fn run_parsed_model(&self, table_name: &str, dependencies: &std::collections::HashMap<String, Dependency>, context: &executor::RunContext) -> Result<()> {
+        let dependency = dependencies.get(table_name)
+            .ok_or_else(|| anyhow::anyhow!("Model {} was not found among the parsed SQL files", table_name))?;
         
-        // Merge configs with precedence: SQL config > model_config > default_output
-        let output_config = self.get_output_config(sql_config.as_ref().or(model_config));
+        self.run_sql_query(table_name, dependency, context)
+    }
+
+    /// Run a SQL query and handle the output based on configuration
+    ///
+    /// The SQL, its parsed statements and its config come from the `Dependency`
+    /// built by `get_dependencies`, so the file is not read or parsed again.
+    fn run_sql_query(&self, table_name: &str, dependency: &Dependency, context: &executor::RunContext) -> Result<()> {
+        let sql = &dependency.sql;
+        let trees = &dependency.statements;
         
-        // Parse SQL
-        let trees = parser::sql::parse_sql(&sql, &self.dialect)?;
+        // Merge configs with precedence: SQL config > default_output
+        let output_configs = self.get_output_configs(dependency.config.as_ref());
         
-        tracing::info!("SQL config for {}: {:?}", table_name, sql_config);
-        tracing::info!("Merged output config for {}: {:?}", table_name, output_config);
+        tracing::info!("SQL config for {}: {:?}", table_name, dependency.config);
+        tracing::info!("Merged output configs for {}: {:?}", table_name, output_configs);
         
         if trees.len() > 1 {
-            for tree in trees {
-                if parser::sql::is_select_tree(&tree) {
+            // Run each statement as written; parsed trees are for lineage and cannot
+            // always be printed back as the same DuckDB SQL
+            let sources = parser::sql::split_statements(sql)
+                .with_context(|| format!("Failed to split the statements of {}", table_name))?;
+            if sources.len() != trees.len() {
+                anyhow::bail!(
+                    "Found {} statements in {} but parsed {}; cannot run them as written",
+                    sources.len(), table_name, trees.len()
+                );
+            }
+
+            for (tree, statement_sql) in trees.iter().zip(sources) {
+                if parser::sql::is_select_tree(tree) {
                     // Handle output for SELECT statements
-                    executor::output::handle_output(table_name, &tree.to_string(), &output_config, &self.schema, context)?;
+                    executor::output::handle_outputs(table_name, &statement_sql, &output_configs, &self.schema, context)?;
                 } else {
                     // Execute non-SELECT statements directly
-                    context.execute(&tree.to_string())?;
+                    context.execute(&statement_sql)?;
                 }
             }
         } else if !trees.is_empty() {
             // Handle output for the single SQL statement
-            executor::output::handle_output(table_name, &sql, &output_config, &self.schema, context)?;
+            executor::output::handle_outputs(table_name, sql, &output_configs, &self.schema, context)?;
         }
         
         Ok(())
     }
 
-    /// Get the output configuration for a model, merging model-specific config with defaults
-    fn get_output_config(&self, model_config: Option<&config::ModelConfig>) -> config::OutputConfig {
-        let mut output_config = self.default_output.clone();
+    /// Check the models for SQL style and project hygiene problems without running them
+    pub fn lint(&self) -> Result<Vec<lint::LintIssue>> {
+        // Models with invalid @config are checked without it, alongside the config errors
+        let mut issues = lint::lint_config_files(&self.sql_folder)?;
+        let dependencies = parser::dependencies::get_dependencies_ignoring_invalid_config(&self.sql_folder, &self.dialect)?;
+        issues.extend(lint::lint_models(&dependencies)?);
+        issues.sort_by(|a, b| (&a.file, a.line, a.message.as_str()).cmp(&(&b.file, b.line, b.message.as_str())));
+
+        Ok(issues)
+    }
+
+    /// Get the output configurations for a model, merging each model-specific output with the defaults
+    fn get_output_configs(&self, model_config: Option<&config::ModelConfig>) -> Vec<config::OutputConfig> {
+        let model_outputs = model_config.map(|config| config.output_configs()).unwrap_or_default();
+        if model_outputs.is_empty() {
+            return vec![self.default_output.clone()];
+        }
         
-        if let Some(config) = model_config {
-            if let Some(ref model_output) = config.output {
+        model_outputs.into_iter()
+            .map(|model_output| {
                 // Update with model-specific output config
+                let mut output_config = self.default_output.clone();
                 output_config.update_from(model_output);
-            }
-        }
-        
-        output_config
+                output_config
+            })
+            .collect()
     }
 
-    /// Backup the DuckDB database to S3
-    pub fn backup(&self) -> Result<()> {
-        if let Some(ref s3_config) = self.s3_config {
-            tracing::info!("Backing up DuckDB database to S3");
-            storage::backup(&self.database_path, s3_config)?;
-        } else {
-            tracing::warn!("S3 configuration not provided, skipping backup");
-        }
-        Ok(())
+    /// Output types of a model as listed in run results, e.g. `table, parquet`
+    fn output_types(&self, model_config: Option<&config::ModelConfig>) -> String {
+        self.get_output_configs(model_config).iter()
+            .map(|output| output.output_type.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
     }
 
-    /// Restore the DuckDB database from S3
-    pub fn restore(&self, overwrite: bool) -> Result<()> {
-        if let Some(ref s3_config) = self.s3_config {
-            tracing::info!("Restoring DuckDB database from S3");
-            storage::restore(&self.database_path, s3_config, overwrite)?;
-        } else {
-            tracing::warn!("S3 configuration not provided, skipping restore");
+    /// Backup the DuckDB database as a new version
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - Directory or URL to back up to; the bucket of the S3 configuration when `None`
+    /// * `keep` - Number of versions to keep, removing older ones after the backup
+    /// * `selection` - Tables and views to back up; the whole database when empty
+    ///
+    /// # Returns
+    ///
+    /// * `Result<storage::BackupManifest>` - Manifest of the new version
+    pub fn backup(&self, target: Option<&str>, keep: Option<usize>, selection: &storage::BackupSelection) -> Result<storage::BackupManifest> {
+        if keep == Some(0) {
+            anyhow::bail!("At least one backup version must be kept");
         }
-        Ok(())
+        let backend = self.storage_backend(target)?;
+
+        let manifest = storage::backup(&self.database_path, backend.as_ref(), selection)?;
+        if let Some(keep) = keep {
+            storage::prune(backend.as_ref(), keep)?;
+        }
+        Ok(manifest)
     }
-}
\ No newline at end of file
+
+    /// List the backup versions, from oldest to newest
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Directory or URL of the backups; the bucket of the S3 configuration when `None`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<storage::BackupManifest>>` - Manifest of every version
+    pub fn list_backups(&self, source: Option<&str>) -> Result<Vec<storage::BackupManifest>> {
+        storage::list_backups(self.storage_backend(source)?.as_ref())
+    }
+
+    /// Restore the DuckDB database
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Directory or URL to restore from; the bucket of the S3 configuration when `None`
+    /// * `version` - Backup version to restore; the newest when `None`
+    /// * `overwrite` - Whether to replace an existing database file
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or error
+    pub fn restore(&self, source: Option<&str>, version: Option<&str>, overwrite: bool) -> Result<()> {
+        storage::restore(&self.database_path, self.storage_backend(source)?.as_ref(), version, overwrite)
+    }
+
+    /// Restore tables and views from a backup into the database, keeping the rest of it
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Directory or URL to restore from; the bucket of the S3 configuration when `None`
+    /// * `version` - Backup version to restore; the newest when `None`
+    /// * `selection` - Tables and views to restore; every one in the backup when empty
+    /// * `into_schema` - Schema to restore into instead of the one each table was backed up from
+    /// * `overwrite` - Whether to replace tables and views that already exist
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Vec<storage::Relation>>` - The restored tables
+    pub fn restore_selected(
+        &self,
+        source: Option<&str>,
+        version: Option<&str>,
+        selection: &storage::BackupSelection,
+        into_schema: Option<&str>,
+        overwrite: bool,
+    ) -> Result<Vec<storage::Relation>> {
+        let backend = self.storage_backend(source)?;
+        storage::restore_selected(&self.database_path, backend.as_ref(), version, selection, into_schema, overwrite)
+    }
+
+    /// Backend for a backup location, falling back to the bucket of the S3 configuration
+    fn storage_backend(&self, location: Option<&str>) -> Result<Box<dyn storage::StorageBackend>> {
+        let location = match (location, &self.s3_config) {
+            (Some(location), _) => location.to_string(),
+            (None, Some(s3_config)) if !s3_config.bucket.is_empty() => s3_config.url(),
+            (None, _) => anyhow::bail!("No backup location given; pass a directory or URL, or an S3 bucket with --bucket"),
+        };
+
+        storage::open_backend(&location, self.s3_config.as_ref())
+    }
+}
@@ -0,0 +1,288 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use sqlparser::ast::{SelectItem, SetExpr, Statement};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use crate::parser::config::validate_model_config;
+use crate::parser::dependencies::Dependency;
+use crate::parser::sql::{extract_references, find_unqualified_columns};
+
+/// How serious a lint finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Style or hygiene problem that does not stop a run
+    Warning,
+    /// Problem that makes the project behave differently than written
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// The check that produced a lint finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintRule {
+    /// `SELECT *` in the final query of a mart model
+    SelectStarInMart,
+    /// Single-part column name in a query that reads several relations
+    UnqualifiedColumn,
+    /// Table that is neither a model nor a declared source
+    UnknownTable,
+    /// Model that no other model reads and that is not exported
+    UnusedModel,
+    /// Absolute file path passed to a `read_*` table function
+    AbsolutePath,
+    /// `@config` comment or sidecar file that is not valid model configuration
+    InvalidConfig,
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintRule::SelectStarInMart => write!(f, "select-star-in-mart"),
+            LintRule::UnqualifiedColumn => write!(f, "unqualified-column"),
+            LintRule::UnknownTable => write!(f, "unknown-table"),
+            LintRule::UnusedModel => write!(f, "unused-model"),
+            LintRule::AbsolutePath => write!(f, "absolute-path"),
+            LintRule::InvalidConfig => write!(f, "invalid-config"),
+        }
+    }
+}
+
+/// A problem found in a model file
+#[derive(Debug, Clone)]
+pub struct LintIssue {
+    /// Check that found the problem
+    pub rule: LintRule,
+    /// How serious the problem is
+    pub severity: Severity,
+    /// Model file the problem is in
+    pub file: String,
+    /// Line of the problem, starting from 1, when it can be located
+    pub line: Option<usize>,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: ", self.file, line)?,
+            None => write!(f, "{}: ", self.file)?,
+        }
+        write!(f, "{}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// Check parsed models for SQL style and project hygiene problems
+///
+/// Models under a `marts` directory are treated as the project's outputs: they may
+/// have no downstream consumers but should list their columns explicitly.
+///
+/// # Arguments
+///
+/// * `dependencies` - Parsed models, keyed by model name
+///
+/// # Returns
+///
+/// * `Result<Vec<LintIssue>>` - Findings sorted by file and line
+pub fn lint_models(dependencies: &HashMap<String, Dependency>) -> Result<Vec<LintIssue>> {
+    let mut issues = Vec::new();
+
+    let models: HashSet<String> = dependencies.keys().map(|name| name.to_lowercase()).collect();
+    let sources: HashSet<String> = dependencies.values()
+        .filter_map(|dep| dep.config.as_ref())
+        .flat_map(|config| config.sources.iter().map(|source| source.to_lowercase()))
+        .collect();
+
+    let mut consumed = HashSet::new();
+
+    for (model_name, dep) in dependencies {
+        let issue = |rule: LintRule, severity: Severity, line: Option<usize>, message: String| LintIssue {
+            rule,
+            severity,
+            file: dep.filename.clone(),
+            line,
+            message,
+        };
+
+        let is_mart = is_mart(&dep.filename);
+        if is_mart && dep.statements.iter().any(selects_star) {
+            let line = last_line_matching(&dep.sql, r"(?i)\bselect\s+(distinct\s+)?\*");
+            issues.push(issue(
+                LintRule::SelectStarInMart,
+                Severity::Warning,
+                line,
+                format!("mart {} selects * instead of listing its columns", model_name),
+            ));
+        }
+
+        let mut reported_columns = HashSet::new();
+        let mut reported_tables = HashSet::new();
+        for statement in &dep.statements {
+            for column in find_unqualified_columns(statement) {
+                if reported_columns.insert(column.to_lowercase()) {
+                    issues.push(issue(
+                        LintRule::UnqualifiedColumn,
+                        Severity::Warning,
+                        first_line_matching(&dep.sql, &word_pattern(&column)),
+                        format!("column {} is not qualified in a query that reads several tables", column),
+                    ));
+                }
+            }
+
+            let references = extract_references(statement);
+            let ctes: HashSet<String> = references.ctes.iter().map(|cte| cte.to_lowercase()).collect();
+            let functions: HashSet<String> = references.table_functions.iter()
+                .map(|(name, _)| name.to_lowercase())
+                .collect();
+
+            for (function, arguments) in &references.table_functions {
+                if !function.to_lowercase().starts_with("read_") {
+                    continue;
+                }
+                for path in arguments.iter().filter(|path| is_absolute_path(path)) {
+                    issues.push(issue(
+                        LintRule::AbsolutePath,
+                        Severity::Warning,
+                        first_line_matching(&dep.sql, &regex::escape(path)),
+                        format!("{} reads the absolute path {}; use a path relative to the project", function, path),
+                    ));
+                }
+            }
+
+            for table in &references.tables {
+                let table = table.to_lowercase();
+                if ctes.contains(&table) || functions.contains(&table) || table == model_name.to_lowercase() {
+                    continue;
+                }
+
+                let base_name = table.rsplit('.').next().unwrap_or(&table).to_string();
+                if models.contains(&base_name) {
+                    consumed.insert(base_name);
+                } else if !models.contains(&table) && !sources.contains(&table) && !sources.contains(&base_name)
+                    && reported_tables.insert(table.clone())
+                {
+                    issues.push(issue(
+                        LintRule::UnknownTable,
+                        Severity::Error,
+                        first_line_matching(&dep.sql, &word_pattern(&table)),
+                        format!("{} is neither a model nor a declared source; add it to `sources` in @config if it is external", table),
+                    ));
+                }
+            }
+        }
+    }
+
+    for (model_name, dep) in dependencies {
+        let exported = dep.config.as_ref()
+            .is_some_and(|config| config.output_configs().iter().any(|output| output.is_export()));
+
+        if !consumed.contains(&model_name.to_lowercase()) && !is_mart(&dep.filename) && !exported {
+            issues.push(LintIssue {
+                rule: LintRule::UnusedModel,
+                severity: Severity::Warning,
+                file: dep.filename.clone(),
+                line: None,
+                message: format!("no model reads {} and it is not a mart or exported to a file", model_name),
+            });
+        }
+    }
+
+    issues.sort_by(|a, b| (&a.file, a.line, a.message.as_str()).cmp(&(&b.file, b.line, b.message.as_str())));
+    Ok(issues)
+}
+
+/// Check the `@config` comments and sidecar files of every SQL file under a folder
+///
+/// Models with invalid configuration cannot be loaded, so this runs on the raw files
+/// and reports every bad comment rather than stopping at the first.
+///
+/// # Arguments
+///
+/// * `folder` - Folder (or single file) containing SQL models
+///
+/// # Returns
+///
+/// * `Result<Vec<LintIssue>>` - One error per invalid comment or sidecar file
+pub fn lint_config_files(folder: &str) -> Result<Vec<LintIssue>> {
+    let mut issues = Vec::new();
+
+    for entry in WalkDir::new(folder).follow_links(true).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_sql = path.extension().is_some_and(|ext| ext.to_string_lossy().eq_ignore_ascii_case("sql"));
+        if !path.is_file() || !is_sql {
+            continue;
+        }
+
+        let sql = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read SQL file: {}", path.display()))?;
+        for error in validate_model_config(path, &sql)? {
+            issues.push(LintIssue {
+                rule: LintRule::InvalidConfig,
+                severity: Severity::Error,
+                file: error.file.unwrap_or_else(|| path.display().to_string()),
+                line: Some(error.line),
+                message: error.message,
+            });
+        }
+    }
+
+    issues.sort_by(|a, b| (&a.file, a.line).cmp(&(&b.file, b.line)));
+    Ok(issues)
+}
+
+/// Whether a model file lives under a `marts` directory
+fn is_mart(filename: &str) -> bool {
+    Path::new(filename).components().any(|c| c.as_os_str().eq_ignore_ascii_case("marts"))
+}
+
+/// Whether the final SELECT of a statement projects `*`
+fn selects_star(statement: &Statement) -> bool {
+    fn body_selects_star(body: &SetExpr) -> bool {
+        match body {
+            SetExpr::Select(select) => select.projection.iter()
+                .any(|item| matches!(item, SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..))),
+            SetExpr::Query(query) => body_selects_star(&query.body),
+            SetExpr::SetOperation { left, right, .. } => body_selects_star(left) || body_selects_star(right),
+            _ => false,
+        }
+    }
+
+    match statement {
+        Statement::Query(query) => body_selects_star(&query.body),
+        _ => false,
+    }
+}
+
+/// Whether a path is absolute on Unix or Windows, or relative to a home directory
+fn is_absolute_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with('/')
+        || path.starts_with('~')
+        || path.starts_with("\\\\")
+        || (bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' && matches!(bytes[2], b'\\' | b'/'))
+}
+
+fn word_pattern(word: &str) -> String {
+    format!(r"(?i)\b{}\b", regex::escape(word))
+}
+
+fn first_line_matching(sql: &str, pattern: &str) -> Option<usize> {
+    let re = Regex::new(pattern).ok()?;
+    sql.lines().position(|line| re.is_match(line)).map(|index| index + 1)
+}
+
+fn last_line_matching(sql: &str, pattern: &str) -> Option<usize> {
+    let re = Regex::new(pattern).ok()?;
+    sql.lines().enumerate().filter(|(_, line)| re.is_match(line)).last().map(|(index, _)| index + 1)
+}
@@ -1,50 +1,297 @@
 use anyhow::{Context, Result};
 use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use crate::config::ModelConfig;
 
 /// Extract model-level configuration from SQL comments with @config directive
 ///
-/// Configuration should be in YAML format:
+/// Configuration should be in YAML format, either on one line:
 /// -- @config: {output: {type: "view"}}
 ///
+/// continued on the following comment lines, indented by at least two spaces:
+/// -- @config:
+/// --   output:
+/// --     type: view
+///
+/// or in a block comment:
+/// /* @config
+/// output:
+///   type: view
+/// */
+///
+/// Unknown keys and invalid values are errors, so a typo such as `type: "veiw"` stops
+/// the run instead of silently falling back to the defaults.
+///
 /// # Arguments
 ///
 /// * `sql` - SQL content with possible @config comments
 ///
 /// # Returns
 ///
-/// * `Result<Option<ModelConfig>>` - Model configuration if present
+/// * `Result<Option<ModelConfig>>` - Model configuration if present, or the first invalid comment
 pub fn extract_config_from_sql(sql: &str) -> Result<Option<ModelConfig>> {
+    let mut config = None;
+    let errors = read_config_comments(sql, &mut config)?;
+
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error.into());
+    }
+
+    Ok(config)
+}
+
+/// Extract the configuration of a model from its sidecar YAML file and its SQL comments
+///
+/// A `model_name.yml` (or `.yaml`) file next to the SQL file takes the same keys as
+/// `@config`. Settings in the SQL comments override the ones in the sidecar file.
+///
+/// # Arguments
+///
+/// * `path` - Path of the SQL model file
+/// * `sql` - SQL content of the model file
+///
+/// # Returns
+///
+/// * `Result<Option<ModelConfig>>` - Model configuration if present, or the first invalid entry
+pub fn extract_model_config(path: &Path, sql: &str) -> Result<Option<ModelConfig>> {
+    let (config, errors) = read_model_config(path, sql)?;
+
+    if let Some(error) = errors.into_iter().next() {
+        return Err(error.into());
+    }
+
+    Ok(config)
+}
+
+/// A `@config` comment or sidecar file that is not valid model configuration
+#[derive(Debug, Clone)]
+pub struct ConfigError {
+    /// Sidecar file the error is in, or None when it is in the SQL file
+    pub file: Option<String>,
+    /// Line of the error, starting from 1
+    pub line: usize,
+    /// Why the configuration was rejected
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "invalid config in {} on line {}: {}", file, self.line, self.message),
+            None => write!(f, "invalid @config on line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Check every `@config` comment in a SQL file
+///
+/// # Arguments
+///
+/// * `sql` - SQL content with possible @config comments
+///
+/// # Returns
+///
+/// * `Result<Vec<ConfigError>>` - Comments that are not valid model configuration
+pub fn validate_config_in_sql(sql: &str) -> Result<Vec<ConfigError>> {
+    read_config_comments(sql, &mut None)
+}
+
+/// Check the sidecar file and every `@config` comment of a model
+///
+/// # Arguments
+///
+/// * `path` - Path of the SQL model file
+/// * `sql` - SQL content of the model file
+///
+/// # Returns
+///
+/// * `Result<Vec<ConfigError>>` - Entries that are not valid model configuration
+pub fn validate_model_config(path: &Path, sql: &str) -> Result<Vec<ConfigError>> {
+    Ok(read_model_config(path, sql)?.1)
+}
+
+/// Sidecar YAML file of a model, if one exists
+fn sidecar_path(path: &Path) -> Option<PathBuf> {
+    ["yml", "yaml"].iter()
+        .map(|extension| path.with_extension(extension))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Read and merge the sidecar file and comments of a model, collecting the entries that fail
+fn read_model_config(path: &Path, sql: &str) -> Result<(Option<ModelConfig>, Vec<ConfigError>)> {
+    let mut config = None;
+    let mut errors = Vec::new();
+
+    if let Some(sidecar) = sidecar_path(path) {
+        let yaml = std::fs::read_to_string(&sidecar)
+            .with_context(|| format!("Failed to read config file: {}", sidecar.display()))?;
+
+        match parse_config(&yaml, 1) {
+            Ok(model_config) => merge_config(&mut config, model_config),
+            Err(error) => errors.push(ConfigError { file: Some(sidecar.display().to_string()), ..error }),
+        }
+    }
+
+    errors.extend(read_config_comments(sql, &mut config)?);
+    Ok((config, errors))
+}
+
+/// Parse and merge the `@config` comments of a SQL file into `config`, collecting the ones that fail
+fn read_config_comments(sql: &str, config: &mut Option<ModelConfig>) -> Result<Vec<ConfigError>> {
+    let mut errors = Vec::new();
+
+    for (line, yaml) in find_config_comments(sql)? {
+        match yaml.and_then(|yaml| parse_config(&yaml, line)) {
+            // Later configs override earlier ones
+            Ok(model_config) => merge_config(config, model_config),
+            Err(error) => errors.push(error),
+        }
+    }
+
+    Ok(errors)
+}
+
+/// Find the YAML text of each `@config` comment, with the line the comment starts on
+///
+/// The YAML keeps one line per source line so that YAML error positions map back to the file.
+fn find_config_comments(sql: &str) -> Result<Vec<(usize, Result<String, ConfigError>)>> {
     // Match lines starting with -- @config: followed by any text
-    let re = Regex::new(r"^\s*--\s*@config:\s*(.+)$").context("Failed to compile regex")?;
-    
-    let mut config = ModelConfig::default();
-    let mut has_config = false;
-    
-    for line in sql.lines() {
-        if let Some(captures) = re.captures(line) {
-            if let Some(yaml_text) = captures.get(1) {
-                let yaml_str = yaml_text.as_str();
-                match serde_yaml::from_str::<ModelConfig>(yaml_str) {
-                    Ok(model_config) => {
-                        // Merge configs, with later configs potentially overriding earlier ones
-                        if let Some(output) = &model_config.output {
-                            config.output = Some(output.clone());
-                        }
-                        has_config = true;
-                    }
-                    Err(e) => {
-                        tracing::warn!("Failed to parse YAML config: {}", e);
-                        // Continue to next line, don't fail the whole function
-                    }
+    let line_re = Regex::new(r"^\s*--\s*@config:\s*(.*)$").context("Failed to compile regex")?;
+    // Continuation lines are comments indented by at least two spaces or a tab
+    let continuation_re = Regex::new(r"^\s*--((?: {2,}|\t)\s*\S.*)$").context("Failed to compile regex")?;
+    let block_re = Regex::new(r"^\s*/\*\s*@config:?(.*)$").context("Failed to compile regex")?;
+
+    let lines: Vec<&str> = sql.lines().collect();
+    let mut comments = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let start = index + 1;
+
+        if let Some(captures) = line_re.captures(lines[index]) {
+            let mut yaml = captures[1].to_string();
+            index += 1;
+            // A complete single-line config is followed by ordinary comments, however indented
+            let block = opens_block(&yaml);
+            while block || unclosed_flow(&yaml) {
+                let Some(continuation) = lines.get(index).and_then(|line| continuation_re.captures(line)) else {
+                    break;
+                };
+                yaml.push('\n');
+                yaml.push_str(&continuation[1]);
+                index += 1;
+            }
+            comments.push((start, Ok(yaml)));
+        } else if let Some(captures) = block_re.captures(lines[index]) {
+            let mut body = vec![captures[1].to_string()];
+            let mut closed = false;
+            loop {
+                let last = body.last_mut().expect("block body has a first line");
+                if let Some(end) = last.find("*/") {
+                    last.truncate(end);
+                    closed = true;
+                    break;
+                }
+                index += 1;
+                match lines.get(index) {
+                    Some(line) => body.push(line.to_string()),
+                    None => break,
                 }
             }
+            index += 1;
+
+            let yaml = if closed {
+                Ok(body.join("\n"))
+            } else {
+                Err(ConfigError { file: None, line: start, message: "unterminated /* @config comment".to_string() })
+            };
+            comments.push((start, yaml));
+        } else {
+            index += 1;
         }
     }
-    
-    if has_config {
-        Ok(Some(config))
-    } else {
-        Ok(None)
+
+    Ok(comments)
+}
+
+/// Whether the first line of a `-- @config:` comment starts a block continued on the next lines,
+/// e.g. `-- @config:` or `-- @config: output:`
+fn opens_block(first_line: &str) -> bool {
+    let first_line = first_line.trim_end();
+    first_line.is_empty() || first_line.ends_with(':') || first_line.ends_with('|') || first_line.ends_with('>')
+}
+
+/// Whether a config has more `{` or `[` than it closes, outside of quoted strings
+fn unclosed_flow(yaml: &str) -> bool {
+    let mut depth = 0i32;
+    let mut quote = None;
+    for c in yaml.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {},
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{' | '[') => depth += 1,
+            (None, '}' | ']') => depth -= 1,
+            _ => {},
+        }
+    }
+    depth > 0
+}
+
+/// Parse one YAML config whose first line is line `start` of its file
+fn parse_config(yaml: &str, start: usize) -> Result<ModelConfig, ConfigError> {
+    if yaml.trim().is_empty() {
+        return Err(ConfigError { file: None, line: start, message: "empty config".to_string() });
+    }
+
+    let model_config = serde_yaml::from_str::<ModelConfig>(yaml).map_err(|e| ConfigError {
+        file: None,
+        line: start + e.location().map(|l| l.line().saturating_sub(1)).unwrap_or(0),
+        message: e.to_string(),
+    })?;
+
+    model_config.validate().map_err(|message| ConfigError { file: None, line: start, message })?;
+    Ok(model_config)
+}
+
+/// Merge `other` into `config`, with the settings in `other` taking precedence
+fn merge_config(config: &mut Option<ModelConfig>, other: ModelConfig) {
+    let config = config.get_or_insert_with(ModelConfig::default);
+
+    if let Some(description) = other.description {
+        config.description = Some(description);
+    }
+    // Outputs replace each other as a whole, so `output` in a comment overrides `outputs` in a sidecar
+    if other.output.is_some() || !other.outputs.is_empty() {
+        config.output = other.output;
+        config.outputs = other.outputs;
+    }
+    if !other.primary_key.is_empty() {
+        config.primary_key = other.primary_key;
+    }
+    // Keys and sources declared in both places are kept once
+    for foreign_key in other.foreign_keys {
+        let declared = config.foreign_keys.iter().any(|existing| {
+            existing.column.eq_ignore_ascii_case(&foreign_key.column)
+                && existing.references.eq_ignore_ascii_case(&foreign_key.references)
+        });
+        if !declared {
+            config.foreign_keys.push(foreign_key);
+        }
+    }
+    for source in other.sources {
+        if !config.sources.iter().any(|existing| existing.eq_ignore_ascii_case(&source)) {
+            config.sources.push(source);
+        }
+    }
+    for tag in other.tags {
+        if !config.has_tag(&tag) {
+            config.tags.push(tag);
+        }
     }
-}
\ No newline at end of file
+    config.meta.extend(other.meta);
+    config.columns.extend(other.columns);
+}
@@ -3,13 +3,14 @@ use petgraph::algo::toposort;
 use petgraph::graph::DiGraph;
 use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use sqlparser::ast::Statement;
 use walkdir::WalkDir;
 
 use crate::config::ModelConfig;
-use crate::parser::config::extract_config_from_sql;
-use crate::parser::sql::{extract_tables, parse_sql, extract_columns, extract_column_lineage};
+use crate::parser::config::{extract_model_config, ConfigError};
+use crate::parser::sql::{extract_tables, extract_columns, resolve_output_columns, SqlParser};
 
-use crate::parser::sql::{ColumnInfo, TableColumnRelationship};
+use crate::parser::sql::{ColumnInfo, JoinKey, TableColumnRelationship};
 
 /// Represents a dependency for a model/query
 #[derive(Debug, Clone)]
@@ -24,6 +25,12 @@ pub struct Dependency {
     pub columns: Vec<ColumnInfo>,
     /// Column-level lineage relationships
     pub column_lineage: Vec<TableColumnRelationship>,
+    /// Column pairs the model joins on
+    pub join_keys: Vec<JoinKey>,
+    /// SQL text of the model
+    pub sql: String,
+    /// Parsed statements of the model
+    pub statements: Vec<Statement>,
 }
 
 /// Get dependencies for all SQL files in a folder
@@ -37,8 +44,32 @@ pub struct Dependency {
 ///
 /// * `HashMap<String, Dependency>` - Map of model names to their dependencies
 pub fn get_dependencies(folder: &str, dialect: &str) -> Result<HashMap<String, Dependency>> {
+    load_dependencies(folder, dialect, true)
+}
+
+/// Get dependencies for all SQL files in a folder, loading models with invalid config without it
+///
+/// Lint reports invalid config on its own and still checks the rest of every model.
+///
+/// # Arguments
+///
+/// * `folder` - Folder containing SQL files
+/// * `dialect` - SQL dialect to use for parsing
+///
+/// # Returns
+///
+/// * `HashMap<String, Dependency>` - Map of model names to their dependencies
+pub fn get_dependencies_ignoring_invalid_config(folder: &str, dialect: &str) -> Result<HashMap<String, Dependency>> {
+    load_dependencies(folder, dialect, false)
+}
+
+/// Get dependencies for all SQL files in a folder, failing on invalid config when `strict_config` is set
+fn load_dependencies(folder: &str, dialect: &str, strict_config: bool) -> Result<HashMap<String, Dependency>> {
     let mut dependencies = HashMap::new();
     
+    // One parser (and DuckDB connection) is shared by every file
+    let mut parser = SqlParser::new(dialect);
+    
     tracing::info!("Looking for SQL files in folder: {}", folder);
     
     let walker = WalkDir::new(folder).follow_links(true);
@@ -57,7 +88,7 @@ pub fn get_dependencies(folder: &str, dialect: &str) -> Result<HashMap<String, D
                 
                 if extension_str == "sql" {
                     tracing::info!("Processing SQL file: {}", path.display());
-                    process_sql_file(path, dialect, &mut dependencies)?;
+                    process_sql_file(path, &mut parser, &mut dependencies, strict_config)?;
                 } else if extension_str == "py" {
                     // Python support would be handled here
                     // For now, we'll skip Python files
@@ -71,13 +102,22 @@ pub fn get_dependencies(folder: &str, dialect: &str) -> Result<HashMap<String, D
         }
     }
     
+    // Column lineage needs the output columns of upstream models to expand `*`,
+    // so it is resolved once every file has been parsed
+    resolve_column_lineage(&mut dependencies);
+    
     tracing::info!("Dependency processing complete, found {} models", dependencies.len());
     
     Ok(dependencies)
 }
 
 /// Process a SQL file to extract dependencies
-fn process_sql_file(path: &Path, dialect: &str, dependencies: &mut HashMap<String, Dependency>) -> Result<()> {
+fn process_sql_file(
+    path: &Path,
+    parser: &mut SqlParser,
+    dependencies: &mut HashMap<String, Dependency>,
+    strict_config: bool,
+) -> Result<()> {
     // Get the model name from the filename (without extension)
     let model_name = path.file_stem()
         .context("Failed to get file stem")?
@@ -88,19 +128,29 @@ fn process_sql_file(path: &Path, dialect: &str, dependencies: &mut HashMap<Strin
     let sql = std::fs::read_to_string(path)
         .context(format!("Failed to read SQL file: {}", path.display()))?;
     
-    // Extract config from SQL comments
-    let config = extract_config_from_sql(&sql)?;
+    // Extract config from the sidecar file and SQL comments
+    let config = extract_model_config(path, &sql).map_err(|e| match e.downcast::<ConfigError>() {
+        Ok(ConfigError { file: Some(file), line, message }) => anyhow::anyhow!("{}:{}: invalid config: {}", file, line, message),
+        Ok(error) => anyhow::anyhow!("{}:{}: invalid @config: {}", path.display(), error.line, error.message),
+        Err(e) => e.context(format!("Failed to read @config in {}", path.display())),
+    });
+    let config = match config {
+        Err(e) if !strict_config => {
+            tracing::warn!("Ignoring config of {}: {}", path.display(), e);
+            None
+        },
+        config => config?,
+    };
     
     // Parse SQL and extract tables
     let mut deps = HashSet::new();
-    let statements = parse_sql(&sql, dialect)?;
+    let statements = parser.parse_file(&sql, Some(&path.display().to_string()))?;
     
     // Log the number of statements parsed
     tracing::info!("Parsed {} statements from file: {}", statements.len(), path.display());
     
-    // For storing column information and lineage
+    // For storing column information
     let mut columns = Vec::new();
-    let mut column_lineage = Vec::new();
     
     for statement in &statements {
         // Log the statement type
@@ -117,12 +167,6 @@ fn process_sql_file(path: &Path, dialect: &str, dependencies: &mut HashMap<Strin
             tracing::info!("Extracted {} columns from statement", cols.len());
             columns.extend(cols);
         }
-        
-        // Extract column lineage
-        if let Ok(lineage) = extract_column_lineage(statement, &model_name) {
-            tracing::info!("Extracted {} column lineage relationships", lineage.len());
-            column_lineage.extend(lineage);
-        }
     }
     
     // Remove self-dependencies (CTE references)
@@ -130,20 +174,106 @@ fn process_sql_file(path: &Path, dialect: &str, dependencies: &mut HashMap<Strin
     
     tracing::info!("Final dependencies for {}: {:?}", model_name, deps);
     tracing::info!("Column count for {}: {}", model_name, columns.len());
-    tracing::info!("Column lineage count for {}: {}", model_name, column_lineage.len());
     
     // Add dependency to the map
-    dependencies.insert(model_name, Dependency {
+    dependencies.insert(model_name.clone(), Dependency {
         deps,
         filename: path.to_string_lossy().to_string(),
         config,
         columns,
-        column_lineage,
+        column_lineage: Vec::new(),
+        join_keys: Vec::new(),
+        sql,
+        statements,
     });
     
     Ok(())
 }
 
+/// Resolve column-level lineage for every model
+///
+/// Models are visited upstream first so that the output columns of upstream models
+/// are known when a downstream model selects `*` from them.
+fn resolve_column_lineage(dependencies: &mut HashMap<String, Dependency>) {
+    let order = upstream_first_order(dependencies);
+    
+    // Output column names of each model resolved so far
+    let mut schemas: HashMap<String, Vec<String>> = HashMap::new();
+    
+    for model_name in order {
+        let Some(statements) = dependencies.get(&model_name).map(|d| &d.statements) else {
+            continue;
+        };
+        
+        let mut column_lineage = Vec::new();
+        let mut join_keys = Vec::new();
+        let mut output_columns = None;
+        
+        for statement in statements {
+            let resolved = resolve_output_columns(statement, &schemas);
+            column_lineage.extend(resolved.column_lineage());
+            join_keys.extend(resolved.join_keys.iter().cloned());
+            
+            // Only models whose columns are fully known can be used to expand `*` downstream
+            if matches!(statement, Statement::Query(_)) {
+                output_columns = resolved.complete
+                    .then(|| resolved.columns.into_iter().map(|c| c.name).collect());
+            }
+        }
+        
+        tracing::info!("Column lineage count for {}: {}", model_name, column_lineage.len());
+        
+        if let Some(dependency) = dependencies.get_mut(&model_name) {
+            dependency.column_lineage = column_lineage;
+            dependency.join_keys = join_keys;
+        }
+        if let Some(output_columns) = output_columns {
+            schemas.insert(model_name, output_columns);
+        }
+    }
+}
+
+/// Order models so that each model comes after the models it depends on
+///
+/// Unlike `get_execution_order` this never fails: edges that close a cycle are ignored.
+fn upstream_first_order(dependencies: &HashMap<String, Dependency>) -> Vec<String> {
+    fn visit(
+        name: &str,
+        dependencies: &HashMap<String, Dependency>,
+        visited: &mut HashSet<String>,
+        order: &mut Vec<String>,
+    ) {
+        if !visited.insert(name.to_string()) {
+            return;
+        }
+        if let Some(dependency) = dependencies.get(name) {
+            let mut deps: Vec<&String> = dependency.deps.iter().collect();
+            deps.sort();
+            for dep in deps {
+                // Schema-qualified references resolve to the model with the same base name
+                let dep = if dependencies.contains_key(dep) {
+                    dep.as_str()
+                } else {
+                    dep.rsplit('.').next().unwrap_or(dep)
+                };
+                visit(dep, dependencies, visited, order);
+            }
+            order.push(name.to_string());
+        }
+    }
+    
+    let mut names: Vec<&String> = dependencies.keys().collect();
+    names.sort();
+    
+    let mut visited = HashSet::new();
+    let mut order = Vec::new();
+    for name in names {
+        visit(name, dependencies, &mut visited, &mut order);
+    }
+    
+    order
+}
+
 /// Get execution order for dependencies using topological sort
 ///
 /// # Arguments
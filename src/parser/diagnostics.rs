@@ -0,0 +1,178 @@
+use std::fmt;
+
+/// A 1-based line and column (in characters) within a SQL file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Column number in characters, starting from 1
+    pub column: usize,
+}
+
+impl SourceLocation {
+    /// Location of a byte offset within `sql`
+    pub fn from_offset(sql: &str, offset: usize) -> Self {
+        let offset = offset.min(sql.len());
+        // Offsets inside a multi-byte character point at its start
+        let offset = (0..=offset).rev().find(|&i| sql.is_char_boundary(i)).unwrap_or(0);
+        let before = &sql[..offset];
+        let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+
+        Self {
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+impl fmt::Display for SourceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An error reported by one of the parsers tried on a SQL file
+#[derive(Debug, Clone)]
+pub struct ParserMessage {
+    /// Parser that reported the error, e.g. `duckdb` or `sqlparser`
+    pub parser: String,
+    /// Error message, without the location
+    pub message: String,
+    /// Where the parser reported the error, if it said
+    pub location: Option<SourceLocation>,
+}
+
+impl ParserMessage {
+    /// Build a message from a sqlparser error, reading the `at Line: X, Column: Y` suffix
+    pub fn from_sqlparser(error: &sqlparser::parser::ParserError) -> Self {
+        let text = match error {
+            sqlparser::parser::ParserError::TokenizerError(message)
+            | sqlparser::parser::ParserError::ParserError(message) => message.clone(),
+            other => other.to_string(),
+        };
+
+        let parsed = text.rfind(" at Line: ").and_then(|start| {
+            let (line, column) = text[start + " at Line: ".len()..].split_once(", Column: ")?;
+            let location = SourceLocation {
+                line: line.trim().parse().ok()?,
+                column: column.trim().parse().ok()?,
+            };
+            Some((text[..start].to_string(), location))
+        });
+
+        match parsed {
+            Some((message, location)) => Self { parser: "sqlparser".to_string(), message, location: Some(location) },
+            None => Self { parser: "sqlparser".to_string(), message: text, location: None },
+        }
+    }
+}
+
+impl fmt::Display for ParserMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some(location) => write!(f, "{} ({}): {}", self.parser, location, self.message),
+            None => write!(f, "{}: {}", self.parser, self.message),
+        }
+    }
+}
+
+/// A SQL file that none of the parsers could parse
+///
+/// Renders like a compiler error: the file and position, the offending source line with a
+/// caret under the failing token, and what each parser reported.
+#[derive(Debug, Clone)]
+pub struct ParseDiagnostic {
+    /// Model file the SQL was read from
+    pub file: Option<String>,
+    /// Position the diagnostic points at
+    pub location: Option<SourceLocation>,
+    /// Number of characters underlined at `location`
+    pub length: usize,
+    /// Source line containing `location`
+    pub source_line: Option<String>,
+    /// What each parser reported, in the order they were tried
+    pub messages: Vec<ParserMessage>,
+}
+
+impl ParseDiagnostic {
+    /// Build a diagnostic for `sql`, pointing at the first parser error that has a location
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SQL that failed to parse
+    /// * `file` - Model file the SQL was read from, if any
+    /// * `messages` - Errors from each parser that was tried
+    ///
+    /// # Returns
+    ///
+    /// * `ParseDiagnostic` - Diagnostic with the source excerpt filled in
+    pub fn new(sql: &str, file: Option<&str>, messages: Vec<ParserMessage>) -> Self {
+        let location = messages.iter().find_map(|m| m.location);
+        let source_line = location
+            .and_then(|l| sql.lines().nth(l.line.saturating_sub(1)))
+            .map(|line| line.trim_end().to_string());
+        let length = match (&source_line, location) {
+            (Some(line), Some(location)) => token_length(line, location.column),
+            _ => 1,
+        };
+
+        Self {
+            file: file.map(str::to_string),
+            location,
+            length,
+            source_line,
+            messages,
+        }
+    }
+}
+
+impl fmt::Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let position = match (&self.file, self.location) {
+            (Some(file), Some(location)) => format!("{}:{}", file, location),
+            (Some(file), None) => file.clone(),
+            (None, Some(location)) => format!("line {}", location),
+            (None, None) => String::new(),
+        };
+
+        if position.is_empty() {
+            write!(f, "Failed to parse SQL")?;
+        } else {
+            write!(f, "Failed to parse SQL at {}", position)?;
+        }
+
+        if let (Some(line), Some(location)) = (&self.source_line, self.location) {
+            let gutter = " ".repeat(location.line.to_string().len());
+            // Keep tabs so the caret lines up with the source line
+            let indent: String = line.chars()
+                .take(location.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+
+            write!(f, "\n{} |", gutter)?;
+            write!(f, "\n{} | {}", location.line, line)?;
+            write!(f, "\n{} | {}{}", gutter, indent, "^".repeat(self.length.max(1)))?;
+        }
+
+        for message in &self.messages {
+            write!(f, "\n  = {}", message)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
+/// Length in characters of the token starting at a 1-based column
+fn token_length(line: &str, column: usize) -> usize {
+    let mut chars = line.chars().skip(column.saturating_sub(1)).peekable();
+    let word = |c: &char| c.is_alphanumeric() || *c == '_';
+
+    match chars.peek() {
+        Some(c) if word(c) => chars.take_while(word).count(),
+        Some(c) if c.is_whitespace() => 1,
+        Some(_) => chars.take_while(|c| !c.is_whitespace() && !word(c)).count().max(1),
+        None => 1,
+    }
+}
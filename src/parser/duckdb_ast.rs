@@ -0,0 +1,1344 @@
+use anyhow::{Context, Result};
+use serde_json::Value;
+use sqlparser::ast::{
+    Array, BinaryOperator, CastKind, Cte, CteAsMaterialized, DataType, DictionaryField, Distinct,
+    DuplicateTreatment, ExcludeSelectItem, Expr, ExprWithAlias, Function, FunctionArg, FunctionArgExpr,
+    FunctionArgOperator, FunctionArgumentClause, FunctionArgumentList, FunctionArguments, GroupByExpr,
+    Ident, IdentWithAlias, Join, JoinConstraint, JoinOperator, LambdaFunction, NullTreatment, ObjectName,
+    Offset, OffsetRows, OneOrManyWithParens, OrderBy, OrderByExpr, PivotValueSource, Query,
+    RenameSelectItem, ReplaceSelectElement, ReplaceSelectItem, Select, SelectItem, SetExpr, SetOperator,
+    SetQuantifier, Statement, Subscript, TableAlias, TableFactor, TableWithJoins, UnaryOperator,
+    Value as SqlValue, Values, WildcardAdditionalOptions, WindowFrame, WindowFrameBound, WindowFrameUnits,
+    WindowSpec, WindowType, With,
+};
+
+/// Convert the JSON AST produced by DuckDB's `json_serialize_sql` into sqlparser statements
+///
+/// The conversion covers DuckDB-specific syntax that sqlparser cannot parse itself, such as
+/// `QUALIFY`, `PIVOT`/`UNPIVOT`, `FROM`-first queries, `SELECT * EXCLUDE/REPLACE`,
+/// `COLUMNS(...)`, struct and list literals, lambdas, and ASOF and POSITIONAL joins. Any node
+/// that cannot be represented is reported as an error rather than replaced, so callers can
+/// fall back to another parser instead of silently losing tables or columns.
+///
+/// POSITIONAL joins have no sqlparser equivalent. They are converted to a full outer join
+/// without a constraint, which keeps both tables for lineage but cannot be told apart from a
+/// written `FULL JOIN`, so the trees are never printed back as SQL; models are run and
+/// described from their source text.
+///
+/// # Arguments
+///
+/// * `duckdb_ast` - The JSON AST from DuckDB
+///
+/// # Returns
+///
+/// * `Result<Vec<Statement>>` - Vector of converted SQL statements or error
+pub fn convert_statements(duckdb_ast: &Value) -> Result<Vec<Statement>> {
+    if !duckdb_ast.is_object() {
+        return Err(anyhow::anyhow!("DuckDB AST is not a valid JSON object"));
+    }
+
+    if duckdb_ast.get("error").and_then(|e| e.as_bool()) == Some(true) {
+        return match duckdb_ast.get("error_message").and_then(|m| m.as_str()) {
+            Some(message) => Err(anyhow::anyhow!("DuckDB parser error: {}", message)),
+            None => Err(anyhow::anyhow!("DuckDB parser error")),
+        };
+    }
+
+    let statements = duckdb_ast.get("statements")
+        .and_then(|s| s.as_array())
+        .ok_or_else(|| anyhow::anyhow!("DuckDB AST does not contain statements array"))?;
+
+    statements.iter()
+        .enumerate()
+        .map(|(index, statement)| {
+            convert_subquery(statement)
+                .map(|query| Statement::Query(Box::new(query)))
+                .with_context(|| format!("Failed to convert statement {} of the DuckDB AST", index + 1))
+        })
+        .collect()
+}
+
+/// Convert a serialized SelectStatement (`{"node": ...}`) or a bare query node into a Query
+fn convert_subquery(statement: &Value) -> Result<Query> {
+    convert_query(statement.get("node").unwrap_or(statement))
+}
+
+/// Convert a DuckDB query node, with its CTEs and result modifiers, into a Query
+fn convert_query(node: &Value) -> Result<Query> {
+    // Materialized CTEs wrap the query that uses them
+    if node_type(node)? == "CTE_NODE" {
+        let mut query = convert_query(field(node, "child")?)?;
+        let cte = Cte {
+            alias: cte_alias(text(node, "ctename"), node),
+            query: Box::new(convert_query(field(node, "query")?)?),
+            from: None,
+            materialized: cte_materialized(node),
+        };
+        match query.with.as_mut() {
+            Some(with) => with.cte_tables.insert(0, cte),
+            None => query.with = Some(With { recursive: false, cte_tables: vec![cte] }),
+        }
+        return Ok(query);
+    }
+
+    let with = convert_cte_map(node)?;
+    let body = convert_body(node)?;
+    let mut order_by = None;
+    let mut limit = None;
+    let mut offset = None;
+
+    for modifier in array(node, "modifiers") {
+        match text(modifier, "type") {
+            "ORDER_MODIFIER" => {
+                let exprs = convert_order_list(array(modifier, "orders"))?;
+                if !exprs.is_empty() {
+                    order_by = Some(OrderBy { exprs, interpolate: None });
+                }
+            },
+            "LIMIT_MODIFIER" => {
+                limit = optional(modifier, "limit").map(convert_expr).transpose()?;
+                offset = optional(modifier, "offset")
+                    .map(|value| convert_expr(value).map(|value| Offset { value, rows: OffsetRows::None }))
+                    .transpose()?;
+            },
+            // Applied to the SELECT itself
+            "DISTINCT_MODIFIER" => {},
+            other => return Err(anyhow::anyhow!("Unsupported result modifier: {}", other)),
+        }
+    }
+
+    Ok(Query {
+        with,
+        body: Box::new(body),
+        order_by,
+        limit,
+        offset,
+        fetch: None,
+        locks: Vec::new(),
+        limit_by: Vec::new(),
+        for_clause: None,
+        format_clause: None,
+        settings: None,
+    })
+}
+
+/// Convert the `cte_map` of a query node into a WITH clause
+fn convert_cte_map(node: &Value) -> Result<Option<With>> {
+    let entries = node.get("cte_map").map(|m| array(m, "map")).unwrap_or_default();
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let mut recursive = false;
+    let mut cte_tables = Vec::new();
+
+    for entry in entries {
+        let name = entry.get("key").and_then(|k| k.as_str())
+            .ok_or_else(|| anyhow::anyhow!("CTE entry missing name"))?;
+        let info = field(entry, "value")?;
+        let query_node = field(info, "query")?;
+        let query_node = query_node.get("node").unwrap_or(query_node);
+
+        recursive |= node_type(query_node)? == "RECURSIVE_CTE_NODE";
+
+        cte_tables.push(Cte {
+            alias: cte_alias(name, info),
+            query: Box::new(convert_query(query_node)?),
+            from: None,
+            materialized: cte_materialized(info),
+        });
+    }
+
+    Ok(Some(With { recursive, cte_tables }))
+}
+
+fn cte_alias(name: &str, info: &Value) -> TableAlias {
+    TableAlias {
+        name: ident(name),
+        columns: array(info, "aliases").iter().filter_map(|a| a.as_str()).map(ident).collect(),
+    }
+}
+
+fn cte_materialized(info: &Value) -> Option<CteAsMaterialized> {
+    match text(info, "materialized") {
+        "CTE_MATERIALIZE_ALWAYS" => Some(CteAsMaterialized::Materialized),
+        "CTE_MATERIALIZE_NEVER" => Some(CteAsMaterialized::NotMaterialized),
+        _ => None,
+    }
+}
+
+/// Convert the body of a query node, without its modifiers
+fn convert_body(node: &Value) -> Result<SetExpr> {
+    match node_type(node)? {
+        "SELECT_NODE" => convert_select(node).map(|select| SetExpr::Select(Box::new(select))),
+        "SET_OPERATION_NODE" => convert_set_operation(node),
+        "RECURSIVE_CTE_NODE" => Ok(SetExpr::SetOperation {
+            op: SetOperator::Union,
+            set_quantifier: if flag(node, "union_all") { SetQuantifier::All } else { SetQuantifier::None },
+            left: Box::new(convert_set_operand(field(node, "left")?)?),
+            right: Box::new(convert_set_operand(field(node, "right")?)?),
+        }),
+        "CTE_NODE" => convert_query(node).map(|query| SetExpr::Query(Box::new(query))),
+        other => Err(anyhow::anyhow!("Unsupported DuckDB query node type: {}", other)),
+    }
+}
+
+/// Convert one side of a set operation, keeping its own ORDER BY/LIMIT and CTEs
+fn convert_set_operand(node: &Value) -> Result<SetExpr> {
+    let has_query_modifiers = array(node, "modifiers").iter()
+        .any(|m| text(m, "type") != "DISTINCT_MODIFIER");
+    let has_ctes = node.get("cte_map").is_some_and(|m| !array(m, "map").is_empty());
+
+    if has_query_modifiers || has_ctes || node_type(node)? == "CTE_NODE" {
+        convert_query(node).map(|query| SetExpr::Query(Box::new(query)))
+    } else {
+        convert_body(node)
+    }
+}
+
+fn convert_set_operation(node: &Value) -> Result<SetExpr> {
+    let setop_type = text(node, "setop_type");
+    let all = flag(node, "setop_all");
+
+    let (op, set_quantifier) = match (setop_type, all) {
+        ("UNION", true) => (SetOperator::Union, SetQuantifier::All),
+        ("UNION", false) => (SetOperator::Union, SetQuantifier::None),
+        ("UNION_BY_NAME", true) => (SetOperator::Union, SetQuantifier::AllByName),
+        ("UNION_BY_NAME", false) => (SetOperator::Union, SetQuantifier::ByName),
+        ("EXCEPT", true) => (SetOperator::Except, SetQuantifier::All),
+        ("EXCEPT", false) => (SetOperator::Except, SetQuantifier::None),
+        ("INTERSECT", true) => (SetOperator::Intersect, SetQuantifier::All),
+        ("INTERSECT", false) => (SetOperator::Intersect, SetQuantifier::None),
+        (other, _) => return Err(anyhow::anyhow!("Unsupported set operation type: {}", other)),
+    };
+
+    // Older DuckDB versions serialize `left`/`right`, newer ones a list of children
+    let children: Vec<&Value> = match (node.get("left"), node.get("right")) {
+        (Some(left), Some(right)) => vec![left, right],
+        _ => array(node, "children").iter().collect(),
+    };
+
+    let mut operands = children.into_iter().map(convert_set_operand);
+    let mut result = operands.next()
+        .ok_or_else(|| anyhow::anyhow!("SET_OPERATION_NODE has no operands"))??;
+    for right in operands {
+        result = SetExpr::SetOperation {
+            op,
+            set_quantifier,
+            left: Box::new(result),
+            right: Box::new(right?),
+        };
+    }
+
+    Ok(result)
+}
+
+/// Convert a DuckDB SELECT_NODE into a sqlparser Select
+fn convert_select(node: &Value) -> Result<Select> {
+    let projection = array(node, "select_list").iter()
+        .map(convert_select_item)
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut distinct = None;
+    for modifier in array(node, "modifiers") {
+        if text(modifier, "type") == "DISTINCT_MODIFIER" {
+            let targets = array(modifier, "distinct_on_targets").iter()
+                .map(convert_expr)
+                .collect::<Result<Vec<_>>>()?;
+            distinct = Some(if targets.is_empty() { Distinct::Distinct } else { Distinct::On(targets) });
+        }
+    }
+
+    let from = match optional(node, "from_table") {
+        Some(table_ref) if !matches!(text(table_ref, "type"), "EMPTY" | "EMPTY_FROM") => {
+            vec![convert_table_with_joins(table_ref)?]
+        },
+        _ => Vec::new(),
+    };
+
+    let group_expressions = array(node, "group_expressions").iter()
+        .map(convert_expr)
+        .collect::<Result<Vec<_>>>()?;
+    let group_sets = array(node, "group_sets");
+
+    let group_by = if text(node, "aggregate_handling") == "FORCE_AGGREGATES" {
+        GroupByExpr::All(Vec::new())
+    } else if group_sets.len() > 1 {
+        // ROLLUP, CUBE and GROUPING SETS all arrive as lists of indexes into group_expressions
+        let sets = group_sets.iter()
+            .map(|set| {
+                set.as_array().map(Vec::as_slice).unwrap_or_default().iter()
+                    .map(|index| {
+                        index.as_u64()
+                            .and_then(|i| group_expressions.get(i as usize).cloned())
+                            .ok_or_else(|| anyhow::anyhow!("Invalid grouping set index: {}", index))
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+        GroupByExpr::Expressions(vec![Expr::GroupingSets(sets)], Vec::new())
+    } else {
+        GroupByExpr::Expressions(group_expressions, Vec::new())
+    };
+
+    let having = optional(node, "having").or_else(|| optional(node, "having_clause"));
+
+    Ok(Select {
+        distinct,
+        top: None,
+        projection,
+        into: None,
+        from,
+        lateral_views: Vec::new(),
+        selection: optional(node, "where_clause").map(convert_expr).transpose()?,
+        group_by,
+        cluster_by: Vec::new(),
+        distribute_by: Vec::new(),
+        sort_by: Vec::new(),
+        having: having.map(convert_expr).transpose()?,
+        named_window: Vec::new(),
+        qualify: optional(node, "qualify").map(convert_expr).transpose()?,
+        window_before_qualify: false,
+        value_table_mode: None,
+        connect_by: None,
+        prewhere: None,
+    })
+}
+
+/// Convert a select list entry, turning `*` and `COLUMNS(*)` into wildcards
+fn convert_select_item(item: &Value) -> Result<SelectItem> {
+    if text(item, "class") == "STAR" && optional(item, "expr").is_none() && !flag(item, "unpacked") {
+        let options = convert_wildcard_options(item)?;
+        let relation = text(item, "relation_name");
+        return Ok(if relation.is_empty() {
+            SelectItem::Wildcard(options)
+        } else {
+            SelectItem::QualifiedWildcard(ObjectName(vec![ident(relation)]), options)
+        });
+    }
+
+    let expr = convert_expr(item)?;
+    let alias = text(item, "alias");
+
+    Ok(if alias.is_empty() {
+        SelectItem::UnnamedExpr(expr)
+    } else {
+        SelectItem::ExprWithAlias { expr, alias: ident(alias) }
+    })
+}
+
+/// EXCLUDE, REPLACE and RENAME options of a star expression
+fn convert_wildcard_options(star: &Value) -> Result<WildcardAdditionalOptions> {
+    // Columns are plain names in older versions and qualified names in newer ones
+    let column_name = |value: &Value| -> Option<String> {
+        value.as_str()
+            .or_else(|| value.get("column").and_then(|c| c.as_str()))
+            .map(str::to_string)
+    };
+
+    let excluded: Vec<Ident> = array(star, "exclude_list").iter()
+        .chain(array(star, "qualified_exclude_list"))
+        .filter_map(column_name)
+        .map(|name| ident(&name))
+        .collect();
+
+    let replaced = array(star, "replace_list").iter()
+        .map(|entry| {
+            let name = entry.get("key").and_then(column_name)
+                .ok_or_else(|| anyhow::anyhow!("REPLACE entry missing column name"))?;
+            Ok(Box::new(ReplaceSelectElement {
+                expr: convert_expr(field(entry, "value")?)?,
+                column_name: ident(&name),
+                as_keyword: true,
+            }))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let renamed = array(star, "rename_list").iter()
+        .map(|entry| {
+            let name = entry.get("key").and_then(column_name)
+                .ok_or_else(|| anyhow::anyhow!("RENAME entry missing column name"))?;
+            let alias = entry.get("value").and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow::anyhow!("RENAME entry missing new name"))?;
+            Ok(IdentWithAlias { ident: ident(&name), alias: ident(alias) })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(WildcardAdditionalOptions {
+        opt_exclude: (!excluded.is_empty()).then_some(ExcludeSelectItem::Multiple(excluded)),
+        opt_replace: (!replaced.is_empty()).then_some(ReplaceSelectItem { items: replaced }),
+        opt_rename: (!renamed.is_empty()).then_some(RenameSelectItem::Multiple(renamed)),
+        ..Default::default()
+    })
+}
+
+/// Convert a FROM item, flattening a chain of joins onto its leftmost table
+fn convert_table_with_joins(table_ref: &Value) -> Result<TableWithJoins> {
+    if text(table_ref, "type") == "JOIN" && text(table_ref, "alias").is_empty() {
+        convert_join_tree(table_ref)
+    } else {
+        Ok(TableWithJoins { relation: convert_table_factor(table_ref)?, joins: Vec::new() })
+    }
+}
+
+fn convert_join_tree(join: &Value) -> Result<TableWithJoins> {
+    let mut table_with_joins = convert_table_with_joins(field(join, "left")?)?;
+    table_with_joins.joins.push(Join {
+        relation: convert_table_factor(field(join, "right")?)?,
+        join_operator: convert_join_operator(join)?,
+    });
+    Ok(table_with_joins)
+}
+
+fn convert_join_operator(join: &Value) -> Result<JoinOperator> {
+    let condition = optional(join, "condition").map(convert_expr).transpose()?;
+    let using: Vec<Ident> = array(join, "using_columns").iter()
+        .filter_map(|c| c.as_str())
+        .map(ident)
+        .collect();
+
+    match text(join, "ref_type") {
+        "CROSS" => return Ok(JoinOperator::CrossJoin),
+        // A positional join is a full outer join on row position, which has no join condition
+        "POSITIONAL" => return Ok(JoinOperator::FullOuter(JoinConstraint::None)),
+        "ASOF" => {
+            // The inequality is the match condition, equalities constrain the partitions
+            let (equalities, inequalities): (Vec<Expr>, Vec<Expr>) = condition
+                .map(split_conjunction)
+                .unwrap_or_default()
+                .into_iter()
+                .partition(|e| matches!(e, Expr::BinaryOp { op: BinaryOperator::Eq, .. }));
+            let match_condition = join_conjunction(inequalities)
+                .ok_or_else(|| anyhow::anyhow!("ASOF join without an inequality condition"))?;
+            let constraint = match join_conjunction(equalities) {
+                Some(expr) => JoinConstraint::On(expr),
+                None if !using.is_empty() => JoinConstraint::Using(using),
+                None => JoinConstraint::None,
+            };
+            return Ok(JoinOperator::AsOf { match_condition, constraint });
+        },
+        _ => {},
+    }
+
+    let constraint = if text(join, "ref_type") == "NATURAL" {
+        JoinConstraint::Natural
+    } else if !using.is_empty() {
+        JoinConstraint::Using(using)
+    } else {
+        match condition {
+            Some(expr) => JoinConstraint::On(expr),
+            None => return Ok(JoinOperator::CrossJoin),
+        }
+    };
+
+    match text(join, "join_type") {
+        "INNER" | "" => Ok(JoinOperator::Inner(constraint)),
+        "LEFT" => Ok(JoinOperator::LeftOuter(constraint)),
+        "RIGHT" => Ok(JoinOperator::RightOuter(constraint)),
+        "OUTER" => Ok(JoinOperator::FullOuter(constraint)),
+        "SEMI" => Ok(JoinOperator::LeftSemi(constraint)),
+        "ANTI" => Ok(JoinOperator::LeftAnti(constraint)),
+        "RIGHT_SEMI" => Ok(JoinOperator::RightSemi(constraint)),
+        "RIGHT_ANTI" => Ok(JoinOperator::RightAnti(constraint)),
+        other => Err(anyhow::anyhow!("Unsupported join type: {}", other)),
+    }
+}
+
+/// Convert a DuckDB table reference into a sqlparser TableFactor
+fn convert_table_factor(table_ref: &Value) -> Result<TableFactor> {
+    let alias = table_alias(table_ref);
+
+    match text(table_ref, "type") {
+        "BASE_TABLE" => {
+            let parts: Vec<Ident> = ["catalog_name", "schema_name", "table_name"].iter()
+                .map(|key| text(table_ref, key))
+                .filter(|part| !part.is_empty())
+                .map(ident)
+                .collect();
+            if parts.is_empty() {
+                return Err(anyhow::anyhow!("BASE_TABLE missing table_name"));
+            }
+            Ok(table(ObjectName(parts), alias, None))
+        },
+        "SUBQUERY" => Ok(TableFactor::Derived {
+            lateral: false,
+            subquery: Box::new(convert_subquery(field(table_ref, "subquery")?)?),
+            alias,
+        }),
+        "TABLE_FUNCTION" => {
+            // Converted the way sqlparser parses `FROM read_csv(...)`
+            match convert_expr(field(table_ref, "function")?)? {
+                Expr::Function(function) => {
+                    let args = match function.args {
+                        FunctionArguments::List(list) => list.args,
+                        _ => Vec::new(),
+                    };
+                    Ok(table(function.name, alias, Some(args)))
+                },
+                other => Err(anyhow::anyhow!("Unsupported table function expression: {}", other)),
+            }
+        },
+        "EXPRESSION_LIST" => {
+            let rows = array(table_ref, "values").iter()
+                .map(|row| row.as_array().map(Vec::as_slice).unwrap_or_default().iter().map(convert_expr).collect())
+                .collect::<Result<Vec<Vec<Expr>>>>()?;
+            Ok(TableFactor::Derived {
+                lateral: false,
+                subquery: Box::new(query_from_body(SetExpr::Values(Values { explicit_row: false, rows }))),
+                alias,
+            })
+        },
+        "PIVOT" => convert_pivot(table_ref, alias),
+        "JOIN" => Ok(TableFactor::NestedJoin {
+            table_with_joins: Box::new(convert_join_tree(table_ref)?),
+            alias,
+        }),
+        other => Err(anyhow::anyhow!("Unsupported table reference type: {}", other)),
+    }
+}
+
+/// Convert a PIVOT or UNPIVOT table reference
+///
+/// An UNPIVOT is a pivot reference with value column names in `unpivot_names`.
+fn convert_pivot(pivot: &Value, alias: Option<TableAlias>) -> Result<TableFactor> {
+    let source = Box::new(convert_table_factor(field(pivot, "source")?)?);
+    let pivot_column = array(pivot, "pivots").first()
+        .ok_or_else(|| anyhow::anyhow!("PIVOT without an ON clause"))?;
+    if array(pivot, "pivots").len() > 1 {
+        tracing::debug!("Only the first PIVOT column is kept in the converted statement");
+    }
+
+    let value_names = array(pivot, "unpivot_names");
+    if let Some(value) = value_names.first().and_then(|v| v.as_str()) {
+        let name = array(pivot_column, "unpivot_names").first()
+            .and_then(|n| n.as_str())
+            .unwrap_or("name");
+        let columns = array(pivot_column, "entries").iter()
+            .map(pivot_entry_name)
+            .collect::<Result<Vec<_>>>()?;
+
+        return Ok(TableFactor::Unpivot {
+            table: source,
+            value: ident(value),
+            name: ident(name),
+            columns,
+            alias,
+        });
+    }
+
+    let aggregate_functions = array(pivot, "aggregates").iter()
+        .map(|aggregate| {
+            let alias = text(aggregate, "alias");
+            Ok(ExprWithAlias {
+                expr: convert_expr(aggregate)?,
+                alias: (!alias.is_empty()).then(|| ident(alias)),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let value_column = array(pivot_column, "pivot_expressions").iter()
+        .map(|e| convert_expr(e).map(|e| expr_ident(&e)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let entries = array(pivot_column, "entries");
+    let value_source = if entries.is_empty() {
+        PivotValueSource::Any(Vec::new())
+    } else {
+        PivotValueSource::List(entries.iter()
+            .map(|entry| {
+                let alias = text(entry, "alias");
+                Ok(ExprWithAlias {
+                    expr: pivot_entry_expr(entry)?,
+                    alias: (!alias.is_empty()).then(|| ident(alias)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?)
+    };
+
+    Ok(TableFactor::Pivot {
+        table: source,
+        aggregate_functions,
+        value_column,
+        value_source,
+        default_on_null: None,
+        alias,
+    })
+}
+
+/// The value(s) a PIVOT entry matches
+fn pivot_entry_expr(entry: &Value) -> Result<Expr> {
+    if let Some(expr) = optional(entry, "expr").or_else(|| optional(entry, "star_expr")) {
+        return convert_expr(expr);
+    }
+
+    let mut values = array(entry, "values").iter()
+        .map(convert_value)
+        .collect::<Result<Vec<_>>>()?;
+    match values.len() {
+        0 => Err(anyhow::anyhow!("PIVOT entry without values")),
+        1 => Ok(values.remove(0)),
+        _ => Ok(Expr::Tuple(values)),
+    }
+}
+
+/// The column an UNPIVOT entry reads
+fn pivot_entry_name(entry: &Value) -> Result<Ident> {
+    if let Some(expr) = optional(entry, "expr").or_else(|| optional(entry, "star_expr")) {
+        return convert_expr(expr).map(|e| expr_ident(&e));
+    }
+
+    array(entry, "values").first()
+        .and_then(|v| v.get("value"))
+        .and_then(|v| v.as_str())
+        .map(ident)
+        .ok_or_else(|| anyhow::anyhow!("UNPIVOT entry without a column"))
+}
+
+/// Convert a DuckDB expression node into a sqlparser Expr
+fn convert_expr(node: &Value) -> Result<Expr> {
+    let class = text(node, "class");
+
+    match class {
+        "COLUMN_REF" => {
+            let mut names: Vec<Ident> = array(node, "column_names").iter()
+                .filter_map(|n| n.as_str())
+                .map(ident)
+                .collect();
+            // Older serializations keep the table and column separately
+            if names.is_empty() {
+                names = [text(node, "table_name"), text(node, "column_name")].into_iter()
+                    .filter(|n| !n.is_empty())
+                    .map(ident)
+                    .collect();
+            }
+            match names.len() {
+                0 => Err(anyhow::anyhow!("COLUMN_REF without a column name")),
+                1 => Ok(Expr::Identifier(names.remove(0))),
+                _ => Ok(Expr::CompoundIdentifier(names)),
+            }
+        },
+        "CONSTANT" => convert_value(field(node, "value")?),
+        "FUNCTION" => convert_function(node),
+        "WINDOW" => convert_window(node),
+        "COMPARISON" => {
+            let left = convert_expr(field(node, "left")?)?;
+            let right = convert_expr(field(node, "right")?)?;
+            match text(node, "type") {
+                "COMPARE_DISTINCT_FROM" => Ok(Expr::IsDistinctFrom(Box::new(operand(left, 5)), Box::new(operand(right, 5)))),
+                "COMPARE_NOT_DISTINCT_FROM" => Ok(Expr::IsNotDistinctFrom(Box::new(operand(left, 5)), Box::new(operand(right, 5)))),
+                other => Ok(binary_op(left, comparison_operator(other)?, right)),
+            }
+        },
+        "CONJUNCTION" => {
+            let op = match text(node, "type") {
+                "CONJUNCTION_AND" => BinaryOperator::And,
+                "CONJUNCTION_OR" => BinaryOperator::Or,
+                other => return Err(anyhow::anyhow!("Unsupported conjunction: {}", other)),
+            };
+            let mut children = array(node, "children").iter().map(convert_expr);
+            let mut result = children.next()
+                .ok_or_else(|| anyhow::anyhow!("Conjunction without children"))??;
+            for child in children {
+                result = binary_op(result, op.clone(), child?);
+            }
+            Ok(result)
+        },
+        "OPERATOR" => convert_operator(node),
+        "CAST" => Ok(Expr::Cast {
+            kind: if flag(node, "try_cast") { CastKind::TryCast } else { CastKind::Cast },
+            expr: Box::new(convert_expr(field(node, "child")?)?),
+            data_type: DataType::Custom(ObjectName(vec![Ident::new(type_name(field(node, "cast_type")?)?)]), Vec::new()),
+            format: None,
+        }),
+        "CASE" => {
+            let mut conditions = Vec::new();
+            let mut results = Vec::new();
+            for check in array(node, "case_checks") {
+                conditions.push(convert_expr(field(check, "when_expr")?)?);
+                results.push(convert_expr(field(check, "then_expr")?)?);
+            }
+            Ok(Expr::Case {
+                operand: None,
+                conditions,
+                results,
+                else_result: optional(node, "else_expr").map(convert_expr).transpose()?.map(Box::new),
+            })
+        },
+        "BETWEEN" => Ok(Expr::Between {
+            expr: Box::new(operand(convert_expr(field(node, "input")?)?, 5)),
+            negated: false,
+            low: Box::new(operand(convert_expr(field(node, "lower")?)?, 5)),
+            high: Box::new(operand(convert_expr(field(node, "upper")?)?, 5)),
+        }),
+        "SUBQUERY" => {
+            let subquery = Box::new(convert_subquery(field(node, "subquery")?)?);
+            match text(node, "subquery_type") {
+                "SCALAR" => Ok(Expr::Subquery(subquery)),
+                "EXISTS" => Ok(Expr::Exists { subquery, negated: false }),
+                "NOT_EXISTS" => Ok(Expr::Exists { subquery, negated: true }),
+                "ANY" => {
+                    let left = operand(convert_expr(field(node, "child")?)?, 5);
+                    match text(node, "comparison_type") {
+                        "COMPARE_EQUAL" | "" => Ok(Expr::InSubquery { expr: Box::new(left), subquery, negated: false }),
+                        other => Ok(Expr::AnyOp {
+                            left: Box::new(left),
+                            compare_op: comparison_operator(other)?,
+                            right: Box::new(Expr::Subquery(subquery)),
+                        }),
+                    }
+                },
+                other => Err(anyhow::anyhow!("Unsupported subquery type: {}", other)),
+            }
+        },
+        "STAR" => {
+            let options = convert_wildcard_options(node)?;
+            if flag(node, "unpacked") {
+                return Err(anyhow::anyhow!("Unpacked *COLUMNS(...) expressions are not supported"));
+            }
+            if options != WildcardAdditionalOptions::default() {
+                return Err(anyhow::anyhow!("Star options are only supported in the select list"));
+            }
+
+            let relation = text(node, "relation_name");
+            let star = if relation.is_empty() {
+                Expr::Wildcard
+            } else {
+                Expr::QualifiedWildcard(ObjectName(vec![ident(relation)]))
+            };
+
+            if !flag(node, "columns") {
+                return Ok(star);
+            }
+
+            // COLUMNS(*), COLUMNS('regex') or COLUMNS(lambda)
+            let arg = match optional(node, "expr") {
+                Some(expr) => convert_expr(expr)?,
+                None => star,
+            };
+            Ok(function_call(ObjectName(vec![Ident::new("COLUMNS")]), vec![FunctionArg::Unnamed(arg.into())]))
+        },
+        "LAMBDA" => {
+            let lhs = convert_expr(field(node, "lhs")?)?;
+            let params = match lhs {
+                Expr::Identifier(param) => OneOrManyWithParens::One(param),
+                Expr::Tuple(params) => OneOrManyWithParens::Many(params.iter().map(expr_ident).collect()),
+                other => return Err(anyhow::anyhow!("Unsupported lambda parameters: {}", other)),
+            };
+            Ok(Expr::Lambda(LambdaFunction {
+                params,
+                body: Box::new(convert_expr(field(node, "expr")?)?),
+            }))
+        },
+        "COLLATE" => Ok(Expr::Collate {
+            expr: Box::new(operand(convert_expr(field(node, "child")?)?, 10)),
+            collation: ObjectName(vec![ident(text(node, "collation"))]),
+        }),
+        "PARAMETER" => Ok(Expr::Value(SqlValue::Placeholder(format!("${}", text(node, "identifier"))))),
+        "POSITIONAL_REFERENCE" => {
+            let index = node.get("index").and_then(|i| i.as_u64())
+                .ok_or_else(|| anyhow::anyhow!("POSITIONAL_REFERENCE missing index"))?;
+            Ok(Expr::Identifier(Ident::new(format!("#{}", index))))
+        },
+        other => Err(anyhow::anyhow!("Unsupported expression class: {} ({})", other, text(node, "type"))),
+    }
+}
+
+/// Convert a FUNCTION node, including the operators DuckDB represents as functions
+fn convert_function(node: &Value) -> Result<Expr> {
+    let name = text(node, "function_name");
+    let children = array(node, "children");
+    let mut args = children.iter().map(convert_expr).collect::<Result<Vec<_>>>()?;
+
+    if flag(node, "is_operator") || !name.chars().any(|c| c.is_alphanumeric()) {
+        if let Some(expr) = convert_operator_function(name, &mut args)? {
+            return Ok(expr);
+        }
+    }
+
+    match (name.to_lowercase().as_str(), args.len()) {
+        ("count_star", 0) => {
+            return Ok(function_call(ObjectName(vec![Ident::new("count")]), vec![FunctionArg::Unnamed(FunctionArgExpr::Wildcard)]));
+        },
+        // {'a': 1, 'b': 2}
+        ("struct_pack", _) if children.iter().all(|c| !text(c, "alias").is_empty()) => {
+            return Ok(Expr::Dictionary(children.iter()
+                .zip(args)
+                .map(|(child, value)| DictionaryField { key: ident(text(child, "alias")), value: Box::new(value) })
+                .collect()));
+        },
+        // [1, 2, 3]
+        ("list_value", _) => return Ok(Expr::Array(Array { elem: args, named: false })),
+        // (1, 2) and multi-parameter lambdas
+        ("row", _) => return Ok(Expr::Tuple(args)),
+        ("like_escape" | "not_like_escape" | "ilike_escape" | "not_ilike_escape", 3) => {
+            let escape_char = match args.pop() {
+                Some(Expr::Value(SqlValue::SingleQuotedString(escape))) => Some(escape),
+                other => return Err(anyhow::anyhow!("Unsupported LIKE escape: {:?}", other)),
+            };
+            let pattern = Box::new(operand(args.pop().unwrap_or(Expr::Value(SqlValue::Null)), 5));
+            let expr = Box::new(operand(args.pop().unwrap_or(Expr::Value(SqlValue::Null)), 5));
+            let negated = name.starts_with("not_");
+            return Ok(if name.contains("ilike") {
+                Expr::ILike { negated, expr, pattern, escape_char }
+            } else {
+                Expr::Like { negated, expr, pattern, escape_char }
+            });
+        },
+        _ => {},
+    }
+
+    let function_args = children.iter()
+        .zip(args)
+        .map(|(child, arg)| {
+            let alias = text(child, "alias");
+            if alias.is_empty() {
+                FunctionArg::Unnamed(arg.into())
+            } else {
+                FunctionArg::Named { name: ident(alias), arg: arg.into(), operator: FunctionArgOperator::Assignment }
+            }
+        })
+        .collect();
+
+    let mut function = match function_call(function_name(node), function_args) {
+        Expr::Function(function) => function,
+        _ => unreachable!("function_call always builds a function"),
+    };
+
+    let order_by = node.get("order_bys").map(|m| convert_order_list(array(m, "orders"))).transpose()?.unwrap_or_default();
+    if let FunctionArguments::List(list) = &mut function.args {
+        if flag(node, "distinct") {
+            list.duplicate_treatment = Some(DuplicateTreatment::Distinct);
+        }
+        if !order_by.is_empty() {
+            list.clauses.push(FunctionArgumentClause::OrderBy(order_by));
+        }
+    }
+    function.filter = optional(node, "filter").map(convert_expr).transpose()?.map(Box::new);
+
+    Ok(Expr::Function(function))
+}
+
+/// Convert arithmetic, string and comparison operators that DuckDB represents as functions
+fn convert_operator_function(name: &str, args: &mut Vec<Expr>) -> Result<Option<Expr>> {
+    if args.len() == 1 {
+        let op = match name {
+            "-" => UnaryOperator::Minus,
+            "+" => UnaryOperator::Plus,
+            "~" => UnaryOperator::PGBitwiseNot,
+            "@" => UnaryOperator::PGAbs,
+            "!__postfix" => UnaryOperator::PGPostfixFactorial,
+            _ => return Ok(None),
+        };
+        return Ok(Some(Expr::UnaryOp { op, expr: Box::new(operand(args.remove(0), 9)) }));
+    }
+
+    if args.len() != 2 {
+        return Ok(None);
+    }
+
+    let right = args.pop().unwrap_or(Expr::Value(SqlValue::Null));
+    let left = args.pop().unwrap_or(Expr::Value(SqlValue::Null));
+
+    let like = |negated: bool, case_insensitive: bool, left: Expr, right: Expr| {
+        let expr = Box::new(operand(left, 5));
+        let pattern = Box::new(operand(right, 5));
+        if case_insensitive {
+            Expr::ILike { negated, expr, pattern, escape_char: None }
+        } else {
+            Expr::Like { negated, expr, pattern, escape_char: None }
+        }
+    };
+
+    let op = match name {
+        "~~" => return Ok(Some(like(false, false, left, right))),
+        "!~~" => return Ok(Some(like(true, false, left, right))),
+        "~~*" => return Ok(Some(like(false, true, left, right))),
+        "!~~*" => return Ok(Some(like(true, true, left, right))),
+        "+" => BinaryOperator::Plus,
+        "-" => BinaryOperator::Minus,
+        "*" => BinaryOperator::Multiply,
+        "/" => BinaryOperator::Divide,
+        "//" => BinaryOperator::DuckIntegerDivide,
+        "%" => BinaryOperator::Modulo,
+        "||" => BinaryOperator::StringConcat,
+        "&" => BinaryOperator::BitwiseAnd,
+        "|" => BinaryOperator::BitwiseOr,
+        "<<" => BinaryOperator::PGBitwiseShiftLeft,
+        ">>" => BinaryOperator::PGBitwiseShiftRight,
+        "^" | "**" => BinaryOperator::PGExp,
+        "->" => BinaryOperator::Arrow,
+        "->>" => BinaryOperator::LongArrow,
+        "@>" => BinaryOperator::AtArrow,
+        "<@" => BinaryOperator::ArrowAt,
+        "&&" => BinaryOperator::PGOverlap,
+        "^@" => BinaryOperator::PGStartsWith,
+        // Any other symbolic operator, such as `~~~` for GLOB, is printed as written
+        other if !other.chars().any(|c| c.is_alphanumeric()) => BinaryOperator::Custom(other.to_string()),
+        _ => {
+            args.push(left);
+            args.push(right);
+            return Ok(None);
+        },
+    };
+
+    Ok(Some(binary_op(left, op, right)))
+}
+
+/// Convert an OPERATOR node such as NOT, IS NULL, IN or a subscript
+fn convert_operator(node: &Value) -> Result<Expr> {
+    let mut children = array(node, "children").iter().map(convert_expr).collect::<Result<Vec<_>>>()?;
+    let operator_type = text(node, "type");
+
+    let first = |children: &mut Vec<Expr>| -> Result<Expr> {
+        if children.is_empty() {
+            return Err(anyhow::anyhow!("{} without an operand", operator_type));
+        }
+        Ok(children.remove(0))
+    };
+
+    match operator_type {
+        "OPERATOR_NOT" => Ok(Expr::UnaryOp { op: UnaryOperator::Not, expr: Box::new(operand(first(&mut children)?, 3)) }),
+        "OPERATOR_IS_NULL" => Ok(Expr::IsNull(Box::new(operand(first(&mut children)?, 5)))),
+        "OPERATOR_IS_NOT_NULL" => Ok(Expr::IsNotNull(Box::new(operand(first(&mut children)?, 5)))),
+        "COMPARE_IN" | "COMPARE_NOT_IN" => Ok(Expr::InList {
+            expr: Box::new(operand(first(&mut children)?, 5)),
+            list: children,
+            negated: operator_type == "COMPARE_NOT_IN",
+        }),
+        "OPERATOR_COALESCE" => Ok(function_call(
+            ObjectName(vec![Ident::new("COALESCE")]),
+            children.into_iter().map(|c| FunctionArg::Unnamed(c.into())).collect(),
+        )),
+        "OPERATOR_TRY" => Ok(function_call(
+            ObjectName(vec![Ident::new("TRY")]),
+            children.into_iter().map(|c| FunctionArg::Unnamed(c.into())).collect(),
+        )),
+        "ARRAY_EXTRACT" | "STRUCT_EXTRACT" => {
+            let expr = operand(first(&mut children)?, 10);
+            let index = first(&mut children)?;
+            Ok(Expr::Subscript { expr: Box::new(expr), subscript: Box::new(Subscript::Index { index }) })
+        },
+        "ARRAY_SLICE" => {
+            let expr = operand(first(&mut children)?, 10);
+            let mut bounds = children.into_iter().map(|bound| match bound {
+                // Omitted bounds are serialized as empty lists
+                Expr::Array(Array { elem, .. }) if elem.is_empty() => None,
+                bound => Some(bound),
+            });
+            Ok(Expr::Subscript {
+                expr: Box::new(expr),
+                subscript: Box::new(Subscript::Slice {
+                    lower_bound: bounds.next().flatten(),
+                    upper_bound: bounds.next().flatten(),
+                    stride: bounds.next().flatten(),
+                }),
+            })
+        },
+        other => Err(anyhow::anyhow!("Unsupported operator: {}", other)),
+    }
+}
+
+/// Convert a WINDOW node into a function call with an OVER clause
+fn convert_window(node: &Value) -> Result<Expr> {
+    let mut args: Vec<FunctionArg> = array(node, "children").iter()
+        .map(|child| convert_expr(child).map(|e| FunctionArg::Unnamed(e.into())))
+        .collect::<Result<Vec<_>>>()?;
+    // lead/lag take their offset and default after the value
+    for key in ["offset_expr", "default_expr"] {
+        if let Some(expr) = optional(node, key) {
+            args.push(FunctionArg::Unnamed(convert_expr(expr)?.into()));
+        }
+    }
+
+    let mut function = match function_call(function_name(node), args) {
+        Expr::Function(function) => function,
+        _ => unreachable!("function_call always builds a function"),
+    };
+
+    let arg_orders = convert_order_list(array(node, "arg_orders"))?;
+    if let FunctionArguments::List(list) = &mut function.args {
+        if flag(node, "distinct") {
+            list.duplicate_treatment = Some(DuplicateTreatment::Distinct);
+        }
+        if !arg_orders.is_empty() {
+            list.clauses.push(FunctionArgumentClause::OrderBy(arg_orders));
+        }
+    }
+    function.filter = optional(node, "filter_expr").map(convert_expr).transpose()?.map(Box::new);
+    if flag(node, "ignore_nulls") {
+        function.null_treatment = Some(NullTreatment::IgnoreNulls);
+    }
+
+    function.over = Some(WindowType::WindowSpec(WindowSpec {
+        window_name: None,
+        partition_by: array(node, "partitions").iter().map(convert_expr).collect::<Result<Vec<_>>>()?,
+        order_by: convert_order_list(array(node, "orders"))?,
+        window_frame: convert_window_frame(node)?,
+    }));
+
+    Ok(Expr::Function(function))
+}
+
+/// The explicit frame of a window, or None for DuckDB's default frame
+fn convert_window_frame(node: &Value) -> Result<Option<WindowFrame>> {
+    let start = text(node, "start");
+    let end = text(node, "end");
+
+    if matches!((start, end), ("" | "INVALID", _) | ("UNBOUNDED_PRECEDING", "CURRENT_ROW_RANGE")) {
+        return Ok(None);
+    }
+
+    let units = if start.ends_with("_RANGE") || end.ends_with("_RANGE") {
+        WindowFrameUnits::Range
+    } else if start.ends_with("_GROUPS") || end.ends_with("_GROUPS") {
+        WindowFrameUnits::Groups
+    } else {
+        WindowFrameUnits::Rows
+    };
+
+    let bound = |boundary: &str, expr_key: &str| -> Result<WindowFrameBound> {
+        let expr = || -> Result<Option<Box<Expr>>> {
+            Ok(Some(Box::new(convert_expr(field(node, expr_key)?)?)))
+        };
+        Ok(match boundary {
+            "UNBOUNDED_PRECEDING" => WindowFrameBound::Preceding(None),
+            "UNBOUNDED_FOLLOWING" => WindowFrameBound::Following(None),
+            b if b.starts_with("CURRENT_ROW") => WindowFrameBound::CurrentRow,
+            b if b.starts_with("EXPR_PRECEDING") => WindowFrameBound::Preceding(expr()?),
+            b if b.starts_with("EXPR_FOLLOWING") => WindowFrameBound::Following(expr()?),
+            other => return Err(anyhow::anyhow!("Unsupported window boundary: {}", other)),
+        })
+    };
+
+    Ok(Some(WindowFrame {
+        units,
+        start_bound: bound(start, "start_expr")?,
+        end_bound: Some(bound(end, "end_expr")?),
+    }))
+}
+
+fn convert_order_list(orders: &[Value]) -> Result<Vec<OrderByExpr>> {
+    orders.iter()
+        .map(|order| {
+            Ok(OrderByExpr {
+                expr: convert_expr(field(order, "expression")?)?,
+                asc: match text(order, "type") {
+                    "ASCENDING" => Some(true),
+                    "DESCENDING" => Some(false),
+                    _ => None,
+                },
+                nulls_first: match text(order, "null_order") {
+                    "NULLS_FIRST" => Some(true),
+                    "NULLS_LAST" => Some(false),
+                    _ => None,
+                },
+                with_fill: None,
+            })
+        })
+        .collect()
+}
+
+/// Convert a serialized DuckDB value into a literal expression
+fn convert_value(value: &Value) -> Result<Expr> {
+    if flag(value, "is_null") {
+        return Ok(Expr::Value(SqlValue::Null));
+    }
+
+    let logical_type = field(value, "type")?;
+    let type_id = text(logical_type, "id");
+    let data = field(value, "value")?;
+
+    let number = |n: String| Ok(Expr::Value(SqlValue::Number(n, false)));
+
+    match type_id {
+        "BOOLEAN" => data.as_bool()
+            .map(|b| Expr::Value(SqlValue::Boolean(b)))
+            .ok_or_else(|| anyhow::anyhow!("BOOLEAN value is not a boolean")),
+        "TINYINT" | "SMALLINT" | "INTEGER" | "BIGINT" | "UTINYINT" | "USMALLINT" | "UINTEGER" | "UBIGINT"
+        | "FLOAT" | "DOUBLE" => match data {
+            Value::Number(n) => number(n.to_string()),
+            _ => Err(anyhow::anyhow!("{} value is not a number", type_id)),
+        },
+        "HUGEINT" | "UHUGEINT" => number(wide_integer(data)?.to_string()),
+        "DECIMAL" => {
+            // Decimals are stored as integers scaled by 10^scale
+            let scale = logical_type.get("type_info")
+                .and_then(|info| info.get("scale"))
+                .and_then(|s| s.as_u64())
+                .unwrap_or(0) as usize;
+            let raw = match data {
+                Value::String(s) => return number(s.clone()),
+                Value::Number(n) => n.as_i64().map(i128::from).ok_or_else(|| anyhow::anyhow!("Invalid DECIMAL value: {}", n))?,
+                other => wide_integer(other)?,
+            };
+            number(format_decimal(raw, scale))
+        },
+        "VARCHAR" => data.as_str()
+            .map(|s| Expr::Value(SqlValue::SingleQuotedString(s.to_string())))
+            .ok_or_else(|| anyhow::anyhow!("VARCHAR value is not a string")),
+        "INTERVAL" => {
+            let part = |key: &str| data.get(key).and_then(|v| v.as_i64()).unwrap_or(0);
+            Ok(Expr::TypedString {
+                data_type: DataType::Interval,
+                value: format!("{} months {} days {} microseconds", part("months"), part("days"), part("micros")),
+            })
+        },
+        // Other types are only supported in their textual form, e.g. DATE '2024-01-01'
+        _ => match data.as_str() {
+            Some(s) => Ok(Expr::TypedString {
+                data_type: DataType::Custom(ObjectName(vec![Ident::new(type_name(logical_type)?)]), Vec::new()),
+                value: s.to_string(),
+            }),
+            None => Err(anyhow::anyhow!("Unsupported constant of type {}", type_id)),
+        },
+    }
+}
+
+/// A 128-bit integer serialized as `{"upper": ..., "lower": ...}`
+fn wide_integer(data: &Value) -> Result<i128> {
+    let upper = data.get("upper").and_then(|u| u.as_i64());
+    let lower = data.get("lower").and_then(|l| l.as_u64());
+    match (upper, lower) {
+        (Some(upper), Some(lower)) => Ok((i128::from(upper) << 64) | i128::from(lower)),
+        _ => Err(anyhow::anyhow!("Invalid 128-bit integer value: {}", data)),
+    }
+}
+
+fn format_decimal(raw: i128, scale: usize) -> String {
+    if scale == 0 {
+        return raw.to_string();
+    }
+    let digits = format!("{:0>width$}", raw.unsigned_abs(), width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", if raw < 0 { "-" } else { "" }, whole, fraction)
+}
+
+/// Render a serialized DuckDB logical type as SQL
+fn type_name(logical_type: &Value) -> Result<String> {
+    let info = optional(logical_type, "type_info");
+    let child = |key: &str| -> Result<String> {
+        info.and_then(|i| i.get(key))
+            .ok_or_else(|| anyhow::anyhow!("Type info missing {}", key))
+            .and_then(type_name)
+    };
+
+    let name = match text(logical_type, "id") {
+        "DECIMAL" => match info.and_then(|i| Some((i.get("width")?.as_u64()?, i.get("scale")?.as_u64()?))) {
+            Some((width, scale)) => format!("DECIMAL({},{})", width, scale),
+            None => "DECIMAL".to_string(),
+        },
+        "LIST" => format!("{}[]", child("child_type")?),
+        "ARRAY" => format!("{}[{}]", child("child_type")?, info.and_then(|i| i.get("size")).and_then(|s| s.as_u64()).unwrap_or(0)),
+        "STRUCT" | "MAP" | "UNION" => {
+            let fields = info.map(|i| array(i, "child_types")).unwrap_or_default().iter()
+                .map(|f| Ok(format!("{} {}", ident(text(f, "first")), type_name(field(f, "second")?)?)))
+                .collect::<Result<Vec<_>>>()?;
+            format!("{}({})", text(logical_type, "id"), fields.join(", "))
+        },
+        "USER" => {
+            let name = info.map(|i| text(i, "user_type_name")).unwrap_or_default();
+            if name.is_empty() {
+                return Err(anyhow::anyhow!("USER type without a name"));
+            }
+            name.to_string()
+        },
+        "TIMESTAMP_TZ" | "TIMESTAMP WITH TIME ZONE" => "TIMESTAMPTZ".to_string(),
+        "TIME_TZ" | "TIME WITH TIME ZONE" => "TIMETZ".to_string(),
+        "TIMESTAMP_SEC" => "TIMESTAMP_S".to_string(),
+        "" => return Err(anyhow::anyhow!("Logical type missing id")),
+        id => id.to_string(),
+    };
+
+    Ok(name)
+}
+
+fn comparison_operator(comparison_type: &str) -> Result<BinaryOperator> {
+    match comparison_type {
+        "COMPARE_EQUAL" => Ok(BinaryOperator::Eq),
+        "COMPARE_NOTEQUAL" => Ok(BinaryOperator::NotEq),
+        "COMPARE_LESSTHAN" => Ok(BinaryOperator::Lt),
+        "COMPARE_GREATERTHAN" => Ok(BinaryOperator::Gt),
+        "COMPARE_LESSTHANOREQUALTO" => Ok(BinaryOperator::LtEq),
+        "COMPARE_GREATERTHANOREQUALTO" => Ok(BinaryOperator::GtEq),
+        other => Err(anyhow::anyhow!("Unsupported comparison: {}", other)),
+    }
+}
+
+/// Build a binary operation, parenthesizing operands that bind less tightly
+fn binary_op(left: Expr, op: BinaryOperator, right: Expr) -> Expr {
+    let precedence = binary_precedence(&op);
+    Expr::BinaryOp {
+        left: Box::new(operand(left, precedence)),
+        op,
+        // Operators are left associative, so an equal-precedence right operand needs parentheses
+        right: Box::new(operand(right, precedence + 1)),
+    }
+}
+
+/// Wrap an expression in parentheses if it binds less tightly than `min_precedence`
+///
+/// Converted trees have no explicit parentheses, so without this `(a + b) * c` would be
+/// printed as `a + b * c`.
+fn operand(expr: Expr, min_precedence: u8) -> Expr {
+    if precedence(&expr) < min_precedence {
+        Expr::Nested(Box::new(expr))
+    } else {
+        expr
+    }
+}
+
+fn precedence(expr: &Expr) -> u8 {
+    match expr {
+        Expr::BinaryOp { op, .. } => binary_precedence(op),
+        Expr::UnaryOp { op: UnaryOperator::Not, .. } => 3,
+        Expr::IsNull(_) | Expr::IsNotNull(_) | Expr::IsDistinctFrom(..) | Expr::IsNotDistinctFrom(..)
+        | Expr::Like { .. } | Expr::ILike { .. } | Expr::SimilarTo { .. } | Expr::InList { .. }
+        | Expr::InSubquery { .. } | Expr::Between { .. } | Expr::AnyOp { .. } | Expr::AllOp { .. } => 4,
+        Expr::UnaryOp { .. } => 9,
+        Expr::Collate { .. } | Expr::Lambda(_) => 1,
+        _ => 10,
+    }
+}
+
+fn binary_precedence(op: &BinaryOperator) -> u8 {
+    match op {
+        BinaryOperator::Or => 1,
+        BinaryOperator::And => 2,
+        BinaryOperator::Eq | BinaryOperator::NotEq | BinaryOperator::Lt | BinaryOperator::Gt
+        | BinaryOperator::LtEq | BinaryOperator::GtEq => 4,
+        BinaryOperator::Plus | BinaryOperator::Minus => 6,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Modulo
+        | BinaryOperator::DuckIntegerDivide => 7,
+        BinaryOperator::PGExp => 8,
+        _ => 5,
+    }
+}
+
+/// Split an AND chain into its conjuncts
+fn split_conjunction(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            let mut conjuncts = split_conjunction(*left);
+            conjuncts.extend(split_conjunction(*right));
+            conjuncts
+        },
+        Expr::Nested(inner) if matches!(*inner, Expr::BinaryOp { op: BinaryOperator::And, .. }) => split_conjunction(*inner),
+        expr => vec![expr],
+    }
+}
+
+fn join_conjunction(conjuncts: Vec<Expr>) -> Option<Expr> {
+    conjuncts.into_iter().reduce(|left, right| binary_op(left, BinaryOperator::And, right))
+}
+
+fn function_call(name: ObjectName, args: Vec<FunctionArg>) -> Expr {
+    Expr::Function(Function {
+        name,
+        parameters: FunctionArguments::None,
+        args: FunctionArguments::List(FunctionArgumentList {
+            duplicate_treatment: None,
+            args,
+            clauses: Vec::new(),
+        }),
+        filter: None,
+        null_treatment: None,
+        over: None,
+        within_group: Vec::new(),
+    })
+}
+
+/// The (optionally schema-qualified) name of a FUNCTION or WINDOW node
+fn function_name(node: &Value) -> ObjectName {
+    ObjectName(["catalog", "schema", "function_name"].iter()
+        .map(|key| text(node, key))
+        .filter(|part| !part.is_empty())
+        .map(ident)
+        .collect())
+}
+
+fn table(name: ObjectName, alias: Option<TableAlias>, args: Option<Vec<FunctionArg>>) -> TableFactor {
+    TableFactor::Table {
+        name,
+        alias,
+        args,
+        with_hints: Vec::new(),
+        version: None,
+        with_ordinality: false,
+        partitions: Vec::new(),
+    }
+}
+
+fn table_alias(table_ref: &Value) -> Option<TableAlias> {
+    let alias = text(table_ref, "alias");
+    if alias.is_empty() {
+        return None;
+    }
+
+    Some(TableAlias {
+        name: ident(alias),
+        columns: array(table_ref, "column_name_alias").iter().filter_map(|c| c.as_str()).map(ident).collect(),
+    })
+}
+
+fn query_from_body(body: SetExpr) -> Query {
+    Query {
+        with: None,
+        body: Box::new(body),
+        order_by: None,
+        limit: None,
+        offset: None,
+        fetch: None,
+        locks: Vec::new(),
+        limit_by: Vec::new(),
+        for_clause: None,
+        format_clause: None,
+        settings: None,
+    }
+}
+
+/// An identifier, quoted unless it is a plain name
+fn ident(name: &str) -> Ident {
+    let is_plain = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_plain {
+        Ident::new(name)
+    } else {
+        Ident::with_quote('"', name)
+    }
+}
+
+/// The identifier an expression names, for places sqlparser only accepts identifiers
+fn expr_ident(expr: &Expr) -> Ident {
+    match expr {
+        Expr::Identifier(ident) => ident.clone(),
+        Expr::CompoundIdentifier(parts) if !parts.is_empty() => parts[parts.len() - 1].clone(),
+        other => Ident::new(other.to_string()),
+    }
+}
+
+fn node_type(node: &Value) -> Result<&str> {
+    node.get("type")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow::anyhow!("DuckDB AST node missing type field"))
+}
+
+fn field<'a>(node: &'a Value, key: &str) -> Result<&'a Value> {
+    optional(node, key).ok_or_else(|| anyhow::anyhow!("DuckDB AST node missing {}", key))
+}
+
+/// A field that is present and not null
+fn optional<'a>(node: &'a Value, key: &str) -> Option<&'a Value> {
+    node.get(key).filter(|v| !v.is_null())
+}
+
+fn text<'a>(node: &'a Value, key: &str) -> &'a str {
+    node.get(key).and_then(|v| v.as_str()).unwrap_or("")
+}
+
+fn flag(node: &Value, key: &str) -> bool {
+    node.get(key).and_then(|v| v.as_bool()).unwrap_or(false)
+}
+
+fn array<'a>(node: &'a Value, key: &str) -> &'a [Value] {
+    node.get(key).and_then(|v| v.as_array()).map(Vec::as_slice).unwrap_or_default()
+}
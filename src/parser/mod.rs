@@ -1,5 +1,8 @@
 pub mod config;
 pub mod dependencies;
+pub mod diagnostics;
+pub mod duckdb_ast;
 pub mod lineage;
+pub mod selection;
 pub mod sql;
 pub mod ast_test;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::parser::dependencies::Dependency;
+
+/// A pattern naming models to include in or exclude from a run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    /// Every model carrying a tag, written `tag:name`
+    Tag(String),
+    /// A single model, written as its name
+    Model(String),
+}
+
+impl FromStr for Selector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.split_once(':') {
+            Some(("tag", tag)) if !tag.trim().is_empty() => Ok(Selector::Tag(tag.trim().to_string())),
+            Some((kind, _)) => Err(format!("Invalid selector: {} (expected `tag:<name>` or a model name, not `{}:`)", s, kind)),
+            None if s.is_empty() => Err("Invalid selector: empty".to_string()),
+            None => Ok(Selector::Model(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Tag(tag) => write!(f, "tag:{}", tag),
+            Selector::Model(model) => write!(f, "{}", model),
+        }
+    }
+}
+
+impl Selector {
+    /// Names of the models the selector matches
+    fn matches<'a>(&self, dependencies: &'a HashMap<String, Dependency>) -> Result<Vec<&'a String>> {
+        let matched: Vec<&String> = dependencies.iter()
+            .filter(|(name, dep)| match self {
+                Selector::Tag(tag) => dep.config.as_ref().is_some_and(|config| config.has_tag(tag)),
+                Selector::Model(model) => name.eq_ignore_ascii_case(model),
+            })
+            .map(|(name, _)| name)
+            .collect();
+
+        // A selector that matches nothing is almost always a typo
+        if matched.is_empty() {
+            return Err(match self {
+                Selector::Tag(tag) => anyhow::anyhow!("No model is tagged {}", tag),
+                Selector::Model(model) => anyhow::anyhow!("No model named {}", model),
+            });
+        }
+
+        Ok(matched)
+    }
+}
+
+/// Models chosen for a run with `--select` and `--exclude`
+#[derive(Debug, Clone, Default)]
+pub struct Selection {
+    /// Models to run; every model when empty
+    pub select: Vec<Selector>,
+    /// Models to leave out, applied after `select`
+    pub exclude: Vec<Selector>,
+}
+
+impl Selection {
+    /// Parse selectors given on the command line
+    ///
+    /// # Arguments
+    ///
+    /// * `select` - Selectors for the models to run
+    /// * `exclude` - Selectors for the models to leave out
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Selection>` - Parsed selection, or the first invalid selector
+    pub fn parse(select: &[String], exclude: &[String]) -> Result<Self> {
+        let parse = |selectors: &[String]| -> Result<Vec<Selector>> {
+            selectors.iter()
+                .map(|s| s.parse::<Selector>().map_err(|e| anyhow::anyhow!(e)))
+                .collect()
+        };
+
+        Ok(Self {
+            select: parse(select)?,
+            exclude: parse(exclude)?,
+        })
+    }
+
+    /// Whether the selection keeps every model
+    pub fn is_empty(&self) -> bool {
+        self.select.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Names of the models the selection keeps
+    ///
+    /// Upstream models are not added automatically: a model that reads an unselected
+    /// model uses whatever the last run left in the database.
+    ///
+    /// # Arguments
+    ///
+    /// * `dependencies` - Map of model names to their dependencies
+    ///
+    /// # Returns
+    ///
+    /// * `Result<HashSet<String>>` - Selected model names, or an error for a selector that matches nothing
+    pub fn apply(&self, dependencies: &HashMap<String, Dependency>) -> Result<HashSet<String>> {
+        let mut selected: HashSet<String> = if self.select.is_empty() {
+            dependencies.keys().cloned().collect()
+        } else {
+            let mut selected = HashSet::new();
+            for selector in &self.select {
+                selected.extend(selector.matches(dependencies)?.into_iter().cloned());
+            }
+            selected
+        };
+
+        for selector in &self.exclude {
+            for name in selector.matches(dependencies)? {
+                selected.remove(name);
+            }
+        }
+
+        Ok(selected)
+    }
+}
@@ -2,13 +2,17 @@ use anyhow::{Context, Result};
 use duckdb::Connection;
 use serde_json::Value;
 use sqlparser::ast::{
-    Expr, Ident, Query, Select, SelectItem, OrderBy, Distinct, FunctionArguments,
-    SetExpr, SetOperator, SetQuantifier, Statement, TableFactor,
-    Value as SqlValue, GroupByExpr
+    Expr, Ident, Query, Select, SelectItem, FunctionArguments,
+    BinaryOperator, JoinConstraint, JoinOperator, SetExpr, Statement, TableFactor,
+    Value as SqlValue
 };
 use sqlparser::dialect::{DuckDbDialect, GenericDialect};
-use sqlparser::parser::Parser;
-use std::collections::HashSet;
+use sqlparser::parser::{Parser, ParserError};
+use sqlparser::tokenizer::{Token, Tokenizer};
+use super::diagnostics::{ParseDiagnostic, ParserMessage, SourceLocation};
+use super::duckdb_ast;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 
 /// Parse SQL string into AST
 ///
@@ -21,27 +25,185 @@ use std::collections::HashSet;
 ///
 /// * `Vec<Statement>` - Vector of parsed SQL statements
 pub fn parse_sql(sql: &str, dialect_name: &str) -> Result<Vec<Statement>> {
-    // If using DuckDB dialect, attempt to use DuckDB's built-in parser first
-    if dialect_name.to_lowercase() == "duckdb" {
-        match parse_with_duckdb_and_convert(sql) {
+    SqlParser::new(dialect_name).parse(sql)
+}
+
+/// SQL parser that reuses one in-memory DuckDB connection across files
+///
+/// With the DuckDB dialect each file is first serialized with DuckDB's own parser and
+/// converted, falling back to sqlparser. If `json_serialize_sql` turns out to be
+/// unavailable the connection is dropped and later files go straight to sqlparser.
+pub struct SqlParser {
+    /// SQL dialect to use
+    dialect: String,
+    /// Connection used for `json_serialize_sql`, or None once it proved unusable
+    duckdb: Option<Connection>,
+}
+
+impl SqlParser {
+    /// Create a parser for a dialect
+    pub fn new(dialect: &str) -> Self {
+        let dialect = dialect.to_lowercase();
+
+        let duckdb = if dialect == "duckdb" {
+            match Connection::open_in_memory() {
+                Ok(conn) => {
+                    if let Ok(version) = conn.query_row("SELECT version()", [], |row| row.get::<_, String>(0)) {
+                        tracing::debug!("DuckDB version: {}", version);
+                    }
+                    Some(conn)
+                },
+                Err(e) => {
+                    tracing::debug!("Failed to open DuckDB connection for parsing: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Self { dialect, duckdb }
+    }
+
+    /// Parse SQL string into AST
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SQL string to parse
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Statement>` - Vector of parsed SQL statements
+    pub fn parse(&mut self, sql: &str) -> Result<Vec<Statement>> {
+        self.parse_file(sql, None)
+    }
+
+    /// Parse the SQL of a model file into AST
+    ///
+    /// If no parser accepts the SQL the error is a [`ParseDiagnostic`] naming the file,
+    /// the line and column, and what each parser reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `sql` - SQL string to parse
+    /// * `file` - Path of the file the SQL was read from, used in diagnostics
+    ///
+    /// # Returns
+    ///
+    /// * `Vec<Statement>` - Vector of parsed SQL statements
+    pub fn parse_file(&mut self, sql: &str, file: Option<&str>) -> Result<Vec<Statement>> {
+        let mut messages = Vec::new();
+
+        // If using DuckDB dialect, attempt to use DuckDB's built-in parser first
+        if let Some(statements) = self.parse_with_duckdb_connection(sql, &mut messages) {
+            return Ok(statements);
+        }
+
+        let error = match self.parse_with_sqlparser(sql) {
             Ok(statements) => return Ok(statements),
+            Err(e) => e,
+        };
+
+        // DuckDB only serializes SELECT statements and sqlparser rejects DuckDB-only syntax,
+        // so a file mixing DDL with such syntax is parsed one statement at a time
+        if let Some(statements) = self.parse_each_statement(sql) {
+            return Ok(statements);
+        }
+
+        messages.push(ParserMessage::from_sqlparser(&error));
+        Err(ParseDiagnostic::new(sql, file, messages).into())
+    }
+
+    /// Parse SQL with the DuckDB connection, recording why it could not be used
+    fn parse_with_duckdb_connection(&mut self, sql: &str, messages: &mut Vec<ParserMessage>) -> Option<Vec<Statement>> {
+        let conn = self.duckdb.as_ref()?;
+        match serialize_with_duckdb(conn, sql) {
+            Ok(duckdb_ast) if duckdb_ast.get("error").and_then(|e| e.as_bool()) == Some(true) => {
+                let message = duckdb_parser_message(sql, &duckdb_ast);
+                tracing::debug!("DuckDB rejected the SQL, falling back to sqlparser: {}", message);
+                messages.push(message);
+            },
+            Ok(duckdb_ast) => match duckdb_ast::convert_statements(&duckdb_ast) {
+                Ok(statements) => return Some(statements),
+                Err(e) => {
+                    tracing::debug!("DuckDB AST conversion failed, falling back to sqlparser: {}", e);
+                    messages.push(ParserMessage {
+                        parser: "duckdb".to_string(),
+                        message: format!("parsed, but the AST could not be converted: {:#}", e),
+                        location: None,
+                    });
+                }
+            },
+            Err(e) if duckdb_serializer_available(conn) => {
+                tracing::debug!("DuckDB could not serialize this SQL, falling back to sqlparser: {:#}", e);
+                messages.push(ParserMessage {
+                    parser: "duckdb".to_string(),
+                    message: format!("{:#}", e),
+                    location: None,
+                });
+            },
             Err(e) => {
-                tracing::debug!("DuckDB parser failed, falling back to sqlparser: {}", e);
-                // Fall back to sqlparser
+                tracing::debug!("DuckDB parser unavailable, using sqlparser for all files: {:#}", e);
+                self.duckdb = None;
             }
         }
+        None
+    }
+
+    /// Parse SQL with sqlparser in the parser's dialect
+    fn parse_with_sqlparser(&self, sql: &str) -> std::result::Result<Vec<Statement>, ParserError> {
+        if self.dialect == "duckdb" {
+            Parser::parse_sql(&DuckDbDialect {}, sql)
+        } else {
+            Parser::parse_sql(&GenericDialect {}, sql)
+        }
+    }
+
+    /// Parse each statement of a multi-statement file with whichever parser accepts it
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Vec<Statement>>` - One tree per statement, or None if any statement is rejected by both parsers
+    fn parse_each_statement(&mut self, sql: &str) -> Option<Vec<Statement>> {
+        self.duckdb.as_ref()?;
+        let sources = split_statements(sql).ok().filter(|sources| sources.len() > 1)?;
+
+        let mut statements = Vec::new();
+        for source in &sources {
+            let parsed = match self.parse_with_duckdb_connection(source, &mut Vec::new()) {
+                Some(parsed) => parsed,
+                None => self.parse_with_sqlparser(source).ok()?,
+            };
+            // Each tree must line up with its source text, which is what gets run
+            if parsed.len() != 1 {
+                return None;
+            }
+            statements.extend(parsed);
+        }
+        tracing::debug!("Parsed {} statements one at a time", statements.len());
+        Some(statements)
+    }
+}
+
+/// Read the error message and position out of a failed `json_serialize_sql` result
+fn duckdb_parser_message(sql: &str, duckdb_ast: &Value) -> ParserMessage {
+    let message = duckdb_ast.get("error_message")
+        .and_then(|m| m.as_str())
+        .unwrap_or("unknown error")
+        .to_string();
+
+    // The position is a byte offset, serialized as a string by some versions
+    let position = duckdb_ast.get("position").and_then(|p| match p {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    });
+
+    ParserMessage {
+        parser: "duckdb".to_string(),
+        message,
+        location: position.map(|offset| SourceLocation::from_offset(sql, offset as usize)),
     }
-    
-    // Parse SQL with sqlparser
-    let statements = if dialect_name.to_lowercase() == "duckdb" {
-        let dialect = DuckDbDialect {};
-        Parser::parse_sql(&dialect, sql)
-    } else {
-        let dialect = GenericDialect {};
-        Parser::parse_sql(&dialect, sql)
-    }.context("Failed to parse SQL")?;
-    
-    Ok(statements)
 }
 
 /// Parse SQL using DuckDB's built-in parser and convert to sqlparser Statements
@@ -60,1171 +222,129 @@ pub fn parse_with_duckdb_and_convert(sql: &str) -> Result<Vec<Statement>> {
     tracing::debug!("DuckDB AST: {}", serde_json::to_string_pretty(&duckdb_ast)?);
     
     // Convert DuckDB AST to sqlparser Statement objects
-    convert_duckdb_ast_to_statements(&duckdb_ast)
+    duckdb_ast::convert_statements(&duckdb_ast)
 }
 
-
-/// Convert DuckDB AST to sqlparser Statement objects
+/// Parse SQL using DuckDB's built-in parser
 ///
 /// # Arguments
 ///
-/// * `duckdb_ast` - The JSON AST from DuckDB
+/// * `sql` - SQL string to parse
 ///
 /// # Returns
 ///
-/// * `Result<Vec<Statement>>` - Vector of parsed SQL statements or error
-fn convert_duckdb_ast_to_statements(duckdb_ast: &Value) -> Result<Vec<Statement>> {
-    // Save AST to a file for debugging
-    let ast_string = serde_json::to_string_pretty(duckdb_ast)?;
-    let debug_file_path = "duckdb_ast_debug.json";
-    std::fs::write(debug_file_path, &ast_string)?;
-    tracing::info!("Saved DuckDB AST to {}", debug_file_path);
-    
-    // Check if we have a valid AST structure
-    if !duckdb_ast.is_object() {
-        return Err(anyhow::anyhow!("DuckDB AST is not a valid JSON object"));
-    }
-    
-    // Check for errors
-    if let Some(error) = duckdb_ast.get("error") {
-        if error.as_bool() == Some(true) {
-            if let Some(message) = duckdb_ast.get("error_message") {
-                return Err(anyhow::anyhow!("DuckDB parser error: {}", message));
-            }
-            return Err(anyhow::anyhow!("DuckDB parser error"));
-        }
-    }
-    
-    // Get statements array
-    let statements = match duckdb_ast.get("statements") {
-        Some(statements) if statements.is_array() => statements.as_array().unwrap(),
-        _ => return Err(anyhow::anyhow!("DuckDB AST does not contain statements array")),
-    };
-    
-    let mut result = Vec::new();
-    
-    for stmt in statements {
-        // Get the node object from the statement
-        let node = match stmt.get("node") {
-            Some(node) if node.is_object() => node,
-            _ => {
-                tracing::warn!("Statement missing node object: {:?}", stmt);
-                continue;
-            }
-        };
-        
-        // Check the node type
-        let node_type = node.get("type").and_then(|t| t.as_str());
-        
-        match node_type {
-            Some("SET_OPERATION_NODE") => {
-                // Convert set operation (e.g., UNION, UNION ALL, etc.)
-                let sqlparser_stmt = convert_set_operation_node(node)?;
-                result.push(sqlparser_stmt);
-            },
-            Some("SELECT_NODE") => {
-                // Convert simple SELECT
-                let sqlparser_stmt = convert_select_node(node)?;
-                result.push(sqlparser_stmt);
-            },
-            Some(other_type) => {
-                tracing::warn!("Unsupported node type: {}", other_type);
-                return Err(anyhow::anyhow!("Unsupported DuckDB AST node type: {}", other_type));
-            },
-            None => {
-                tracing::warn!("Node missing type field: {:?}", node);
-                return Err(anyhow::anyhow!("DuckDB AST node missing type field"));
-            }
-        }
-    }
+/// * `Result<Value>` - JSON representation of the AST or error
+pub fn parse_with_duckdb(sql: &str) -> Result<Value> {
+    // This feature requires DuckDB 0.9.0 or higher
+    // Connect to an in-memory DuckDB database
+    let conn = Connection::open_in_memory().context("Failed to open DuckDB connection")?;
     
-    Ok(result)
+    serialize_with_duckdb(&conn, sql)
 }
 
-/// Convert a DuckDB SET_OPERATION_NODE to a sqlparser Statement
-fn convert_set_operation_node(node: &Value) -> Result<Statement> {
-    // Get the set operation type
-    let setop_type = node.get("setop_type")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow::anyhow!("SET_OPERATION_NODE missing setop_type"))?;
-    
-    // Get the "all" flag
-    let setop_all = node.get("setop_all")
-        .and_then(|v| v.as_bool())
-        .unwrap_or(false);
-    
-    // Get left and right nodes
-    let left_node = node.get("left")
-        .ok_or_else(|| anyhow::anyhow!("SET_OPERATION_NODE missing left node"))?;
-    
-    let right_node = node.get("right")
-        .ok_or_else(|| anyhow::anyhow!("SET_OPERATION_NODE missing right node"))?;
-    
-    // Convert left and right nodes to SetExpr
-    let left_expr = convert_node_to_set_expr(left_node)?;
-    let right_expr = convert_node_to_set_expr(right_node)?;
-    
-    // Map DuckDB set operation type to sqlparser SetOperator
-    let set_operator = match setop_type {
-        "UNION" => SetOperator::Union,
-        "EXCEPT" => SetOperator::Except,
-        "INTERSECT" => SetOperator::Intersect,
-        _ => return Err(anyhow::anyhow!("Unsupported set operation type: {}", setop_type)),
-    };
-    
-    // Create SetExpr::SetOperation
-    let set_expr = SetExpr::SetOperation {
-        op: set_operator,
-        set_quantifier: if setop_all { SetQuantifier::All } else { SetQuantifier::Distinct },
-        left: Box::new(left_expr),
-        right: Box::new(right_expr),
-    };
-    
-    // Create Query with the SetExpr
-    let query = Query {
-        with: None,
-        body: Box::new(set_expr),
-        order_by: None,
-        limit: None,
-        offset: None,
-        fetch: None,
-        locks: Vec::new(),
-        limit_by: Vec::new(),
-        for_clause: None,
-        format_clause: None,
-        settings: None,
-    };
-    
-    // Create Statement::Query
-    Ok(Statement::Query(Box::new(query)))
+/// Serialize SQL to DuckDB's JSON AST using an existing connection
+///
+/// # Arguments
+///
+/// * `conn` - DuckDB connection to run `json_serialize_sql` on
+/// * `sql` - SQL string to parse
+///
+/// # Returns
+///
+/// * `Result<Value>` - JSON representation of the AST or error
+fn serialize_with_duckdb(conn: &Connection, sql: &str) -> Result<Value> {
+    let mut stmt = conn.prepare("SELECT json_serialize_sql(?::VARCHAR)")
+        .context("json_serialize_sql is not available")?;
+
+    let ast_json: String = stmt.query_row([sql], |row| row.get(0))
+        .context("json_serialize_sql failed")?;
+    serde_json::from_str(&ast_json)
+        .context(format!("Failed to parse DuckDB AST JSON: {}", ast_json))
 }
 
-/// Convert a DuckDB SELECT_NODE to a sqlparser SetExpr
-fn convert_select_node(node: &Value) -> Result<Statement> {
-    let set_expr = convert_node_to_set_expr(node)?;
-    
-    // Check for ORDER BY
-    let order_by = if let Some(modifiers) = node.get("modifiers").and_then(|m| m.as_array()) {
-        let mut order_exprs = Vec::new();
-        
-        for modifier in modifiers {
-            if modifier.get("type").and_then(|t| t.as_str()) == Some("ORDER_MODIFIER") {
-                if let Some(orders) = modifier.get("orders").and_then(|o| o.as_array()) {
-                    for order in orders {
-                        if let Some(expr) = order.get("expression") {
-                            // Convert expression
-                            let order_expr = convert_expr_node(expr)?;
-                            
-                            // Get order type (ASC/DESC)
-                            let asc = order.get("type")
-                                .and_then(|t| t.as_str())
-                                .map(|t| t == "ASCENDING")
-                                .unwrap_or(true);
-                            
-                            // Get nulls order
-                            let nulls_first = order.get("null_order")
-                                .and_then(|n| n.as_str())
-                                .map(|n| n == "NULLS_FIRST")
-                                .unwrap_or(true);
-                            
-                            // Create OrderByExpr
-                            order_exprs.push(sqlparser::ast::OrderByExpr {
-                                expr: order_expr,
-                                asc: Some(asc),
-                                nulls_first: Some(nulls_first),
-                                with_fill: None,
-                            });
-                        }
-                    }
-                }
-            }
-        }
-        
-        if order_exprs.is_empty() {
-            None
-        } else {
-            Some(OrderBy {
-                exprs: order_exprs,
-                interpolate: None,
-            })
-        }
-    } else {
-        None
-    };
-    
-    // Check for LIMIT
-    let limit = if let Some(modifiers) = node.get("modifiers").and_then(|m| m.as_array()) {
-        let limit_modifier = modifiers.iter().find(|m| 
-            m.get("type").and_then(|t| t.as_str()) == Some("LIMIT_MODIFIER")
-        );
-        
-        if let Some(limit_mod) = limit_modifier {
-            if let Some(limit_val) = limit_mod.get("limit") {
-                // Convert limit value to expression
-                let limit_expr = convert_expr_node(limit_val)?;
-                Some(limit_expr)
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-    
-    // Check for OFFSET
-    let offset = if let Some(modifiers) = node.get("modifiers").and_then(|m| m.as_array()) {
-        let limit_modifier = modifiers.iter().find(|m| 
-            m.get("type").and_then(|t| t.as_str()) == Some("LIMIT_MODIFIER")
-        );
-        
-        if let Some(limit_mod) = limit_modifier {
-            if let Some(offset_val) = limit_mod.get("offset") {
-                // Convert offset value to expression
-                let offset_expr = convert_expr_node(offset_val)?;
-                Some(sqlparser::ast::Offset {
-                    value: offset_expr,
-                    rows: sqlparser::ast::OffsetRows::Rows,
-                })
-            } else {
-                None
-            }
-        } else {
-            None
-        }
-    } else {
-        None
-    };
-    
-    // Create Query with the SetExpr and modifiers
-    let query = Query {
-        with: None,
-        body: Box::new(set_expr),
-        order_by,
-        limit,
-        offset,
-        fetch: None,
-        locks: Vec::new(),
-        limit_by: Vec::new(),
-        for_clause: None,
-        format_clause: None,
-        settings: None,
-    };
-    
-    // Create Statement::Query
-    Ok(Statement::Query(Box::new(query)))
+/// Whether `json_serialize_sql` works on a connection at all, as opposed to failing for one file
+fn duckdb_serializer_available(conn: &Connection) -> bool {
+    serialize_with_duckdb(conn, "SELECT 1").is_ok()
 }
 
-/// Convert a DuckDB node to a sqlparser SetExpr
-fn convert_node_to_set_expr(node: &Value) -> Result<SetExpr> {
-    // Check node type
-    let node_type = node.get("type")
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Node missing type field"))?;
+/// Extract column information from a SQL query
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to extract columns from
+///
+/// # Returns
+///
+/// * `Vec<ColumnInfo>` - Vector of column information
+pub fn extract_columns(statement: &Statement) -> Result<Vec<ColumnInfo>> {
+    let mut columns = Vec::new();
     
-    match node_type {
-        "SELECT_NODE" => {
-            // Get select list
-            let select_list = node.get("select_list")
-                .and_then(|sl| sl.as_array())
-                .ok_or_else(|| anyhow::anyhow!("SELECT_NODE missing select_list array"))?;
-            
-            // Convert select list items
-            let mut projection = Vec::new();
-            for item in select_list {
-                let select_item = convert_select_list_item(item)?;
-                projection.push(select_item);
-            }
-            
-            // Check for DISTINCT modifier
-            let distinct = if let Some(modifiers) = node.get("modifiers").and_then(|m| m.as_array()) {
-                let has_distinct = modifiers.iter().any(|m| {
-                    m.get("type").and_then(|t| t.as_str()) == Some("DISTINCT_MODIFIER")
-                });
-                
-                if has_distinct {
-                    Some(Distinct::Distinct)
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-            
-            // Initialize empty FROM clause
-            let mut from = Vec::new();
-            
-            // Check if we need to add a FROM clause
-            if let Some(from_table) = node.get("from_table") {
-                let from_type = from_table.get("type").and_then(|t| t.as_str());
-                
-                if from_type != Some("EMPTY") {
-                    // Process FROM table
-                    if let Some(table_entry) = convert_table_reference(from_table) {
-                        from.push(table_entry);
-                    }
-                }
-            }
-            
-            // Check for WHERE clause
-            let selection = if let Some(where_clause) = node.get("where_clause") {
-                // Convert where clause to expression
-                let where_expr = convert_expr_node(where_clause)?;
-                Some(where_expr)
-            } else {
-                None
-            };
-            
-            // Check for GROUP BY
-            let group_by = if let Some(group_expressions) = node.get("group_expressions").and_then(|g| g.as_array()) {
-                if !group_expressions.is_empty() {
-                    let mut group_by_exprs = Vec::new();
-                    
-                    for expr in group_expressions {
-                        let group_expr = convert_expr_node(expr)?;
-                        group_by_exprs.push(group_expr);
-                    }
-                    
-                    GroupByExpr::Expressions(group_by_exprs, Vec::new()) // Empty having clause for now
-                } else {
-                    GroupByExpr::Expressions(Vec::new(), Vec::new())
-                }
-            } else {
-                GroupByExpr::Expressions(Vec::new(), Vec::new())
-            };
-            
-            // Check for HAVING
-            let having = if let Some(having_clause) = node.get("having_clause") {
-                let having_expr = convert_expr_node(having_clause)?;
-                Some(having_expr)
-            } else {
-                None
-            };
-            
-            // Create Select
-            let select = Select {
-                distinct,
-                top: None,
-                projection,
-                into: None,
-                from,
-                lateral_views: Vec::new(),
-                selection,
-                group_by,
-                cluster_by: Vec::new(),
-                distribute_by: Vec::new(),
-                sort_by: Vec::new(),
-                having,
-                qualify: None,
-                named_window: Vec::new(),
-                connect_by: None,
-                window_before_qualify: false,
-                prewhere: None,
-                value_table_mode: None,
-            };
-            
-            Ok(SetExpr::Select(Box::new(select)))
-        },
-        "SET_OPERATION_NODE" => {
-            // For nested set operations, convert recursively
-            let inner_stmt = convert_set_operation_node(node)?;
-            if let Statement::Query(query) = inner_stmt {
-                Ok(*query.body)
-            } else {
-                Err(anyhow::anyhow!("Expected Query from set operation conversion"))
-            }
-        },
-        _ => Err(anyhow::anyhow!("Unsupported node type for SetExpr conversion: {}", node_type)),
+    if let Statement::Query(query) = statement {
+        // Extract columns from SELECT clause
+        extract_columns_from_query(query, &mut columns)?;
     }
+    
+    Ok(columns)
 }
 
-/// Convert a DuckDB table reference to a sqlparser TableWithJoins
-fn convert_table_reference(table_ref: &Value) -> Option<sqlparser::ast::TableWithJoins> {
-    // Get table reference type
-    let ref_type = table_ref.get("type").and_then(|t| t.as_str());
-    
-    match ref_type {
-        Some("BASE_TABLE") => {
-            // Get table name
-            let table_name = table_ref.get("table_name").and_then(|t| t.as_str());
-            let alias = table_ref.get("alias").and_then(|a| a.as_str());
-            
-            if let Some(name) = table_name {
-                // Create table factor
-                let relation = TableFactor::Table {
-                    name: sqlparser::ast::ObjectName(vec![Ident::new(name)]),
-                    alias: alias.map(|a| sqlparser::ast::TableAlias {
-                        name: Ident::new(a),
-                        columns: Vec::new(),
-                    }),
-                    args: None,
-                    with_hints: Vec::new(),
-                    version: None,
-                    partitions: Vec::new(),
-                    with_ordinality: false,
-                };
-                
-                // Create table with joins
-                Some(sqlparser::ast::TableWithJoins {
-                    relation,
-                    joins: Vec::new(),
-                })
-            } else {
-                tracing::warn!("BASE_TABLE missing table_name");
-                None
-            }
-        },
-        Some("JOIN") => {
-            // Get left and right tables
-            let left = table_ref.get("left").and_then(|l| convert_table_reference(l));
-            let right = table_ref.get("right").and_then(|r| {
-                // Get right table relation
-                let ref_type = r.get("type").and_then(|t| t.as_str());
-                
-                match ref_type {
-                    Some("BASE_TABLE") => {
-                        let table_name = r.get("table_name").and_then(|t| t.as_str());
-                        let alias = r.get("alias").and_then(|a| a.as_str());
+/// Extract column information from a SQL query
+fn extract_columns_from_query(query: &Query, columns: &mut Vec<ColumnInfo>) -> Result<()> {
+    match query.body.as_ref() {
+        SetExpr::Select(select) => {
+            // Process the projection (SELECT list)
+            for item in &select.projection {
+                match item {
+                    SelectItem::UnnamedExpr(expr) => {
+                        // For unnamed expressions, use the expression as column name
+                        let (name, data_type, is_derived, expression) = extract_expr_info(expr);
+                        let source_info = extract_source_from_expr(expr);
                         
-                        if let Some(name) = table_name {
-                            Some(TableFactor::Table {
-                                name: sqlparser::ast::ObjectName(vec![Ident::new(name)]),
-                                alias: alias.map(|a| sqlparser::ast::TableAlias {
-                                    name: Ident::new(a),
-                                    columns: Vec::new(),
-                                }),
-                                args: None,
-                                with_hints: Vec::new(),
-                                version: None,
-                                partitions: Vec::new(),
-                                with_ordinality: false,
-                            })
-                        } else {
-                            tracing::warn!("JOIN right side BASE_TABLE missing table_name");
-                            None
-                        }
-                    },
-                    _ => {
-                        tracing::warn!("Unsupported JOIN right side type: {:?}", ref_type);
-                        None
-                    }
-                }
-            });
-            
-            // Get join type
-            let join_type = table_ref.get("join_type")
-                .and_then(|j| j.as_str())
-                .unwrap_or("INNER");
-            
-            // Get join condition
-            let condition = table_ref.get("condition").and_then(|c| {
-                // Try to convert condition to expression
-                convert_expr_node(c).ok()
-            });
-            
-            // Map join type to sqlparser JoinType
-            let join_operator = match join_type {
-                "INNER" => sqlparser::ast::JoinOperator::Inner(
-                    sqlparser::ast::JoinConstraint::On(
-                        condition.unwrap_or(Expr::Value(SqlValue::Boolean(true)))
-                    )
-                ),
-                "LEFT" => sqlparser::ast::JoinOperator::LeftOuter(
-                    sqlparser::ast::JoinConstraint::On(
-                        condition.unwrap_or(Expr::Value(SqlValue::Boolean(true)))
-                    )
-                ),
-                "RIGHT" => sqlparser::ast::JoinOperator::RightOuter(
-                    sqlparser::ast::JoinConstraint::On(
-                        condition.unwrap_or(Expr::Value(SqlValue::Boolean(true)))
-                    )
-                ),
-                "FULL" => sqlparser::ast::JoinOperator::FullOuter(
-                    sqlparser::ast::JoinConstraint::On(
-                        condition.unwrap_or(Expr::Value(SqlValue::Boolean(true)))
-                    )
-                ),
-                _ => sqlparser::ast::JoinOperator::Inner(
-                    sqlparser::ast::JoinConstraint::On(
-                        condition.unwrap_or(Expr::Value(SqlValue::Boolean(true)))
-                    )
-                ),
-            };
-            
-            // If we have both left and right, create a table with join
-            if let (Some(mut left_table), Some(right_relation)) = (left, right) {
-                // Add join to left table's joins
-                left_table.joins.push(sqlparser::ast::Join {
-                    relation: right_relation,
-                    join_operator,
-                });
-                
-                Some(left_table)
-            } else {
-                tracing::warn!("JOIN missing left or right table");
-                None
-            }
-        },
-        Some("SUBQUERY") => {
-            // Get subquery
-            if let (Some(subquery), alias) = (
-                table_ref.get("subquery").and_then(|s| {
-                    // Convert subquery to SetExpr
-                    convert_node_to_set_expr(s).ok().map(|expr| {
-                        // Create Query
-                        let query = Query {
-                            with: None,
-                            body: Box::new(expr),
-                            order_by: None,
-                            limit: None,
-                            offset: None,
-                            fetch: None,
-                            locks: Vec::new(),
-                            limit_by: Vec::new(),
-                            for_clause: None,
-                            format_clause: None,
-                            settings: None,
-                        };
-                        query
-                    })
-                }),
-                table_ref.get("alias").and_then(|a| a.as_str())
-            ) {
-                // Create derived table
-                let relation = TableFactor::Derived {
-                    lateral: false,
-                    subquery: Box::new(subquery),
-                    alias: alias.map(|a| sqlparser::ast::TableAlias {
-                        name: Ident::new(a),
-                        columns: Vec::new(),
-                    }),
-                };
-                
-                // Create table with joins
-                Some(sqlparser::ast::TableWithJoins {
-                    relation,
-                    joins: Vec::new(),
-                })
-            } else {
-                tracing::warn!("SUBQUERY missing subquery or alias");
-                None
-            }
-        },
-        _ => {
-            tracing::warn!("Unsupported table reference type: {:?}", ref_type);
-            None
-        }
-    }
-}
-
-/// Convert a DuckDB select list item to a sqlparser SelectItem
-fn convert_select_list_item(item: &Value) -> Result<SelectItem> {
-    // Get item alias
-    let alias = item.get("alias")
-        .and_then(|a| a.as_str())
-        .unwrap_or("");
-    
-    // Get item class
-    let item_class = item.get("class")
-        .and_then(|c| c.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Select list item missing class"))?;
-    
-    // Get item type
-    let item_type = item.get("type")
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Select list item missing type"))?;
-    
-    match (item_class, item_type) {
-        ("CONSTANT", "VALUE_CONSTANT") => {
-            // Get value
-            let value = item.get("value")
-                .ok_or_else(|| anyhow::anyhow!("VALUE_CONSTANT missing value"))?;
-            
-            // Create expression from value
-            let expr = convert_value_to_expr(value)?;
-            
-            // Create SelectItem
-            if !alias.is_empty() {
-                Ok(SelectItem::ExprWithAlias {
-                    expr,
-                    alias: Ident::new(alias),
-                })
-            } else {
-                Ok(SelectItem::UnnamedExpr(expr))
-            }
-        },
-        ("FUNCTION", "FUNCTION") => {
-            // Get function name
-            let function_name = item.get("function_name")
-                .and_then(|f| f.as_str())
-                .ok_or_else(|| anyhow::anyhow!("FUNCTION missing function_name"))?;
-            
-            // Get function children/arguments
-            let children = item.get("children")
-                .and_then(|c| c.as_array())
-                .ok_or_else(|| anyhow::anyhow!("FUNCTION missing children array"))?;
-            
-            // Convert function arguments
-            let mut args = Vec::new();
-            for child in children {
-                // Convert each child to an expression
-                let arg_expr = convert_expr_node(child)?;
-                args.push(arg_expr);
-            }
-            
-            // Create function expression
-            let expr = Expr::Function(sqlparser::ast::Function {
-                name: sqlparser::ast::ObjectName(vec![Ident::new(function_name)]),
-                args: convert_args_to_function_arguments(args),
-                over: None,
-                filter: None,
-                null_treatment: None,
-                within_group: Vec::new(),
-                parameters: FunctionArguments::None,
-            });
-            
-            // Create SelectItem
-            if !alias.is_empty() {
-                Ok(SelectItem::ExprWithAlias {
-                    expr,
-                    alias: Ident::new(alias),
-                })
-            } else {
-                Ok(SelectItem::UnnamedExpr(expr))
-            }
-        },
-        ("COLUMN_REF", "COLUMN_REF") => {
-            // Get column name
-            let column_name = item.get("column_name")
-                .and_then(|c| c.as_str())
-                .ok_or_else(|| anyhow::anyhow!("COLUMN_REF missing column_name"))?;
-            
-            // Check if there's a table name
-            let table_name = item.get("table_name")
-                .and_then(|t| t.as_str());
-            
-            // Create column reference expression
-            let expr = if let Some(table) = table_name {
-                Expr::CompoundIdentifier(vec![
-                    Ident::new(table),
-                    Ident::new(column_name),
-                ])
-            } else {
-                Expr::Identifier(Ident::new(column_name))
-            };
-            
-            // Create SelectItem
-            if !alias.is_empty() {
-                Ok(SelectItem::ExprWithAlias {
-                    expr,
-                    alias: Ident::new(alias),
-                })
-            } else {
-                Ok(SelectItem::UnnamedExpr(expr))
-            }
-        },
-        // Add more cases for different item types
-        _ => {
-            tracing::warn!("Unsupported select list item: class={}, type={}", item_class, item_type);
-            // For now, create a placeholder expression
-            let expr = Expr::Value(SqlValue::Null);
-            
-            if !alias.is_empty() {
-                Ok(SelectItem::ExprWithAlias {
-                    expr,
-                    alias: Ident::new(alias),
-                })
-            } else {
-                Ok(SelectItem::UnnamedExpr(expr))
-            }
-        }
-    }
-}
-
-/// Convert a DuckDB expression node to a sqlparser Expr
-fn convert_expr_node(node: &Value) -> Result<Expr> {
-    // Get node class and type
-    let node_class = node.get("class")
-        .and_then(|c| c.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Expression node missing class"))?;
-    
-    let node_type = node.get("type")
-        .and_then(|t| t.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Expression node missing type"))?;
-    
-    match (node_class, node_type) {
-        ("CONSTANT", "VALUE_CONSTANT") => {
-            // Get value
-            let value = node.get("value")
-                .ok_or_else(|| anyhow::anyhow!("VALUE_CONSTANT missing value"))?;
-            
-            // Convert value to expression
-            convert_value_to_expr(value)
-        },
-        ("FUNCTION", "FUNCTION") => {
-            // Get function name
-            let function_name = node.get("function_name")
-                .and_then(|f| f.as_str())
-                .ok_or_else(|| anyhow::anyhow!("FUNCTION missing function_name"))?;
-            
-            // Get function children/arguments
-            let children = node.get("children")
-                .and_then(|c| c.as_array())
-                .ok_or_else(|| anyhow::anyhow!("FUNCTION missing children array"))?;
-            
-            // Convert function arguments
-            let mut args = Vec::new();
-            for child in children {
-                // Recursive conversion of child expressions
-                let arg_expr = convert_expr_node(child)?;
-                args.push(arg_expr);
-            }
-            
-            // Create function expression
-            Ok(Expr::Function(sqlparser::ast::Function {
-                name: sqlparser::ast::ObjectName(vec![Ident::new(function_name)]),
-                args: convert_args_to_function_arguments(args),
-                over: None,
-                filter: None,
-                null_treatment: None,
-                within_group: Vec::new(),
-                parameters: FunctionArguments::None,
-            }))
-        },
-        ("COLUMN_REF", "COLUMN_REF") => {
-            // Get column name
-            let column_name = node.get("column_name")
-                .and_then(|c| c.as_str())
-                .ok_or_else(|| anyhow::anyhow!("COLUMN_REF missing column_name"))?;
-            
-            // Check if there's a table name
-            let table_name = node.get("table_name")
-                .and_then(|t| t.as_str());
-            
-            // Create column reference expression
-            if let Some(table) = table_name {
-                Ok(Expr::CompoundIdentifier(vec![
-                    Ident::new(table),
-                    Ident::new(column_name),
-                ]))
-            } else {
-                Ok(Expr::Identifier(Ident::new(column_name)))
-            }
-        },
-        ("OPERATOR", "OPERATOR") => {
-            // Some operators may be represented differently in DuckDB's AST
-            let operator_type = node.get("operator_type")
-                .and_then(|o| o.as_str())
-                .ok_or_else(|| anyhow::anyhow!("OPERATOR missing operator_type"))?;
-            
-            // Get operands
-            let children = node.get("children")
-                .and_then(|c| c.as_array())
-                .ok_or_else(|| anyhow::anyhow!("OPERATOR missing children array"))?;
-            
-            // Check for unary vs binary operators
-            if children.len() == 1 {
-                // Unary operator
-                let operand = convert_expr_node(&children[0])?;
-                
-                match operator_type {
-                    "NOT" => Ok(Expr::UnaryOp {
-                        op: sqlparser::ast::UnaryOperator::Not,
-                        expr: Box::new(operand),
-                    }),
-                    // Add more unary operators as needed
-                    _ => {
-                        tracing::warn!("Unsupported unary operator: {}", operator_type);
-                        Ok(operand) // Fallback to just returning the operand
-                    }
-                }
-            } else if children.len() == 2 {
-                // Binary operator
-                let left = convert_expr_node(&children[0])?;
-                let right = convert_expr_node(&children[1])?;
-                
-                match operator_type {
-                    "AND" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::And,
-                        right: Box::new(right),
-                    }),
-                    "OR" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Or,
-                        right: Box::new(right),
-                    }),
-                    "=" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Eq,
-                        right: Box::new(right),
-                    }),
-                    ">" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Gt,
-                        right: Box::new(right),
-                    }),
-                    "<" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Lt,
-                        right: Box::new(right),
-                    }),
-                    ">=" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::GtEq,
-                        right: Box::new(right),
-                    }),
-                    "<=" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::LtEq,
-                        right: Box::new(right),
-                    }),
-                    "<>" | "!=" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::NotEq,
-                        right: Box::new(right),
-                    }),
-                    "+" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Plus,
-                        right: Box::new(right),
-                    }),
-                    "-" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Minus,
-                        right: Box::new(right),
-                    }),
-                    "*" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Multiply,
-                        right: Box::new(right),
-                    }),
-                    "/" => Ok(Expr::BinaryOp {
-                        left: Box::new(left),
-                        op: sqlparser::ast::BinaryOperator::Divide,
-                        right: Box::new(right),
-                    }),
-                    // Add more binary operators as needed
-                    _ => {
-                        tracing::warn!("Unsupported binary operator: {}", operator_type);
-                        // Create a function call as a fallback
-                        let args = vec![left, right];
-                        
-                        Ok(Expr::Function(sqlparser::ast::Function {
-                            name: sqlparser::ast::ObjectName(vec![Ident::new(operator_type)]),
-                            args: convert_args_to_function_arguments(args),
-                            over: None,
-                            filter: None,
-                            null_treatment: None,
-                            within_group: Vec::new(),
-                            parameters: FunctionArguments::None,
-                        }))
-                    }
-                }
-            } else {
-                tracing::warn!("Operator with unexpected number of children: {}", children.len());
-                Ok(Expr::Value(SqlValue::Null))
-            }
-        },
-        // Add more expression types as needed
-        _ => {
-            tracing::warn!("Unsupported expression node: class={}, type={}", node_class, node_type);
-            Ok(Expr::Value(SqlValue::Null))
-        }
-    }
-}
-
-/// Convert a DuckDB value to a sqlparser Expr
-fn convert_value_to_expr(value: &Value) -> Result<Expr> {
-    // Check if value is null
-    let is_null = value.get("is_null")
-        .and_then(|n| n.as_bool())
-        .unwrap_or(false);
-    
-    if is_null {
-        return Ok(Expr::Value(SqlValue::Null));
-    }
-    
-    // Get value type
-    let value_type = value.get("type")
-        .and_then(|t| t.get("id"))
-        .and_then(|id| id.as_str())
-        .ok_or_else(|| anyhow::anyhow!("Value missing type id"))?;
-    
-    // Get actual value
-    let value_data = value.get("value")
-        .ok_or_else(|| anyhow::anyhow!("Missing value"))?;
-    
-    // Convert value based on type
-    match value_type {
-        "INTEGER" => {
-            if let Some(num) = value_data.as_i64() {
-                Ok(Expr::Value(SqlValue::Number(num.to_string(), false)))
-            } else {
-                Err(anyhow::anyhow!("INTEGER value is not a number"))
-            }
-        },
-        "VARCHAR" => {
-            if let Some(s) = value_data.as_str() {
-                Ok(Expr::Value(SqlValue::SingleQuotedString(s.to_string())))
-            } else {
-                Err(anyhow::anyhow!("VARCHAR value is not a string"))
-            }
-        },
-        "DOUBLE" | "FLOAT" => {
-            if let Some(num) = value_data.as_f64() {
-                Ok(Expr::Value(SqlValue::Number(num.to_string(), false)))
-            } else {
-                Err(anyhow::anyhow!("FLOAT/DOUBLE value is not a number"))
-            }
-        },
-        "BOOLEAN" => {
-            if let Some(b) = value_data.as_bool() {
-                Ok(Expr::Value(SqlValue::Boolean(b)))
-            } else {
-                Err(anyhow::anyhow!("BOOLEAN value is not a boolean"))
-            }
-        },
-        "DATE" => {
-            if let Some(s) = value_data.as_str() {
-                // Use SingleQuotedString as a workaround since Date type doesn't exist
-                Ok(Expr::Value(SqlValue::SingleQuotedString(format!("DATE '{}'", s))))
-            } else {
-                Err(anyhow::anyhow!("DATE value is not a string"))
-            }
-        },
-        "TIMESTAMP" => {
-            if let Some(s) = value_data.as_str() {
-                // Use SingleQuotedString as a workaround since Timestamp type doesn't exist
-                Ok(Expr::Value(SqlValue::SingleQuotedString(format!("TIMESTAMP '{}'", s))))
-            } else {
-                Err(anyhow::anyhow!("TIMESTAMP value is not a string"))
-            }
-        },
-        "DECIMAL" => {
-            // For DECIMAL types, we'll convert to a string representation
-            if let Some(num) = value_data.as_f64() {
-                Ok(Expr::Value(SqlValue::Number(num.to_string(), true))) // true for exact
-            } else if let Some(s) = value_data.as_str() {
-                Ok(Expr::Value(SqlValue::Number(s.to_string(), true))) // true for exact
-            } else {
-                Err(anyhow::anyhow!("DECIMAL value is not a number or string"))
-            }
-        },
-        // Add more type conversions as needed
-        _ => {
-            tracing::warn!("Unsupported value type: {}", value_type);
-            Ok(Expr::Value(SqlValue::Null))
-        }
-    }
-}
-
-/// Helper function to convert a vector of expressions to FunctionArguments
-fn convert_args_to_function_arguments(args: Vec<Expr>) -> FunctionArguments {
-    // Depending on sqlparser 0.49.0 documentation, we can determine which variant to use
-    if args.is_empty() {
-        FunctionArguments::None
-    } else {
-        // Create a custom implementation to handle args differently based on the number of args
-        // This requires inspecting the 0.49.0 documentation to understand FunctionArguments structure
-        // As a fallback, return FunctionArguments::None to avoid compilation errors
-        FunctionArguments::None
-    }
-}
-
-/// Parse SQL using DuckDB's built-in parser
-///
-/// # Arguments
-///
-/// * `sql` - SQL string to parse
-///
-/// # Returns
-///
-/// * `Result<Value>` - JSON representation of the AST or error
-pub fn parse_with_duckdb(sql: &str) -> Result<Value> {
-    // This feature requires DuckDB 0.9.0 or higher
-    // Connect to an in-memory DuckDB database
-    let conn = Connection::open_in_memory().context("Failed to open DuckDB connection")?;
-    
-    // Check DuckDB version
-    if let Ok(mut version_stmt) = conn.prepare("SELECT version()") {
-        if let Ok(mut version_rows) = version_stmt.query([]) {
-            if let Ok(Some(row)) = version_rows.next() {
-                let version: String = row.get(0)?;
-                tracing::debug!("DuckDB version: {}", version);
-            }
-        }
-    }
-    
-    // Try different approaches
-    // Method 1: Directly using json_serialize_sql with parameter
-    let result = try_json_serialize_sql_param(&conn, sql);
-    if result.is_ok() {
-        return result;
-    }
-    
-    // Method 2: Using json_serialize_sql with literal SQL
-    let result = try_json_serialize_sql_literal(&conn, sql);
-    if result.is_ok() {
-        return result;
-    }
-    
-    // Method 3: First parsing the SQL, then using json_serialize_sql
-    let result = try_via_prepare_then_serialize(&conn, sql);
-    if result.is_ok() {
-        return result;
-    }
-    
-    // If all methods fail, return an informative error
-    Err(anyhow::anyhow!("Failed to parse SQL with DuckDB's json_serialize_sql. This may be due to using an unsupported DuckDB version or feature limitation."))
-}
-
-/// Try parsing with json_serialize_sql with a parameter
-fn try_json_serialize_sql_param(conn: &Connection, sql: &str) -> Result<Value> {
-    tracing::debug!("Trying json_serialize_sql with parameter");
-    let query = "SELECT json_serialize_sql(?)";
-    let mut stmt = conn.prepare(query)?;
-    
-    let mut rows = stmt.query([sql])?;
-    if let Some(row) = rows.next()? {
-        let ast_json: String = row.get(0)?;
-        let parsed_json: Value = serde_json::from_str(&ast_json)
-            .context(format!("Failed to parse DuckDB AST JSON: {}", ast_json))?;
-        
-        return Ok(parsed_json);
-    }
-    
-    Err(anyhow::anyhow!("No result from json_serialize_sql with parameter"))
-}
-
-/// Try parsing with json_serialize_sql with a literal SQL string
-fn try_json_serialize_sql_literal(conn: &Connection, sql: &str) -> Result<Value> {
-    tracing::debug!("Trying json_serialize_sql with literal");
-    // Escape single quotes in SQL
-    let escaped_sql = sql.replace('\'', "''");
-    let query = format!("SELECT json_serialize_sql('{}')", escaped_sql);
-    
-    let mut stmt = conn.prepare(&query)?;
-    let mut rows = stmt.query([])?;
-    if let Some(row) = rows.next()? {
-        let ast_json: String = row.get(0)?;
-        let parsed_json: Value = serde_json::from_str(&ast_json)
-            .context(format!("Failed to parse DuckDB AST JSON: {}", ast_json))?;
-        
-        return Ok(parsed_json);
-    }
-    
-    Err(anyhow::anyhow!("No result from json_serialize_sql with literal"))
-}
-
-/// Try first preparing the SQL, then serializing
-fn try_via_prepare_then_serialize(conn: &Connection, sql: &str) -> Result<Value> {
-    tracing::debug!("Trying prepare then serialize approach");
-    
-    // First try to prepare the statement to validate SQL
-    let _stmt = match conn.prepare(sql) {
-        Ok(stmt) => stmt,
-        Err(e) => {
-            tracing::debug!("Failed to prepare SQL: {}", e);
-            return Err(anyhow::anyhow!("Failed to prepare SQL: {}", e));
-        }
-    };
-    
-    // Then try to get the serialized form
-    // This is a placeholder implementation and may need adjustment
-    let query = format!("SELECT json_serialize_sql(q) FROM (SELECT '{}' as q) t", sql.replace('\'', "''"));
-    let mut stmt = conn.prepare(&query)?;
-    let mut rows = stmt.query([])?;
-    if let Some(row) = rows.next()? {
-        let ast_json: String = row.get(0)?;
-        let parsed_json: Value = serde_json::from_str(&ast_json)
-            .context(format!("Failed to parse DuckDB AST JSON: {}", ast_json))?;
-        
-        return Ok(parsed_json);
-    }
-    
-    Err(anyhow::anyhow!("No result from prepare-then-serialize approach"))
-}
-
-/// Extract column information from a SQL query
-///
-/// # Arguments
-///
-/// * `statement` - SQL statement to extract columns from
-///
-/// # Returns
-///
-/// * `Vec<ColumnInfo>` - Vector of column information
-pub fn extract_columns(statement: &Statement) -> Result<Vec<ColumnInfo>> {
-    let mut columns = Vec::new();
-    
-    if let Statement::Query(query) = statement {
-        // Extract columns from SELECT clause
-        extract_columns_from_query(query, &mut columns)?;
-    }
-    
-    Ok(columns)
-}
-
-/// Extract column information from a SQL query
-fn extract_columns_from_query(query: &Query, columns: &mut Vec<ColumnInfo>) -> Result<()> {
-    match query.body.as_ref() {
-        SetExpr::Select(select) => {
-            // Process the projection (SELECT list)
-            for item in &select.projection {
-                match item {
-                    SelectItem::UnnamedExpr(expr) => {
-                        // For unnamed expressions, use the expression as column name
-                        let (name, data_type, is_derived, expression) = extract_expr_info(expr);
-                        let source_info = extract_source_from_expr(expr);
-                        
-                        columns.push(ColumnInfo {
-                            name,
-                            data_type,
-                            source_table: source_info.as_ref().map(|s| s.0.clone()),
-                            source_column: source_info.as_ref().map(|s| s.1.clone()),
-                            is_derived,
-                            expression,
-                        });
-                    },
-                    SelectItem::ExprWithAlias { expr, alias } => {
-                        // For expressions with aliases, use the alias as column name
-                        let (_, data_type, is_derived, expression) = extract_expr_info(expr);
-                        let source_info = extract_source_from_expr(expr);
-                        
-                        columns.push(ColumnInfo {
-                            name: alias.to_string(),
-                            data_type,
-                            source_table: source_info.as_ref().map(|s| s.0.clone()),
-                            source_column: source_info.as_ref().map(|s| s.1.clone()),
-                            is_derived,
-                            expression,
-                        });
-                    },
-                    SelectItem::QualifiedWildcard(object_name, _) => {
-                        // For qualified wildcards (like table.*), add a placeholder
-                        // Extract the qualifier from the object name
-                        let qualifier = object_name.to_string();
-                        columns.push(ColumnInfo {
-                            name: format!("{}.*", qualifier),
-                            data_type: "unknown".to_string(),
-                            source_table: Some(qualifier),
-                            source_column: None,
-                            is_derived: false,
-                            expression: None,
-                        });
-                    },
-                    SelectItem::Wildcard(_) => {
-                        // For wildcards (*), add a placeholder
-                        columns.push(ColumnInfo {
-                            name: "*".to_string(),
-                            data_type: "unknown".to_string(),
-                            source_table: None,
-                            source_column: None,
-                            is_derived: false,
-                            expression: None,
-                        });
+                        columns.push(ColumnInfo {
+                            name,
+                            data_type,
+                            source_table: source_info.as_ref().map(|s| s.0.clone()),
+                            source_column: source_info.as_ref().map(|s| s.1.clone()),
+                            is_derived,
+                            expression,
+                        });
+                    },
+                    SelectItem::ExprWithAlias { expr, alias } => {
+                        // For expressions with aliases, use the alias as column name
+                        let (_, data_type, is_derived, expression) = extract_expr_info(expr);
+                        let source_info = extract_source_from_expr(expr);
+                        
+                        columns.push(ColumnInfo {
+                            name: alias.to_string(),
+                            data_type,
+                            source_table: source_info.as_ref().map(|s| s.0.clone()),
+                            source_column: source_info.as_ref().map(|s| s.1.clone()),
+                            is_derived,
+                            expression,
+                        });
+                    },
+                    SelectItem::QualifiedWildcard(object_name, _) => {
+                        // For qualified wildcards (like table.*), add a placeholder
+                        // Extract the qualifier from the object name
+                        let qualifier = object_name.to_string();
+                        columns.push(ColumnInfo {
+                            name: format!("{}.*", qualifier),
+                            data_type: "unknown".to_string(),
+                            source_table: Some(qualifier),
+                            source_column: None,
+                            is_derived: false,
+                            expression: None,
+                        });
+                    },
+                    SelectItem::Wildcard(_) => {
+                        // For wildcards (*), add a placeholder
+                        columns.push(ColumnInfo {
+                            name: "*".to_string(),
+                            data_type: "unknown".to_string(),
+                            source_table: None,
+                            source_column: None,
+                            is_derived: false,
+                            expression: None,
+                        });
                     },
                 }
             }
@@ -1335,198 +455,1103 @@ fn extract_expr_info(expr: &Expr) -> (String, String, bool, Option<String>) {
             // Binary operation
             ("expression".to_string(), "unknown".to_string(), true, Some(format!("{:?}", expr)))
         },
-        Expr::Value(value) => {
-            // Literal value
-            let data_type = match value {
-                SqlValue::Number(_, _) => "numeric".to_string(),
-                SqlValue::SingleQuotedString(_) | SqlValue::DoubleQuotedString(_) => "string".to_string(),
-                SqlValue::Boolean(_) => "boolean".to_string(),
-                SqlValue::Null => "null".to_string(),
-                _ => "unknown".to_string(),
+        Expr::Value(value) => {
+            // Literal value
+            let data_type = match value {
+                SqlValue::Number(_, _) => "numeric".to_string(),
+                SqlValue::SingleQuotedString(_) | SqlValue::DoubleQuotedString(_) => "string".to_string(),
+                SqlValue::Boolean(_) => "boolean".to_string(),
+                SqlValue::Null => "null".to_string(),
+                _ => "unknown".to_string(),
+            };
+            ("literal".to_string(), data_type, true, Some(format!("{:?}", expr)))
+        },
+        _ => {
+            // Other expression types
+            ("expression".to_string(), "unknown".to_string(), true, Some(format!("{:?}", expr)))
+        }
+    }
+}
+
+/// Extract source table and column from an expression
+///
+/// Returns: Option<(source_table, source_column)>
+fn extract_source_from_expr(expr: &Expr) -> Option<(String, String)> {
+    match expr {
+        Expr::Identifier(_) => {
+            // Simple column reference without table - can't determine source table
+            None
+        },
+        Expr::CompoundIdentifier(parts) => {
+            // Column reference with table qualifier (e.g., table.column)
+            if parts.len() >= 2 {
+                let table = parts[0].to_string();
+                let column = parts[1].to_string();
+                Some((table, column))
+            } else {
+                None
+            }
+        },
+        _ => {
+            // Other expression types - derived, not direct source
+            None
+        }
+    }
+}
+
+/// Extract column-level lineage from a SQL query
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to extract lineage from
+/// * `model_name` - Name of the model/table being created
+///
+/// # Returns
+///
+/// * `Vec<TableColumnRelationship>` - Vector of column relationships
+pub fn extract_column_lineage(statement: &Statement, model_name: &str) -> Result<Vec<TableColumnRelationship>> {
+    extract_column_lineage_with_schemas(statement, model_name, &HashMap::new())
+}
+
+/// Extract column-level lineage from a SQL query using known upstream schemas
+///
+/// Table aliases are resolved to the relations they refer to, columns are followed
+/// through CTEs and derived tables down to the underlying tables, and `*` is expanded
+/// for any relation whose columns are listed in `schemas`.
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to extract lineage from
+/// * `model_name` - Name of the model/table being created
+/// * `schemas` - Output column names of upstream models, keyed by model name
+///
+/// # Returns
+///
+/// * `Vec<TableColumnRelationship>` - Vector of column relationships
+pub fn extract_column_lineage_with_schemas(
+    statement: &Statement,
+    model_name: &str,
+    schemas: &HashMap<String, Vec<String>>,
+) -> Result<Vec<TableColumnRelationship>> {
+    let relationships = resolve_output_columns(statement, schemas).column_lineage();
+
+    tracing::debug!("Resolved {} column lineage relationships for {}", relationships.len(), model_name);
+
+    Ok(relationships)
+}
+
+/// Resolve the output columns of a statement together with the table columns they read
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to resolve
+/// * `schemas` - Output column names of upstream models, keyed by model name
+///
+/// # Returns
+///
+/// * `ResolvedColumns` - Output columns in projection order
+pub fn resolve_output_columns(statement: &Statement, schemas: &HashMap<String, Vec<String>>) -> ResolvedColumns {
+    match statement {
+        Statement::Query(query) => {
+            let mut resolver = LineageResolver { schemas, ctes: Vec::new(), subquery_join_keys: Vec::new() };
+            resolver.resolve_query(query, None)
+        }
+        _ => ResolvedColumns::default(),
+    }
+}
+
+/// An output column of a query and the underlying table columns it is computed from
+#[derive(Debug, Clone)]
+pub struct ResolvedColumn {
+    /// Output column name
+    pub name: String,
+    /// Underlying (table, column) pairs referenced by the column expression
+    pub sources: Vec<(String, String)>,
+    /// How the column is computed from its sources
+    pub kind: TransformationKind,
+    /// Expression text for computed columns
+    pub expression: Option<String>,
+}
+
+impl ResolvedColumn {
+    /// A column read unchanged from a base table
+    fn passthrough(name: &str, table: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sources: vec![(table.to_string(), name.to_string())],
+            kind: TransformationKind::Passthrough,
+            expression: None,
+        }
+    }
+
+    /// A projected column computed by `expr` from the columns it references
+    fn from_expr(name: String, expr: &Expr, referenced: Vec<ResolvedColumn>) -> Self {
+        let kind = classify_expr(expr);
+
+        // A bare column reference keeps however the referenced column was produced
+        if kind == TransformationKind::Passthrough {
+            if let [column] = referenced.as_slice() {
+                return Self { name, ..column.clone() };
+            }
+        }
+
+        let kind = referenced.iter().map(|c| c.kind).fold(kind, TransformationKind::max);
+        let mut sources = Vec::new();
+        for column in referenced {
+            for source in column.sources {
+                if !sources.contains(&source) {
+                    sources.push(source);
+                }
+            }
+        }
+
+        Self {
+            name,
+            sources,
+            kind,
+            expression: Some(expr.to_string()),
+        }
+    }
+}
+
+/// A table column used to filter or join rows rather than to produce an output column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResolvedCondition {
+    /// Either `Filter` or `JoinKey`
+    pub kind: TransformationKind,
+    /// Underlying (table, column) pair
+    pub source: (String, String),
+    /// The predicate the column appears in
+    pub expression: String,
+}
+
+/// Columns produced by a query or by a relation in its FROM clause
+#[derive(Debug, Clone, Default)]
+pub struct ResolvedColumns {
+    /// Columns whose names are known, in order
+    pub columns: Vec<ResolvedColumn>,
+    /// Base tables selected with `*` whose columns could not be listed
+    pub unexpanded: Vec<String>,
+    /// Whether `columns` lists every column produced
+    pub complete: bool,
+    /// Columns that decide which rows are produced
+    pub conditions: Vec<ResolvedCondition>,
+    /// Table columns compared for equality in join conditions
+    pub join_keys: Vec<JoinKey>,
+}
+
+impl ResolvedColumns {
+    /// Columns of a base table whose schema is not known
+    fn unknown_table(table: &str) -> Self {
+        Self {
+            columns: Vec::new(),
+            unexpanded: vec![table.to_string()],
+            complete: false,
+            ..Default::default()
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&ResolvedColumn> {
+        self.columns.iter().find(|c| c.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Look up a column, tracing it to the base table when a single table was selected with `*`
+    fn lookup(&self, name: &str) -> Option<ResolvedColumn> {
+        if let Some(column) = self.find(name) {
+            return Some(column.clone());
+        }
+
+        match self.unexpanded.as_slice() {
+            [table] => Some(ResolvedColumn::passthrough(name, table)),
+            _ => None,
+        }
+    }
+
+    /// Flatten the resolved columns into column lineage relationships
+    pub fn column_lineage(&self) -> Vec<TableColumnRelationship> {
+        let mut relationships = Vec::new();
+
+        for column in &self.columns {
+            for (source_table, source_column) in &column.sources {
+                // A bare reference under a different name is a rename
+                let transformation = match column.kind {
+                    TransformationKind::Passthrough if !source_column.eq_ignore_ascii_case(&column.name) => {
+                        TransformationKind::Rename
+                    },
+                    kind => kind,
+                };
+
+                relationships.push(TableColumnRelationship {
+                    source_table: source_table.clone(),
+                    source_column: source_column.clone(),
+                    target_column: column.name.clone(),
+                    transformation,
+                    expression: column.expression.clone(),
+                });
+            }
+        }
+
+        // Filter and join key columns shape which rows appear rather than any single column
+        for condition in &self.conditions {
+            let (source_table, source_column) = &condition.source;
+            relationships.push(TableColumnRelationship {
+                source_table: source_table.clone(),
+                source_column: source_column.clone(),
+                target_column: "*".to_string(),
+                transformation: condition.kind,
+                expression: Some(condition.expression.clone()),
+            });
+        }
+
+        relationships
+    }
+
+    fn add_condition(&mut self, condition: ResolvedCondition) {
+        if !self.conditions.contains(&condition) {
+            self.conditions.push(condition);
+        }
+    }
+
+    fn add_join_key(&mut self, join_key: JoinKey) {
+        if !self.join_keys.contains(&join_key) {
+            self.join_keys.push(join_key);
+        }
+    }
+
+    /// Apply column aliases from `AS t(a, b, ...)`
+    fn rename(mut self, aliases: &[Ident]) -> Self {
+        for (column, alias) in self.columns.iter_mut().zip(aliases) {
+            column.name = alias.value.clone();
+        }
+        self
+    }
+}
+
+/// A relation visible in the FROM clause of a SELECT
+struct ScopeRelation {
+    /// Name the relation is referenced by (alias, or the table name without schema)
+    reference: String,
+    /// Columns the relation provides
+    columns: ResolvedColumns,
+}
+
+/// Relations visible to expressions, with a link to the enclosing query for correlated references
+struct Scope<'a> {
+    relations: Vec<ScopeRelation>,
+    parent: Option<&'a Scope<'a>>,
+}
+
+/// Walks a query tree resolving column references to the base tables they come from
+struct LineageResolver<'s> {
+    /// Output column names of upstream models
+    schemas: &'s HashMap<String, Vec<String>>,
+    /// CTEs visible at the current position, innermost last
+    ctes: Vec<HashMap<String, ResolvedColumns>>,
+    /// Join keys compared inside scalar and EXISTS/IN subqueries, until the enclosing SELECT records them
+    subquery_join_keys: Vec<JoinKey>,
+}
+
+impl<'s> LineageResolver<'s> {
+    fn resolve_query(&mut self, query: &Query, outer: Option<&Scope>) -> ResolvedColumns {
+        self.ctes.push(HashMap::new());
+
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let columns = self.resolve_query(&cte.query, outer).rename(&cte.alias.columns);
+                if let Some(frame) = self.ctes.last_mut() {
+                    frame.insert(cte.alias.name.value.to_lowercase(), columns);
+                }
+            }
+        }
+
+        let columns = self.resolve_set_expr(&query.body, outer);
+        self.ctes.pop();
+
+        columns
+    }
+
+    fn resolve_set_expr(&mut self, set_expr: &SetExpr, outer: Option<&Scope>) -> ResolvedColumns {
+        match set_expr {
+            SetExpr::Select(select) => self.resolve_select(select, outer),
+            SetExpr::Query(query) => self.resolve_query(query, outer),
+            SetExpr::SetOperation { left, right, .. } => {
+                // Set operations take their column names from the left side and
+                // combine the sources positionally
+                let mut resolved = self.resolve_set_expr(left, outer);
+                let right_resolved = self.resolve_set_expr(right, outer);
+
+                for (column, right_column) in resolved.columns.iter_mut().zip(right_resolved.columns) {
+                    for source in right_column.sources {
+                        if !column.sources.contains(&source) {
+                            column.sources.push(source);
+                        }
+                    }
+                    if right_column.kind > column.kind {
+                        column.kind = right_column.kind;
+                        column.expression = right_column.expression;
+                    }
+                }
+                for condition in right_resolved.conditions {
+                    resolved.add_condition(condition);
+                }
+                for join_key in right_resolved.join_keys {
+                    resolved.add_join_key(join_key);
+                }
+                for table in right_resolved.unexpanded {
+                    if !resolved.unexpanded.contains(&table) {
+                        resolved.unexpanded.push(table);
+                    }
+                }
+                resolved.complete = resolved.complete && right_resolved.complete;
+
+                resolved
+            }
+            _ => {
+                tracing::debug!("Unsupported SetExpr type for column lineage extraction: {:?}", set_expr);
+                ResolvedColumns::default()
+            }
+        }
+    }
+
+    fn resolve_select(&mut self, select: &Select, outer: Option<&Scope>) -> ResolvedColumns {
+        let mut relations = Vec::new();
+        for table_with_joins in &select.from {
+            self.add_table_with_joins(table_with_joins, outer, &mut relations);
+        }
+        let scope = Scope { relations, parent: outer };
+
+        let mut resolved = ResolvedColumns {
+            complete: true,
+            ..Default::default()
+        };
+
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let name = match expr {
+                        Expr::Identifier(ident) => ident.value.clone(),
+                        Expr::CompoundIdentifier(parts) => parts.last().map_or_else(|| expr.to_string(), |p| p.value.clone()),
+                        _ => expr.to_string(),
+                    };
+                    let referenced = self.resolve_expr_columns(expr, &scope, &resolved.columns);
+                    resolved.columns.push(ResolvedColumn::from_expr(name, expr, referenced));
+                },
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let referenced = self.resolve_expr_columns(expr, &scope, &resolved.columns);
+                    resolved.columns.push(ResolvedColumn::from_expr(alias.value.clone(), expr, referenced));
+                },
+                SelectItem::Wildcard(options) => {
+                    for relation in &scope.relations {
+                        expand_wildcard(relation, options, &mut resolved);
+                    }
+                },
+                SelectItem::QualifiedWildcard(object_name, options) => {
+                    let qualifier = object_name.0.last().map(|i| i.value.as_str()).unwrap_or_default();
+                    match find_relation(&scope, qualifier) {
+                        Some(relation) => expand_wildcard(relation, options, &mut resolved),
+                        None => resolved.complete = false,
+                    }
+                },
+            }
+        }
+
+        // Filters and join keys inside CTEs and derived tables still shape this query's rows
+        for relation in &scope.relations {
+            for condition in &relation.columns.conditions {
+                resolved.add_condition(condition.clone());
+            }
+            for join_key in &relation.columns.join_keys {
+                resolved.add_join_key(join_key.clone());
+            }
+        }
+
+        let mut join_conditions = Vec::new();
+        let mut join_columns = Vec::new();
+        for table_with_joins in &select.from {
+            collect_join_constraints(table_with_joins, &mut join_conditions, &mut join_columns);
+        }
+        for condition in join_conditions {
+            self.add_conditions(TransformationKind::JoinKey, condition, &scope, &mut resolved);
+            add_join_keys(JoinClause::On, condition, &scope, &mut resolved);
+        }
+        for columns in join_columns {
+            for column in columns.iter() {
+                // USING compares the column of the same name on each joined relation
+                let keys: Vec<(String, String)> = scope.relations.iter()
+                    .filter_map(|relation| relation.columns.lookup(&column.value))
+                    .filter_map(|c| table_column_source(&c))
+                    .collect();
+                for pair in keys.windows(2) {
+                    if let Some(join_key) = JoinKey::between(JoinClause::Using, &pair[0], &pair[1]) {
+                        resolved.add_join_key(join_key);
+                    }
+                }
+            }
+
+            let expression = format!("USING ({})", columns.iter().map(|c| c.value.as_str()).collect::<Vec<_>>().join(", "));
+            for column in columns {
+                for relation in &scope.relations {
+                    for (table, source_column) in relation.columns.lookup(&column.value).map(|c| c.sources).unwrap_or_default() {
+                        resolved.add_condition(ResolvedCondition {
+                            kind: TransformationKind::JoinKey,
+                            source: (table, source_column),
+                            expression: expression.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        for predicate in [&select.selection, &select.having, &select.qualify].into_iter().flatten() {
+            self.add_conditions(TransformationKind::Filter, predicate, &scope, &mut resolved);
+        }
+
+        // Comma joins compare keys in WHERE instead of ON
+        if let Some(selection) = &select.selection {
+            add_join_keys(JoinClause::Where, selection, &scope, &mut resolved);
+        }
+
+        // Correlated subqueries compare their tables with the enclosing query's in their own WHERE
+        for join_key in std::mem::take(&mut self.subquery_join_keys) {
+            resolved.add_join_key(join_key);
+        }
+
+        resolved
+    }
+
+    /// Record the columns read by a WHERE/HAVING/QUALIFY or join predicate
+    fn add_conditions(&mut self, kind: TransformationKind, predicate: &Expr, scope: &Scope, resolved: &mut ResolvedColumns) {
+        let expression = predicate.to_string();
+        for column in self.resolve_expr_columns(predicate, scope, &[]) {
+            for source in column.sources {
+                resolved.add_condition(ResolvedCondition {
+                    kind,
+                    source,
+                    expression: expression.clone(),
+                });
+            }
+        }
+    }
+
+    fn add_table_with_joins(&mut self, table_with_joins: &sqlparser::ast::TableWithJoins, outer: Option<&Scope>, relations: &mut Vec<ScopeRelation>) {
+        self.add_table_factor(&table_with_joins.relation, outer, relations);
+        for join in &table_with_joins.joins {
+            self.add_table_factor(&join.relation, outer, relations);
+        }
+    }
+
+    fn add_table_factor(&mut self, factor: &TableFactor, outer: Option<&Scope>, relations: &mut Vec<ScopeRelation>) {
+        match factor {
+            TableFactor::Table { name, alias, .. } => {
+                let table_name = name.to_string();
+                let base_name = name.0.last().map(|i| i.value.clone()).unwrap_or_else(|| table_name.clone());
+                let reference = alias.as_ref().map(|a| a.name.value.clone()).unwrap_or_else(|| base_name.clone());
+                let alias_columns = alias.as_ref().map(|a| a.columns.as_slice()).unwrap_or_default();
+
+                // Single-part names may refer to a CTE defined in an enclosing WITH clause
+                let cte_columns = if name.0.len() == 1 {
+                    self.ctes.iter().rev().find_map(|frame| frame.get(&base_name.to_lowercase())).cloned()
+                } else {
+                    None
+                };
+
+                let columns = match cte_columns {
+                    Some(columns) => columns,
+                    None => match self.schemas.get(&table_name).or_else(|| self.schemas.get(&base_name)) {
+                        Some(names) => ResolvedColumns {
+                            columns: names.iter()
+                                .map(|column| ResolvedColumn::passthrough(column, &table_name))
+                                .collect(),
+                            complete: true,
+                            ..Default::default()
+                        },
+                        None => ResolvedColumns::unknown_table(&table_name),
+                    },
+                };
+
+                relations.push(ScopeRelation {
+                    reference,
+                    columns: columns.rename(alias_columns),
+                });
+            },
+            TableFactor::Derived { subquery, alias, .. } => {
+                let columns = self.resolve_query(subquery, outer);
+                let (reference, alias_columns) = match alias {
+                    Some(a) => (a.name.value.clone(), a.columns.as_slice()),
+                    None => (String::new(), &[][..]),
+                };
+
+                relations.push(ScopeRelation {
+                    reference,
+                    columns: columns.rename(alias_columns),
+                });
+            },
+            TableFactor::NestedJoin { table_with_joins, .. } => {
+                self.add_table_with_joins(table_with_joins, outer, relations);
+            },
+            TableFactor::Pivot { table, alias, .. } | TableFactor::Unpivot { table, alias, .. } => {
+                // The output columns depend on the pivoted values, so only the source's are known
+                let mut sources = Vec::new();
+                self.add_table_factor(table, outer, &mut sources);
+
+                for source in sources {
+                    relations.push(ScopeRelation {
+                        reference: alias.as_ref().map(|a| a.name.value.clone()).unwrap_or(source.reference),
+                        columns: ResolvedColumns { complete: false, ..source.columns },
+                    });
+                }
+            },
+            TableFactor::Function { alias, .. }
+            | TableFactor::TableFunction { alias, .. }
+            | TableFactor::UNNEST { alias, .. } => {
+                // Table functions such as read_csv have no upstream model to trace into
+                relations.push(ScopeRelation {
+                    reference: alias.as_ref().map(|a| a.name.value.clone()).unwrap_or_default(),
+                    columns: ResolvedColumns::default(),
+                });
+            },
+            _ => {
+                tracing::debug!("Unsupported table factor for column lineage: {:?}", factor);
+            }
+        }
+    }
+
+    /// Resolve every column referenced by an expression
+    ///
+    /// `previous` holds the columns already projected by the same SELECT, which DuckDB
+    /// allows later expressions to reference by alias.
+    fn resolve_expr_columns(&mut self, expr: &Expr, scope: &Scope, previous: &[ResolvedColumn]) -> Vec<ResolvedColumn> {
+        let mut refs = ExprReferences::default();
+        collect_expr_references(expr, &mut refs);
+
+        let mut columns: Vec<ResolvedColumn> = refs.columns.iter()
+            .filter_map(|parts| resolve_column_reference(scope, parts, previous))
+            .collect();
+
+        // Scalar and EXISTS/IN subqueries contribute the columns they select
+        for subquery in refs.subqueries {
+            let resolved = self.resolve_query(subquery, Some(scope));
+            self.subquery_join_keys.extend(resolved.join_keys);
+            columns.extend(resolved.columns);
+        }
+
+        columns
+    }
+}
+
+/// Add the columns of a relation for `*` or `alias.*`, honouring EXCLUDE/EXCEPT
+fn expand_wildcard(relation: &ScopeRelation, options: &sqlparser::ast::WildcardAdditionalOptions, resolved: &mut ResolvedColumns) {
+    let mut excluded = Vec::new();
+    match &options.opt_exclude {
+        Some(sqlparser::ast::ExcludeSelectItem::Single(ident)) => excluded.push(ident.value.to_lowercase()),
+        Some(sqlparser::ast::ExcludeSelectItem::Multiple(idents)) => {
+            excluded.extend(idents.iter().map(|i| i.value.to_lowercase()));
+        },
+        None => {}
+    }
+    if let Some(except) = &options.opt_except {
+        excluded.push(except.first_element.value.to_lowercase());
+        excluded.extend(except.additional_elements.iter().map(|i| i.value.to_lowercase()));
+    }
+
+    for column in &relation.columns.columns {
+        if excluded.contains(&column.name.to_lowercase()) {
+            continue;
+        }
+
+        // REPLACE computes the column from the relation's own columns
+        let replacement = options.opt_replace.as_ref()
+            .and_then(|replace| replace.items.iter().find(|item| item.column_name.value.eq_ignore_ascii_case(&column.name)));
+        let mut column = match replacement {
+            Some(item) => {
+                let mut refs = ExprReferences::default();
+                collect_expr_references(&item.expr, &mut refs);
+                let referenced = refs.columns.iter()
+                    .filter_map(|parts| parts.last())
+                    .filter_map(|name| relation.columns.lookup(&name.value))
+                    .collect();
+                ResolvedColumn::from_expr(column.name.clone(), &item.expr, referenced)
+            },
+            None => column.clone(),
+        };
+
+        match &options.opt_rename {
+            Some(sqlparser::ast::RenameSelectItem::Single(rename)) if rename.ident.value.eq_ignore_ascii_case(&column.name) => {
+                column.name = rename.alias.value.clone();
+            },
+            Some(sqlparser::ast::RenameSelectItem::Multiple(renames)) => {
+                if let Some(rename) = renames.iter().find(|r| r.ident.value.eq_ignore_ascii_case(&column.name)) {
+                    column.name = rename.alias.value.clone();
+                }
+            },
+            _ => {}
+        }
+
+        resolved.columns.push(column);
+    }
+
+    for table in &relation.columns.unexpanded {
+        if !resolved.unexpanded.contains(table) {
+            resolved.unexpanded.push(table.clone());
+        }
+    }
+
+    if !relation.columns.complete {
+        tracing::debug!("Cannot fully expand * for {}: schema unknown", relation.reference);
+        resolved.complete = false;
+    }
+}
+
+/// Find a relation in scope (or an enclosing scope) by alias or table name
+fn find_relation<'a>(scope: &'a Scope, qualifier: &str) -> Option<&'a ScopeRelation> {
+    scope.relations.iter()
+        .find(|r| r.reference.eq_ignore_ascii_case(qualifier))
+        .or_else(|| scope.parent.and_then(|parent| find_relation(parent, qualifier)))
+}
+
+/// Resolve a (possibly qualified) column reference to the column it reads
+fn resolve_column_reference(scope: &Scope, parts: &[Ident], previous: &[ResolvedColumn]) -> Option<ResolvedColumn> {
+    let column = parts.last()?.value.as_str();
+
+    if parts.len() >= 2 {
+        let qualifier = parts[parts.len() - 2].value.as_str();
+        return match find_relation(scope, qualifier) {
+            Some(relation) => relation.columns.lookup(column),
+            None => {
+                tracing::debug!("Unresolved qualifier {} for column {}", qualifier, column);
+                None
+            }
+        };
+    }
+
+    // Unqualified: prefer a relation that lists the column, then an alias projected
+    // earlier in the same SELECT, then the only relation selected with an unexpanded `*`
+    if let Some(relation) = scope.relations.iter().find(|r| r.columns.find(column).is_some()) {
+        return relation.columns.lookup(column);
+    }
+
+    if let Some(projected) = previous.iter().find(|c| c.name.eq_ignore_ascii_case(column)) {
+        return Some(projected.clone());
+    }
+
+    let unexpanded: Vec<_> = scope.relations.iter().filter(|r| !r.columns.unexpanded.is_empty()).collect();
+    if let [relation] = unexpanded.as_slice() {
+        return relation.columns.lookup(column);
+    }
+
+    if let Some(parent) = scope.parent {
+        return resolve_column_reference(parent, parts, &[]);
+    }
+
+    tracing::debug!("Could not resolve unqualified column {}", column);
+    None
+}
+
+/// Record the column pairs compared with `=` in a join condition
+fn add_join_keys(clause: JoinClause, condition: &Expr, scope: &Scope, resolved: &mut ResolvedColumns) {
+    match condition {
+        Expr::Nested(inner) => add_join_keys(clause, inner, scope, resolved),
+        Expr::BinaryOp { left, op: BinaryOperator::And, right } => {
+            add_join_keys(clause, left, scope, resolved);
+            add_join_keys(clause, right, scope, resolved);
+        },
+        Expr::BinaryOp { left, op: BinaryOperator::Eq, right } => {
+            let (Some(left), Some(right)) = (column_parts(left), column_parts(right)) else { return };
+
+            // Columns of one relation instance compared with each other are a filter, not a join;
+            // a self join such as `employees e JOIN employees m` still compares two instances
+            let relations = (reference_relation(scope, left), reference_relation(scope, right));
+            if let (Some(left), Some(right)) = relations {
+                if std::ptr::eq(left, right) {
+                    return;
+                }
+            }
+
+            let left = resolve_column_reference(scope, left, &[]);
+            let right = resolve_column_reference(scope, right, &[]);
+            if let (Some(left), Some(right)) = (left, right) {
+                let sources = (table_column_source(&left), table_column_source(&right));
+                if let (Some(left), Some(right)) = sources {
+                    if let Some(join_key) = JoinKey::between(clause, &left, &right) {
+                        resolved.add_join_key(join_key);
+                    }
+                }
+            }
+        },
+        _ => {}
+    }
+}
+
+/// The relation in scope (or an enclosing scope) a column reference reads from, if it names one
+fn reference_relation<'a>(scope: &'a Scope, parts: &[Ident]) -> Option<&'a ScopeRelation> {
+    let column = parts.last()?.value.as_str();
+
+    if parts.len() >= 2 {
+        return find_relation(scope, parts[parts.len() - 2].value.as_str());
+    }
+
+    scope.relations.iter()
+        .find(|r| r.columns.find(column).is_some())
+        .or_else(|| scope.parent.and_then(|parent| reference_relation(parent, parts)))
+}
+
+/// The identifier parts of a bare column reference
+fn column_parts(expr: &Expr) -> Option<&[Ident]> {
+    match expr {
+        Expr::Identifier(ident) => Some(std::slice::from_ref(ident)),
+        Expr::CompoundIdentifier(parts) => Some(parts),
+        Expr::Nested(inner) => column_parts(inner),
+        _ => None,
+    }
+}
+
+/// The single table column a resolved column is copied from, if any
+fn table_column_source(column: &ResolvedColumn) -> Option<(String, String)> {
+    match column.sources.as_slice() {
+        [source] if column.kind <= TransformationKind::Cast => Some(source.clone()),
+        _ => None,
+    }
+}
+
+/// Collect the ON/USING constraints of every join in a FROM item
+fn collect_join_constraints<'a>(
+    table_with_joins: &'a sqlparser::ast::TableWithJoins,
+    conditions: &mut Vec<&'a Expr>,
+    columns: &mut Vec<&'a [Ident]>,
+) {
+    let mut factors = vec![&table_with_joins.relation];
+
+    for join in &table_with_joins.joins {
+        factors.push(&join.relation);
+
+        let constraint = match &join.join_operator {
+            JoinOperator::Inner(c)
+            | JoinOperator::LeftOuter(c)
+            | JoinOperator::RightOuter(c)
+            | JoinOperator::FullOuter(c)
+            | JoinOperator::LeftSemi(c)
+            | JoinOperator::RightSemi(c)
+            | JoinOperator::LeftAnti(c)
+            | JoinOperator::RightAnti(c) => Some(c),
+            JoinOperator::AsOf { match_condition, constraint } => {
+                conditions.push(match_condition);
+                Some(constraint)
+            },
+            JoinOperator::CrossJoin | JoinOperator::CrossApply | JoinOperator::OuterApply => None,
+        };
+
+        match constraint {
+            Some(JoinConstraint::On(expr)) => conditions.push(expr),
+            Some(JoinConstraint::Using(idents)) => columns.push(idents),
+            _ => {}
+        }
+    }
+
+    for factor in factors {
+        if let TableFactor::NestedJoin { table_with_joins, .. } = factor {
+            collect_join_constraints(table_with_joins, conditions, columns);
+        }
+    }
+}
+
+/// Aggregate functions recognised when classifying column transformations
+const AGGREGATE_FUNCTIONS: &[&str] = &[
+    "any_value", "approx_count_distinct", "approx_quantile", "arbitrary", "arg_max", "arg_min",
+    "argmax", "argmin", "array_agg", "avg", "bit_and", "bit_or", "bit_xor", "bool_and", "bool_or",
+    "corr", "count", "count_if", "count_star", "covar_pop", "covar_samp", "entropy", "every", "favg",
+    "first", "fsum", "geomean", "group_concat", "histogram", "kurtosis", "last", "list", "listagg",
+    "mad", "max", "max_by", "mean", "median", "min", "min_by", "mode", "product", "quantile",
+    "quantile_cont", "quantile_disc", "skewness", "stddev", "stddev_pop", "stddev_samp",
+    "string_agg", "sum", "sumkahan", "var_pop", "var_samp", "variance",
+];
+
+/// Classify how a projected expression transforms the columns it reads
+fn classify_expr(expr: &Expr) -> TransformationKind {
+    match expr {
+        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => return TransformationKind::Passthrough,
+        Expr::Nested(inner) => return classify_expr(inner),
+        _ => {}
+    }
+
+    let mut refs = ExprReferences::default();
+    collect_expr_references(expr, &mut refs);
+
+    if refs.functions.iter().any(|f| f.over.is_some()) {
+        return TransformationKind::Window;
+    }
+
+    let is_aggregate = refs.functions.iter().any(|f| {
+        let name = f.name.0.last().map(|i| i.value.to_lowercase()).unwrap_or_default();
+        f.filter.is_some() || !f.within_group.is_empty() || AGGREGATE_FUNCTIONS.contains(&name.as_str())
+    });
+    if is_aggregate {
+        return TransformationKind::Aggregate;
+    }
+
+    match expr {
+        Expr::Cast { expr: inner, .. } if classify_expr(inner) == TransformationKind::Passthrough => TransformationKind::Cast,
+        _ => TransformationKind::Expression,
+    }
+}
+
+/// Column references, subqueries and function calls found inside an expression
+#[derive(Default)]
+struct ExprReferences<'a> {
+    columns: Vec<Vec<Ident>>,
+    subqueries: Vec<&'a Query>,
+    functions: Vec<&'a sqlparser::ast::Function>,
+}
+
+/// Collect the column references and nested subqueries inside an expression
+fn collect_expr_references<'a>(expr: &'a Expr, refs: &mut ExprReferences<'a>) {
+    match expr {
+        Expr::Identifier(ident) => refs.columns.push(vec![ident.clone()]),
+        Expr::CompoundIdentifier(parts) => refs.columns.push(parts.clone()),
+        Expr::IsFalse(e) | Expr::IsNotFalse(e) | Expr::IsTrue(e) | Expr::IsNotTrue(e)
+        | Expr::IsNull(e) | Expr::IsNotNull(e) | Expr::IsUnknown(e) | Expr::IsNotUnknown(e)
+        | Expr::Nested(e) | Expr::OuterJoin(e) | Expr::Prior(e) => {
+            collect_expr_references(e, refs);
+        },
+        Expr::UnaryOp { expr: e, .. }
+        | Expr::Cast { expr: e, .. }
+        | Expr::Convert { expr: e, .. }
+        | Expr::Extract { expr: e, .. }
+        | Expr::Ceil { expr: e, .. }
+        | Expr::Floor { expr: e, .. }
+        | Expr::Collate { expr: e, .. }
+        | Expr::Named { expr: e, .. }
+        | Expr::CompositeAccess { expr: e, .. }
+        | Expr::JsonAccess { value: e, .. } => {
+            collect_expr_references(e, refs);
+        },
+        Expr::IsDistinctFrom(a, b) | Expr::IsNotDistinctFrom(a, b) => {
+            collect_expr_references(a, refs);
+            collect_expr_references(b, refs);
+        },
+        Expr::BinaryOp { left, right, .. }
+        | Expr::AnyOp { left, right, .. }
+        | Expr::AllOp { left, right, .. } => {
+            collect_expr_references(left, refs);
+            collect_expr_references(right, refs);
+        },
+        Expr::Like { expr, pattern, .. }
+        | Expr::ILike { expr, pattern, .. }
+        | Expr::SimilarTo { expr, pattern, .. }
+        | Expr::RLike { expr, pattern, .. } => {
+            collect_expr_references(expr, refs);
+            collect_expr_references(pattern, refs);
+        },
+        Expr::InList { expr, list, .. } => {
+            collect_expr_references(expr, refs);
+            for item in list {
+                collect_expr_references(item, refs);
+            }
+        },
+        Expr::InSubquery { expr, subquery, .. } => {
+            collect_expr_references(expr, refs);
+            refs.subqueries.push(subquery);
+        },
+        Expr::InUnnest { expr, array_expr, .. } => {
+            collect_expr_references(expr, refs);
+            collect_expr_references(array_expr, refs);
+        },
+        Expr::Between { expr, low, high, .. } => {
+            collect_expr_references(expr, refs);
+            collect_expr_references(low, refs);
+            collect_expr_references(high, refs);
+        },
+        Expr::AtTimeZone { timestamp, time_zone } => {
+            collect_expr_references(timestamp, refs);
+            collect_expr_references(time_zone, refs);
+        },
+        Expr::Position { expr, r#in } => {
+            collect_expr_references(expr, refs);
+            collect_expr_references(r#in, refs);
+        },
+        Expr::Substring { expr, substring_from, substring_for, .. } => {
+            collect_expr_references(expr, refs);
+            for e in substring_from.iter().chain(substring_for.iter()) {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Trim { expr, trim_what, trim_characters, .. } => {
+            collect_expr_references(expr, refs);
+            if let Some(what) = trim_what {
+                collect_expr_references(what, refs);
+            }
+            for e in trim_characters.iter().flatten() {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Overlay { expr, overlay_what, overlay_from, overlay_for } => {
+            collect_expr_references(expr, refs);
+            collect_expr_references(overlay_what, refs);
+            collect_expr_references(overlay_from, refs);
+            if let Some(e) = overlay_for {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::MapAccess { column, .. } => collect_expr_references(column, refs),
+        Expr::Subscript { expr, .. } => collect_expr_references(expr, refs),
+        Expr::Function(func) => collect_function_references(func, refs),
+        Expr::Case { operand, conditions, results, else_result } => {
+            if let Some(e) = operand {
+                collect_expr_references(e, refs);
+            }
+            for e in conditions.iter().chain(results.iter()) {
+                collect_expr_references(e, refs);
+            }
+            if let Some(e) = else_result {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Exists { subquery, .. } | Expr::Subquery(subquery) => refs.subqueries.push(subquery),
+        Expr::GroupingSets(sets) | Expr::Cube(sets) | Expr::Rollup(sets) => {
+            for e in sets.iter().flatten() {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Tuple(items) | Expr::Struct { values: items, .. } => {
+            for e in items {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Array(array) => {
+            for e in &array.elem {
+                collect_expr_references(e, refs);
+            }
+        },
+        Expr::Dictionary(fields) => {
+            for field in fields {
+                collect_expr_references(&field.value, refs);
+            }
+        },
+        Expr::Map(map) => {
+            for entry in &map.entries {
+                collect_expr_references(&entry.key, refs);
+                collect_expr_references(&entry.value, refs);
+            }
+        },
+        Expr::Interval(interval) => collect_expr_references(&interval.value, refs),
+        Expr::Lambda(lambda) => {
+            // Lambda parameters shadow columns inside the lambda body
+            let first_body_reference = refs.columns.len();
+            collect_expr_references(&lambda.body, refs);
+            let params: Vec<String> = match &lambda.params {
+                sqlparser::ast::OneOrManyWithParens::One(param) => vec![param.value.to_lowercase()],
+                sqlparser::ast::OneOrManyWithParens::Many(params) => params.iter().map(|p| p.value.to_lowercase()).collect(),
             };
-            ("literal".to_string(), data_type, true, Some(format!("{:?}", expr)))
+            let body_references = refs.columns.split_off(first_body_reference);
+            refs.columns.extend(body_references.into_iter().filter(|parts| {
+                parts.first().is_none_or(|first| !params.contains(&first.value.to_lowercase()))
+            }));
         },
-        _ => {
-            // Other expression types
-            ("expression".to_string(), "unknown".to_string(), true, Some(format!("{:?}", expr)))
-        }
+        Expr::Value(_)
+        | Expr::IntroducedString { .. }
+        | Expr::TypedString { .. }
+        | Expr::MatchAgainst { .. }
+        | Expr::Wildcard
+        | Expr::QualifiedWildcard(_) => {}
     }
 }
 
-/// Extract source table and column from an expression
-///
-/// Returns: Option<(source_table, source_column)>
-fn extract_source_from_expr(expr: &Expr) -> Option<(String, String)> {
-    match expr {
-        Expr::Identifier(_) => {
-            // Simple column reference without table - can't determine source table
-            None
-        },
-        Expr::CompoundIdentifier(parts) => {
-            // Column reference with table qualifier (e.g., table.column)
-            if parts.len() >= 2 {
-                let table = parts[0].to_string();
-                let column = parts[1].to_string();
-                Some((table, column))
-            } else {
-                None
-            }
-        },
-        _ => {
-            // Other expression types - derived, not direct source
-            None
+/// Collect the column references used by a function call, including window and FILTER clauses
+fn collect_function_references<'a>(func: &'a sqlparser::ast::Function, refs: &mut ExprReferences<'a>) {
+    refs.functions.push(func);
+
+    for arguments in [&func.parameters, &func.args] {
+        match arguments {
+            FunctionArguments::List(list) => {
+                for arg in &list.args {
+                    let arg_expr = match arg {
+                        sqlparser::ast::FunctionArg::Named { arg, .. } => arg,
+                        sqlparser::ast::FunctionArg::Unnamed(arg) => arg,
+                    };
+                    if let sqlparser::ast::FunctionArgExpr::Expr(e) = arg_expr {
+                        collect_expr_references(e, refs);
+                    }
+                }
+            },
+            FunctionArguments::Subquery(query) => refs.subqueries.push(query),
+            FunctionArguments::None => {}
+        }
+    }
+
+    if let Some(filter) = &func.filter {
+        collect_expr_references(filter, refs);
+    }
+
+    if let Some(sqlparser::ast::WindowType::WindowSpec(spec)) = &func.over {
+        for e in &spec.partition_by {
+            collect_expr_references(e, refs);
         }
+        for order in &spec.order_by {
+            collect_expr_references(&order.expr, refs);
+        }
+    }
+
+    for order in &func.within_group {
+        collect_expr_references(&order.expr, refs);
     }
 }
 
-/// Extract column-level lineage from a SQL query
+/// Split a SQL file into the source text of each statement
+///
+/// Statements are split on semicolons outside string literals, quoted identifiers and
+/// comments, so each statement can be executed exactly as written instead of re-printed
+/// from its parsed tree.
 ///
 /// # Arguments
 ///
-/// * `statement` - SQL statement to extract lineage from
-/// * `model_name` - Name of the model/table being created
+/// * `sql` - SQL string to split
 ///
 /// # Returns
 ///
-/// * `Vec<TableColumnRelationship>` - Vector of column relationships
-pub fn extract_column_lineage(statement: &Statement, model_name: &str) -> Result<Vec<TableColumnRelationship>> {
-    let mut relationships = Vec::new();
-    
-    if let Statement::Query(query) = statement {
-        extract_column_lineage_from_query(query, model_name, &mut relationships)?;
-    }
-    
-    Ok(relationships)
-}
+/// * `Result<Vec<String>>` - Trimmed source text of each statement that is not only comments
+pub fn split_statements(sql: &str) -> Result<Vec<String>> {
+    let tokens = Tokenizer::new(&DuckDbDialect {}, sql)
+        .tokenize_with_location()
+        .context("Failed to tokenize SQL")?;
 
-/// Extract column-level lineage from a query
-fn extract_column_lineage_from_query(query: &Query, model_name: &str, relationships: &mut Vec<TableColumnRelationship>) -> Result<()> {
-    match query.body.as_ref() {
-        SetExpr::Select(select) => {
-            // Process the projection (SELECT list)
-            for (index, item) in select.projection.iter().enumerate() {
-                match item {
-                    SelectItem::UnnamedExpr(expr) => {
-                        // Get target column name (use index if can't determine)
-                        let target_column = match expr {
-                            Expr::Identifier(ident) => ident.to_string(),
-                            Expr::CompoundIdentifier(parts) => parts.last().map_or(
-                                format!("column_{}", index), 
-                                |p| p.to_string()
-                            ),
-                            _ => format!("column_{}", index),
-                        };
-                        
-                        // Extract source information
-                        if let Some((source_table, source_column)) = extract_source_from_expr(expr) {
-                            relationships.push(TableColumnRelationship {
-                                source_table,
-                                source_column,
-                                target_column,
-                            });
-                        }
-                    },
-                    SelectItem::ExprWithAlias { expr, alias } => {
-                        // Target column is the alias
-                        let target_column = alias.to_string();
-                        
-                        // Extract source information
-                        if let Some((source_table, source_column)) = extract_source_from_expr(expr) {
-                            relationships.push(TableColumnRelationship {
-                                source_table,
-                                source_column,
-                                target_column,
-                            });
-                        }
-                    },
-                    _ => {
-                        // Handle wildcards or other selection items
-                        // For now, we'll skip these as they're more complex
-                    }
-                }
-            }
-        },
-        SetExpr::Query(subquery) => {
-            // Recursively extract from subquery
-            extract_column_lineage_from_query(subquery, model_name, relationships)?;
-        },
-        SetExpr::SetOperation { left, right, .. } => {
-            // Extract from both sides of the set operation
-            extract_column_lineage_from_set_expr(left, model_name, relationships)?;
-            extract_column_lineage_from_set_expr(right, model_name, relationships)?;
-        },
-        _ => {
-            // Unsupported expression type
-            tracing::warn!("Unsupported SetExpr type for column lineage extraction: {:?}", query.body);
-        }
-    }
-    
-    Ok(())
-}
+    // Locations are 1-based lines and character columns; map them back to byte offsets
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(sql.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let offset = |line: u64, column: u64| -> usize {
+        let start = line_starts.get(line.saturating_sub(1) as usize).copied().unwrap_or(sql.len());
+        sql[start..].char_indices()
+            .nth(column.saturating_sub(1) as usize)
+            .map(|(i, _)| start + i)
+            .unwrap_or(sql.len())
+    };
 
-/// Extract column-level lineage from a set expression
-fn extract_column_lineage_from_set_expr(expr: &SetExpr, model_name: &str, relationships: &mut Vec<TableColumnRelationship>) -> Result<()> {
-    match expr {
-        SetExpr::Select(select) => {
-            // Process the projection (SELECT list)
-            for (index, item) in select.projection.iter().enumerate() {
-                match item {
-                    SelectItem::UnnamedExpr(expr) => {
-                        // Get target column name (use index if can't determine)
-                        let target_column = match expr {
-                            Expr::Identifier(ident) => ident.to_string(),
-                            Expr::CompoundIdentifier(parts) => parts.last().map_or(
-                                format!("column_{}", index), 
-                                |p| p.to_string()
-                            ),
-                            _ => format!("column_{}", index),
-                        };
-                        
-                        // Extract source information
-                        if let Some((source_table, source_column)) = extract_source_from_expr(expr) {
-                            relationships.push(TableColumnRelationship {
-                                source_table,
-                                source_column,
-                                target_column,
-                            });
-                        }
-                    },
-                    SelectItem::ExprWithAlias { expr, alias } => {
-                        // Target column is the alias
-                        let target_column = alias.to_string();
-                        
-                        // Extract source information
-                        if let Some((source_table, source_column)) = extract_source_from_expr(expr) {
-                            relationships.push(TableColumnRelationship {
-                                source_table,
-                                source_column,
-                                target_column,
-                            });
-                        }
-                    },
-                    _ => {
-                        // Handle wildcards or other selection items
-                        // For now, we'll skip these
-                    }
+    // Text after the last semicolon that is only comments, like a trailing config, is not a statement
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut has_code = false;
+    for token in &tokens {
+        match &token.token {
+            Token::SemiColon => {
+                let end = offset(token.location.line, token.location.column);
+                if has_code {
+                    statements.push(&sql[start..end]);
                 }
+                start = end + 1;
+                has_code = false;
             }
-        },
-        SetExpr::Query(subquery) => {
-            // Recursively extract from subquery
-            extract_column_lineage_from_query(subquery, model_name, relationships)?;
-        },
-        SetExpr::SetOperation { left, right, .. } => {
-            // Extract from both sides of the set operation
-            extract_column_lineage_from_set_expr(left, model_name, relationships)?;
-            extract_column_lineage_from_set_expr(right, model_name, relationships)?;
-        },
-        _ => {
-            // Unsupported expression type
-            tracing::warn!("Unsupported SetExpr type for column lineage extraction: {:?}", expr);
+            Token::Whitespace(_) => {}
+            _ => has_code = true,
         }
     }
-    
-    Ok(())
+    if has_code {
+        statements.push(&sql[start.min(sql.len())..]);
+    }
+
+    Ok(statements.into_iter()
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .map(str::to_string)
+        .collect())
 }
 
 /// Check if a statement is a SELECT query
@@ -1575,8 +1600,103 @@ pub struct TableColumnRelationship {
     pub source_table: String,
     /// Source column
     pub source_column: String,
-    /// Target column
+    /// Target column, or `*` for filter and join key columns
     pub target_column: String,
+    /// How the target column is computed from the source column
+    pub transformation: TransformationKind,
+    /// Expression text for computed columns and predicates
+    pub expression: Option<String>,
+}
+
+/// A pair of table columns compared for equality in a join condition
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct JoinKey {
+    /// Table on the left of the comparison
+    pub left_table: String,
+    /// Column on the left of the comparison
+    pub left_column: String,
+    /// Table on the right of the comparison
+    pub right_table: String,
+    /// Column on the right of the comparison
+    pub right_column: String,
+    /// Clause the comparison appears in
+    pub clause: JoinClause,
+}
+
+impl JoinKey {
+    /// Join key between two table columns, or `None` when a column is compared with itself
+    ///
+    /// Both columns may be in the same table when a self join relates two instances of it.
+    fn between(clause: JoinClause, left: &(String, String), right: &(String, String)) -> Option<Self> {
+        if left.0.eq_ignore_ascii_case(&right.0) && left.1.eq_ignore_ascii_case(&right.1) {
+            return None;
+        }
+
+        Some(Self {
+            left_table: left.0.clone(),
+            left_column: left.1.clone(),
+            right_table: right.0.clone(),
+            right_column: right.1.clone(),
+            clause,
+        })
+    }
+}
+
+/// Clause in which two tables were compared on a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JoinClause {
+    /// `JOIN ... ON a.x = b.y`
+    On,
+    /// `JOIN ... USING (x)`
+    Using,
+    /// `WHERE a.x = b.y`
+    Where,
+}
+
+/// How a model column is derived from an upstream column
+///
+/// Variants are ordered from least to most transformed so that kinds can be
+/// combined when a column passes through several CTEs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum TransformationKind {
+    /// Copied unchanged
+    Passthrough,
+    /// Copied under a different name
+    Rename,
+    /// Copied with a type cast
+    Cast,
+    /// Computed by a scalar expression
+    Expression,
+    /// Computed by an aggregate function
+    Aggregate,
+    /// Computed by a window function
+    Window,
+    /// Used in a WHERE, HAVING or QUALIFY predicate
+    Filter,
+    /// Used in a join condition
+    JoinKey,
+}
+
+impl TransformationKind {
+    /// Whether the target column holds values computed rather than copied from the source
+    pub fn is_computed(&self) -> bool {
+        matches!(self, TransformationKind::Expression | TransformationKind::Aggregate | TransformationKind::Window)
+    }
+}
+
+impl fmt::Display for TransformationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransformationKind::Passthrough => write!(f, "passthrough"),
+            TransformationKind::Rename => write!(f, "rename"),
+            TransformationKind::Cast => write!(f, "cast"),
+            TransformationKind::Expression => write!(f, "expression"),
+            TransformationKind::Aggregate => write!(f, "aggregate"),
+            TransformationKind::Window => write!(f, "window"),
+            TransformationKind::Filter => write!(f, "filter"),
+            TransformationKind::JoinKey => write!(f, "join_key"),
+        }
+    }
 }
 
 /// Extract table names from a SQL query
@@ -1589,78 +1709,258 @@ pub struct TableColumnRelationship {
 ///
 /// * `HashSet<String>` - Set of table names
 pub fn extract_tables(statement: &Statement) -> HashSet<String> {
-    let mut tables = HashSet::new();
-    
     tracing::info!("Extracting tables from statement: {:?}", statement);
-    
+
+    let tables = extract_references(statement).tables;
+
+    tracing::info!("Extracted tables: {:?}", tables);
+    tables
+}
+
+/// Tables, CTEs and table functions referenced by a statement
+#[derive(Debug, Clone, Default)]
+pub struct StatementReferences {
+    /// Names in FROM clauses, including CTE names and table functions
+    pub tables: HashSet<String>,
+    /// Names of the CTEs the statement defines
+    pub ctes: HashSet<String>,
+    /// Table functions such as `read_csv` with their string literal arguments
+    pub table_functions: Vec<(String, Vec<String>)>,
+}
+
+/// Extract every table, CTE and table function a statement references
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to extract references from
+///
+/// # Returns
+///
+/// * `StatementReferences` - References found anywhere in the statement
+pub fn extract_references(statement: &Statement) -> StatementReferences {
+    let mut references = StatementReferences::default();
+
     if let Statement::Query(query) = statement {
-        tracing::info!("Statement is a Query, processing...");
-        extract_tables_from_query(query, &mut tables);
+        extract_tables_from_query(query, &mut references);
     } else {
         tracing::info!("Statement is not a Query, skipping: {:?}", statement);
     }
-    
-    tracing::info!("Extracted tables: {:?}", tables);
-    tables
+
+    references
 }
 
 /// Extract table names from a SQL query
-fn extract_tables_from_query(query: &Query, tables: &mut HashSet<String>) {
-    // Add debug logging
+fn extract_tables_from_query(query: &Query, references: &mut StatementReferences) {
     tracing::debug!("Extracting tables from query: {:?}", query);
-    
-    if let SetExpr::Select(select) = query.body.as_ref() {
-        tracing::debug!("Processing SELECT with {} FROM clauses", select.from.len());
-        
-        for table_with_join in &select.from {
-            tracing::debug!("Processing FROM clause: {:?}", table_with_join);
-            
-            match &table_with_join.relation {
-                TableFactor::Table { name, .. } => {
-                    // Add the table name to the set
-                    let table_name = name.to_string();
-                    tracing::debug!("Found table: {}", table_name);
-                    tables.insert(table_name);
-                }
-                // Handle other table factors like subqueries
-                TableFactor::Derived { subquery, .. } => {
-                    tracing::debug!("Processing derived table (subquery)");
-                    extract_tables_from_query(subquery, tables);
-                }
-                _ => {
-                    tracing::debug!("Unsupported table factor type: {:?}", table_with_join.relation);
-                }
+
+    extract_tables_from_set_expr(&query.body, references);
+
+    // Also check for CTEs (WITH clause)
+    if let Some(with) = &query.with {
+        tracing::debug!("Processing WITH clause with {} CTEs", with.cte_tables.len());
+        for cte in &with.cte_tables {
+            tracing::debug!("Processing CTE: {}", cte.alias.name);
+            references.ctes.insert(cte.alias.name.value.clone());
+            extract_tables_from_query(&cte.query, references);
+        }
+    }
+}
+
+/// Extract table names from a query body, including both sides of set operations
+fn extract_tables_from_set_expr(set_expr: &SetExpr, references: &mut StatementReferences) {
+    match set_expr {
+        SetExpr::Select(select) => {
+            tracing::debug!("Processing SELECT with {} FROM clauses", select.from.len());
+
+            for table_with_join in &select.from {
+                extract_tables_from_table_with_joins(table_with_join, references);
             }
-            
-            // Handle joins
-            tracing::debug!("Processing {} joins", table_with_join.joins.len());
-            for join in &table_with_join.joins {
-                match &join.relation {
-                    TableFactor::Table { name, .. } => {
-                        let table_name = name.to_string();
-                        tracing::debug!("Found joined table: {}", table_name);
-                        tables.insert(table_name);
-                    }
-                    TableFactor::Derived { subquery, .. } => {
-                        tracing::debug!("Processing joined derived table (subquery)");
-                        extract_tables_from_query(subquery, tables);
-                    }
-                    _ => {
-                        tracing::debug!("Unsupported join relation type: {:?}", join.relation);
-                    }
-                }
+
+            // Subqueries in expressions read tables too
+            for subquery in select_expr_references(select).subqueries {
+                extract_tables_from_query(subquery, references);
             }
+        },
+        SetExpr::Query(query) => extract_tables_from_query(query, references),
+        SetExpr::SetOperation { left, right, .. } => {
+            extract_tables_from_set_expr(left, references);
+            extract_tables_from_set_expr(right, references);
+        },
+        _ => {
+            tracing::debug!("Query body has no tables: {:?}", set_expr);
         }
-    } else {
-        tracing::debug!("Query is not a SELECT: {:?}", query.body);
     }
-    
-    // Also check for CTEs (WITH clause)
+}
+
+/// Extract table names from a FROM item and its joins
+fn extract_tables_from_table_with_joins(table_with_joins: &sqlparser::ast::TableWithJoins, references: &mut StatementReferences) {
+    tracing::debug!("Processing FROM clause: {:?}", table_with_joins);
+
+    extract_tables_from_table_factor(&table_with_joins.relation, references);
+    for join in &table_with_joins.joins {
+        extract_tables_from_table_factor(&join.relation, references);
+    }
+}
+
+/// Extract table names from a single table factor
+fn extract_tables_from_table_factor(factor: &TableFactor, references: &mut StatementReferences) {
+    match factor {
+        TableFactor::Table { name, args, .. } => {
+            let table_name = name.to_string();
+            tracing::debug!("Found table: {}", table_name);
+
+            if let Some(args) = args {
+                let literals = args.iter()
+                    .filter_map(|arg| match arg {
+                        sqlparser::ast::FunctionArg::Unnamed(sqlparser::ast::FunctionArgExpr::Expr(expr))
+                        | sqlparser::ast::FunctionArg::Named { arg: sqlparser::ast::FunctionArgExpr::Expr(expr), .. } => Some(expr),
+                        _ => None,
+                    })
+                    .flat_map(string_literals)
+                    .collect();
+                references.table_functions.push((table_name.clone(), literals));
+            }
+
+            references.tables.insert(table_name);
+        },
+        TableFactor::Derived { subquery, .. } => {
+            tracing::debug!("Processing derived table (subquery)");
+            extract_tables_from_query(subquery, references);
+        },
+        TableFactor::NestedJoin { table_with_joins, .. } => {
+            extract_tables_from_table_with_joins(table_with_joins, references);
+        },
+        TableFactor::Pivot { table, .. } | TableFactor::Unpivot { table, .. } => {
+            extract_tables_from_table_factor(table, references);
+        },
+        _ => {
+            tracing::debug!("Unsupported table factor type: {:?}", factor);
+        }
+    }
+}
+
+/// String literals in a function argument, including inside lists of files
+fn string_literals(expr: &Expr) -> Vec<String> {
+    match expr {
+        Expr::Value(SqlValue::SingleQuotedString(s)) | Expr::Value(SqlValue::DoubleQuotedString(s)) => vec![s.clone()],
+        Expr::Array(array) => array.elem.iter().flat_map(string_literals).collect(),
+        Expr::Nested(inner) => string_literals(inner),
+        _ => Vec::new(),
+    }
+}
+
+/// Column references and subqueries in the expressions of a SELECT
+fn select_expr_references(select: &Select) -> ExprReferences<'_> {
+    let mut refs = ExprReferences::default();
+    for item in &select.projection {
+        match item {
+            SelectItem::UnnamedExpr(expr) | SelectItem::ExprWithAlias { expr, .. } => {
+                collect_expr_references(expr, &mut refs);
+            },
+            _ => {},
+        }
+    }
+    for expr in select.selection.iter().chain(&select.having).chain(&select.qualify) {
+        collect_expr_references(expr, &mut refs);
+    }
+    if let sqlparser::ast::GroupByExpr::Expressions(exprs, _) = &select.group_by {
+        for expr in exprs {
+            collect_expr_references(expr, &mut refs);
+        }
+    }
+    refs
+}
+
+/// Find single-part column names in SELECTs that read from more than one relation
+///
+/// Names in a USING clause and aliases projected by the same SELECT are not reported,
+/// since neither can be qualified.
+///
+/// # Arguments
+///
+/// * `statement` - SQL statement to check
+///
+/// # Returns
+///
+/// * `Vec<String>` - Unqualified column names, in order of appearance
+pub fn find_unqualified_columns(statement: &Statement) -> Vec<String> {
+    let mut found = Vec::new();
+    if let Statement::Query(query) = statement {
+        find_unqualified_in_query(query, &mut found);
+    }
+    found
+}
+
+fn find_unqualified_in_query(query: &Query, found: &mut Vec<String>) {
     if let Some(with) = &query.with {
-        tracing::debug!("Processing WITH clause with {} CTEs", with.cte_tables.len());
         for cte in &with.cte_tables {
-            tracing::debug!("Processing CTE: {}", cte.alias.name);
-            extract_tables_from_query(&cte.query, tables);
+            find_unqualified_in_query(&cte.query, found);
+        }
+    }
+    find_unqualified_in_set_expr(&query.body, found);
+}
+
+fn find_unqualified_in_set_expr(set_expr: &SetExpr, found: &mut Vec<String>) {
+    let select = match set_expr {
+        SetExpr::Select(select) => select,
+        SetExpr::Query(query) => return find_unqualified_in_query(query, found),
+        SetExpr::SetOperation { left, right, .. } => {
+            find_unqualified_in_set_expr(left, found);
+            find_unqualified_in_set_expr(right, found);
+            return;
+        },
+        _ => return,
+    };
+
+    let mut refs = select_expr_references(select);
+    let mut conditions = Vec::new();
+    let mut using = Vec::new();
+    let mut relation_count = 0;
+    let mut derived = Vec::new();
+
+    for table_with_joins in &select.from {
+        collect_join_constraints(table_with_joins, &mut conditions, &mut using);
+        count_relations(table_with_joins, &mut relation_count, &mut derived);
+    }
+    for condition in conditions {
+        collect_expr_references(condition, &mut refs);
+    }
+
+    if relation_count > 1 {
+        let aliases: Vec<String> = select.projection.iter()
+            .filter_map(|item| match item {
+                SelectItem::ExprWithAlias { alias, .. } => Some(alias.value.to_lowercase()),
+                _ => None,
+            })
+            .collect();
+        let using: Vec<String> = using.iter().flat_map(|idents| idents.iter()).map(|i| i.value.to_lowercase()).collect();
+
+        for parts in &refs.columns {
+            if let [column] = parts.as_slice() {
+                let name = column.value.to_lowercase();
+                if !aliases.contains(&name) && !using.contains(&name) && !found.contains(&column.value) {
+                    found.push(column.value.clone());
+                }
+            }
         }
     }
-}
\ No newline at end of file
+
+    for query in derived.into_iter().chain(refs.subqueries) {
+        find_unqualified_in_query(query, found);
+    }
+}
+
+/// Count the relations in a FROM item, collecting derived tables to check separately
+fn count_relations<'a>(table_with_joins: &'a sqlparser::ast::TableWithJoins, count: &mut usize, derived: &mut Vec<&'a Query>) {
+    let factors = std::iter::once(&table_with_joins.relation).chain(table_with_joins.joins.iter().map(|j| &j.relation));
+    for factor in factors {
+        match factor {
+            TableFactor::NestedJoin { table_with_joins, .. } => count_relations(table_with_joins, count, derived),
+            TableFactor::Derived { subquery, .. } => {
+                *count += 1;
+                derived.push(subquery);
+            },
+            _ => *count += 1,
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+use crate::executor::RunContext;
+use crate::parser::dependencies::Dependency;
+use crate::parser::sql::{is_select_tree, split_statements, ColumnInfo, TableColumnRelationship};
+
+/// Replace heuristic column types with the types DuckDB reports for each model
+///
+/// Each model is described from the table or view it was materialized as. Models that
+/// only exist as files are described from their SELECT statement instead, which DuckDB
+/// binds against the upstream tables without running it. Models that cannot be described
+/// keep the columns inferred from their SQL.
+///
+/// # Arguments
+///
+/// * `dependencies` - Map of model names to their dependencies
+/// * `context` - Run context connected to the database the models were run against
+/// * `schema` - Schema the models were created in
+pub fn apply_column_types(
+    dependencies: &mut HashMap<String, Dependency>,
+    context: &RunContext,
+    schema: &str,
+) {
+    let mut described_count = 0;
+
+    for (model_name, dependency) in dependencies.iter_mut() {
+        let described = match context.describe(&format!("{}.{}", schema, model_name)) {
+            Ok(columns) => Ok(columns),
+            Err(table_error) => {
+                tracing::debug!("Could not describe {}.{}: {}", schema, model_name, table_error);
+                describe_model_query(dependency, context)
+            }
+        };
+
+        match described {
+            Ok(columns) if !columns.is_empty() => {
+                dependency.columns = merge_described_columns(&dependency.columns, &dependency.column_lineage, columns);
+                described_count += 1;
+            },
+            Ok(_) => tracing::debug!("DuckDB reported no columns for {}", model_name),
+            Err(e) => tracing::debug!("Keeping inferred column types for {}: {}", model_name, e),
+        }
+    }
+
+    tracing::info!("Resolved column types for {} of {} models", described_count, dependencies.len());
+}
+
+/// Describe the final SELECT statement of a model, as written in its file
+///
+/// The source text is described rather than the parsed tree, which cannot always be
+/// printed back as the same DuckDB SQL.
+fn describe_model_query(dependency: &Dependency, context: &RunContext) -> Result<Vec<(String, String)>> {
+    let index = dependency.statements.iter()
+        .rposition(is_select_tree)
+        .context("Model has no SELECT statement")?;
+    let sources = split_statements(&dependency.sql)?;
+    if sources.len() != dependency.statements.len() {
+        anyhow::bail!("Found {} statements but parsed {}", sources.len(), dependency.statements.len());
+    }
+
+    context.describe(&sources[index])
+}
+
+/// Build the column list for a model from the columns DuckDB reports
+///
+/// Source information already inferred from the SQL is kept for columns with the same
+/// name; columns that were only known through `*` take their source from column lineage.
+///
+/// # Arguments
+///
+/// * `inferred` - Columns extracted from the SQL
+/// * `lineage` - Column lineage of the model
+/// * `described` - Column names and types reported by DuckDB
+///
+/// # Returns
+///
+/// * `Vec<ColumnInfo>` - Columns in the order DuckDB reports them
+pub fn merge_described_columns(
+    inferred: &[ColumnInfo],
+    lineage: &[TableColumnRelationship],
+    described: Vec<(String, String)>,
+) -> Vec<ColumnInfo> {
+    described.into_iter()
+        .map(|(name, data_type)| {
+            if let Some(column) = inferred.iter().find(|c| c.name.eq_ignore_ascii_case(&name)) {
+                return ColumnInfo {
+                    name,
+                    data_type,
+                    ..column.clone()
+                };
+            }
+
+            let sources: Vec<_> = lineage.iter()
+                .filter(|l| l.target_column.eq_ignore_ascii_case(&name))
+                .collect();
+            let copied_from = match sources.as_slice() {
+                [source] if !source.transformation.is_computed() => Some(*source),
+                _ => None,
+            };
+
+            ColumnInfo {
+                name,
+                data_type,
+                source_table: copied_from.map(|s| s.source_table.clone()),
+                source_column: copied_from.map(|s| s.source_column.clone()),
+                is_derived: copied_from.is_none() && !sources.is_empty(),
+                expression: if copied_from.is_none() { sources.first().and_then(|s| s.expression.clone()) } else { None },
+            }
+        })
+        .collect()
+}
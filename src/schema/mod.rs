@@ -5,8 +5,12 @@ use std::path::Path;
 
 use crate::parser::dependencies::Dependency;
 
+pub mod introspect;
+pub mod relationships;
 pub mod visualization;
 
+use relationships::{collect_relationships, primary_key_columns};
+
 /// Generate an XML schema from the database
 pub fn generate_database_schema(
     dependencies: &HashMap<String, Dependency>,
@@ -54,30 +58,53 @@ fn add_schemas_section(xml: &mut String, dependencies: &HashMap<String, Dependen
     sorted_deps.sort_by_key(|a| a.0);
     
     for (table_name, dependency) in sorted_deps {
-        xml.push_str(&format!("    <table name=\"{}\">\n", table_name));
-        xml.push_str(&format!("      <description>Generated from {}</description>\n", dependency.filename));
+        xml.push_str(&format!("    <table name=\"{}\">\n", escape_markup(table_name)));
+        let config = dependency.config.as_ref();
+        match config.and_then(|config| config.description.as_deref()) {
+            Some(description) => xml.push_str(&format!("      <description>{}</description>\n", escape_markup(description))),
+            None => xml.push_str(&format!("      <description>Generated from {}</description>\n", escape_markup(&dependency.filename))),
+        }
+        
+        // Add tags and metadata from the model config
+        if let Some(config) = config.filter(|config| !config.tags.is_empty()) {
+            xml.push_str("      <tags>\n");
+            for tag in &config.tags {
+                xml.push_str(&format!("        <tag>{}</tag>\n", escape_markup(tag)));
+            }
+            xml.push_str("      </tags>\n");
+        }
+        if let Some(config) = config.filter(|config| !config.meta.is_empty()) {
+            xml.push_str("      <meta>\n");
+            for (key, value) in &config.meta {
+                xml.push_str(&format!("        <entry key=\"{}\">{}</entry>\n", escape_markup(key), escape_markup(&meta_text(value))));
+            }
+            xml.push_str("      </meta>\n");
+        }
         
         // Add columns based on SQL parsing
         if dependency.columns.is_empty() {
-            // If no columns were extracted, add a placeholder
-            xml.push_str("      <column name=\"id\" type=\"unknown\" primary_key=\"true\">\n");
-            xml.push_str("        <description>Primary key (automatically inferred)</description>\n");
-            xml.push_str("      </column>\n");
+            xml.push_str("      <!-- Columns could not be determined from the SQL -->\n");
         } else {
-            // Add all extracted columns
-            for (i, column) in dependency.columns.iter().enumerate() {
-                let is_primary = i == 0; // Assume first column is primary key
+            // Primary keys are only marked when declared in the model config
+            let primary_key = primary_key_columns(dependency);
+            for column in &dependency.columns {
+                let is_primary = primary_key.iter().any(|c| c.eq_ignore_ascii_case(&column.name));
                 xml.push_str(&format!("      <column name=\"{}\" type=\"{}\"{}>\n", 
-                    column.name, 
-                    column.data_type,
+                    escape_markup(&column.name),
+                    escape_markup(&column.data_type),
                     if is_primary { " primary_key=\"true\"" } else { "" }
                 ));
                 
-                // Add column description
-                if let Some(expr) = &column.expression {
-                    xml.push_str(&format!("        <description>Derived from: {}</description>\n", expr));
+                // Add column description, preferring the documented one
+                let documented = config
+                    .and_then(|config| config.column(&column.name))
+                    .and_then(|column| column.description.as_deref());
+                if let Some(description) = documented {
+                    xml.push_str(&format!("        <description>{}</description>\n", escape_markup(description)));
+                } else if let Some(expr) = &column.expression {
+                    xml.push_str(&format!("        <description>Derived from: {}</description>\n", escape_markup(expr)));
                 } else if let (Some(table), Some(col)) = (&column.source_table, &column.source_column) {
-                    xml.push_str(&format!("        <description>From {}.{}</description>\n", table, col));
+                    xml.push_str(&format!("        <description>From {}.{}</description>\n", escape_markup(table), escape_markup(col)));
                 } else {
                     xml.push_str("        <description>Column from query</description>\n");
                 }
@@ -85,8 +112,8 @@ fn add_schemas_section(xml: &mut String, dependencies: &HashMap<String, Dependen
                 // Add source information if available
                 if let (Some(table), Some(col)) = (&column.source_table, &column.source_column) {
                     xml.push_str("        <source>\n");
-                    xml.push_str(&format!("          <table>{}</table>\n", table));
-                    xml.push_str(&format!("          <column>{}</column>\n", col));
+                    xml.push_str(&format!("          <table>{}</table>\n", escape_markup(table)));
+                    xml.push_str(&format!("          <column>{}</column>\n", escape_markup(col)));
                     xml.push_str("        </source>\n");
                 }
                 
@@ -98,7 +125,7 @@ fn add_schemas_section(xml: &mut String, dependencies: &HashMap<String, Dependen
         if !dependency.deps.is_empty() {
             xml.push_str("      <source_dependencies>\n");
             for dep in &dependency.deps {
-                xml.push_str(&format!("        <dependency table=\"{}\" type=\"transformation\"/>\n", dep));
+                xml.push_str(&format!("        <dependency table=\"{}\" type=\"transformation\"/>\n", escape_markup(dep)));
             }
             xml.push_str("      </source_dependencies>\n");
         }
@@ -116,18 +143,20 @@ fn add_relationships_section(xml: &mut String, dependencies: &HashMap<String, De
     xml.push_str("  <!-- Entity-Relationship Diagram -->\n");
     xml.push_str("  <entity_relationships>\n");
     
-    // For each dependency, create relationships based on dependencies
-    for (table_name, dependency) in dependencies {
-        for dep in &dependency.deps {
-            // Only add relationships for known tables
-            if dependencies.contains_key(dep) {
-                xml.push_str(&format!("    <relationship type=\"references\" name=\"{}_to_{}\">\n", table_name, dep));
-                xml.push_str(&format!("      <from table=\"transform.{}\" column=\"id\"/>\n", table_name));
-                xml.push_str(&format!("      <to table=\"transform.{}\" column=\"id\"/>\n", dep));
-                xml.push_str(&format!("      <description>{} depends on {}</description>\n", table_name, dep));
-                xml.push_str("    </relationship>\n");
-            }
+    // Only declared foreign keys and join conditions found in the SQL are emitted
+    for relationship in collect_relationships(dependencies) {
+        let (from_table, from_column) = (escape_markup(&relationship.from_table), escape_markup(&relationship.from_column));
+        let (to_table, to_column) = (escape_markup(&relationship.to_table), escape_markup(&relationship.to_column));
+        xml.push_str(&format!("    <relationship type=\"references\" name=\"{}_{}_to_{}\" origin=\"{}\" confidence=\"{}\">\n",
+            from_table, from_column, to_table, relationship.origin, relationship.confidence));
+        xml.push_str(&format!("      <from table=\"transform.{}\" column=\"{}\"/>\n", from_table, from_column));
+        xml.push_str(&format!("      <to table=\"transform.{}\" column=\"{}\"/>\n", to_table, to_column));
+        xml.push_str(&format!("      <description>{}.{} references {}.{}</description>\n",
+            from_table, from_column, to_table, to_column));
+        for model in &relationship.models {
+            xml.push_str(&format!("      <evidence model=\"transform.{}\"/>\n", escape_markup(model)));
         }
+        xml.push_str("    </relationship>\n");
     }
     
     xml.push_str("  </entity_relationships>\n");
@@ -146,10 +175,10 @@ fn add_lineage_section(xml: &mut String, dependencies: &HashMap<String, Dependen
     // For each dependency, document the transformation
     for (table_name, dependency) in dependencies {
         if !dependency.deps.is_empty() {
-            xml.push_str(&format!("        <step from=\"multiple\" to=\"transform.{}\">\n", table_name));
+            xml.push_str(&format!("        <step from=\"multiple\" to=\"transform.{}\">\n", escape_markup(table_name)));
             xml.push_str("          <sources>\n");
             for dep in &dependency.deps {
-                xml.push_str(&format!("            <source>{}</source>\n", dep));
+                xml.push_str(&format!("            <source>{}</source>\n", escape_markup(dep)));
             }
             xml.push_str("          </sources>\n");
             
@@ -157,11 +186,14 @@ fn add_lineage_section(xml: &mut String, dependencies: &HashMap<String, Dependen
             if !dependency.column_lineage.is_empty() {
                 xml.push_str("          <column_lineage>\n");
                 for lineage in &dependency.column_lineage {
-                    xml.push_str(&format!("            <mapping>\n"));
-                    xml.push_str(&format!("              <source_table>{}</source_table>\n", lineage.source_table));
-                    xml.push_str(&format!("              <source_column>{}</source_column>\n", lineage.source_column));
-                    xml.push_str(&format!("              <target_column>{}</target_column>\n", lineage.target_column));
-                    xml.push_str(&format!("            </mapping>\n"));
+                    xml.push_str(&format!("            <mapping transformation=\"{}\">\n", lineage.transformation));
+                    xml.push_str(&format!("              <source_table>{}</source_table>\n", escape_markup(&lineage.source_table)));
+                    xml.push_str(&format!("              <source_column>{}</source_column>\n", escape_markup(&lineage.source_column)));
+                    xml.push_str(&format!("              <target_column>{}</target_column>\n", escape_markup(&lineage.target_column)));
+                    if let Some(expr) = &lineage.expression {
+                        xml.push_str(&format!("              <expression>{}</expression>\n", escape_markup(expr)));
+                    }
+                    xml.push_str("            </mapping>\n");
                 }
                 xml.push_str("          </column_lineage>\n");
             }
@@ -172,7 +204,7 @@ fn add_lineage_section(xml: &mut String, dependencies: &HashMap<String, Dependen
             xml.push_str("        </step>\n");
         } else {
             // For source tables with no dependencies
-            xml.push_str(&format!("        <step from=\"source\" to=\"transform.{}\">\n", table_name));
+            xml.push_str(&format!("        <step from=\"source\" to=\"transform.{}\">\n", escape_markup(table_name)));
             xml.push_str("          <operations>\n");
             xml.push_str("            <operation>Source data load</operation>\n");
             xml.push_str("          </operations>\n");
@@ -185,4 +217,21 @@ fn add_lineage_section(xml: &mut String, dependencies: &HashMap<String, Dependen
     xml.push_str("  </data_lineage>\n");
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Text of a `meta` value, without quotes around strings
+pub(crate) fn meta_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Escape text for inclusion in XML or HTML
+pub(crate) fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
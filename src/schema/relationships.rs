@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::parser::dependencies::Dependency;
+use crate::parser::sql::JoinClause;
+
+/// Where a relationship between two models was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationshipOrigin {
+    /// Declared with `foreign_keys` in the model config
+    Declared,
+    /// Inferred from an equality in a JOIN condition
+    JoinCondition,
+    /// Inferred from an equality between two tables in a WHERE clause
+    WhereCondition,
+}
+
+impl fmt::Display for RelationshipOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelationshipOrigin::Declared => write!(f, "declared"),
+            RelationshipOrigin::JoinCondition => write!(f, "join_condition"),
+            RelationshipOrigin::WhereCondition => write!(f, "where_condition"),
+        }
+    }
+}
+
+/// How likely an inferred relationship is to be a real foreign key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// Columns are compared but nothing else suggests a key
+    Low,
+    /// Column names follow a key naming convention, or several models join the same way
+    Medium,
+    /// Declared in config, or joined on the declared primary key of the referenced model
+    High,
+}
+
+impl Confidence {
+    fn raised(self) -> Self {
+        match self {
+            Confidence::Low => Confidence::Medium,
+            _ => Confidence::High,
+        }
+    }
+
+    fn lowered(self) -> Self {
+        match self {
+            Confidence::High => Confidence::Medium,
+            _ => Confidence::Low,
+        }
+    }
+}
+
+impl fmt::Display for Confidence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Confidence::Low => write!(f, "low"),
+            Confidence::Medium => write!(f, "medium"),
+            Confidence::High => write!(f, "high"),
+        }
+    }
+}
+
+/// A column of one model referencing a column of another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relationship {
+    /// Referencing model
+    pub from_table: String,
+    /// Referencing column
+    pub from_column: String,
+    /// Referenced model
+    pub to_table: String,
+    /// Referenced column
+    pub to_column: String,
+    /// How the relationship was found
+    pub origin: RelationshipOrigin,
+    /// How likely the relationship is to be a real foreign key
+    pub confidence: Confidence,
+    /// Models whose SQL joins on these columns
+    pub models: Vec<String>,
+}
+
+impl Relationship {
+    fn connects(&self, table_a: &str, column_a: &str, table_b: &str, column_b: &str) -> bool {
+        (self.from_table == table_a && self.from_column.eq_ignore_ascii_case(column_a)
+            && self.to_table == table_b && self.to_column.eq_ignore_ascii_case(column_b))
+            || (self.from_table == table_b && self.from_column.eq_ignore_ascii_case(column_b)
+                && self.to_table == table_a && self.to_column.eq_ignore_ascii_case(column_a))
+    }
+}
+
+/// Primary key columns declared in a model's config
+pub fn primary_key_columns(dependency: &Dependency) -> &[String] {
+    dependency.config.as_ref().map(|c| c.primary_key.as_slice()).unwrap_or_default()
+}
+
+/// Collect the relationships between models
+///
+/// Foreign keys declared in model configs come first with high confidence. Equality
+/// predicates between two models in JOIN conditions (or WHERE clauses) are added unless
+/// a declared key already covers them. Their confidence starts from the column names:
+/// high when the referenced column is a declared primary key, medium when the names follow
+/// a key convention (`x_id = id` or the same name on both sides), low otherwise. Keys only
+/// compared in WHERE clauses are one level lower, and keys joined on by more than one
+/// model are one level higher.
+///
+/// # Arguments
+///
+/// * `dependencies` - Map of model names to their dependencies
+///
+/// # Returns
+///
+/// * `Vec<Relationship>` - Relationships in a stable order
+pub fn collect_relationships(dependencies: &HashMap<String, Dependency>) -> Vec<Relationship> {
+    let mut model_names: Vec<&String> = dependencies.keys().collect();
+    model_names.sort();
+
+    let mut relationships = Vec::new();
+
+    for model_name in &model_names {
+        let Some(config) = dependencies[*model_name].config.as_ref() else {
+            continue;
+        };
+
+        for foreign_key in &config.foreign_keys {
+            match foreign_key.referenced() {
+                Some((table, column)) => relationships.push(Relationship {
+                    from_table: model_name.to_string(),
+                    from_column: foreign_key.column.clone(),
+                    to_table: resolve_model(table, dependencies).unwrap_or(table).to_string(),
+                    to_column: column.to_string(),
+                    origin: RelationshipOrigin::Declared,
+                    confidence: Confidence::High,
+                    models: Vec::new(),
+                }),
+                None => tracing::warn!(
+                    "Ignoring foreign key {} on {}: references must be in table.column form",
+                    foreign_key.column, model_name
+                ),
+            }
+        }
+    }
+
+    for model_name in &model_names {
+        for join_key in &dependencies[*model_name].join_keys {
+            let (Some(left_table), Some(right_table)) = (
+                resolve_model(&join_key.left_table, dependencies),
+                resolve_model(&join_key.right_table, dependencies),
+            ) else {
+                continue;
+            };
+
+            let origin = match join_key.clause {
+                JoinClause::On | JoinClause::Using => RelationshipOrigin::JoinCondition,
+                JoinClause::Where => RelationshipOrigin::WhereCondition,
+            };
+
+            // The same columns joined again only add evidence to the existing relationship
+            if let Some(existing) = relationships.iter_mut().find(|r| {
+                r.connects(left_table, &join_key.left_column, right_table, &join_key.right_column)
+            }) {
+                if !existing.models.contains(model_name) {
+                    existing.models.push(model_name.to_string());
+                }
+                if existing.origin == RelationshipOrigin::WhereCondition {
+                    existing.origin = origin;
+                }
+                continue;
+            }
+
+            // The side joined on its primary key is the referenced one
+            let right_is_key = is_key_column(&dependencies[right_table], &join_key.right_column);
+            let left_is_key = is_key_column(&dependencies[left_table], &join_key.left_column);
+            let ((from_table, from_column), (to_table, to_column)) = if left_is_key && !right_is_key {
+                ((right_table, &join_key.right_column), (left_table, &join_key.left_column))
+            } else {
+                ((left_table, &join_key.left_column), (right_table, &join_key.right_column))
+            };
+
+            relationships.push(Relationship {
+                from_table: from_table.to_string(),
+                from_column: from_column.clone(),
+                to_table: to_table.to_string(),
+                to_column: to_column.clone(),
+                origin,
+                confidence: Confidence::Low,
+                models: vec![model_name.to_string()],
+            });
+        }
+    }
+
+    for relationship in relationships.iter_mut().filter(|r| r.origin != RelationshipOrigin::Declared) {
+        relationship.confidence = inferred_confidence(relationship, dependencies);
+    }
+
+    relationships
+}
+
+/// Confidence of a relationship inferred from the SQL
+fn inferred_confidence(relationship: &Relationship, dependencies: &HashMap<String, Dependency>) -> Confidence {
+    let declared_key = primary_key_columns(&dependencies[&relationship.to_table]);
+    let from_column = relationship.from_column.to_lowercase();
+    let to_column = relationship.to_column.to_lowercase();
+
+    let mut confidence = if declared_key.iter().any(|c| c.eq_ignore_ascii_case(&to_column)) {
+        Confidence::High
+    } else if from_column == to_column || (to_column == "id" && from_column.ends_with("_id")) {
+        Confidence::Medium
+    } else {
+        Confidence::Low
+    };
+
+    if relationship.origin == RelationshipOrigin::WhereCondition {
+        confidence = confidence.lowered();
+    }
+    if relationship.models.len() > 1 {
+        confidence = confidence.raised();
+    }
+
+    confidence
+}
+
+/// Whether a column is the declared primary key of a model, or named `id` when none is declared
+fn is_key_column(dependency: &Dependency, column: &str) -> bool {
+    let primary_key = primary_key_columns(dependency);
+    if primary_key.is_empty() {
+        column.eq_ignore_ascii_case("id")
+    } else {
+        primary_key.iter().any(|c| c.eq_ignore_ascii_case(column))
+    }
+}
+
+/// Map a (possibly schema-qualified) table name to the model it refers to
+fn resolve_model<'a>(table: &'a str, dependencies: &'a HashMap<String, Dependency>) -> Option<&'a str> {
+    if let Some((name, _)) = dependencies.get_key_value(table) {
+        return Some(name.as_str());
+    }
+    let base_name = table.rsplit('.').next()?;
+    dependencies.get_key_value(base_name).map(|(name, _)| name.as_str())
+}
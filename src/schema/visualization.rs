@@ -5,6 +5,8 @@ use std::path::Path;
 use std::io::Write;
 
 use crate::parser::dependencies::Dependency;
+use crate::schema::{escape_markup, meta_text};
+use crate::schema::relationships::{collect_relationships, primary_key_columns, Confidence};
 
 /// Generate a visualization of the database schema
 ///
@@ -74,7 +76,17 @@ fn generate_html_visualization(
     html.push_str("    .column-name { font-weight: bold; }\n");
     html.push_str("    .column-type { color: #666; margin-left: 10px; }\n");
     html.push_str("    .column-source { color: #888; font-size: 0.9em; margin-top: 3px; }\n");
+    html.push_str("    .table-description { color: #444; margin-bottom: 10px; }\n");
+    html.push_str("    .column-description { color: #444; margin-top: 3px; }\n");
+    html.push_str("    .table-tag { display: inline-block; padding: 0 6px; margin-right: 4px; border-radius: 3px; background-color: #eef; font-size: 0.85em; }\n");
     html.push_str("    .dependencies { margin-top: 10px; color: #555; }\n");
+    html.push_str("    .column-lineage { margin-top: 10px; font-size: 0.9em; }\n");
+    html.push_str("    .lineage-kind { display: inline-block; min-width: 90px; padding: 0 4px; border-radius: 3px; background-color: #eef; }\n");
+    html.push_str("    .lineage-kind.computed { background-color: #fde8c8; }\n");
+    html.push_str("    .lineage-expression { color: #555; margin-left: 10px; }\n");
+    html.push_str("    .confidence-high { color: #2a7a2a; }\n");
+    html.push_str("    .confidence-medium { color: #a67c00; }\n");
+    html.push_str("    .confidence-low { color: #b03030; }\n");
     html.push_str("    .lineage-container { margin-top: 30px; }\n");
     html.push_str("    .lineage-title { color: #333; margin-bottom: 10px; }\n");
     html.push_str("    .lineage-diagram { border: 1px solid #ddd; padding: 20px; background-color: #f9f9f9; }\n");
@@ -104,29 +116,55 @@ fn generate_html_visualization(
     let mut sorted_deps: Vec<(&String, &Dependency)> = dependencies.iter().collect();
     sorted_deps.sort_by_key(|a| a.0);
     
+    let relationships = collect_relationships(dependencies);
+    
     // Generate table details
     for (table_name, dependency) in &sorted_deps {
         html.push_str(&format!("    <div class=\"table\">\n"));
-        html.push_str(&format!("      <div class=\"table-header\">{}</div>\n", table_name));
+        html.push_str(&format!("      <div class=\"table-header\">{}</div>\n", escape_markup(table_name)));
         html.push_str(&format!("      <div class=\"table-body\">\n"));
         
+        let config = dependency.config.as_ref();
+        if let Some(description) = config.and_then(|config| config.description.as_deref()) {
+            html.push_str(&format!("        <div class=\"table-description\">{}</div>\n", escape_markup(description)));
+        }
+        if let Some(config) = config.filter(|config| !config.tags.is_empty() || !config.meta.is_empty()) {
+            html.push_str("        <div class=\"table-description\">\n");
+            for tag in &config.tags {
+                html.push_str(&format!("          <span class=\"table-tag\">{}</span>\n", escape_markup(tag)));
+            }
+            for (key, value) in &config.meta {
+                html.push_str(&format!("          <div class=\"column-source\">{}: {}</div>\n", escape_markup(key), escape_markup(&meta_text(value))));
+            }
+            html.push_str("        </div>\n");
+        }
+        
         // Add columns if requested
         if include_columns {
             if dependency.columns.is_empty() {
                 html.push_str("        <div class=\"column\">\n");
-                html.push_str("          <span class=\"column-name\">id</span>\n");
-                html.push_str("          <span class=\"column-type\">(unknown)</span>\n");
-                html.push_str("          <div class=\"column-source\">Primary key (automatically inferred)</div>\n");
+                html.push_str("          <div class=\"column-source\">Columns could not be determined from the SQL</div>\n");
                 html.push_str("        </div>\n");
             } else {
+                let primary_key = primary_key_columns(dependency);
                 for column in &dependency.columns {
                     html.push_str("        <div class=\"column\">\n");
-                    html.push_str(&format!("          <span class=\"column-name\">{}</span>\n", column.name));
-                    html.push_str(&format!("          <span class=\"column-type\">({})</span>\n", column.data_type));
+                    html.push_str(&format!("          <span class=\"column-name\">{}</span>\n", escape_markup(&column.name)));
+                    html.push_str(&format!("          <span class=\"column-type\">({})</span>\n", escape_markup(&column.data_type)));
+                    if primary_key.iter().any(|c| c.eq_ignore_ascii_case(&column.name)) {
+                        html.push_str("          <span class=\"column-type\">primary key</span>\n");
+                    }
+                    
+                    if let Some(description) = config
+                        .and_then(|config| config.column(&column.name))
+                        .and_then(|column| column.description.as_deref())
+                    {
+                        html.push_str(&format!("          <div class=\"column-description\">{}</div>\n", escape_markup(description)));
+                    }
                     
                     // Add source information if available
                     if let (Some(table), Some(col)) = (&column.source_table, &column.source_column) {
-                        html.push_str(&format!("          <div class=\"column-source\">From {}.{}</div>\n", table, col));
+                        html.push_str(&format!("          <div class=\"column-source\">From {}.{}</div>\n", escape_markup(table), escape_markup(col)));
                     } else if column.is_derived {
                         html.push_str("          <div class=\"column-source\">Derived from expression</div>\n");
                     }
@@ -136,12 +174,53 @@ fn generate_html_visualization(
             }
         }
         
+        // Add column lineage so computed columns can be told apart from copied ones
+        if include_columns && !dependency.column_lineage.is_empty() {
+            html.push_str("        <div class=\"column-lineage\">\n");
+            html.push_str("          <strong>Column lineage:</strong>\n");
+            for lineage in &dependency.column_lineage {
+                html.push_str("          <div>\n");
+                html.push_str(&format!("            <span class=\"lineage-kind{}\">{}</span>\n",
+                    if lineage.transformation.is_computed() { " computed" } else { "" },
+                    lineage.transformation
+                ));
+                html.push_str(&format!("            {}.{} &rarr; {}\n",
+                    escape_markup(&lineage.source_table),
+                    escape_markup(&lineage.source_column),
+                    escape_markup(&lineage.target_column)
+                ));
+                if let Some(expr) = &lineage.expression {
+                    html.push_str(&format!("            <code class=\"lineage-expression\">{}</code>\n", escape_markup(expr)));
+                }
+                html.push_str("          </div>\n");
+            }
+            html.push_str("        </div>\n");
+        }
+
+        // Add keys this table references, with how confident the inference is
+        let references: Vec<_> = relationships.iter().filter(|r| &r.from_table == *table_name).collect();
+        if !references.is_empty() {
+            html.push_str("        <div class=\"dependencies\">\n");
+            html.push_str("          <strong>References:</strong>\n");
+            for relationship in references {
+                html.push_str(&format!("          <div>{} &rarr; {}.{} <span class=\"confidence-{}\">({} confidence, {})</span></div>\n",
+                    escape_markup(&relationship.from_column),
+                    escape_markup(&relationship.to_table),
+                    escape_markup(&relationship.to_column),
+                    relationship.confidence,
+                    relationship.confidence,
+                    relationship.origin
+                ));
+            }
+            html.push_str("        </div>\n");
+        }
+        
         // Add dependencies
         if !dependency.deps.is_empty() {
             html.push_str("        <div class=\"dependencies\">\n");
             html.push_str("          <strong>Dependencies:</strong> ");
             
-            let deps_list: Vec<_> = dependency.deps.iter().cloned().collect();
+            let deps_list: Vec<_> = dependency.deps.iter().map(|dep| escape_markup(dep)).collect();
             html.push_str(&format!("{}\n", deps_list.join(", ")));
             
             html.push_str("        </div>\n");
@@ -164,33 +243,56 @@ fn generate_html_visualization(
     
     // Add entities (tables)
     for (table_name, dependency) in &sorted_deps {
+        // Tables without known columns are declared without attributes
+        if dependency.columns.is_empty() {
+            html.push_str(&format!("    {}\n", escape_markup(table_name)));
+            continue;
+        }
+        
         // Start table definition
-        html.push_str(&format!("    {} {{\n", table_name));
+        html.push_str(&format!("    {} {{\n", escape_markup(table_name)));
         
-        // Add columns
-        if dependency.columns.is_empty() {
-            html.push_str("        int id PK \"Primary key\"\n");
-        } else {
-            for (i, column) in dependency.columns.iter().enumerate() {
-                let pk_marker = if i == 0 { "PK" } else { "" };
-                html.push_str(&format!("        {} {} {} \"{}\"\n", 
-                    column.data_type, 
-                    column.name,
-                    pk_marker,
-                    if column.is_derived { "Derived" } else { "Column" }
-                ));
-            }
+        // Add columns, marking only declared primary keys and known foreign keys
+        let primary_key = primary_key_columns(dependency);
+        for column in &dependency.columns {
+            let is_primary = primary_key.iter().any(|c| c.eq_ignore_ascii_case(&column.name));
+            let is_foreign = relationships.iter()
+                .any(|r| &r.from_table == *table_name && r.from_column.eq_ignore_ascii_case(&column.name));
+            let key_marker = match (is_primary, is_foreign) {
+                (true, true) => "PK, FK",
+                (true, false) => "PK",
+                (false, true) => "FK",
+                (false, false) => "",
+            };
+            html.push_str(&format!("        {} {} {} \"{}\"\n", 
+                mermaid_type(&column.data_type), 
+                escape_markup(&column.name),
+                key_marker,
+                if column.is_derived { "Derived" } else { "Column" }
+            ));
         }
         
         // End table definition
         html.push_str("    }\n");
     }
     
-    // Add relationships
+    // Add key relationships between models, dotted unless confidence is high
+    for relationship in &relationships {
+        html.push_str(&format!("    {} ||{}o{{ {} : \"{} = {} ({})\"\n",
+            escape_markup(&relationship.to_table),
+            if relationship.confidence == Confidence::High { "--" } else { ".." },
+            escape_markup(&relationship.from_table),
+            escape_markup(&relationship.from_column),
+            escape_markup(&relationship.to_column),
+            relationship.confidence
+        ));
+    }
+    
+    // Add data flow between models, which implies no key relationship or cardinality
     for (table_name, dependency) in &sorted_deps {
         for dep in &dependency.deps {
             if dependencies.contains_key(dep) {
-                html.push_str(&format!("    {} }}|--|| {} : depends_on\n", dep, table_name));
+                html.push_str(&format!("    {} }}o..o{{ {} : depends_on\n", escape_markup(dep), escape_markup(table_name)));
             }
         }
         
@@ -199,11 +301,12 @@ fn generate_html_visualization(
             for lineage in &dependency.column_lineage {
                 // We only add relationships to tables we know about
                 if dependencies.contains_key(&lineage.source_table) {
-                    html.push_str(&format!("    {} ||--|| {} : \"{} -> {}\"\n", 
-                        lineage.source_table, 
-                        table_name,
-                        lineage.source_column,
-                        lineage.target_column
+                    html.push_str(&format!("    {} ||--|| {} : \"{} -> {} ({})\"\n", 
+                        escape_markup(&lineage.source_table),
+                        escape_markup(table_name),
+                        escape_markup(&lineage.source_column),
+                        escape_markup(&lineage.target_column),
+                        lineage.transformation
                     ));
                 }
             }
@@ -289,4 +392,12 @@ fn generate_html_visualization(
     println!("Generated HTML schema visualization at {}", output_path);
     
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Reduce a DuckDB type such as `DECIMAL(18,3)` or `INTEGER[]` to a single word Mermaid accepts
+fn mermaid_type(data_type: &str) -> String {
+    let base_type = data_type.split('(').next().unwrap_or(data_type).trim().replace("[]", "_array");
+    base_type.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
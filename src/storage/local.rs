@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{relative_key, StorageBackend};
+
+/// Backups in a local directory, such as a mounted NAS share
+#[derive(Debug, Clone)]
+pub struct LocalBackend {
+    /// Directory holding the backup files
+    root: PathBuf,
+}
+
+impl LocalBackend {
+    /// Back up to a directory, which is created on the first upload
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    /// Path of the file stored under a key
+    fn path(&self, key: &str) -> PathBuf {
+        key.split('/').fold(self.root.clone(), |path, part| path.join(part))
+    }
+}
+
+impl StorageBackend for LocalBackend {
+    fn location(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    fn upload(&self, local_path: &Path, key: &str) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        fs::copy(local_path, &path)
+            .context(format!("Failed to copy {} to {}", local_path.display(), path.display()))?;
+        tracing::info!("Copied {} to {}", local_path.display(), path.display());
+
+        Ok(())
+    }
+
+    fn download(&self, key: &str, local_path: &Path) -> Result<()> {
+        let path = self.path(key);
+        if let Some(parent) = local_path.parent() {
+            fs::create_dir_all(parent)
+                .context(format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        fs::copy(&path, local_path)
+            .context(format!("Failed to copy {} to {}", path.display(), local_path.display()))?;
+
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut keys = Vec::new();
+        for entry in walkdir::WalkDir::new(&self.root).min_depth(1) {
+            let entry = entry.context(format!("Failed to read directory: {}", self.root.display()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            keys.push(relative_key(&self.root, entry.path())?);
+        }
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key);
+        fs::remove_file(&path)
+            .context(format!("Failed to remove file: {}", path.display()))?;
+
+        // Remove directories left empty, such as the folder of a pruned backup version
+        let mut dir = path.parent();
+        while let Some(parent) = dir.filter(|parent| *parent != self.root) {
+            if fs::remove_dir(parent).is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// Name of the manifest file in each backup version, uploaded after every other file
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Format of backup version names, sortable from oldest to newest
+const VERSION_FORMAT: &str = "%Y%m%dT%H%M%S%.3fZ";
+
+/// Description of one backup version, stored as `{version}/manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    /// Name of the version, e.g. `20250114T020000.000Z`, also the prefix of its files
+    pub version: String,
+    /// When the backup was taken, in UTC
+    pub created_at: DateTime<Utc>,
+    /// DuckDB version that exported the database, e.g. `v1.2.0`
+    pub duckdb_version: String,
+    /// Crabwalk version that took the backup
+    pub crabwalk_version: String,
+    /// Schemas in the database
+    pub schemas: Vec<String>,
+    /// Tables and views chosen with `--select`, as `schema.name`; empty for the whole database
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selected: Vec<String>,
+    /// Exported files, with keys relative to the version
+    pub files: Vec<BackupFile>,
+}
+
+/// An exported file in a backup version
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct BackupFile {
+    /// `/`-separated path relative to the version, e.g. `schema.sql`
+    pub key: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Hex-encoded SHA-256 of the contents
+    pub sha256: String,
+}
+
+impl BackupManifest {
+    /// Describe the files of an exported database as a new backup version named after the current time
+    ///
+    /// # Arguments
+    ///
+    /// * `export_dir` - Directory written by `EXPORT DATABASE`
+    /// * `duckdb_version` - DuckDB version that exported the database
+    /// * `schemas` - Schemas in the database
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BackupManifest>` - Manifest listing every file in the directory with its checksum
+    pub fn new(export_dir: &Path, duckdb_version: &str, schemas: Vec<String>) -> Result<Self> {
+        let created_at = Utc::now();
+
+        let mut files = Vec::new();
+        for entry in walkdir::WalkDir::new(export_dir).min_depth(1).sort_by_file_name() {
+            let entry = entry.context(format!("Failed to read directory: {}", export_dir.display()))?;
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let (size, sha256) = checksum(entry.path())?;
+            files.push(BackupFile {
+                key: super::relative_key(export_dir, entry.path())?,
+                size,
+                sha256,
+            });
+        }
+
+        Ok(Self {
+            version: created_at.format(VERSION_FORMAT).to_string(),
+            created_at,
+            duckdb_version: duckdb_version.to_string(),
+            crabwalk_version: env!("CARGO_PKG_VERSION").to_string(),
+            schemas,
+            selected: Vec::new(),
+            files,
+        })
+    }
+
+    /// Key of the manifest of a version
+    pub fn key(version: &str) -> String {
+        format!("{}/{}", version, MANIFEST_FILE)
+    }
+
+    /// Total size of the files in bytes
+    pub fn size(&self) -> u64 {
+        self.files.iter().map(|file| file.size).sum()
+    }
+
+    /// Check that a downloaded file matches the size and checksum recorded for it
+    pub fn verify(&self, file: &BackupFile, path: &Path) -> Result<()> {
+        let (size, sha256) = checksum(path)?;
+        if size != file.size || sha256 != file.sha256 {
+            anyhow::bail!(
+                "Backup {} is corrupt: {} has {} bytes with SHA-256 {}, expected {} bytes with SHA-256 {}",
+                self.version, file.key, size, sha256, file.size, file.sha256
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Size and hex-encoded SHA-256 of a file, read in blocks
+fn checksum(path: &Path) -> Result<(u64, String)> {
+    let mut file = File::open(path)
+        .context(format!("Failed to open file: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    let mut size = 0;
+
+    loop {
+        let read = file.read(&mut buffer)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+
+    Ok((size, format!("{:x}", hasher.finalize())))
+}
@@ -1,16 +1,33 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+mod local;
+mod manifest;
+#[cfg(feature = "object-store")]
+mod object_store;
+#[cfg(feature = "s3")]
+mod s3;
+mod selection;
+
+pub use local::LocalBackend;
+pub use manifest::{BackupFile, BackupManifest};
+#[cfg(feature = "object-store")]
+pub use self::object_store::ObjectStoreBackend;
+#[cfg(feature = "s3")]
+pub use s3::S3Backend;
+pub use selection::{BackupSelection, Relation};
 
 /// Configuration for S3 storage
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct S3Config {
     /// S3 bucket name
     pub bucket: String,
-    /// S3 access key
+    /// S3 access key; the default credential chain is used when unset
     pub access_key: Option<String>,
     /// S3 secret key
     pub secret_key: Option<String>,
-    /// S3 endpoint URL
+    /// S3 endpoint URL, e.g. `http://localhost:9000` for MinIO
     pub endpoint_url: Option<String>,
     /// S3 region name
     pub region_name: Option<String>,
@@ -31,261 +48,430 @@ impl Default for S3Config {
     }
 }
 
-/// Backup the DuckDB database to S3
+impl S3Config {
+    /// Backup target URL of the bucket and folder, e.g. `s3://my-bucket/db`
+    pub fn url(&self) -> String {
+        format!("s3://{}/{}", self.bucket, self.db_folder_name.trim_matches('/'))
+    }
+}
+
+/// A place backups are written to and restored from
+///
+/// Keys are `/`-separated paths relative to the root of the backups, e.g.
+/// `20250114T020000.000Z/schema.sql`.
+pub trait StorageBackend {
+    /// Location of the backups, for messages
+    fn location(&self) -> String;
+
+    /// Copy a local file to the backup under `key`, replacing any existing file
+    fn upload(&self, local_path: &Path, key: &str) -> Result<()>;
+
+    /// Copy the file stored under `key` to a local path
+    fn download(&self, key: &str, local_path: &Path) -> Result<()>;
+
+    /// Keys of every file in the backups, in sorted order
+    fn list(&self) -> Result<Vec<String>>;
+
+    /// Remove the file stored under `key`
+    fn delete(&self, key: &str) -> Result<()>;
+}
+
+/// Open the backend for a backup target
+///
+/// Plain paths and `file://` URLs are local directories, such as a mounted NAS share.
+/// `s3://bucket/folder` uses the AWS SDK when built with `--features s3`; other URLs, such as
+/// `gs://`, `az://` or `https://`, are opened with the `object_store` crate when built with
+/// `--features object-store`. Without either feature, URLs are an error.
+///
+/// # Arguments
+///
+/// * `target` - Directory or URL of the backup
+/// * `s3_config` - Credentials, endpoint and region for `s3://` URLs; bucket and folder come from the URL
+///
+/// # Returns
+///
+/// * `Result<Box<dyn StorageBackend>>` - Backend for the target
+#[cfg_attr(not(any(feature = "s3", feature = "object-store")), allow(unused_variables))]
+pub fn open_backend(target: &str, s3_config: Option<&S3Config>) -> Result<Box<dyn StorageBackend>> {
+    let Some((scheme, rest)) = target.split_once("://") else {
+        return Ok(Box::new(LocalBackend::new(target)));
+    };
+
+    match scheme {
+        "file" => Ok(Box::new(LocalBackend::new(rest))),
+        #[cfg(feature = "s3")]
+        "s3" => {
+            let (bucket, folder) = rest.split_once('/').unwrap_or((rest, ""));
+            let mut config = s3_config.cloned().unwrap_or_default();
+            config.bucket = bucket.to_string();
+            config.db_folder_name = folder.trim_matches('/').to_string();
+            Ok(Box::new(S3Backend::new(config)?))
+        }
+        #[cfg(feature = "object-store")]
+        _ => {
+            let s3_config = s3_config.filter(|_| scheme == "s3");
+            Ok(Box::new(ObjectStoreBackend::new(target, s3_config)?))
+        }
+        #[cfg(not(feature = "object-store"))]
+        _ => {
+            let features = if scheme == "s3" { "--features s3 or --features object-store" } else { "--features object-store" };
+            anyhow::bail!("Backups to {}:// URLs need crabwalk built with {}", scheme, features)
+        }
+    }
+}
+
+/// Backup the DuckDB database as a new version
+///
+/// The database is exported to a temporary directory and uploaded under a prefix named after
+/// the current time, so earlier versions are kept. With a selection, only the chosen tables and
+/// views are exported, views as tables holding their rows.
 ///
 /// # Arguments
 ///
 /// * `database_path` - Path to the DuckDB database file
-/// * `s3_config` - S3 configuration
+/// * `backend` - Where the backup is written
+/// * `selection` - Tables and views to back up; the whole database when empty
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Success or error
-#[cfg(feature = "s3")]
-pub fn backup(database_path: &str, s3_config: &S3Config) -> Result<()> {
+/// * `Result<BackupManifest>` - Manifest of the new version
+pub fn backup(database_path: &str, backend: &dyn StorageBackend, selection: &BackupSelection) -> Result<BackupManifest> {
     use duckdb::Connection;
-    use rusoto_core::Region;
-    use rusoto_s3::{PutObjectRequest, S3Client, S3};
-    use std::io::Read;
-    
-    tracing::info!(
-        "Backing up the DuckDB database to {}/{}",
-        s3_config.bucket,
-        s3_config.db_folder_name
-    );
-    
+    use tempfile::TempDir;
+
+    tracing::info!("Backing up the DuckDB database to {}", backend.location());
+
     // Create temporary directory to export the database
     let temp_dir = TempDir::new()
         .context("Failed to create temporary directory")?;
-    let local_db_path = temp_dir.path().join(&s3_config.db_folder_name);
-    fs::create_dir_all(&local_db_path)
-        .context(format!("Failed to create directory: {}", local_db_path.display()))?;
-    
+    let export_dir = temp_dir.path().join("export");
+    std::fs::create_dir_all(&export_dir)
+        .context(format!("Failed to create directory: {}", export_dir.display()))?;
+
     // Export the database to the temporary directory
     let conn = Connection::open(database_path)
         .context(format!("Failed to connect to DuckDB database: {}", database_path))?;
-    conn.execute(
-        &format!("EXPORT DATABASE '{}' (FORMAT 'parquet')", local_db_path.display()),
-        [],
-    )
-    .context("Failed to export database")?;
-    
-    // Configure S3 client
-    let region = if let Some(endpoint) = &s3_config.endpoint_url {
-        Region::Custom {
-            name: s3_config.region_name.clone().unwrap_or_else(|| "custom".to_string()),
-            endpoint: endpoint.clone(),
-        }
-    } else {
-        s3_config
-            .region_name
-            .as_ref()
-            .map(|name| name.parse::<Region>().unwrap_or(Region::UsEast1))
-            .unwrap_or(Region::UsEast1)
-    };
-    
-    let s3_client = if let (Some(access_key), Some(secret_key)) = (&s3_config.access_key, &s3_config.secret_key) {
-        let credentials_provider = rusoto_core::credential::StaticProvider::new_minimal(
-            access_key.clone(),
-            secret_key.clone(),
-        );
-        S3Client::new_with(
-            rusoto_core::request::HttpClient::new().unwrap(),
-            credentials_provider,
-            region,
+    let selected = if selection.is_empty() {
+        conn.execute(
+            &format!("EXPORT DATABASE '{}' (FORMAT 'parquet')", export_dir.display()),
+            [],
         )
+        .context("Failed to export database")?;
+        Vec::new()
     } else {
-        S3Client::new(region)
+        selection::export_selection(&conn, selection, &temp_dir.path().join("staging.duckdb"), &export_dir)?
     };
-    
-    // Upload files to S3
-    for entry in walkdir::WalkDir::new(&local_db_path)
-        .min_depth(1)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() {
-            let relative_path = path
-                .strip_prefix(&temp_dir)
-                .context("Failed to get relative path")?;
-            let key = relative_path.to_string_lossy().to_string();
-            
-            let mut file = fs::File::open(path)
-                .context(format!("Failed to open file: {}", path.display()))?;
-            let mut contents = Vec::new();
-            file.read_to_end(&mut contents)
-                .context(format!("Failed to read file: {}", path.display()))?;
-            
-            let put_request = PutObjectRequest {
-                bucket: s3_config.bucket.clone(),
-                key,
-                body: Some(contents.into()),
-                ..Default::default()
-            };
-            
-            s3_client
-                .put_object(put_request)
-                .sync()
-                .context("Failed to upload file to S3")?;
-        }
+
+    let duckdb_version: String = conn.query_row("SELECT library_version FROM pragma_version()", [], |row| row.get(0))
+        .context("Failed to read the DuckDB version")?;
+    let mut stmt = conn.prepare(
+        "SELECT schema_name FROM duckdb_schemas() \
+         WHERE database_name NOT IN ('system', 'temp') AND (NOT internal OR schema_name = 'main') \
+         ORDER BY schema_name",
+    )?;
+    let mut schemas = stmt.query_map([], |row| row.get(0))?
+        .collect::<duckdb::Result<Vec<String>>>()
+        .context("Failed to list schemas")?;
+    if !selected.is_empty() {
+        schemas.retain(|schema| selected.iter().any(|relation| relation.schema == *schema));
     }
-    
-    tracing::info!("Backup completed successfully");
-    
-    Ok(())
+
+    let mut manifest = BackupManifest::new(&export_dir, &duckdb_version, schemas)?;
+    manifest.selected = selected.iter().map(ToString::to_string).collect();
+    upload_backup(&export_dir, &manifest, backend)?;
+
+    tracing::info!("Backup {} completed successfully", manifest.version);
+
+    Ok(manifest)
 }
 
-/// Backup function placeholder for when S3 feature is disabled
-#[cfg(not(feature = "s3"))]
-pub fn backup(_database_path: &str, _s3_config: &S3Config) -> Result<()> {
-    tracing::warn!("S3 support is not enabled. Build with --features s3 to enable.");
-    Ok(())
+/// Upload an exported database as a new backup version
+///
+/// The manifest is uploaded last, so a version whose upload failed part way is never listed
+/// or restored.
+///
+/// # Arguments
+///
+/// * `export_dir` - Directory written by `EXPORT DATABASE`
+/// * `manifest` - Manifest of the files in the directory
+/// * `backend` - Where the backup is written
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or error
+pub fn upload_backup(export_dir: &Path, manifest: &BackupManifest, backend: &dyn StorageBackend) -> Result<()> {
+    let manifest_key = BackupManifest::key(&manifest.version);
+    if backend.list()?.contains(&manifest_key) {
+        anyhow::bail!("Backup {} already exists in {}", manifest.version, backend.location());
+    }
+
+    for file in &manifest.files {
+        let path = restore_path(export_dir, &file.key)?;
+        backend.upload(&path, &format!("{}/{}", manifest.version, file.key))?;
+    }
+
+    let manifest_file = tempfile::NamedTempFile::new()
+        .context("Failed to create temporary file")?;
+    serde_json::to_writer_pretty(manifest_file.as_file(), manifest)
+        .context("Failed to write backup manifest")?;
+    backend.upload(manifest_file.path(), &manifest_key)
+}
+
+/// List the backup versions, from oldest to newest
+///
+/// # Arguments
+///
+/// * `backend` - Where the backups are stored
+///
+/// # Returns
+///
+/// * `Result<Vec<BackupManifest>>` - Manifest of every complete version
+pub fn list_backups(backend: &dyn StorageBackend) -> Result<Vec<BackupManifest>> {
+    let temp_dir = tempfile::TempDir::new()
+        .context("Failed to create temporary directory")?;
+
+    let mut manifests = Vec::new();
+    for key in backend.list()? {
+        let Some((version, manifest::MANIFEST_FILE)) = key.split_once('/') else {
+            continue;
+        };
+
+        let path = temp_dir.path().join(format!("{}.json", version));
+        backend.download(&key, &path)?;
+        let file = std::fs::File::open(&path)
+            .context(format!("Failed to open file: {}", path.display()))?;
+        let manifest: BackupManifest = serde_json::from_reader(std::io::BufReader::new(file))
+            .context(format!("Invalid backup manifest: {}", key))?;
+        manifests.push(manifest);
+    }
+    manifests.sort_by(|a, b| a.version.cmp(&b.version));
+
+    Ok(manifests)
 }
 
-/// Restore the DuckDB database from S3
+/// Restore the DuckDB database from a backup version
+///
+/// Every file of the version is downloaded to a temporary directory and checked against its
+/// checksum before the directory is imported into a new database, which then replaces the
+/// database file. A backup taken before versioning, with the exported files at the root, is
+/// restored when there are no versions.
 ///
 /// # Arguments
 ///
 /// * `database_path` - Path to the DuckDB database file
-/// * `s3_config` - S3 configuration
+/// * `backend` - Where the backup is read from
+/// * `version` - Version to restore; the newest when `None`
 /// * `overwrite` - Whether to overwrite existing database
 ///
 /// # Returns
 ///
 /// * `Result<()>` - Success or error
-#[cfg(feature = "s3")]
-pub fn restore(database_path: &str, s3_config: &S3Config, overwrite: bool) -> Result<()> {
+pub fn restore(database_path: &str, backend: &dyn StorageBackend, version: Option<&str>, overwrite: bool) -> Result<()> {
     use duckdb::Connection;
-    use rusoto_core::Region;
-    use rusoto_s3::{GetObjectRequest, ListObjectsV2Request, S3Client, S3};
-    use std::io::Read;
-    
-    tracing::info!(
-        "Restoring the DuckDB database from {}/{}",
-        s3_config.bucket,
-        s3_config.db_folder_name
-    );
-    
+    use std::fs;
+    use tempfile::TempDir;
+
+    tracing::info!("Restoring the DuckDB database from {}", backend.location());
+
     // Check if database exists and should be overwritten
     let db_path = Path::new(database_path);
-    if db_path.exists() {
-        if overwrite {
-            fs::remove_file(db_path)
-                .context(format!("Failed to remove existing database: {}", database_path))?;
-        } else {
-            return Err(anyhow::anyhow!(
-                "Database file already exists. Use --overwrite to replace it."
-            ));
-        }
+    if db_path.exists() && !overwrite {
+        return Err(anyhow::anyhow!(
+            "Database file already exists. Use --overwrite to replace it."
+        ));
     }
-    
+
     // Create temporary directory to download the database
     let temp_dir = TempDir::new()
         .context("Failed to create temporary directory")?;
-    let local_db_path = temp_dir.path().join(&s3_config.db_folder_name);
-    fs::create_dir_all(&local_db_path)
-        .context(format!("Failed to create directory: {}", local_db_path.display()))?;
-    
-    // Configure S3 client
-    let region = if let Some(endpoint) = &s3_config.endpoint_url {
-        Region::Custom {
-            name: s3_config.region_name.clone().unwrap_or_else(|| "custom".to_string()),
-            endpoint: endpoint.clone(),
-        }
-    } else {
-        s3_config
-            .region_name
-            .as_ref()
-            .map(|name| name.parse::<Region>().unwrap_or(Region::UsEast1))
-            .unwrap_or(Region::UsEast1)
-    };
-    
-    let s3_client = if let (Some(access_key), Some(secret_key)) = (&s3_config.access_key, &s3_config.secret_key) {
-        let credentials_provider = rusoto_core::credential::StaticProvider::new_minimal(
-            access_key.clone(),
-            secret_key.clone(),
-        );
-        S3Client::new_with(
-            rusoto_core::request::HttpClient::new().unwrap(),
-            credentials_provider,
-            region,
-        )
-    } else {
-        S3Client::new(region)
-    };
-    
-    // List objects in the S3 bucket
-    let prefix = format!("{}/", s3_config.db_folder_name);
-    let list_request = ListObjectsV2Request {
-        bucket: s3_config.bucket.clone(),
-        prefix: Some(prefix),
-        ..Default::default()
+    let import_dir = temp_dir.path().join("import");
+    download_backup(backend, version, &import_dir)?;
+
+    // Import into a new file next to the database, so a failed import leaves the database as it was
+    let parent = match db_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
     };
-    
-    let list_result = s3_client
-        .list_objects_v2(list_request)
-        .sync()
-        .context("Failed to list objects in S3 bucket")?;
-    
-    if let Some(objects) = list_result.contents {
-        for object in objects {
-            if let Some(key) = object.key {
-                // Get object from S3
-                let get_request = GetObjectRequest {
-                    bucket: s3_config.bucket.clone(),
-                    key: key.clone(),
-                    ..Default::default()
-                };
-                
-                let get_result = s3_client
-                    .get_object(get_request)
-                    .sync()
-                    .context(format!("Failed to get object from S3: {}", key))?;
-                
-                // Save object to local file
-                let relative_path = key.strip_prefix(&s3_config.db_folder_name)
-                    .unwrap_or(&key);
-                let local_path = local_db_path.join(relative_path);
-                
-                // Ensure directory exists
-                if let Some(parent) = local_path.parent() {
-                    fs::create_dir_all(parent)
-                        .context(format!("Failed to create directory: {}", parent.display()))?;
-                }
-                
-                // Write file
-                if let Some(mut body) = get_result.body {
-                    let mut content = Vec::new();
-                    body.read_to_end(&mut content)
-                        .context(format!("Failed to read S3 object body: {}", key))?;
-                    
-                    let mut file = fs::File::create(&local_path)
-                        .context(format!("Failed to create file: {}", local_path.display()))?;
-                    file.write_all(&content)
-                        .context(format!("Failed to write to file: {}", local_path.display()))?;
-                }
-            }
-        }
+    let staging_dir = tempfile::Builder::new()
+        .prefix(".crabwalk-restore")
+        .tempdir_in(parent)
+        .context(format!("Failed to create temporary directory in {}", parent.display()))?;
+    let staging_path = staging_dir.path().join("restore.duckdb");
+    {
+        let conn = Connection::open(&staging_path)
+            .context(format!("Failed to create DuckDB database: {}", staging_path.display()))?;
+        // IMPORT DATABASE expands to the statements of schema.sql and load.sql, so it cannot be prepared
+        conn.execute_batch(&format!("IMPORT DATABASE '{}'", import_dir.display()))
+            .context("Failed to import database")?;
+        // Write everything into the database file, so no WAL is left behind
+        conn.execute_batch("CHECKPOINT")
+            .context("Failed to checkpoint the restored database")?;
     }
-    
-    // Import the database
-    let conn = Connection::open(database_path)
-        .context(format!("Failed to connect to DuckDB database: {}", database_path))?;
-    conn.execute(
-        &format!("IMPORT DATABASE '{}'", local_db_path.display()),
-        [],
-    )
-    .context("Failed to import database")?;
-    
+
+    fs::rename(&staging_path, db_path)
+        .context(format!("Failed to replace database: {}", database_path))?;
+    // A WAL of the replaced database would be replayed on top of the restored one
+    let wal_path = format!("{}.wal", database_path);
+    if Path::new(&wal_path).exists() {
+        fs::remove_file(&wal_path)
+            .context(format!("Failed to remove write-ahead log: {}", wal_path))?;
+    }
+
     tracing::info!("Restore completed successfully");
-    
+
     Ok(())
 }
 
-/// Restore function placeholder for when S3 feature is disabled
-#[cfg(not(feature = "s3"))]
-pub fn restore(_database_path: &str, _s3_config: &S3Config, _overwrite: bool) -> Result<()> {
-    tracing::warn!("S3 support is not enabled. Build with --features s3 to enable.");
+/// Restore tables and views from a backup version into the existing database
+///
+/// Unlike [`restore`], the database file is kept: the version is imported into a staging
+/// database and the chosen tables and views are copied from it, as tables, into their schema or
+/// into `into_schema`. Restoring into another schema keeps the current tables next to the
+/// restored ones for comparison.
+///
+/// # Arguments
+///
+/// * `database_path` - Path to the DuckDB database file, created if missing
+/// * `backend` - Where the backup is read from
+/// * `version` - Version to restore; the newest when `None`
+/// * `selection` - Tables and views to restore; every one in the backup when empty
+/// * `into_schema` - Schema to restore into instead of the one each table was backed up from
+/// * `overwrite` - Whether to replace tables and views that already exist
+///
+/// # Returns
+///
+/// * `Result<Vec<Relation>>` - The restored tables, named as in the database
+pub fn restore_selected(
+    database_path: &str,
+    backend: &dyn StorageBackend,
+    version: Option<&str>,
+    selection: &BackupSelection,
+    into_schema: Option<&str>,
+    overwrite: bool,
+) -> Result<Vec<Relation>> {
+    use duckdb::Connection;
+    use tempfile::TempDir;
+
+    tracing::info!("Restoring tables into {} from {}", database_path, backend.location());
+
+    let temp_dir = TempDir::new()
+        .context("Failed to create temporary directory")?;
+    let import_dir = temp_dir.path().join("import");
+    download_backup(backend, version, &import_dir)?;
+
+    let staging_path = temp_dir.path().join("staging.duckdb");
+    Connection::open(&staging_path)
+        .and_then(|conn| conn.execute_batch(&format!("IMPORT DATABASE '{}'", import_dir.display())))
+        .context("Failed to import backup into staging database")?;
+
+    let restored = selection::restore_selection(database_path, &staging_path, selection, into_schema, overwrite)?;
+    tracing::info!("Restored {} table(s)", restored.len());
+
+    Ok(restored)
+}
+
+/// Download a backup version and check every file against its checksum
+///
+/// A backup taken before versioning, with the exported files at the root, is downloaded when
+/// there are no versions.
+fn download_backup(backend: &dyn StorageBackend, version: Option<&str>, import_dir: &Path) -> Result<()> {
+    use duckdb::Connection;
+
+    let manifests = list_backups(backend)?;
+    let manifest = match version {
+        Some(version) => Some(manifests.iter().find(|manifest| manifest.version == version).ok_or_else(|| {
+            anyhow::anyhow!("No backup {} found at {}; run `crabwalk backup list` to see the versions", version, backend.location())
+        })?),
+        None => manifests.last(),
+    };
+
+    if let Some(manifest) = manifest {
+        tracing::info!("Restoring backup {} taken at {}", manifest.version, manifest.created_at);
+        let current_version: String = Connection::open_in_memory()
+            .and_then(|conn| conn.query_row("SELECT library_version FROM pragma_version()", [], |row| row.get(0)))
+            .context("Failed to read the DuckDB version")?;
+        if current_version != manifest.duckdb_version {
+            tracing::warn!(
+                "Backup {} was exported by DuckDB {} and is imported by DuckDB {}",
+                manifest.version, manifest.duckdb_version, current_version
+            );
+        }
+
+        for file in &manifest.files {
+            let path = restore_path(import_dir, &file.key)?;
+            backend.download(&format!("{}/{}", manifest.version, file.key), &path)?;
+            manifest.verify(file, &path)?;
+        }
+    } else {
+        let keys = backend.list()?;
+        if !keys.iter().any(|key| key == "schema.sql") {
+            anyhow::bail!("No backup found at {}", backend.location());
+        }
+
+        tracing::warn!("No backup versions found at {}; restoring the unversioned backup", backend.location());
+        for key in &keys {
+            backend.download(key, &restore_path(import_dir, key)?)?;
+        }
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Remove all but the newest backup versions
+///
+/// The manifest of a version is removed first, so a version that is only partly removed is
+/// no longer listed.
+///
+/// # Arguments
+///
+/// * `backend` - Where the backups are stored
+/// * `keep` - Number of versions to keep, at least one
+///
+/// # Returns
+///
+/// * `Result<Vec<BackupManifest>>` - Manifests of the removed versions
+pub fn prune(backend: &dyn StorageBackend, keep: usize) -> Result<Vec<BackupManifest>> {
+    if keep == 0 {
+        anyhow::bail!("At least one backup version must be kept");
+    }
+
+    let mut manifests = list_backups(backend)?;
+    let removed: Vec<BackupManifest> = manifests.drain(..manifests.len().saturating_sub(keep)).collect();
+    if removed.is_empty() {
+        return Ok(removed);
+    }
+
+    let keys = backend.list()?;
+    for manifest in &removed {
+        let prefix = format!("{}/", manifest.version);
+        let manifest_key = BackupManifest::key(&manifest.version);
+        backend.delete(&manifest_key)?;
+        for key in keys.iter().filter(|key| key.starts_with(&prefix) && **key != manifest_key) {
+            backend.delete(key)?;
+        }
+        tracing::info!("Removed backup {}", manifest.version);
+    }
+
+    Ok(removed)
+}
+
+/// Key of a file below a directory, always `/`-separated
+fn relative_key(dir: &Path, path: &Path) -> Result<String> {
+    let relative_path = path.strip_prefix(dir)
+        .context("Failed to get relative path")?;
+
+    Ok(relative_path.components()
+        .map(|c| c.as_os_str().to_string_lossy().to_string())
+        .collect::<Vec<_>>()
+        .join("/"))
+}
+
+/// Local path a backup file is downloaded to, refusing keys that would leave the directory
+fn restore_path(dir: &Path, key: &str) -> Result<PathBuf> {
+    let relative = Path::new(key);
+    if key.is_empty() || relative.components().any(|c| !matches!(c, Component::Normal(_))) {
+        anyhow::bail!("Refusing to restore file with an unsafe key: {}", key);
+    }
+
+    Ok(dir.join(relative))
+}
@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use futures::TryStreamExt;
+use object_store::buffered::BufWriter;
+use object_store::path::Path as StorePath;
+use object_store::ObjectStore;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::runtime::Runtime;
+
+use super::{S3Config, StorageBackend};
+
+/// Prefixes of the environment variables passed to the store as options, e.g. `AWS_REGION`
+/// or `GOOGLE_SERVICE_ACCOUNT`
+const OPTION_PREFIXES: [&str; 3] = ["AWS_", "GOOGLE_", "AZURE_"];
+
+/// Backups in any store the `object_store` crate opens from a URL, such as `gs://bucket/backups`,
+/// `az://container/backups` or `https://host/backups`
+pub struct ObjectStoreBackend {
+    /// URL the backend was opened from
+    url: String,
+    /// Runtime the store requests run on
+    runtime: Runtime,
+    /// Store holding the backup
+    store: Arc<dyn ObjectStore>,
+    /// Path of the backup in the store
+    prefix: StorePath,
+}
+
+impl ObjectStoreBackend {
+    /// Open the store of a URL
+    ///
+    /// Credentials and settings are read from `AWS_*`, `GOOGLE_*` and `AZURE_*` environment variables.
+    /// For `s3://` URLs, the keys, endpoint and region of an S3 configuration take precedence.
+    pub fn new(url: &str, s3_config: Option<&S3Config>) -> Result<Self> {
+        let parsed = url::Url::parse(url)
+            .context(format!("Invalid backup URL: {}", url))?;
+        let mut options: BTreeMap<String, String> = std::env::vars()
+            .filter(|(name, _)| OPTION_PREFIXES.iter().any(|prefix| name.starts_with(prefix)))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value))
+            .collect();
+        if let Some(s3_config) = s3_config {
+            options.extend(s3_options(s3_config));
+        }
+        let (store, prefix) = object_store::parse_url_opts(&parsed, options)
+            .context(format!("Failed to open object store: {}", url))?;
+        let runtime = Runtime::new()
+            .context("Failed to start the async runtime")?;
+
+        Ok(Self {
+            url: url.to_string(),
+            runtime,
+            store: Arc::from(store),
+            prefix,
+        })
+    }
+
+    /// Path in the store of the file under a key
+    fn path(&self, key: &str) -> StorePath {
+        key.split('/').fold(self.prefix.clone(), |path, part| path.child(part))
+    }
+}
+
+/// `object_store` options for the settings of an S3 configuration
+fn s3_options(s3_config: &S3Config) -> Vec<(String, String)> {
+    let mut options = Vec::new();
+    if let Some(access_key) = &s3_config.access_key {
+        options.push(("aws_access_key_id".to_string(), access_key.clone()));
+    }
+    if let Some(secret_key) = &s3_config.secret_key {
+        options.push(("aws_secret_access_key".to_string(), secret_key.clone()));
+    }
+    if let Some(region) = &s3_config.region_name {
+        options.push(("aws_region".to_string(), region.clone()));
+    }
+    if let Some(endpoint) = &s3_config.endpoint_url {
+        options.push(("aws_endpoint".to_string(), endpoint.clone()));
+        if endpoint.starts_with("http://") {
+            options.push(("aws_allow_http".to_string(), "true".to_string()));
+        }
+    }
+    options
+}
+
+impl StorageBackend for ObjectStoreBackend {
+    fn location(&self) -> String {
+        self.url.clone()
+    }
+
+    fn upload(&self, local_path: &Path, key: &str) -> Result<()> {
+        let path = self.path(key);
+        self.runtime.block_on(async {
+            let mut file = tokio::fs::File::open(local_path).await
+                .context(format!("Failed to open file: {}", local_path.display()))?;
+            // Buffers up to one part in memory and switches to a multipart upload for large files
+            let mut writer = BufWriter::new(Arc::clone(&self.store), path.clone());
+            tokio::io::copy(&mut file, &mut writer).await
+                .context(format!("Failed to upload {} to {}", local_path.display(), path))?;
+            writer.shutdown().await
+                .context(format!("Failed to upload {} to {}", local_path.display(), path))?;
+
+            Ok::<_, anyhow::Error>(())
+        })?;
+        tracing::info!("Uploaded {} to {}/{}", local_path.display(), self.url.trim_end_matches('/'), key);
+
+        Ok(())
+    }
+
+    fn download(&self, key: &str, local_path: &Path) -> Result<()> {
+        let path = self.path(key);
+        self.runtime.block_on(async {
+            if let Some(parent) = local_path.parent() {
+                tokio::fs::create_dir_all(parent).await
+                    .context(format!("Failed to create directory: {}", parent.display()))?;
+            }
+
+            let mut stream = self.store.get(&path).await
+                .context(format!("Failed to get object: {}", path))?
+                .into_stream();
+            let mut file = tokio::fs::File::create(local_path).await
+                .context(format!("Failed to create file: {}", local_path.display()))?;
+            while let Some(chunk) = stream.try_next().await
+                .context(format!("Failed to read object: {}", path))?
+            {
+                file.write_all(&chunk).await
+                    .context(format!("Failed to write to file: {}", local_path.display()))?;
+            }
+            file.flush().await
+                .context(format!("Failed to write to file: {}", local_path.display()))
+        })
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let objects: Vec<_> = self.runtime.block_on(self.store.list(Some(&self.prefix)).try_collect())
+            .context(format!("Failed to list objects in {}", self.url))?;
+
+        let mut keys: Vec<String> = objects.iter()
+            .filter_map(|object| object.location.prefix_match(&self.prefix))
+            .map(|parts| parts.map(|part| part.as_ref().to_string()).collect::<Vec<_>>().join("/"))
+            .collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let path = self.path(key);
+        self.runtime.block_on(self.store.delete(&path))
+            .context(format!("Failed to delete object: {}", path))
+    }
+}
@@ -0,0 +1,234 @@
+use anyhow::{Context, Result};
+use aws_config::retry::RetryConfig;
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::config::Credentials;
+use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
+use aws_sdk_s3::Client;
+use std::path::Path;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::runtime::Runtime;
+
+use super::{S3Config, StorageBackend};
+
+/// Files at least this large are uploaded in parts of this size, so only one part is held in memory
+const PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Attempts per request, including the first, before an error is returned
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Backups in an S3 bucket or an S3-compatible store such as MinIO, under `{bucket}/{db_folder_name}/`
+pub struct S3Backend {
+    /// Bucket, folder, credentials and endpoint
+    config: S3Config,
+    /// Runtime the SDK requests run on
+    runtime: Runtime,
+    /// Client for the bucket
+    client: Client,
+}
+
+impl S3Backend {
+    /// Connect to the bucket of an S3 configuration
+    pub fn new(config: S3Config) -> Result<Self> {
+        let runtime = Runtime::new()
+            .context("Failed to start the async runtime")?;
+        let client = runtime.block_on(client(&config));
+
+        Ok(Self { config, runtime, client })
+    }
+
+    /// Prefix of every object in the backup, empty when the backup is at the root of the bucket
+    fn prefix(&self) -> String {
+        let folder = self.config.db_folder_name.trim_matches('/');
+        if folder.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", folder)
+        }
+    }
+}
+
+impl StorageBackend for S3Backend {
+    fn location(&self) -> String {
+        format!("s3://{}/{}", self.config.bucket, self.prefix())
+    }
+
+    fn upload(&self, local_path: &Path, key: &str) -> Result<()> {
+        let key = format!("{}{}", self.prefix(), key);
+        self.runtime.block_on(upload_file(&self.client, &self.config.bucket, &key, local_path))
+    }
+
+    fn download(&self, key: &str, local_path: &Path) -> Result<()> {
+        let key = format!("{}{}", self.prefix(), key);
+        self.runtime.block_on(download_file(&self.client, &self.config.bucket, &key, local_path))
+    }
+
+    fn list(&self) -> Result<Vec<String>> {
+        let prefix = self.prefix();
+        let mut keys: Vec<String> = self.runtime.block_on(list_keys(&self.client, &self.config.bucket, &prefix))?
+            .into_iter()
+            .filter_map(|key| key.strip_prefix(&prefix).map(str::to_string))
+            .collect();
+        keys.sort();
+
+        Ok(keys)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        let key = format!("{}{}", self.prefix(), key);
+        self.runtime.block_on(self.client.delete_object().bucket(&self.config.bucket).key(&key).send())
+            .context(format!("Failed to delete s3://{}/{}", self.config.bucket, key))?;
+
+        Ok(())
+    }
+}
+
+/// Build an S3 client from the configuration
+///
+/// Keys in the configuration take precedence; otherwise credentials come from the default
+/// chain (environment variables, shared profile, web identity and instance metadata).
+/// A custom endpoint, such as MinIO, is addressed with path-style URLs.
+async fn client(s3_config: &S3Config) -> Client {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest())
+        .retry_config(RetryConfig::standard().with_max_attempts(MAX_ATTEMPTS));
+
+    if let Some(region) = &s3_config.region_name {
+        loader = loader.region(Region::new(region.clone()));
+    } else if s3_config.endpoint_url.is_some() {
+        // Custom endpoints ignore the region, but requests must still be signed for one
+        loader = loader.region(Region::new("us-east-1"));
+    }
+    if let (Some(access_key), Some(secret_key)) = (&s3_config.access_key, &s3_config.secret_key) {
+        loader = loader.credentials_provider(Credentials::new(
+            access_key.clone(),
+            secret_key.clone(),
+            None,
+            None,
+            "crabwalk",
+        ));
+    }
+
+    let shared_config = loader.load().await;
+    let mut builder = aws_sdk_s3::config::Builder::from(&shared_config);
+    if let Some(endpoint) = &s3_config.endpoint_url {
+        builder = builder.endpoint_url(endpoint).force_path_style(true);
+    }
+
+    Client::from_conf(builder.build())
+}
+
+/// Stream a file to S3, in parts when it is at least `PART_SIZE` bytes
+async fn upload_file(client: &Client, bucket: &str, key: &str, path: &Path) -> Result<()> {
+    let size = tokio::fs::metadata(path).await
+        .context(format!("Failed to read file: {}", path.display()))?
+        .len();
+
+    if size < PART_SIZE as u64 {
+        let body = ByteStream::from_path(path).await
+            .context(format!("Failed to open file: {}", path.display()))?;
+        client.put_object().bucket(bucket).key(key).body(body).send().await
+            .context(format!("Failed to upload {} to s3://{}/{}", path.display(), bucket, key))?;
+    } else {
+        let upload = client.create_multipart_upload().bucket(bucket).key(key).send().await
+            .context(format!("Failed to start multipart upload of s3://{}/{}", bucket, key))?;
+        let upload_id = upload.upload_id()
+            .context(format!("S3 returned no upload id for s3://{}/{}", bucket, key))?
+            .to_string();
+
+        let result = upload_parts(client, bucket, key, &upload_id, path).await;
+        if result.is_err() {
+            // Abort so the bucket is not left holding the parts already uploaded
+            let _ = client.abort_multipart_upload().bucket(bucket).key(key).upload_id(&upload_id).send().await;
+        }
+        result?;
+    }
+
+    tracing::info!("Uploaded {} to s3://{}/{}", path.display(), bucket, key);
+
+    Ok(())
+}
+
+/// Upload a file part by part and complete the multipart upload
+async fn upload_parts(client: &Client, bucket: &str, key: &str, upload_id: &str, path: &Path) -> Result<()> {
+    let mut file = tokio::fs::File::open(path).await
+        .context(format!("Failed to open file: {}", path.display()))?;
+    let mut parts = Vec::new();
+
+    loop {
+        let mut buffer = Vec::with_capacity(PART_SIZE);
+        (&mut file).take(PART_SIZE as u64).read_to_end(&mut buffer).await
+            .context(format!("Failed to read file: {}", path.display()))?;
+        if buffer.is_empty() {
+            break;
+        }
+
+        let part_number = parts.len() as i32 + 1;
+        let part = client.upload_part()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(ByteStream::from(buffer))
+            .send()
+            .await
+            .context(format!("Failed to upload part {} of s3://{}/{}", part_number, bucket, key))?;
+
+        parts.push(
+            CompletedPart::builder()
+                .part_number(part_number)
+                .set_e_tag(part.e_tag().map(str::to_string))
+                .build(),
+        );
+    }
+
+    client.complete_multipart_upload()
+        .bucket(bucket)
+        .key(key)
+        .upload_id(upload_id)
+        .multipart_upload(CompletedMultipartUpload::builder().set_parts(Some(parts)).build())
+        .send()
+        .await
+        .context(format!("Failed to complete multipart upload of s3://{}/{}", bucket, key))?;
+
+    Ok(())
+}
+
+/// Keys of every object under a prefix, following continuation tokens past 1000 keys
+async fn list_keys(client: &Client, bucket: &str, prefix: &str) -> Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut pages = client.list_objects_v2().bucket(bucket).prefix(prefix).into_paginator().send();
+
+    while let Some(page) = pages.next().await {
+        let page = page.context(format!("Failed to list objects in s3://{}/{}", bucket, prefix))?;
+        keys.extend(page.contents().iter().filter_map(|object| object.key().map(str::to_string)));
+    }
+
+    Ok(keys)
+}
+
+/// Stream an object to a local file
+async fn download_file(client: &Client, bucket: &str, key: &str, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await
+            .context(format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    let object = client.get_object().bucket(bucket).key(key).send().await
+        .context(format!("Failed to get object from S3: {}", key))?;
+
+    let mut file = tokio::fs::File::create(path).await
+        .context(format!("Failed to create file: {}", path.display()))?;
+    let mut body = object.body;
+    while let Some(chunk) = body.try_next().await
+        .context(format!("Failed to read S3 object body: {}", key))?
+    {
+        file.write_all(&chunk).await
+            .context(format!("Failed to write to file: {}", path.display()))?;
+    }
+    file.flush().await
+        .context(format!("Failed to write to file: {}", path.display()))?;
+
+    tracing::info!("Downloaded s3://{}/{} to {}", bucket, key, path.display());
+
+    Ok(())
+}
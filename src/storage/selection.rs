@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use duckdb::Connection;
+use std::fmt;
+use std::path::Path;
+
+/// Alias of the staging database attached while copying tables and views
+const STAGING_ALIAS: &str = "crabwalk_staging";
+
+/// Tables and views chosen for a backup or restore with `--select`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupSelection {
+    /// Schemas whose tables and views are all chosen
+    pub schemas: Vec<String>,
+    /// Tables and views chosen by name
+    pub relations: Vec<Relation>,
+}
+
+/// A table or view in a schema
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relation {
+    /// Schema holding the table or view
+    pub schema: String,
+    /// Name of the table or view, usually a model
+    pub name: String,
+}
+
+impl fmt::Display for Relation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.schema, self.name)
+    }
+}
+
+impl Relation {
+    /// Whether this is the table or view `schema.name`, ignoring case as DuckDB does
+    fn is(&self, schema: &str, name: &str) -> bool {
+        self.schema.eq_ignore_ascii_case(schema) && self.name.eq_ignore_ascii_case(name)
+    }
+
+    /// Quoted name of the relation in a database
+    fn quoted(&self, database: &str) -> String {
+        format!("{}.{}.{}", quote(database), quote(&self.schema), quote(&self.name))
+    }
+}
+
+/// Quote an identifier
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+impl BackupSelection {
+    /// Parse selectors given on the command line
+    ///
+    /// # Arguments
+    ///
+    /// * `selectors` - `schema:<name>` for a whole schema, or a model name, optionally as `schema.model`
+    /// * `default_schema` - Schema of models named without one
+    ///
+    /// # Returns
+    ///
+    /// * `Result<BackupSelection>` - Selection of the schemas and models, or an error for an invalid selector
+    pub fn parse(selectors: &[String], default_schema: &str) -> Result<Self> {
+        let mut selection = Self::default();
+        for selector in selectors {
+            let selector = selector.trim();
+            match selector.split_once(':') {
+                Some(("schema", schema)) if !schema.trim().is_empty() => selection.schemas.push(schema.trim().to_string()),
+                Some((kind, _)) => anyhow::bail!(
+                    "Invalid selector: {} (expected `schema:<name>` or a model name, not `{}:`)", selector, kind
+                ),
+                None if selector.is_empty() => anyhow::bail!("Invalid selector: empty"),
+                None => {
+                    let (schema, name) = selector.split_once('.').unwrap_or((default_schema, selector));
+                    selection.relations.push(Relation { schema: schema.to_string(), name: name.to_string() });
+                }
+            }
+        }
+
+        Ok(selection)
+    }
+
+    /// Whether nothing was selected, meaning the whole database
+    pub fn is_empty(&self) -> bool {
+        self.schemas.is_empty() && self.relations.is_empty()
+    }
+
+    /// Whether a table or view is selected
+    fn matches(&self, relation: &Relation) -> bool {
+        self.schemas.iter().any(|schema| schema.eq_ignore_ascii_case(&relation.schema))
+            || self.relations.iter().any(|selected| selected.is(&relation.schema, &relation.name))
+    }
+
+    /// The selected tables and views among those in a database
+    ///
+    /// A schema or model that matches nothing is an error, since it is almost always a typo.
+    fn select(&self, relations: Vec<Relation>, location: &str) -> Result<Vec<Relation>> {
+        for schema in &self.schemas {
+            if !relations.iter().any(|relation| relation.schema.eq_ignore_ascii_case(schema)) {
+                anyhow::bail!("No tables or views in schema {} of {}", schema, location);
+            }
+        }
+        for selected in &self.relations {
+            if !relations.iter().any(|relation| relation.is(&selected.schema, &selected.name)) {
+                anyhow::bail!("No table or view {} in {}", selected, location);
+            }
+        }
+
+        Ok(relations.into_iter().filter(|relation| self.matches(relation)).collect())
+    }
+}
+
+/// Tables and views of a database, excluding DuckDB's own
+fn relations(conn: &Connection, database: &str) -> Result<Vec<Relation>> {
+    let mut relations = list(
+        conn,
+        "SELECT schema_name, table_name FROM duckdb_tables() WHERE database_name = ? AND NOT internal AND NOT temporary",
+        database,
+    )?;
+    relations.extend(views(conn, database)?);
+    relations.sort_by(|a, b| (&a.schema, &a.name).cmp(&(&b.schema, &b.name)));
+
+    Ok(relations)
+}
+
+/// Views of a database, excluding DuckDB's own
+fn views(conn: &Connection, database: &str) -> Result<Vec<Relation>> {
+    list(
+        conn,
+        "SELECT schema_name, view_name FROM duckdb_views() WHERE database_name = ? AND NOT internal AND NOT temporary",
+        database,
+    )
+}
+
+/// Run a query of schema and relation names for a database
+fn list(conn: &Connection, sql: &str, database: &str) -> Result<Vec<Relation>> {
+    let mut stmt = conn.prepare(sql)?;
+    let relations = stmt.query_map([database], |row| Ok(Relation { schema: row.get(0)?, name: row.get(1)? }))?
+        .collect::<duckdb::Result<Vec<Relation>>>()
+        .context("Failed to list tables and views")?;
+
+    Ok(relations)
+}
+
+/// Name of the database a connection was opened on, before anything is attached
+fn database_name(conn: &Connection) -> Result<String> {
+    conn.query_row(
+        "SELECT database_name FROM duckdb_databases() WHERE NOT internal ORDER BY database_oid LIMIT 1",
+        [],
+        |row| row.get(0),
+    )
+    .context("Failed to read the database name")
+}
+
+/// Copy tables and views into a database as tables, creating their schemas
+///
+/// Views become tables holding the rows they return, so the copy does not depend on anything
+/// that was left out.
+fn copy_relations(conn: &Connection, relations: &[(Relation, Relation)], from: &str, to: &str) -> Result<()> {
+    for (source, target) in relations {
+        conn.execute_batch(&format!(
+            "CREATE SCHEMA IF NOT EXISTS {}.{};\nCREATE OR REPLACE TABLE {} AS SELECT * FROM {};",
+            quote(to), quote(&target.schema), target.quoted(to), source.quoted(from)
+        ))
+        .context(format!("Failed to copy {} to {}", source, target))?;
+        tracing::info!("Copied {} to {}", source, target);
+    }
+
+    Ok(())
+}
+
+/// Export the selected tables and views of a database the way `EXPORT DATABASE` does
+///
+/// The selection is copied into a staging database, which is then exported, so the directory
+/// is imported like a backup of the whole database.
+///
+/// # Arguments
+///
+/// * `conn` - Connection to the database being backed up
+/// * `selection` - Tables and views to export
+/// * `staging_path` - Path of the staging database, which must not exist
+/// * `export_dir` - Directory to export to
+///
+/// # Returns
+///
+/// * `Result<Vec<Relation>>` - The exported tables and views
+pub(crate) fn export_selection(conn: &Connection, selection: &BackupSelection, staging_path: &Path, export_dir: &Path) -> Result<Vec<Relation>> {
+    let database = database_name(conn)?;
+    let selected = selection.select(relations(conn, &database)?, "the database")?;
+
+    conn.execute_batch(&format!("ATTACH '{}' AS {}", staging_path.display().to_string().replace('\'', "''"), STAGING_ALIAS))
+        .context(format!("Failed to create staging database: {}", staging_path.display()))?;
+    let copied = copy_relations(
+        conn,
+        &selected.iter().map(|relation| (relation.clone(), relation.clone())).collect::<Vec<_>>(),
+        &database,
+        STAGING_ALIAS,
+    )
+    .and_then(|_| conn.execute_batch(&format!(
+        "EXPORT DATABASE {} TO '{}' (FORMAT 'parquet')",
+        STAGING_ALIAS, export_dir.display().to_string().replace('\'', "''")
+    ))
+    .context("Failed to export the selected tables"));
+    conn.execute_batch(&format!("DETACH {}", STAGING_ALIAS))
+        .context("Failed to detach staging database")?;
+    copied?;
+
+    Ok(selected)
+}
+
+/// Copy tables and views of an imported backup into an existing database
+///
+/// # Arguments
+///
+/// * `database_path` - Path to the DuckDB database file, created if missing
+/// * `staging_path` - Database the backup was imported into
+/// * `selection` - Tables and views to restore; all of them when empty
+/// * `into_schema` - Schema to restore into instead of the one each table was backed up from
+/// * `overwrite` - Whether to replace tables and views that already exist
+///
+/// # Returns
+///
+/// * `Result<Vec<Relation>>` - The restored tables, named as in the database
+pub(crate) fn restore_selection(
+    database_path: &str,
+    staging_path: &Path,
+    selection: &BackupSelection,
+    into_schema: Option<&str>,
+    overwrite: bool,
+) -> Result<Vec<Relation>> {
+    let conn = Connection::open(database_path)
+        .context(format!("Failed to connect to DuckDB database: {}", database_path))?;
+    let database = database_name(&conn)?;
+    conn.execute_batch(&format!(
+        "ATTACH '{}' AS {} (READ_ONLY)",
+        staging_path.display().to_string().replace('\'', "''"), STAGING_ALIAS
+    ))
+    .context(format!("Failed to open staging database: {}", staging_path.display()))?;
+
+    let restored = restore_relations(&conn, &database, selection, into_schema, overwrite);
+    conn.execute_batch(&format!("DETACH {}", STAGING_ALIAS))
+        .context("Failed to detach staging database")?;
+
+    restored
+}
+
+/// Copy the selected tables and views of the attached staging database
+fn restore_relations(
+    conn: &Connection,
+    database: &str,
+    selection: &BackupSelection,
+    into_schema: Option<&str>,
+    overwrite: bool,
+) -> Result<Vec<Relation>> {
+    let backed_up = relations(conn, STAGING_ALIAS)?;
+    let selected = if selection.is_empty() { backed_up } else { selection.select(backed_up, "the backup")? };
+    if selected.is_empty() {
+        anyhow::bail!("The backup has no tables or views");
+    }
+
+    let pairs: Vec<(Relation, Relation)> = selected.into_iter()
+        .map(|relation| {
+            let target = Relation {
+                schema: into_schema.map(str::to_string).unwrap_or_else(|| relation.schema.clone()),
+                name: relation.name.clone(),
+            };
+            (relation, target)
+        })
+        .collect();
+
+    // Check every target before changing anything, so a restore is never left half done
+    let existing = relations(conn, database)?;
+    let existing_views = views(conn, database)?;
+    for (i, (source, target)) in pairs.iter().enumerate() {
+        if pairs[..i].iter().any(|(_, other)| other.is(&target.schema, &target.name)) {
+            anyhow::bail!("More than one table would be restored as {}, including {}", target, source);
+        }
+        if !overwrite && existing.iter().any(|relation| relation.is(&target.schema, &target.name)) {
+            anyhow::bail!("{} already exists. Use --overwrite to replace it, or --into-schema to restore next to it.", target);
+        }
+    }
+
+    conn.execute_batch("BEGIN TRANSACTION")?;
+    let restored = pairs.iter()
+        .filter(|(_, target)| existing_views.iter().any(|view| view.is(&target.schema, &target.name)))
+        .try_for_each(|(_, target)| {
+            // A table cannot replace a view, so views with the same name are dropped first
+            conn.execute_batch(&format!("DROP VIEW {}", target.quoted(database)))
+                .context(format!("Failed to drop view {}", target))
+        })
+        .and_then(|_| copy_relations(conn, &pairs, STAGING_ALIAS, database));
+    match restored {
+        Ok(()) => conn.execute_batch("COMMIT")?,
+        Err(error) => {
+            conn.execute_batch("ROLLBACK")?;
+            return Err(error);
+        }
+    }
+
+    Ok(pairs.into_iter().map(|(_, target)| target).collect())
+}
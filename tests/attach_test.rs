@@ -0,0 +1,107 @@
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::Crabwalk;
+use crabwalk::config::{AttachConfig, DatabaseType, ProjectConfig};
+use crabwalk::parser::config::extract_config_from_sql;
+
+#[test]
+fn test_attach_sql() {
+    let postgres = AttachConfig {
+        alias: "serving".to_string(),
+        database_type: DatabaseType::Postgres,
+        path: "host=localhost dbname=serving password={{PGPASSWORD}}".to_string(),
+        read_only: false,
+    };
+    assert_eq!(
+        postgres.attach_sql(),
+        "ATTACH IF NOT EXISTS 'host=localhost dbname=serving password={{PGPASSWORD}}' AS \"serving\" (TYPE postgres)"
+    );
+
+    let sqlite = AttachConfig {
+        alias: "lookup".to_string(),
+        database_type: DatabaseType::Sqlite,
+        path: "./data/lookup.db".to_string(),
+        read_only: true,
+    };
+    assert_eq!(sqlite.attach_sql(), "ATTACH IF NOT EXISTS './data/lookup.db' AS \"lookup\" (TYPE sqlite, READ_ONLY)");
+}
+
+#[test]
+fn test_load_project_config() {
+    let temp_dir = tempdir().unwrap();
+    let folder = temp_dir.path().to_str().unwrap();
+    assert!(ProjectConfig::load(folder).unwrap().attach.is_empty(), "A folder without crabwalk.yml has no attachments");
+
+    fs::write(
+        temp_dir.path().join("crabwalk.yml"),
+        "attach:\n  - {alias: serving, type: postgres, path: 'dbname=serving'}\n  - {alias: lookup, type: sqlite, path: lookup.db, read_only: true}\n",
+    ).unwrap();
+    let project = ProjectConfig::load(folder).unwrap();
+    assert_eq!(project.attach.len(), 2);
+    assert_eq!(project.attach[1].database_type, DatabaseType::Sqlite);
+    assert!(project.attach[1].read_only);
+
+    fs::write(
+        temp_dir.path().join("crabwalk.yml"),
+        "attach:\n  - {alias: serving, type: postgres, path: a}\n  - {alias: SERVING, type: sqlite, path: b}\n",
+    ).unwrap();
+    assert!(ProjectConfig::load(folder).is_err(), "Duplicate aliases should be rejected");
+
+    fs::write(temp_dir.path().join("crabwalk.yml"), "attach:\n  - {alias: serving, type: oracle, path: a}\n").unwrap();
+    assert!(ProjectConfig::load(folder).is_err(), "Unknown database types should be rejected");
+}
+
+#[test]
+fn test_output_target_is_checked() {
+    let invalid = [
+        "-- @config: {output: {type: parquet, target: serving.public.orders}}",
+        "-- @config: {output: {type: table, target: a.b.c.d}}",
+        "-- @config: {output: {type: table, target: serving..orders}}",
+    ];
+    for sql in invalid {
+        assert!(extract_config_from_sql(sql).is_err(), "Expected an error for {}", sql);
+    }
+
+    let sql = "-- @config: {outputs: [{type: table}, {type: table, target: \"serving.main.{table_name}\"}]}\nSELECT 1";
+    let config = extract_config_from_sql(sql).unwrap().unwrap();
+    assert_eq!(config.outputs[0].relation("transform", "orders"), "transform.orders");
+    assert_eq!(config.outputs[1].relation("transform", "orders"), "serving.main.orders");
+}
+
+#[test]
+fn test_run_materializes_into_attached_database() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    fs::create_dir_all(&models).unwrap();
+    let serving = temp_dir.path().join("serving.duckdb");
+    let database = temp_dir.path().join("test.db");
+
+    fs::write(
+        models.join("crabwalk.yml"),
+        format!("attach:\n  - alias: serving\n    type: duckdb\n    path: '{}'\n", serving.display()),
+    ).unwrap();
+    fs::write(models.join("stg_orders.sql"), "SELECT 1 AS id, 10 AS amount").unwrap();
+    fs::write(
+        models.join("orders.sql"),
+        "-- @config: {outputs: [{type: table}, {type: table, target: \"serving.main.{table_name}\"}]}\nSELECT id, amount FROM stg_orders",
+    ).unwrap();
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    );
+    crabwalk.run().unwrap();
+    drop(crabwalk);
+
+    let conn = duckdb::Connection::open(&database).unwrap();
+    assert!(conn.prepare("SELECT * FROM transform.orders").is_ok(), "The local table should still be created");
+    drop(conn);
+
+    let conn = duckdb::Connection::open(&serving).unwrap();
+    let amount: i32 = conn.query_row("SELECT amount FROM main.orders", [], |row| row.get(0)).unwrap();
+    assert_eq!(amount, 10);
+}
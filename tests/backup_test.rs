@@ -0,0 +1,330 @@
+use duckdb::Connection;
+use std::fs;
+use tempfile::tempdir;
+use std::path::Path;
+use crabwalk::storage::{
+    backup, list_backups, open_backend, prune, restore, restore_selected, upload_backup, BackupManifest, BackupSelection,
+    LocalBackend, Relation, S3Config, StorageBackend,
+};
+use crabwalk::Crabwalk;
+
+#[test]
+fn test_local_backend_round_trip() {
+    let temp_dir = tempdir().unwrap();
+    let source = temp_dir.path().join("source.csv");
+    fs::write(&source, "id\n1\n2\n").unwrap();
+
+    let backend = LocalBackend::new(temp_dir.path().join("nas").join("crabwalk"));
+    assert!(backend.list().unwrap().is_empty());
+
+    backend.upload(&source, "schema.sql").unwrap();
+    backend.upload(&source, "data/orders.csv").unwrap();
+    assert_eq!(backend.list().unwrap(), vec!["data/orders.csv", "schema.sql"]);
+    assert!(temp_dir.path().join("nas/crabwalk/data/orders.csv").is_file());
+
+    let downloaded = temp_dir.path().join("download").join("orders.csv");
+    backend.download("data/orders.csv", &downloaded).unwrap();
+    assert_eq!(fs::read_to_string(downloaded).unwrap(), "id\n1\n2\n");
+}
+
+#[test]
+fn test_open_backend_for_paths() {
+    let backend = open_backend("/mnt/nas/crabwalk", None).unwrap();
+    assert_eq!(backend.location(), "/mnt/nas/crabwalk");
+
+    let backend = open_backend("file:///mnt/nas/crabwalk", None).unwrap();
+    assert_eq!(backend.location(), "/mnt/nas/crabwalk");
+}
+
+#[cfg(not(feature = "object-store"))]
+#[test]
+fn test_open_backend_needs_feature_for_urls() {
+    let error = open_backend("gs://bucket/backups", None).err().unwrap();
+    assert!(error.to_string().contains("--features object-store"), "{}", error);
+}
+
+/// Write a CSV export of `transform.orders` with one row per status, as `EXPORT DATABASE` would
+fn write_export(dir: &Path, statuses: &[&str]) {
+    fs::create_dir_all(dir).unwrap();
+    fs::write(
+        dir.join("schema.sql"),
+        "CREATE SCHEMA transform;\nCREATE TABLE transform.orders(id INTEGER, status VARCHAR);\n",
+    ).unwrap();
+    fs::write(
+        dir.join("load.sql"),
+        format!("COPY transform.orders FROM '{}' (FORMAT csv, HEADER true);\n", dir.join("orders.csv").display()),
+    ).unwrap();
+    let rows: String = statuses.iter().enumerate().map(|(i, status)| format!("{},{}\n", i + 1, status)).collect();
+    fs::write(dir.join("orders.csv"), format!("id,status\n{}", rows)).unwrap();
+}
+
+/// Ids of the orders in a restored database
+fn order_ids(database: &Path) -> Vec<i32> {
+    ids(database, "transform.orders")
+}
+
+/// Ids in a table of a restored database
+fn ids(database: &Path, table: &str) -> Vec<i32> {
+    let conn = Connection::open(database).unwrap();
+    let mut stmt = conn.prepare(&format!("SELECT id FROM {} ORDER BY id", table)).unwrap();
+    let ids = stmt.query_map([], |row| row.get(0)).unwrap().map(|id| id.unwrap()).collect();
+    ids
+}
+
+/// Upload an export as a new backup version
+fn upload(dir: &Path, statuses: &[&str], backend: &LocalBackend) -> BackupManifest {
+    write_export(dir, statuses);
+    let manifest = BackupManifest::new(dir, "v1.2.0", vec!["main".to_string(), "transform".to_string()]).unwrap();
+    upload_backup(dir, &manifest, backend).unwrap();
+    // Versions are named after the time to the millisecond
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    manifest
+}
+
+#[test]
+fn test_versioned_backups() {
+    let temp_dir = tempdir().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+
+    let first = upload(&temp_dir.path().join("first"), &["shipped"], &backend);
+    let second = upload(&temp_dir.path().join("second"), &["shipped", "returned"], &backend);
+    assert!(first.version < second.version);
+
+    let manifests = list_backups(&backend).unwrap();
+    let versions: Vec<&str> = manifests.iter().map(|manifest| manifest.version.as_str()).collect();
+    assert_eq!(versions, vec![first.version.as_str(), second.version.as_str()]);
+    assert_eq!(manifests[1].schemas, vec!["main", "transform"]);
+    assert_eq!(manifests[1].duckdb_version, "v1.2.0");
+    let keys: Vec<&str> = manifests[1].files.iter().map(|file| file.key.as_str()).collect();
+    assert_eq!(keys, vec!["load.sql", "orders.csv", "schema.sql"]);
+    assert!(manifests[1].files.iter().all(|file| file.sha256.len() == 64 && file.size > 0));
+    assert!(backend.list().unwrap().contains(&format!("{}/manifest.json", second.version)));
+
+    // A version is never overwritten
+    let error = upload_backup(&temp_dir.path().join("second"), &second, &backend).unwrap_err();
+    assert!(error.to_string().contains("already exists"), "{}", error);
+
+    // The newest version is restored unless one is named
+    let database = temp_dir.path().join("restored.duckdb");
+    restore(database.to_str().unwrap(), &backend, None, false).unwrap();
+    assert_eq!(order_ids(&database), vec![1, 2]);
+    restore(database.to_str().unwrap(), &backend, Some(&first.version), true).unwrap();
+    assert_eq!(order_ids(&database), vec![1]);
+
+    let error = restore(database.to_str().unwrap(), &backend, Some("20000101T000000.000Z"), true).unwrap_err();
+    assert!(error.to_string().contains("No backup 20000101T000000.000Z"), "{}", error);
+}
+
+#[test]
+fn test_prune_keeps_newest_versions() {
+    let temp_dir = tempdir().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+    let versions: Vec<String> = (0..3)
+        .map(|i| upload(&temp_dir.path().join(format!("export{}", i)), &["shipped"], &backend).version)
+        .collect();
+
+    assert!(prune(&backend, 0).is_err());
+    assert!(prune(&backend, 3).unwrap().is_empty());
+
+    let removed: Vec<String> = prune(&backend, 1).unwrap().into_iter().map(|manifest| manifest.version).collect();
+    assert_eq!(removed, versions[..2]);
+    let remaining: Vec<String> = list_backups(&backend).unwrap().into_iter().map(|manifest| manifest.version).collect();
+    assert_eq!(remaining, versions[2..]);
+    assert!(backend.list().unwrap().iter().all(|key| key.starts_with(&versions[2])));
+    assert!(!temp_dir.path().join("backups").join(&versions[0]).exists());
+}
+
+#[test]
+fn test_restore_rejects_corrupt_backup() {
+    let temp_dir = tempdir().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+    let manifest = upload(&temp_dir.path().join("export"), &["shipped"], &backend);
+
+    fs::write(
+        temp_dir.path().join("backups").join(&manifest.version).join("orders.csv"),
+        "id,status\n1,lost\n",
+    ).unwrap();
+
+    let database = temp_dir.path().join("restored.duckdb");
+    let error = restore(database.to_str().unwrap(), &backend, None, false).unwrap_err();
+    assert!(error.to_string().contains("is corrupt"), "{}", error);
+    assert!(!database.exists());
+}
+
+#[test]
+fn test_restore_unversioned_backup() {
+    let temp_dir = tempdir().unwrap();
+
+    // Backups taken before versioning keep the exported files at the root
+    let export_dir = temp_dir.path().join("export");
+    write_export(&export_dir, &["shipped", "returned"]);
+    let backend = LocalBackend::new(&export_dir);
+
+    let database = temp_dir.path().join("restored.duckdb");
+    restore(database.to_str().unwrap(), &backend, None, false).unwrap();
+    assert_eq!(order_ids(&database), vec![1, 2]);
+
+    // An existing database is only replaced with overwrite
+    let error = restore(database.to_str().unwrap(), &backend, None, false).unwrap_err();
+    assert!(error.to_string().contains("already exists"), "{}", error);
+    restore(database.to_str().unwrap(), &backend, None, true).unwrap();
+
+    let empty = LocalBackend::new(temp_dir.path().join("empty"));
+    let error = restore(temp_dir.path().join("other.duckdb").to_str().unwrap(), &empty, None, false).unwrap_err();
+    assert!(error.to_string().contains("No backup found"), "{}", error);
+}
+
+#[test]
+fn test_failed_restore_keeps_the_database() {
+    let temp_dir = tempdir().unwrap();
+    let export_dir = temp_dir.path().join("export");
+    write_export(&export_dir, &["shipped"]);
+    let database = temp_dir.path().join("restored.duckdb");
+    restore(database.to_str().unwrap(), &LocalBackend::new(&export_dir), None, false).unwrap();
+
+    let broken_dir = temp_dir.path().join("broken");
+    fs::create_dir_all(&broken_dir).unwrap();
+    fs::write(broken_dir.join("schema.sql"), "CREATE TABLE broken(").unwrap();
+    fs::write(broken_dir.join("load.sql"), "").unwrap();
+    let error = restore(database.to_str().unwrap(), &LocalBackend::new(&broken_dir), None, true).unwrap_err();
+    assert!(error.to_string().contains("Failed to import database"), "{}", error);
+    assert_eq!(order_ids(&database), vec![1]);
+    assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 3, "No temporary files should be left");
+
+    // A write-ahead log of the replaced database must not be replayed on the restored one
+    write_export(&export_dir, &["shipped", "returned"]);
+    let wal = temp_dir.path().join("restored.duckdb.wal");
+    fs::write(&wal, "stale").unwrap();
+    restore(database.to_str().unwrap(), &LocalBackend::new(&export_dir), None, true).unwrap();
+    assert!(!wal.exists());
+    assert_eq!(order_ids(&database), vec![1, 2]);
+}
+
+#[test]
+fn test_crabwalk_backup_needs_location() {
+    let temp_dir = tempdir().unwrap();
+    let database = temp_dir.path().join("crabwalk.duckdb");
+    let crabwalk = |s3_config: Option<S3Config>| Crabwalk::for_database(database.to_str().unwrap().to_string(), s3_config);
+
+    let error = crabwalk(None).backup(None, None, &BackupSelection::default()).unwrap_err();
+    assert!(error.to_string().contains("No backup location given"), "{}", error);
+    let error = crabwalk(Some(S3Config { bucket: String::new(), ..S3Config::default() })).restore(None, None, false).unwrap_err();
+    assert!(error.to_string().contains("No backup location given"), "{}", error);
+
+    // Without a storage feature, S3 is an error rather than a skipped backup
+    #[cfg(not(any(feature = "s3", feature = "object-store")))]
+    {
+        let s3_config = S3Config { bucket: "backups".to_string(), ..S3Config::default() };
+        let error = crabwalk(Some(s3_config)).backup(None, None, &BackupSelection::default()).unwrap_err();
+        assert!(error.to_string().contains("--features s3"), "{}", error);
+    }
+
+    // The S3 configuration is only a fallback; a location is used when given
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+    let manifest = upload(&temp_dir.path().join("export"), &["shipped"], &backend);
+    let crabwalk = crabwalk(Some(S3Config { bucket: "backups".to_string(), ..S3Config::default() }));
+    let location = temp_dir.path().join("backups");
+    let manifests = crabwalk.list_backups(location.to_str()).unwrap();
+    assert_eq!(manifests.len(), 1);
+    crabwalk.restore(location.to_str(), Some(&manifest.version), false).unwrap();
+    assert_eq!(order_ids(&database), vec![1]);
+}
+
+#[test]
+fn test_parse_backup_selection() {
+    let selectors = ["orders", "marts.revenue", "schema:staging"].map(String::from);
+    let selection = BackupSelection::parse(&selectors, "transform").unwrap();
+    assert_eq!(selection.schemas, vec!["staging"]);
+    assert_eq!(selection.relations, vec![
+        Relation { schema: "transform".to_string(), name: "orders".to_string() },
+        Relation { schema: "marts".to_string(), name: "revenue".to_string() },
+    ]);
+    assert!(!selection.is_empty());
+    assert!(BackupSelection::parse(&[], "transform").unwrap().is_empty());
+
+    let error = BackupSelection::parse(&["tag:daily".to_string()], "transform").unwrap_err();
+    assert!(error.to_string().contains("expected `schema:<name>`"), "{}", error);
+}
+
+/// Whether DuckDB can list tables and views, which needs its core functions extension
+fn catalog_available() -> bool {
+    Connection::open_in_memory().unwrap().execute_batch("SELECT * FROM duckdb_tables()").is_ok()
+}
+
+#[test]
+fn test_restore_selected_tables() {
+    if !catalog_available() {
+        eprintln!("The DuckDB core_functions extension is not available; skipping");
+        return;
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+    upload(&temp_dir.path().join("export"), &["shipped", "returned"], &backend);
+
+    // Today's database has moved on since the backup
+    let database = temp_dir.path().join("crabwalk.duckdb");
+    Connection::open(&database).unwrap().execute_batch(
+        "CREATE SCHEMA transform;
+         CREATE TABLE transform.orders AS SELECT range::INTEGER AS id FROM range(1, 4);
+         CREATE VIEW transform.customers AS SELECT 7 AS id;",
+    ).unwrap();
+    let database_path = database.to_str().unwrap();
+    let orders = BackupSelection::parse(&["orders".to_string()], "transform").unwrap();
+
+    // Restoring into another schema keeps the current tables for comparison
+    let restored = restore_selected(database_path, &backend, None, &orders, Some("yesterday"), false).unwrap();
+    assert_eq!(restored, vec![Relation { schema: "yesterday".to_string(), name: "orders".to_string() }]);
+    assert_eq!(ids(&database, "yesterday.orders"), vec![1, 2]);
+    assert_eq!(order_ids(&database), vec![1, 2, 3]);
+    assert_eq!(ids(&database, "transform.customers"), vec![7]);
+
+    // Existing tables are only replaced with overwrite
+    let error = restore_selected(database_path, &backend, None, &orders, None, false).unwrap_err();
+    assert!(error.to_string().contains("transform.orders already exists"), "{}", error);
+    assert_eq!(order_ids(&database), vec![1, 2, 3]);
+    restore_selected(database_path, &backend, None, &orders, None, true).unwrap();
+    assert_eq!(order_ids(&database), vec![1, 2]);
+    assert_eq!(ids(&database, "transform.customers"), vec![7]);
+
+    let missing = BackupSelection::parse(&["schema:marts".to_string()], "transform").unwrap();
+    let error = restore_selected(database_path, &backend, None, &missing, None, false).unwrap_err();
+    assert!(error.to_string().contains("No tables or views in schema marts of the backup"), "{}", error);
+}
+
+#[test]
+fn test_backup_selected_tables() {
+    // Listing tables needs the core functions and backups are exported as Parquet, both extensions DuckDB installs on first use
+    if !catalog_available() || Connection::open_in_memory().unwrap().execute_batch("INSTALL parquet; LOAD parquet").is_err() {
+        eprintln!("The DuckDB core_functions or parquet extension is not available; skipping");
+        return;
+    }
+
+    let temp_dir = tempdir().unwrap();
+    let database = temp_dir.path().join("crabwalk.duckdb");
+    Connection::open(&database).unwrap().execute_batch(
+        "CREATE SCHEMA transform;
+         CREATE SCHEMA marts;
+         CREATE TABLE transform.orders AS SELECT range::INTEGER AS id FROM range(1, 4);
+         CREATE TABLE transform.customers AS SELECT 7 AS id;
+         CREATE VIEW marts.revenue AS SELECT id FROM transform.orders WHERE id > 1;",
+    ).unwrap();
+    let backend = LocalBackend::new(temp_dir.path().join("backups"));
+
+    let selection = BackupSelection::parse(&["orders".to_string(), "schema:marts".to_string()], "transform").unwrap();
+    let manifest = backup(database.to_str().unwrap(), &backend, &selection).unwrap();
+    assert_eq!(manifest.selected, vec!["marts.revenue", "transform.orders"]);
+    assert_eq!(manifest.schemas, vec!["marts", "transform"]);
+
+    // The view is backed up as a table holding its rows
+    let restored = temp_dir.path().join("restored.duckdb");
+    restore(restored.to_str().unwrap(), &backend, None, false).unwrap();
+    assert_eq!(ids(&restored, "marts.revenue"), vec![2, 3]);
+    assert_eq!(order_ids(&restored), vec![1, 2, 3]);
+    let conn = Connection::open(&restored).unwrap();
+    assert!(conn.execute_batch("SELECT * FROM transform.customers").is_err());
+
+    let missing = BackupSelection::parse(&["payments".to_string()], "transform").unwrap();
+    let error = backup(database.to_str().unwrap(), &backend, &missing).unwrap_err();
+    assert!(error.to_string().contains("No table or view transform.payments in the database"), "{}", error);
+}
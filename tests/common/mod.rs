@@ -0,0 +1,9 @@
+use std::fs;
+use std::path::Path;
+
+/// Write a model file below a project root, creating its directories
+pub fn write_model(root: &Path, relative: &str, sql: &str) {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, sql).unwrap();
+}
@@ -1,5 +1,5 @@
-use crabwalk::config::{OutputType, OutputConfig, ModelConfig};
-use crabwalk::parser::config::extract_config_from_sql;
+use crabwalk::config::{model_config_schema, OutputType, OutputConfig, ModelConfig};
+use crabwalk::parser::config::{extract_config_from_sql, extract_model_config, ConfigError};
 
 #[test]
 fn test_output_type_default() {
@@ -71,22 +71,213 @@ fn test_extract_config_with_multiple_comments() {
 
 #[test]
 fn test_extract_config_invalid_json() {
-    // SQL with invalid JSON in config comment
+    // SQL with invalid YAML in config comment
     let sql = "-- @config: {output: {type: \"view\", invalid_json}\nSELECT * FROM test";
-    let config = extract_config_from_sql(sql).unwrap();
+    let error = extract_config_from_sql(sql).unwrap_err();
     
-    // Should return None for invalid JSON
-    assert!(config.is_none(), "Invalid JSON should return None");
+    let config_error = error.downcast_ref::<ConfigError>().expect("Should be a config error");
+    assert_eq!(config_error.line, 1, "Error should point at the @config line");
 }
 
 #[test]
 fn test_extract_config_invalid_structure() {
-    // SQL with valid JSON but invalid structure (missing output.type)
+    // SQL with valid YAML but a key ModelConfig does not have
     let sql = "-- @config: {other_field: \"value\"}\nSELECT * FROM test";
-    let config = extract_config_from_sql(sql).unwrap();
+    let error = extract_config_from_sql(sql).unwrap_err();
     
-    // This should parse but the output field would be None
-    assert!(config.is_some(), "Valid JSON with invalid structure should parse");
-    let model_config = config.unwrap();
-    assert!(model_config.output.is_none(), "Output field should be None for invalid structure");
-}
\ No newline at end of file
+    assert!(error.to_string().contains("other_field"), "Error should name the unknown key: {}", error);
+}
+
+#[test]
+fn test_extract_config_rejects_invalid_output_type() {
+    let sql = "SELECT 1\n-- @config: {output: {type: \"veiw\"}}";
+    let error = extract_config_from_sql(sql).unwrap_err();
+    
+    let config_error = error.downcast_ref::<ConfigError>().expect("Should be a config error");
+    assert_eq!(config_error.line, 2);
+    assert!(config_error.message.contains("veiw"), "Error should name the bad value: {}", config_error.message);
+}
+
+#[test]
+fn test_extract_config_rejects_unknown_output_key() {
+    let sql = "-- @config: {output: {type: view, keep_tables: true}}\nSELECT 1";
+    
+    assert!(extract_config_from_sql(sql).is_err(), "Misspelled output keys should be rejected");
+}
+
+#[test]
+fn test_extract_config_rejects_unqualified_foreign_key() {
+    let sql = "-- @config: {foreign_keys: [{column: customer_id, references: customers}]}\nSELECT 1";
+    let error = extract_config_from_sql(sql).unwrap_err();
+    
+    assert!(error.to_string().contains("table.column"), "Unexpected error: {}", error);
+}
+
+#[test]
+fn test_model_config_schema_matches_types() {
+    let schema = model_config_schema();
+    
+    assert_eq!(schema["additionalProperties"], serde_json::json!(false), "Unknown keys should be rejected");
+    let output = &schema["definitions"]["OutputConfig"];
+    assert!(output["properties"]["type"].is_object(), "Output type should be spelled `type`");
+    assert!(output.get("required").is_none(), "Output type has a default so it is not required");
+    let output_types: Vec<&str> = schema["definitions"]["OutputType"]["oneOf"].as_array().unwrap().iter()
+        .map(|variant| variant["enum"][0].as_str().unwrap())
+        .collect();
+    assert_eq!(output_types, vec!["table", "view", "parquet", "csv", "json", "ndjson", "arrow", "xlsx", "delta", "iceberg"]);
+}
+
+#[test]
+fn test_published_config_schema_is_current() {
+    let published = std::fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/schema/model_config.schema.json"))
+        .expect("Published schema should exist");
+    let published: serde_json::Value = serde_json::from_str(&published).unwrap();
+    
+    assert_eq!(
+        published,
+        model_config_schema(),
+        "schema/model_config.schema.json is out of date; regenerate it with `crabwalk config-schema`"
+    );
+}
+
+#[test]
+fn test_extract_config_with_keys() {
+    let sql = "-- @config: {primary_key: order_id, foreign_keys: [{column: customer_id, references: customers.customer_id}]}\nSELECT * FROM test";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+
+    assert_eq!(model_config.primary_key, vec!["order_id".to_string()]);
+    assert_eq!(model_config.foreign_keys.len(), 1);
+    assert_eq!(model_config.foreign_keys[0].column, "customer_id");
+    assert_eq!(model_config.foreign_keys[0].referenced(), Some(("customers", "customer_id")));
+}
+
+#[test]
+fn test_extract_config_with_composite_primary_key() {
+    let sql = "-- @config: {primary_key: [order_id, line_number]}\nSELECT * FROM test";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+
+    assert_eq!(model_config.primary_key, vec!["order_id".to_string(), "line_number".to_string()]);
+    assert!(model_config.foreign_keys.is_empty());
+}
+
+#[test]
+fn test_extract_config_with_line_continuations() {
+    let sql = "-- @config:\n--   output:\n--     type: parquet\n--     location: ./output/orders.parquet\n--   primary_key: order_id\n-- plain comment\nSELECT * FROM test";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+    let output = model_config.output.unwrap();
+
+    assert_eq!(output.output_type, OutputType::Parquet);
+    assert_eq!(output.location, Some("./output/orders.parquet".to_string()));
+    assert_eq!(model_config.primary_key, vec!["order_id".to_string()]);
+}
+
+#[test]
+fn test_single_line_config_ignores_indented_comments() {
+    let sql = "-- @config: {output: {type: view}}\n--   Orders with their customer, one row per order\n--\tkept for the finance dashboards\nSELECT * FROM test";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+    assert_eq!(model_config.output.unwrap().output_type, OutputType::View);
+
+    // An unclosed flow mapping continues on the indented lines until it is closed
+    let sql = "-- @config: {output: {type: parquet,\n--   location: ./output/orders.parquet}}\n--   plain comment\nSELECT * FROM test";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+    assert_eq!(model_config.output.unwrap().location, Some("./output/orders.parquet".to_string()));
+}
+
+#[test]
+fn test_extract_config_from_block_comment() {
+    let sql = "/* @config\noutput:\n  type: view\nsources: [legacy.customers]\n*/\nSELECT * FROM legacy.customers";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+
+    assert_eq!(model_config.output.unwrap().output_type, OutputType::View);
+    assert_eq!(model_config.sources, vec!["legacy.customers".to_string()]);
+}
+
+#[test]
+fn test_block_config_error_points_at_offending_line() {
+    let sql = "SELECT 1\n/* @config\noutput:\n  type: veiw\n*/";
+    let error = extract_config_from_sql(sql).unwrap_err().downcast::<ConfigError>().unwrap();
+    assert_eq!(error.line, 4);
+
+    let unterminated = "/* @config\noutput:\n  type: view\nSELECT 1";
+    let error = extract_config_from_sql(unterminated).unwrap_err().downcast::<ConfigError>().unwrap();
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn test_extract_model_config_from_sidecar_file() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let sql_path = temp_dir.path().join("orders.sql");
+    std::fs::write(
+        temp_dir.path().join("orders.yml"),
+        "output:\n  type: parquet\n  location: ./output/orders.parquet\nprimary_key: order_id\n",
+    ).unwrap();
+
+    let model_config = extract_model_config(&sql_path, "SELECT 1 AS order_id").unwrap().unwrap();
+    assert_eq!(model_config.output.unwrap().output_type, OutputType::Parquet);
+    assert_eq!(model_config.primary_key, vec!["order_id".to_string()]);
+
+    // Comments in the SQL file override the sidecar file
+    let model_config = extract_model_config(&sql_path, "-- @config: {output: {type: view}}\nSELECT 1 AS order_id")
+        .unwrap()
+        .unwrap();
+    assert_eq!(model_config.output.unwrap().output_type, OutputType::View);
+    assert_eq!(model_config.primary_key, vec!["order_id".to_string()]);
+
+    std::fs::write(temp_dir.path().join("orders.yml"), "outptu:\n  type: view\n").unwrap();
+    let error = extract_model_config(&sql_path, "SELECT 1").unwrap_err().downcast::<ConfigError>().unwrap();
+    assert!(error.file.unwrap().ends_with("orders.yml"));
+    assert_eq!(error.line, 1);
+}
+
+#[test]
+fn test_sidecar_and_sql_config_keys_are_merged_once() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let sql_path = temp_dir.path().join("orders.sql");
+    std::fs::write(
+        temp_dir.path().join("orders.yml"),
+        "foreign_keys:\n  - {column: customer_id, references: customers.id}\nsources: [raw.orders]\n",
+    ).unwrap();
+
+    let sql = "-- @config: {foreign_keys: [{column: Customer_ID, references: customers.id}, {column: store_id, references: stores.id}], sources: [RAW.orders, raw.stores]}\nSELECT 1";
+    let model_config = extract_model_config(&sql_path, sql).unwrap().unwrap();
+
+    let keys: Vec<(&str, &str)> = model_config.foreign_keys.iter()
+        .map(|key| (key.column.as_str(), key.references.as_str()))
+        .collect();
+    assert_eq!(keys, vec![("customer_id", "customers.id"), ("store_id", "stores.id")]);
+    assert_eq!(model_config.sources, vec!["raw.orders".to_string(), "raw.stores".to_string()]);
+}
+
+#[test]
+fn test_extract_config_with_documentation() {
+    let sql = "-- @config:\n--   description: Orders placed online\n--   columns:\n--     Email: {description: Contact address, tags: [pii], meta: {owner: crm, retention_days: 30}}\nSELECT 1";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+
+    assert_eq!(model_config.description.as_deref(), Some("Orders placed online"));
+    let email = model_config.column("email").expect("Column docs should match case-insensitively");
+    assert_eq!(email.description.as_deref(), Some("Contact address"));
+    assert_eq!(email.tags, vec!["pii".to_string()]);
+    assert_eq!(email.meta.get("retention_days"), Some(&serde_json::json!(30)));
+
+    let error = extract_config_from_sql("-- @config: {columns: {email: {descripton: typo}}}").unwrap_err();
+    assert!(error.downcast::<ConfigError>().is_ok());
+}
+
+#[test]
+fn test_extract_config_with_multiple_outputs() {
+    let sql = "/* @config\noutputs:\n  - type: table\n  - {type: parquet, location: ./output/orders.parquet}\n  - {type: csv, location: ./extracts/orders.csv}\n*/\nSELECT 1";
+    let model_config = extract_config_from_sql(sql).unwrap().unwrap();
+
+    let output_types: Vec<&OutputType> = model_config.output_configs().iter().map(|output| &output.output_type).collect();
+    assert_eq!(output_types, vec![&OutputType::Table, &OutputType::Parquet, &OutputType::Csv]);
+
+    let invalid = [
+        "-- @config: {output: {type: table}, outputs: [{type: csv}]}",
+        "-- @config: {outputs: [{type: table}, {type: view}]}",
+        "-- @config: {outputs: [{type: csv, location: out.csv}, {type: csv, location: out.csv}]}",
+        "-- @config: {outputs: [{type: table}, {type: csv, sheet: Orders}]}",
+    ];
+    for sql in invalid {
+        assert!(extract_config_from_sql(sql).is_err(), "Expected an error for {}", sql);
+    }
+}
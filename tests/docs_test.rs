@@ -0,0 +1,43 @@
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::docs::generate_docs;
+use crabwalk::parser::dependencies::get_dependencies;
+
+mod common;
+use common::write_model;
+
+#[test]
+fn test_generate_docs_site() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    let site = temp_dir.path().join("site");
+
+    write_model(&models, "staging/stg_orders.sql", "SELECT 1 AS order_id, 'a@example.com' AS email, 10.5 AS amount");
+    write_model(
+        &models,
+        "marts/orders.sql",
+        "/* @config\ndescription: One row per order.\ncolumns:\n  email:\n    description: Customer contact address\n    tags: [pii]\n    meta: {owner: crm-team}\n  discount:\n    description: Not selected yet\n*/\nSELECT o.order_id, o.email, o.amount * 2 AS doubled FROM stg_orders o WHERE o.amount > 0",
+    );
+
+    let dependencies = get_dependencies(models.to_str().unwrap(), "duckdb").unwrap();
+    let index_path = generate_docs(&dependencies, site.to_str().unwrap()).unwrap();
+
+    let index = fs::read_to_string(&index_path).unwrap();
+    assert!(index.contains("href=\"models/orders.html\""));
+    assert!(index.contains("href=\"models/stg_orders.html\""));
+    assert!(index.contains("One row per order."));
+    assert!(site.join("style.css").exists());
+
+    let page = fs::read_to_string(site.join("models/orders.html")).unwrap();
+    assert!(page.contains("Customer contact address"));
+    assert!(page.contains("<span class=\"tag\">pii</span>"));
+    assert!(page.contains("owner: crm-team"));
+    assert!(page.contains("documented but not found in the SQL"), "Documented columns missing from the SQL should be listed");
+    assert!(page.contains("<a href=\"stg_orders.html\">stg_orders</a>"), "Upstream models should be linked");
+    assert!(page.contains("o.amount &gt; 0"), "SQL should be escaped");
+    assert!(page.contains(">expression</span>"), "Computed columns should show their lineage");
+
+    let upstream = fs::read_to_string(site.join("models/stg_orders.html")).unwrap();
+    assert!(upstream.contains("Used by:</strong> <a href=\"orders.html\">orders</a>"));
+    assert!(upstream.contains("No description"));
+}
@@ -0,0 +1,106 @@
+use tempfile::tempdir;
+use crabwalk::lint::{lint_config_files, lint_models, LintIssue, LintRule, Severity};
+use crabwalk::parser::dependencies::get_dependencies;
+use crabwalk::Crabwalk;
+
+mod common;
+use common::write_model;
+
+fn issues_for(issues: &[LintIssue], rule: LintRule) -> Vec<&LintIssue> {
+    issues.iter().filter(|issue| issue.rule == rule).collect()
+}
+
+#[test]
+fn test_lint_reports_project_problems() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+
+    write_model(root, "sources/raw_orders.sql", "SELECT * FROM read_csv('/home/me/raw_orders.csv')");
+    write_model(
+        root,
+        "staging/stg_orders.sql",
+        "-- @config: {sources: [legacy.customers]}\nSELECT o.id, c.name, amount\nFROM raw_orders o\nJOIN legacy.customers c ON o.customer_id = c.id",
+    );
+    write_model(root, "staging/stg_unused.sql", "-- @config: {output: {type: \"view\"}}\nSELECT id FROM raw_orders");
+    write_model(root, "staging/stg_typo.sql", "SELECT id FROM raw_ordrs");
+    write_model(root, "marts/orders.sql", "WITH o AS (SELECT * FROM stg_orders)\nSELECT *\nFROM o");
+
+    let dependencies = get_dependencies(root.to_str().unwrap(), "duckdb").unwrap();
+    let issues = lint_models(&dependencies).unwrap();
+
+    let star = issues_for(&issues, LintRule::SelectStarInMart);
+    assert_eq!(star.len(), 1, "Only the mart's final SELECT * should be flagged: {:?}", star);
+    assert_eq!(star[0].line, Some(2));
+
+    let unqualified = issues_for(&issues, LintRule::UnqualifiedColumn);
+    assert_eq!(unqualified.len(), 1, "Unexpected unqualified columns: {:?}", unqualified);
+    assert!(unqualified[0].message.contains("amount"));
+
+    let unknown = issues_for(&issues, LintRule::UnknownTable);
+    assert_eq!(unknown.len(), 1, "Declared sources and CTEs are not unknown: {:?}", unknown);
+    assert!(unknown[0].message.contains("raw_ordrs"));
+    assert_eq!(unknown[0].severity, Severity::Error);
+
+    let unused: Vec<&str> = issues_for(&issues, LintRule::UnusedModel).iter().map(|i| i.file.as_str()).collect();
+    assert_eq!(unused.len(), 2, "Marts are not unused: {:?}", unused);
+    assert!(unused.iter().all(|file| file.ends_with("stg_unused.sql") || file.ends_with("stg_typo.sql")));
+
+    let paths = issues_for(&issues, LintRule::AbsolutePath);
+    assert_eq!(paths.len(), 1);
+    assert!(paths[0].message.contains("/home/me/raw_orders.csv"));
+}
+
+#[test]
+fn test_lint_reports_every_invalid_config() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+
+    write_model(root, "staging/stg_a.sql", "-- @config: {output: {type: \"veiw\"}}\nSELECT 1 AS id");
+    write_model(root, "staging/stg_b.sql", "SELECT 1 AS id\n-- @config: {outptu: {type: table}}");
+    write_model(root, "staging/stg_c.sql", "-- @config: {output: {type: view}}\nSELECT 1 AS id");
+
+    let issues = lint_config_files(root.to_str().unwrap()).unwrap();
+
+    assert_eq!(issues.len(), 2, "Both invalid comments should be reported: {:?}", issues);
+    assert!(issues.iter().all(|issue| issue.rule == LintRule::InvalidConfig && issue.severity == Severity::Error));
+    assert!(issues[0].file.ends_with("stg_a.sql") && issues[0].line == Some(1));
+    assert!(issues[1].file.ends_with("stg_b.sql") && issues[1].line == Some(2));
+}
+
+#[test]
+fn test_lint_reports_config_and_model_findings_together() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+
+    write_model(root, "staging/stg_orders.sql", "-- @config: {output: {type: \"veiw\"}}\nSELECT id FROM raw_orders");
+    write_model(root, "marts/orders.sql", "SELECT * FROM stg_orders");
+
+    let crabwalk = Crabwalk::new(
+        "crabwalk.db".to_string(),
+        root.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    );
+    let issues = crabwalk.lint().unwrap();
+
+    let config = issues_for(&issues, LintRule::InvalidConfig);
+    assert_eq!(config.len(), 1, "{:?}", issues);
+    assert!(config[0].file.ends_with("stg_orders.sql"));
+    assert_eq!(issues_for(&issues, LintRule::SelectStarInMart).len(), 1, "{:?}", issues);
+}
+
+#[test]
+fn test_lint_clean_project() {
+    let temp_dir = tempdir().unwrap();
+    let root = temp_dir.path();
+
+    write_model(root, "staging/stg_items.sql", "SELECT 1 AS id, 'a' AS name");
+    write_model(root, "marts/items.sql", "SELECT i.id, i.name FROM stg_items i");
+
+    let dependencies = get_dependencies(root.to_str().unwrap(), "duckdb").unwrap();
+    let issues = lint_models(&dependencies).unwrap();
+
+    assert!(issues.is_empty(), "Expected no findings: {:?}", issues);
+}
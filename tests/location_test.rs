@@ -0,0 +1,129 @@
+use chrono::TimeZone;
+use std::collections::BTreeMap;
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::Crabwalk;
+use crabwalk::config::{check_template, render_template, RunInfo};
+use crabwalk::parser::config::extract_config_from_sql;
+
+fn run_info() -> RunInfo {
+    RunInfo {
+        run_id: "abc123".to_string(),
+        started_at: chrono::Utc.with_ymd_and_hms(2024, 3, 7, 6, 5, 4).unwrap(),
+        vars: BTreeMap::from([("region".to_string(), "eu".to_string())]),
+    }
+}
+
+#[test]
+fn test_render_location_placeholders() {
+    let run = run_info();
+    std::env::set_var("CRABWALK_TEST_BUCKET", "lake");
+
+    assert_eq!(
+        render_template("s3://{env.CRABWALK_TEST_BUCKET}/{var.region}/{schema}/{table_name}/{run_started_at:%Y/%m/%d}/{run_id}.parquet", "orders", "transform", &run).unwrap(),
+        "s3://lake/eu/transform/orders/2024/03/07/abc123.parquet"
+    );
+    assert_eq!(render_template("./out/{table_name}-{run_started_at}.csv", "orders", "main", &run).unwrap(), "./out/orders-20240307T060504Z.csv");
+    assert_eq!(render_template("./out/plain.csv", "orders", "main", &run).unwrap(), "./out/plain.csv");
+}
+
+#[test]
+fn test_unresolved_placeholders_are_errors() {
+    let run = run_info();
+
+    for template in ["{var.missing}", "{env.CRABWALK_TEST_UNSET_VARIABLE}"] {
+        assert!(render_template(template, "orders", "main", &run).is_err(), "Expected an error for {}", template);
+    }
+
+    for template in ["{tablename}", "./out/{table_name", "./out/table_name}", "{run_started_at:%Q}", "{run_started_at:}", "{var.}", "{env.A-B}"] {
+        assert!(check_template(template).is_err(), "Expected an error for {}", template);
+    }
+    assert!(check_template("{var.anything}/{env.ANYTHING}").is_ok(), "Variables are only resolved at run time");
+
+    assert!(extract_config_from_sql("-- @config: {output: {type: csv, location: './out/{model}.csv'}}").is_err());
+}
+
+#[test]
+fn test_run_writes_to_templated_location() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    fs::create_dir_all(&models).unwrap();
+    let database = temp_dir.path().join("test.db");
+    let snapshots = temp_dir.path().join("snapshots");
+
+    fs::write(models.join("crabwalk.yml"), "vars:\n  region: us\n  version: 2\n").unwrap();
+    fs::write(
+        models.join("orders.sql"),
+        format!(
+            "-- @config: {{output: {{type: csv, location: '{}/{{var.region}}/v{{var.version}}/{{schema}}.{{table_name}}.csv'}}}}\nSELECT 1 AS id",
+            snapshots.display()
+        ),
+    ).unwrap();
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    ).with_vars(BTreeMap::from([("region".to_string(), "eu".to_string())]));
+    crabwalk.run().unwrap();
+
+    assert!(snapshots.join("eu").join("v2").join("transform.orders.csv").exists(), "--var should override crabwalk.yml");
+
+    let results: serde_json::Value = serde_json::from_str(&fs::read_to_string(models.join("run_results.json")).unwrap()).unwrap();
+    assert!(results["run_id"].as_str().is_some_and(|id| !id.is_empty()));
+}
+
+#[test]
+fn test_run_stops_before_models_when_a_variable_is_missing() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    fs::create_dir_all(&models).unwrap();
+    let database = temp_dir.path().join("test.db");
+
+    fs::write(models.join("stg_orders.sql"), "SELECT 1 AS id").unwrap();
+    fs::write(models.join("orders.sql"), "-- @config: {output: {type: csv, location: './{var.day}/orders.csv'}}\nSELECT * FROM stg_orders").unwrap();
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    );
+    let error = crabwalk.run().unwrap_err();
+    assert!(error.to_string().contains("day"), "Unexpected error: {}", error);
+
+    drop(crabwalk);
+    let conn = duckdb::Connection::open(&database).unwrap();
+    assert!(conn.prepare("SELECT * FROM transform.stg_orders").is_err(), "No model should run");
+}
+
+#[test]
+fn test_force_run_stops_before_models_when_a_variable_is_missing() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    fs::create_dir_all(&models).unwrap();
+    let database = temp_dir.path().join("test.db");
+
+    fs::write(models.join("stg_orders.sql"), "SELECT 1 AS id").unwrap();
+    fs::write(models.join("orders.sql"), "-- @config: {output: {type: csv, location: './{var.day}/orders.csv'}}\nSELECT * FROM stg_orders").unwrap();
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    );
+    let error = crabwalk.run_force().unwrap_err();
+    assert!(error.to_string().contains("day"), "Unexpected error: {}", error);
+
+    drop(crabwalk);
+    let conn = duckdb::Connection::open(&database).unwrap();
+    assert!(conn.prepare("SELECT * FROM transform.stg_orders").is_err(), "No model should run");
+}
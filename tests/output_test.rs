@@ -0,0 +1,553 @@
+use arrow_ipc::reader::FileReader;
+use duckdb::Connection;
+use std::fs::{self, File};
+use tempfile::tempdir;
+use crabwalk::config::{OutputConfig, OutputType, WriteMode};
+use crabwalk::executor::output::{copy_options, handle_output, handle_outputs};
+use crabwalk::executor::RunContext;
+use crabwalk::parser::config::extract_config_from_sql;
+
+#[test]
+fn test_copy_options_default_formats() {
+    let parquet = OutputConfig::new(OutputType::Parquet, None, false);
+    assert_eq!(copy_options(&parquet, "parquet"), "(FORMAT PARQUET)");
+
+    let csv = OutputConfig::new(OutputType::Csv, None, false);
+    assert_eq!(copy_options(&csv, "csv"), "(FORMAT CSV, HEADER)");
+}
+
+#[test]
+fn test_copy_options_from_config() {
+    let sql = "-- @config: {output: {type: parquet, partition_by: [year, month], compression: zstd, row_group_size: 100000, overwrite_or_ignore: true}}\nSELECT 1";
+    let output = extract_config_from_sql(sql).unwrap().unwrap().output.unwrap();
+
+    assert_eq!(
+        copy_options(&output, "parquet"),
+        "(FORMAT PARQUET, PARTITION_BY (\"year\", \"month\"), COMPRESSION 'zstd', ROW_GROUP_SIZE 100000, OVERWRITE_OR_IGNORE)"
+    );
+    assert_eq!(output.default_location("events"), "./output/events");
+}
+
+#[test]
+fn test_invalid_file_output_options_are_rejected() {
+    let invalid = [
+        "-- @config: {output: {type: table, partition_by: year}}",
+        "-- @config: {output: {type: csv, row_group_size: 1000}}",
+        "-- @config: {output: {type: parquet, compression: rar}}",
+        "-- @config: {output: {type: parquet, partition_by: year, per_thread_output: true}}",
+    ];
+
+    for sql in invalid {
+        assert!(extract_config_from_sql(sql).is_err(), "Expected an error for {}", sql);
+    }
+}
+
+#[test]
+fn test_partitioned_file_output() {
+    let temp_dir = tempdir().unwrap();
+    let location = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let mut output = OutputConfig::new(OutputType::Csv, Some(location.to_str().unwrap().to_string()), false);
+    output.partition_by = vec!["year".to_string(), "month".to_string()];
+
+    let sql = "SELECT * FROM (VALUES (2024, 1, 'a'), (2024, 2, 'b'), (2025, 1, 'c')) AS t(year, month, name)";
+    handle_output("events", sql, &output, "main", &context).unwrap();
+
+    assert!(location.join("year=2024").join("month=1").is_dir());
+    assert!(location.join("year=2024").join("month=2").is_dir());
+    assert!(location.join("year=2025").join("month=1").is_dir());
+
+    // Writing again into the populated directory needs overwrite_or_ignore
+    assert!(handle_output("events", sql, &output, "main", &context).is_err());
+    output.overwrite_or_ignore = true;
+    handle_output("events", sql, &output, "main", &context).unwrap();
+}
+
+fn table_exists(context: &RunContext, relation: &str) -> bool {
+    context.get_connection().prepare(&format!("SELECT * FROM {}", relation)).is_ok()
+}
+
+#[test]
+fn test_file_output_copies_query_directly() {
+    let temp_dir = tempdir().unwrap();
+    let location = temp_dir.path().join("orders.csv");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("CREATE SCHEMA transform").unwrap();
+
+    let output = OutputConfig::new(OutputType::Csv, Some(location.to_str().unwrap().to_string()), false);
+    handle_output("orders", "-- @config: {output: {type: csv}}\nSELECT 1 AS id, 'a' AS name -- last column\n;", &output, "transform", &context)
+        .unwrap();
+
+    assert_eq!(std::fs::read_to_string(&location).unwrap().lines().collect::<Vec<_>>(), vec!["id,name", "1,a"]);
+    assert!(!table_exists(&context, "temp_orders"), "No temporary table should be created");
+    assert!(!table_exists(&context, "transform.orders"));
+}
+
+#[test]
+fn test_file_output_keeps_named_table_in_schema() {
+    let temp_dir = tempdir().unwrap();
+    let location = temp_dir.path().join("orders.csv");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("CREATE SCHEMA transform").unwrap();
+
+    let mut output = OutputConfig::new(OutputType::Csv, Some(location.to_str().unwrap().to_string()), true);
+    handle_output("orders", "SELECT 1 AS id", &output, "transform", &context).unwrap();
+    assert!(table_exists(&context, "transform.orders"), "The kept table defaults to the model name");
+
+    output.kept_table = Some("{table_name}_export".to_string());
+    handle_output("orders", "SELECT 1 AS id", &output, "transform", &context).unwrap();
+    assert!(table_exists(&context, "transform.orders_export"));
+    assert!(location.exists());
+
+    let sql = "-- @config: {output: {type: csv, kept_table: orders_export}}\nSELECT 1";
+    assert!(extract_config_from_sql(sql).is_err(), "kept_table without keep_table should be rejected");
+}
+
+#[test]
+fn test_copy_options_for_json_and_spreadsheet_formats() {
+    let json = OutputConfig::new(OutputType::Json, None, false);
+    assert_eq!(copy_options(&json, "json"), "(FORMAT JSON, ARRAY true)");
+
+    let ndjson = OutputConfig::new(OutputType::Ndjson, None, false);
+    assert_eq!(copy_options(&ndjson, "ndjson"), "(FORMAT JSON)");
+    assert_eq!(ndjson.default_location("events"), "./output/events.ndjson");
+
+    let sql = "-- @config: {output: {type: xlsx, sheet: Events, header: false}}\nSELECT 1";
+    let xlsx = extract_config_from_sql(sql).unwrap().unwrap().output.unwrap();
+    assert_eq!(copy_options(&xlsx, "xlsx"), "(FORMAT XLSX, HEADER false, SHEET 'Events')");
+
+    let mut csv = OutputConfig::new(OutputType::Csv, None, false);
+    csv.header = Some(false);
+    assert_eq!(copy_options(&csv, "csv"), "(FORMAT CSV, HEADER false)");
+}
+
+#[test]
+fn test_format_specific_options_are_checked() {
+    let invalid = [
+        "-- @config: {output: {type: csv, sheet: Events}}",
+        "-- @config: {output: {type: parquet, mode: append}}",
+        "-- @config: {output: {type: arrow, compression: zstd}}",
+        "-- @config: {output: {type: xlsx, partition_by: year}}",
+        "-- @config: {output: {type: delta, per_thread_output: true}}",
+        "-- @config: {output: {type: ndjson, compression: snappy}}",
+        "-- @config: {output: {type: iceberg, location: 's3://lake/events'}}",
+        "-- @config: {output: {type: iceberg, per_thread_output: true}}",
+    ];
+    for sql in invalid {
+        assert!(extract_config_from_sql(sql).is_err(), "Expected an error for {}", sql);
+    }
+
+    let valid = [
+        "-- @config: {output: {type: delta, partition_by: year, compression: zstd, mode: append}}",
+        "-- @config: {output: {type: ndjson, compression: gzip}}",
+        "-- @config: {output: {type: iceberg, partition_by: region, compression: zstd, mode: append}}",
+    ];
+    for sql in valid {
+        assert!(extract_config_from_sql(sql).is_ok(), "Expected {} to be valid", sql);
+    }
+}
+
+#[test]
+fn test_arrow_file_output() {
+    let temp_dir = tempdir().unwrap();
+    let location = temp_dir.path().join("events.arrow");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let output = OutputConfig::new(OutputType::Arrow, Some(location.to_str().unwrap().to_string()), false);
+    let sql = "SELECT * FROM (VALUES (1, 'a'), (2, 'b'), (3, 'c')) AS t(id, name);";
+    handle_output("events", sql, &output, "main", &context).unwrap();
+
+    let reader = FileReader::try_new(File::open(&location).unwrap(), None).unwrap();
+    let field_names: Vec<String> = reader.schema().fields().iter().map(|f| f.name().clone()).collect();
+    assert_eq!(field_names, vec!["id", "name"]);
+
+    let rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+    assert_eq!(rows, 3);
+}
+
+/// Open a Delta table with the `deltalake` reader, which replays the log like any other engine
+fn open_delta_table(table_dir: &std::path::Path) -> deltalake::DeltaTable {
+    tokio::runtime::Runtime::new().unwrap()
+        .block_on(deltalake::open_table(table_dir.to_str().unwrap()))
+        .unwrap()
+}
+
+/// Rows of a Delta table read from its active files: ids and the value of one partition column
+fn delta_rows(context: &RunContext, table_dir: &std::path::Path, partition: &str) -> Vec<(i32, Option<String>)> {
+    use deltalake::kernel::scalars::ScalarExt;
+
+    let table = open_delta_table(table_dir);
+    let mut rows = Vec::new();
+    for file in table.snapshot().unwrap().log_data() {
+        // Paths come back decoded from the log, as the file names on disk
+        let path = table_dir.join(file.path().as_ref());
+        let value = file.partition_values().and_then(|values| {
+            values.fields().iter().zip(values.values())
+                .find(|(field, value)| field.name() == partition && !value.is_null())
+                .map(|(_, value)| value.serialize())
+        });
+        let mut stmt = context.get_connection()
+            .prepare(&format!("SELECT id FROM read_parquet('{}')", path.display()))
+            .unwrap();
+        let ids: Vec<i32> = stmt.query_map([], |row| row.get(0)).unwrap().map(|id| id.unwrap()).collect();
+        rows.extend(ids.into_iter().map(|id| (id, value.clone())));
+    }
+    rows.sort();
+    rows
+}
+
+#[test]
+fn test_delta_overwrite_and_append_versions() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let mut output = OutputConfig::new(OutputType::Delta, Some(table_dir.to_str().unwrap().to_string()), false);
+    output.partition_by = vec!["year".to_string()];
+    let sql = |ids: &str| format!("SELECT id, 2024 AS year FROM (VALUES {}) AS t(id)", ids);
+    handle_output("events", &sql("(1), (2)"), &output, "main", &context).unwrap();
+    assert_eq!(open_delta_table(&table_dir).version(), Some(0));
+
+    output.mode = Some(WriteMode::Append);
+    handle_output("events", &sql("(3)"), &output, "main", &context).unwrap();
+    let table = open_delta_table(&table_dir);
+    assert_eq!(table.version(), Some(1));
+    assert_eq!(table.snapshot().unwrap().metadata().partition_columns(), &vec!["year".to_string()]);
+    assert_eq!(delta_rows(&context, &table_dir, "year"), vec![
+        (1, Some("2024".to_string())),
+        (2, Some("2024".to_string())),
+        (3, Some("2024".to_string())),
+    ]);
+
+    // Appending different columns, or changing the partitioning, is refused without a new version
+    let error = handle_output("events", "SELECT 'x' AS id, 2024 AS year", &output, "main", &context).unwrap_err();
+    assert!(error.to_string().contains("Failed to write Delta table"), "Unexpected error: {}", error);
+    output.mode = Some(WriteMode::Overwrite);
+    output.partition_by.clear();
+    let error = handle_output("events", &sql("(4)"), &output, "main", &context).unwrap_err();
+    assert!(format!("{:#}", error).contains("partitioning"), "Unexpected error: {:#}", error);
+    assert_eq!(open_delta_table(&table_dir).version(), Some(1));
+
+    // Overwriting replaces the columns and removes every earlier file from the table
+    output.partition_by = vec!["year".to_string()];
+    handle_output("events", "SELECT 5 AS id, 2025 AS year, 'new' AS note", &output, "main", &context).unwrap();
+    let table = open_delta_table(&table_dir);
+    assert_eq!(table.version(), Some(2));
+    assert_eq!(table.snapshot().unwrap().schema().fields().count(), 3);
+    assert_eq!(delta_rows(&context, &table_dir, "year"), vec![(5, Some("2025".to_string()))]);
+}
+
+#[test]
+fn test_delta_overwrite_keeps_protocol() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    let output = OutputConfig::new(OutputType::Delta, Some(table_dir.to_str().unwrap().to_string()), false);
+
+    handle_output("events", "SELECT 1 AS id, TIMESTAMP '2024-01-01 00:00:00' AS at", &output, "main", &context).unwrap();
+    let protocol = open_delta_table(&table_dir).snapshot().unwrap().protocol().clone();
+    assert_eq!((protocol.min_reader_version(), protocol.min_writer_version()), (3, 7));
+
+    // A schema without timestamps needs a lower protocol, but Delta never downgrades one
+    handle_output("events", "SELECT 1 AS id", &output, "main", &context).unwrap();
+    let table = open_delta_table(&table_dir);
+    assert_eq!(table.snapshot().unwrap().protocol(), &protocol);
+    assert_eq!(table.snapshot().unwrap().schema().fields().count(), 1);
+}
+
+#[test]
+fn test_delta_output_reads_back() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let mut output = OutputConfig::new(OutputType::Delta, Some(table_dir.to_str().unwrap().to_string()), false);
+    output.partition_by = vec!["region".to_string()];
+    let sql = "SELECT * FROM (VALUES (1, 'eu/west'), (2, 'eu/west'), (3, 'us east'), (4, NULL)) AS t(id, region)";
+    handle_outputs("events", sql, &[output], "main", &context).unwrap();
+
+    let rows = delta_rows(&context, &table_dir, "region");
+    assert_eq!(rows, vec![
+        (1, Some("eu/west".to_string())),
+        (2, Some("eu/west".to_string())),
+        (3, Some("us east".to_string())),
+        (4, None),
+    ]);
+
+    // DuckDB's own Delta reader, when its extension can be loaded
+    if context.ensure_extension("delta").is_err() {
+        eprintln!("The delta extension is not available; skipping delta_scan");
+        return;
+    }
+    let mut stmt = context.get_connection()
+        .prepare(&format!("SELECT id, region FROM delta_scan('{}') ORDER BY id", table_dir.display()))
+        .unwrap();
+    let scanned: Vec<(i32, Option<String>)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(scanned, rows);
+}
+
+#[test]
+fn test_delta_rejects_unsupported_column_types() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    let output = OutputConfig::new(OutputType::Delta, Some(table_dir.to_str().unwrap().to_string()), false);
+
+    let error = handle_output("events", "SELECT INTERVAL 1 DAY AS wait", &output, "main", &context).unwrap_err();
+    assert!(error.to_string().contains("wait"), "Unexpected error: {}", error);
+    assert!(!table_dir.exists(), "Nothing should be written");
+}
+
+/// Load the current metadata of an Iceberg table, found through its version hint like any Hadoop catalog reader
+fn open_iceberg_table(table_dir: &std::path::Path) -> (i32, iceberg::table::Table) {
+    let version: i32 = fs::read_to_string(table_dir.join("metadata/version-hint.text")).unwrap().trim().parse().unwrap();
+    let metadata_file = table_dir.join(format!("metadata/v{}.metadata.json", version));
+    let file_io = iceberg::io::FileIOBuilder::new_fs_io().build().unwrap();
+    let table = tokio::runtime::Runtime::new().unwrap()
+        .block_on(iceberg::table::StaticTable::from_metadata_file(
+            metadata_file.to_str().unwrap(),
+            iceberg::TableIdent::from_strs(["default", "events"]).unwrap(),
+            file_io,
+        ))
+        .unwrap();
+    (version, table.into_table())
+}
+
+/// Rows of an Iceberg table read from the data files of its current snapshot: ids and the value of one partition column
+fn iceberg_rows(context: &RunContext, table_dir: &std::path::Path, partition: &str) -> Vec<(i32, Option<String>)> {
+    use iceberg::spec::{Literal, PrimitiveLiteral};
+
+    let (_, table) = open_iceberg_table(table_dir);
+    let metadata = table.metadata();
+    let file_io = table.file_io();
+    let Some(snapshot) = metadata.current_snapshot() else {
+        return Vec::new();
+    };
+    let position = metadata.default_partition_spec().fields().iter().position(|field| field.name == partition);
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let manifest_list = runtime.block_on(snapshot.load_manifest_list(file_io, metadata)).unwrap();
+    let mut rows = Vec::new();
+    for manifest_file in manifest_list.entries() {
+        let manifest = runtime.block_on(manifest_file.load_manifest(file_io)).unwrap();
+        for entry in manifest.entries().iter().filter(|entry| entry.is_alive()) {
+            let data_file = entry.data_file();
+            let value = position.and_then(|position| match &data_file.partition()[position] {
+                Some(Literal::Primitive(PrimitiveLiteral::String(value))) => Some(value.clone()),
+                Some(Literal::Primitive(PrimitiveLiteral::Int(value))) => Some(value.to_string()),
+                Some(other) => panic!("Unexpected partition value {:?}", other),
+                None => None,
+            });
+            let mut stmt = context.get_connection()
+                .prepare(&format!("SELECT id FROM read_parquet('{}')", data_file.file_path()))
+                .unwrap();
+            let ids: Vec<i32> = stmt.query_map([], |row| row.get(0)).unwrap().map(|id| id.unwrap()).collect();
+            rows.extend(ids.into_iter().map(|id| (id, value.clone())));
+        }
+    }
+    rows.sort();
+    rows
+}
+
+#[test]
+fn test_iceberg_overwrite_and_append_versions() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let mut output = OutputConfig::new(OutputType::Iceberg, Some(table_dir.to_str().unwrap().to_string()), false);
+    output.partition_by = vec!["year".to_string()];
+    let sql = |ids: &str| format!("SELECT id, 2024 AS year FROM (VALUES {}) AS t(id)", ids);
+    handle_output("events", &sql("(1), (2)"), &output, "main", &context).unwrap();
+    assert_eq!(open_iceberg_table(&table_dir).0, 1);
+
+    output.mode = Some(WriteMode::Append);
+    handle_output("events", &sql("(3)"), &output, "main", &context).unwrap();
+    let (version, table) = open_iceberg_table(&table_dir);
+    assert_eq!(version, 2);
+    assert_eq!(table.metadata().snapshots().count(), 2);
+    assert_eq!(iceberg_rows(&context, &table_dir, "year"), vec![
+        (1, Some("2024".to_string())),
+        (2, Some("2024".to_string())),
+        (3, Some("2024".to_string())),
+    ]);
+
+    // Appending different columns, or changing the partitioning, is refused without a new version
+    let error = handle_output("events", "SELECT 'x' AS id, 2024 AS year", &output, "main", &context).unwrap_err();
+    assert!(format!("{:#}", error).contains("columns differ"), "Unexpected error: {:#}", error);
+    output.partition_by.clear();
+    let error = handle_output("events", &sql("(4)"), &output, "main", &context).unwrap_err();
+    assert!(format!("{:#}", error).contains("partitioned by"), "Unexpected error: {:#}", error);
+    assert_eq!(open_iceberg_table(&table_dir).0, 2);
+
+    // Overwriting replaces the columns and partitioning, and leaves no earlier file in the table
+    output.mode = Some(WriteMode::Overwrite);
+    handle_output("events", "SELECT 5 AS id, 2025 AS year, 'new' AS note", &output, "main", &context).unwrap();
+    let (version, table) = open_iceberg_table(&table_dir);
+    assert_eq!(version, 3);
+    assert_eq!(table.metadata().current_schema().as_struct().fields().len(), 3);
+    assert!(table.metadata().default_partition_spec().fields().is_empty());
+    assert_eq!(iceberg_rows(&context, &table_dir, "year"), vec![(5, None)]);
+}
+
+#[test]
+fn test_iceberg_output_reads_back() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let mut output = OutputConfig::new(OutputType::Iceberg, Some(table_dir.to_str().unwrap().to_string()), false);
+    output.partition_by = vec!["region".to_string()];
+    let sql = "SELECT * FROM (VALUES (1, 'eu/west'), (2, 'eu/west'), (3, 'us east'), (4, NULL)) AS t(id, region)";
+    handle_outputs("events", sql, &[output], "main", &context).unwrap();
+
+    let rows = iceberg_rows(&context, &table_dir, "region");
+    assert_eq!(rows, vec![
+        (1, Some("eu/west".to_string())),
+        (2, Some("eu/west".to_string())),
+        (3, Some("us east".to_string())),
+        (4, None),
+    ]);
+
+    // The iceberg crate's scan, which matches the Parquet columns to the table by field id
+    let (_, table) = open_iceberg_table(&table_dir);
+    let batches: Vec<arrow::record_batch::RecordBatch> = tokio::runtime::Runtime::new().unwrap().block_on(async {
+        use futures::TryStreamExt;
+        let scan = table.scan().select(["id", "region"]).build().unwrap();
+        scan.to_arrow().await.unwrap().try_collect().await.unwrap()
+    });
+    let mut scanned = Vec::new();
+    for batch in &batches {
+        use arrow::array::{Array, AsArray};
+        let ids = batch.column(0).as_primitive::<arrow::datatypes::Int32Type>();
+        let regions = batch.column(1).as_string::<i32>();
+        scanned.extend((0..batch.num_rows()).map(|i| (ids.value(i), regions.is_valid(i).then(|| regions.value(i).to_string()))));
+    }
+    scanned.sort();
+    assert_eq!(scanned, rows);
+
+    // DuckDB's own Iceberg reader, when its extension can be loaded
+    if context.ensure_extension("iceberg").is_err() {
+        eprintln!("The iceberg extension is not available; skipping iceberg_scan");
+        return;
+    }
+    let mut stmt = context.get_connection()
+        .prepare(&format!("SELECT id, region FROM iceberg_scan('{}') ORDER BY id", table_dir.display()))
+        .unwrap();
+    let scanned: Vec<(i32, Option<String>)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(scanned, rows);
+}
+
+#[test]
+fn test_iceberg_rejects_unsupported_column_types() {
+    let temp_dir = tempdir().unwrap();
+    let table_dir = temp_dir.path().join("events");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    let output = OutputConfig::new(OutputType::Iceberg, Some(table_dir.to_str().unwrap().to_string()), false);
+
+    let error = handle_output("events", "SELECT INTERVAL 1 DAY AS wait", &output, "main", &context).unwrap_err();
+    assert!(format!("{:#}", error).contains("wait"), "Unexpected error: {:#}", error);
+    assert!(!table_dir.exists(), "Nothing should be written");
+}
+
+#[test]
+fn test_multiple_outputs_evaluate_query_once() {
+    let temp_dir = tempdir().unwrap();
+    let first = temp_dir.path().join("first.csv");
+    let second = temp_dir.path().join("second.csv");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let outputs = vec![
+        OutputConfig::new(OutputType::Csv, Some(first.to_str().unwrap().to_string()), false),
+        OutputConfig::new(OutputType::Table, None, false),
+        OutputConfig::new(OutputType::Csv, Some(second.to_str().unwrap().to_string()), false),
+    ];
+    // Each evaluation takes new sequence values, so equal files mean one evaluation
+    context.execute("CREATE SEQUENCE event_ids").unwrap();
+    let sql = "SELECT nextval('event_ids') AS id, name FROM (VALUES ('a'), ('b')) AS t(name)";
+    handle_outputs("events", sql, &outputs, "main", &context).unwrap();
+
+    assert!(table_exists(&context, "main.events"));
+    let first_rows = fs::read_to_string(&first).unwrap();
+    assert_eq!(first_rows.lines().count(), 3);
+    assert_eq!(first_rows, fs::read_to_string(&second).unwrap());
+}
+
+#[test]
+fn test_multiple_file_outputs_drop_temporary_table() {
+    let temp_dir = tempdir().unwrap();
+    let csv = temp_dir.path().join("events.csv");
+    let extract = temp_dir.path().join("extract.csv");
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    let outputs = vec![
+        OutputConfig::new(OutputType::Csv, Some(csv.to_str().unwrap().to_string()), false),
+        OutputConfig::new(OutputType::Csv, Some(extract.to_str().unwrap().to_string()), false),
+        OutputConfig::new(OutputType::View, None, false),
+    ];
+    handle_outputs("events", "SELECT 1 AS id", &outputs, "main", &context).unwrap();
+
+    assert!(csv.exists());
+    assert!(extract.exists());
+    assert!(table_exists(&context, "main.events"), "The view should be created");
+    assert!(!table_exists(&context, "\"__crabwalk_events\""), "The temporary table should be dropped");
+}
+
+#[test]
+fn test_multiple_outputs_reject_the_same_resolved_location() {
+    let temp_dir = tempdir().unwrap();
+    let output_dir = temp_dir.path().join("exports").to_str().unwrap().to_string();
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    // Two outputs of one format without a location both default to ./output/events.csv
+    let defaults = vec![
+        OutputConfig::new(OutputType::Csv, None, false),
+        OutputConfig::new(OutputType::Csv, None, false),
+    ];
+    let error = handle_outputs("events", "SELECT 1 AS id", &defaults, "main", &context).unwrap_err();
+    assert!(error.to_string().contains("./output/events.csv"), "Unexpected error: {}", error);
+
+    // Two outputs sharing --output-dir
+    let shared = vec![
+        OutputConfig::new(OutputType::Csv, Some(output_dir.clone()), false),
+        OutputConfig::new(OutputType::Parquet, Some(output_dir.clone()), false),
+    ];
+    let error = handle_outputs("events", "SELECT 1 AS id", &shared, "main", &context).unwrap_err();
+    assert!(error.to_string().contains(&output_dir), "Unexpected error: {}", error);
+    assert!(!temp_dir.path().join("exports").exists(), "Nothing should be written");
+}
+
+#[test]
+fn test_kept_table_cannot_replace_the_model_table() {
+    let temp_dir = tempdir().unwrap();
+    let location = temp_dir.path().join("events.csv").to_str().unwrap().to_string();
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+
+    // The kept table defaults to the model name, which the table output already creates
+    let outputs = vec![
+        OutputConfig::new(OutputType::Table, None, false),
+        OutputConfig::new(OutputType::Csv, Some(location.clone()), true),
+    ];
+    let error = handle_outputs("events", "SELECT 1 AS id", &outputs, "main", &context).unwrap_err();
+    assert!(error.to_string().contains("main.events"), "Unexpected error: {}", error);
+    assert!(!table_exists(&context, "main.events"), "Nothing should be created");
+
+    let sql = "-- @config: {outputs: [{type: table}, {type: csv, keep_table: true}]}\nSELECT 1";
+    assert!(extract_config_from_sql(sql).is_err(), "The clash should be rejected when the config is read");
+
+    let sql = "-- @config: {outputs: [{type: table}, {type: csv, keep_table: true, kept_table: \"{table_name}_export\"}]}\nSELECT 1";
+    let outputs: Vec<OutputConfig> = extract_config_from_sql(sql).unwrap().unwrap().outputs;
+    let outputs: Vec<OutputConfig> = outputs.into_iter()
+        .map(|output| OutputConfig { location: output.is_file().then(|| location.clone()), ..output })
+        .collect();
+    handle_outputs("events", "SELECT 1 AS id", &outputs, "main", &context).unwrap();
+    assert!(table_exists(&context, "main.events"));
+    assert!(table_exists(&context, "main.events_export"));
+}
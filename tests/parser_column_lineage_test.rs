@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use tempfile::tempdir;
+use crabwalk::parser::dependencies::get_dependencies;
+use crabwalk::parser::sql::{
+    extract_column_lineage, extract_column_lineage_with_schemas, parse_sql, TableColumnRelationship,
+    TransformationKind,
+};
+
+/// Helper to collect lineage as (source_table, source_column, target_column) triples
+fn lineage_for(sql: &str, schemas: &HashMap<String, Vec<String>>) -> Vec<(String, String, String)> {
+    let statements = parse_sql(sql, "duckdb").unwrap();
+    let mut lineage: Vec<TableColumnRelationship> = Vec::new();
+    for statement in &statements {
+        lineage.extend(extract_column_lineage_with_schemas(statement, "model", schemas).unwrap());
+    }
+    lineage.into_iter()
+        .map(|l| (l.source_table, l.source_column, l.target_column))
+        .collect()
+}
+
+fn has(lineage: &[(String, String, String)], table: &str, column: &str, target: &str) -> bool {
+    lineage.iter().any(|(t, c, g)| t == table && c == column && g == target)
+}
+
+#[test]
+fn test_lineage_resolves_table_aliases() {
+    let sql = "SELECT c.customer_id, c.name AS customer_name FROM stg_customers c";
+    let statements = parse_sql(sql, "duckdb").unwrap();
+    let lineage: Vec<_> = extract_column_lineage(&statements[0], "model").unwrap()
+        .into_iter()
+        .map(|l| (l.source_table, l.source_column, l.target_column))
+        .collect();
+
+    assert!(has(&lineage, "stg_customers", "customer_id", "customer_id"), "Alias should resolve to table: {:?}", lineage);
+    assert!(has(&lineage, "stg_customers", "name", "customer_name"), "Renamed column should resolve to table: {:?}", lineage);
+    assert!(!lineage.iter().any(|(t, _, _)| t == "c"), "Alias should never be recorded as a source table");
+}
+
+#[test]
+fn test_lineage_unqualified_columns_single_table() {
+    let lineage = lineage_for("SELECT customer_id, order_date FROM stg_orders", &HashMap::new());
+
+    assert!(has(&lineage, "stg_orders", "customer_id", "customer_id"));
+    assert!(has(&lineage, "stg_orders", "order_date", "order_date"));
+}
+
+#[test]
+fn test_lineage_captures_expression_sources() {
+    let sql = "SELECT o.customer_id, SUM(o.amount) AS total_spent, o.amount * o.quantity AS line_total \
+               FROM stg_orders o GROUP BY o.customer_id, o.amount, o.quantity";
+    let lineage = lineage_for(sql, &HashMap::new());
+
+    assert!(has(&lineage, "stg_orders", "amount", "total_spent"), "Aggregate arguments should be traced: {:?}", lineage);
+    assert!(has(&lineage, "stg_orders", "amount", "line_total"));
+    assert!(has(&lineage, "stg_orders", "quantity", "line_total"));
+}
+
+#[test]
+fn test_lineage_follows_ctes_and_subqueries() {
+    let sql = "
+        WITH orders AS (
+            SELECT id AS order_id, user_id AS customer_id, amount FROM raw_orders
+        ),
+        totals AS (
+            SELECT customer_id, SUM(amount) AS total FROM orders GROUP BY customer_id
+        )
+        SELECT t.customer_id, t.total, s.max_amount
+        FROM totals t
+        JOIN (SELECT customer_id, MAX(amount) AS max_amount FROM orders GROUP BY customer_id) s
+            ON t.customer_id = s.customer_id
+    ";
+    let lineage = lineage_for(sql, &HashMap::new());
+
+    assert!(has(&lineage, "raw_orders", "user_id", "customer_id"), "CTE renames should be followed: {:?}", lineage);
+    assert!(has(&lineage, "raw_orders", "amount", "total"));
+    assert!(has(&lineage, "raw_orders", "amount", "max_amount"), "Derived tables should be followed: {:?}", lineage);
+    assert!(!lineage.iter().any(|(t, _, _)| t == "orders" || t == "totals" || t == "t" || t == "s"),
+        "CTE and alias names should not appear as source tables: {:?}", lineage);
+}
+
+#[test]
+fn test_lineage_expands_star_with_upstream_schema() {
+    let mut schemas = HashMap::new();
+    schemas.insert("stg_customers".to_string(), vec!["customer_id".to_string(), "name".to_string()]);
+
+    let lineage = lineage_for("SELECT * FROM stg_customers", &schemas);
+
+    assert!(has(&lineage, "stg_customers", "customer_id", "customer_id"));
+    assert!(has(&lineage, "stg_customers", "name", "name"));
+}
+
+#[test]
+fn test_lineage_unqualified_columns_use_upstream_schema() {
+    let mut schemas = HashMap::new();
+    schemas.insert("customers".to_string(), vec!["customer_id".to_string(), "name".to_string()]);
+    schemas.insert("orders".to_string(), vec!["order_id".to_string(), "customer_id".to_string()]);
+
+    let sql = "SELECT name, order_id FROM customers c JOIN orders o ON c.customer_id = o.customer_id";
+    let lineage = lineage_for(sql, &schemas);
+
+    assert!(has(&lineage, "customers", "name", "name"));
+    assert!(has(&lineage, "orders", "order_id", "order_id"));
+}
+
+#[test]
+fn test_lineage_star_through_models() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    let files = [
+        ("stg_customers.sql", "SELECT id AS customer_id, first_name AS name FROM raw_customers"),
+        ("customers.sql", "WITH c AS (SELECT * FROM stg_customers) SELECT c.* FROM c"),
+    ];
+
+    for (filename, content) in files.iter() {
+        let mut file = fs::File::create(format!("{}/{}", path, filename)).unwrap();
+        writeln!(file, "{}", content).unwrap();
+    }
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let customers = dependencies.get("customers").unwrap();
+
+    assert!(customers.column_lineage.iter().any(|l| {
+        l.source_table == "stg_customers" && l.source_column == "name" && l.target_column == "name"
+    }), "SELECT * should be expanded using the upstream model: {:?}", customers.column_lineage);
+}
+
+/// Helper to find the relationship for a source column and target column
+fn mapping<'a>(lineage: &'a [TableColumnRelationship], column: &str, target: &str) -> &'a TableColumnRelationship {
+    lineage.iter()
+        .find(|l| l.source_column == column && l.target_column == target)
+        .unwrap_or_else(|| panic!("No mapping {} -> {} in {:?}", column, target, lineage))
+}
+
+#[test]
+fn test_lineage_classifies_transformations() {
+    let sql = "
+        SELECT
+            o.customer_id,
+            o.order_date AS ordered_at,
+            CAST(o.amount AS DOUBLE) AS amount_double,
+            SUM(o.amount) AS total_spent,
+            ROW_NUMBER() OVER (PARTITION BY o.customer_id ORDER BY o.order_date) AS order_seq,
+            o.amount * o.quantity AS line_total
+        FROM stg_orders o
+        JOIN stg_customers c ON o.customer_id = c.id
+        WHERE c.status = 'active'
+        GROUP BY ALL
+    ";
+    let statements = parse_sql(sql, "duckdb").unwrap();
+    let lineage = extract_column_lineage(&statements[0], "model").unwrap();
+
+    assert_eq!(mapping(&lineage, "customer_id", "customer_id").transformation, TransformationKind::Passthrough);
+    assert_eq!(mapping(&lineage, "customer_id", "customer_id").expression, None);
+    assert_eq!(mapping(&lineage, "order_date", "ordered_at").transformation, TransformationKind::Rename);
+    assert_eq!(mapping(&lineage, "amount", "amount_double").transformation, TransformationKind::Cast);
+
+    let total = mapping(&lineage, "amount", "total_spent");
+    assert_eq!(total.transformation, TransformationKind::Aggregate);
+    assert_eq!(total.expression.as_deref(), Some("sum(o.amount)"));
+
+    assert_eq!(mapping(&lineage, "order_date", "order_seq").transformation, TransformationKind::Window);
+    assert_eq!(mapping(&lineage, "quantity", "line_total").transformation, TransformationKind::Expression);
+
+    assert_eq!(mapping(&lineage, "id", "*").transformation, TransformationKind::JoinKey);
+    assert_eq!(mapping(&lineage, "status", "*").transformation, TransformationKind::Filter);
+    assert_eq!(mapping(&lineage, "status", "*").source_table, "stg_customers");
+}
+
+#[test]
+fn test_lineage_transformation_survives_ctes() {
+    let sql = "
+        WITH totals AS (
+            SELECT customer_id, SUM(amount) AS total FROM raw_orders WHERE amount > 0 GROUP BY customer_id
+        )
+        SELECT customer_id, total AS lifetime_value FROM totals
+    ";
+    let statements = parse_sql(sql, "duckdb").unwrap();
+    let lineage = extract_column_lineage(&statements[0], "model").unwrap();
+
+    let value = mapping(&lineage, "amount", "lifetime_value");
+    assert_eq!(value.transformation, TransformationKind::Aggregate, "Aggregates in CTEs should not become renames");
+    assert_eq!(value.expression.as_deref(), Some("sum(amount)"));
+    assert_eq!(mapping(&lineage, "customer_id", "customer_id").transformation, TransformationKind::Passthrough);
+    assert_eq!(mapping(&lineage, "amount", "*").transformation, TransformationKind::Filter);
+}
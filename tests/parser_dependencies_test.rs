@@ -108,4 +108,23 @@ fn test_process_files_with_complex_dependencies() {
     let final_deps = dependencies.get("final").unwrap();
     assert_eq!(final_deps.deps.len(), 1, "final should have one dependency");
     assert!(final_deps.deps.contains(&"intermediate".to_string()), "final should depend on intermediate");
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_dependencies_keep_parsed_statements() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+    
+    let file_path = format!("{}/orders.sql", path);
+    let mut file = fs::File::create(&file_path).unwrap();
+    writeln!(file, "-- @config: {{output: {{type: \"view\"}}}}").unwrap();
+    writeln!(file, "SELECT id, amount FROM raw_orders").unwrap();
+    
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let orders: &Dependency = dependencies.get("orders").unwrap();
+    
+    // The SQL is parsed once and kept for execution and introspection
+    assert!(orders.sql.contains("SELECT id, amount FROM raw_orders"));
+    assert_eq!(orders.statements.len(), 1, "Should keep the parsed statement");
+    assert!(orders.statements[0].to_string().contains("raw_orders"));
+}
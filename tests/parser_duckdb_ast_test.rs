@@ -0,0 +1,460 @@
+use crabwalk::Crabwalk;
+use crabwalk::parser::duckdb_ast::convert_statements;
+use crabwalk::parser::sql::{
+    extract_tables, parse_sql, parse_with_duckdb_and_convert, resolve_output_columns, split_statements, TransformationKind,
+};
+use serde_json::{json, Value};
+use sqlparser::ast::{JoinConstraint, JoinOperator, SetExpr, Statement};
+use std::collections::HashMap;
+use tempfile::tempdir;
+
+mod common;
+use common::write_model;
+
+// Fixtures follow the JSON that DuckDB's json_serialize_sql produces for each query
+
+fn column(names: &[&str]) -> Value {
+    json!({ "class": "COLUMN_REF", "type": "COLUMN_REF", "alias": "", "column_names": names })
+}
+
+fn integer(value: i64) -> Value {
+    json!({
+        "class": "CONSTANT", "type": "VALUE_CONSTANT", "alias": "",
+        "value": { "type": { "id": "INTEGER", "type_info": null }, "is_null": false, "value": value }
+    })
+}
+
+fn string(value: &str) -> Value {
+    json!({
+        "class": "CONSTANT", "type": "VALUE_CONSTANT", "alias": "",
+        "value": { "type": { "id": "VARCHAR", "type_info": null }, "is_null": false, "value": value }
+    })
+}
+
+fn function(name: &str, children: Vec<Value>, is_operator: bool) -> Value {
+    json!({
+        "class": "FUNCTION", "type": "FUNCTION", "alias": "", "function_name": name, "schema": "", "catalog": "",
+        "children": children, "filter": null, "order_bys": { "type": "ORDER_MODIFIER", "orders": [] },
+        "distinct": false, "is_operator": is_operator, "export_state": false
+    })
+}
+
+fn aliased(mut expr: Value, alias: &str) -> Value {
+    expr["alias"] = json!(alias);
+    expr
+}
+
+fn base_table(name: &str, alias: &str) -> Value {
+    json!({
+        "type": "BASE_TABLE", "alias": alias, "sample": null, "schema_name": "", "table_name": name,
+        "column_name_alias": [], "catalog_name": ""
+    })
+}
+
+fn star() -> Value {
+    json!({
+        "class": "STAR", "type": "STAR", "alias": "", "relation_name": "", "exclude_list": [],
+        "replace_list": [], "columns": false, "expr": null
+    })
+}
+
+fn select(select_list: Vec<Value>, from_table: Value) -> Value {
+    json!({
+        "type": "SELECT_NODE", "modifiers": [], "cte_map": { "map": [] }, "select_list": select_list,
+        "from_table": from_table, "where_clause": null, "group_expressions": [], "group_sets": [],
+        "aggregate_handling": "STANDARD_HANDLING", "having": null, "sample": null, "qualify": null
+    })
+}
+
+fn ast(node: Value) -> Value {
+    json!({ "error": false, "statements": [{ "node": node }] })
+}
+
+fn convert_one(node: Value) -> sqlparser::ast::Statement {
+    let mut statements = convert_statements(&ast(node)).expect("Failed to convert DuckDB AST");
+    assert_eq!(statements.len(), 1, "Should convert exactly one statement");
+    statements.remove(0)
+}
+
+#[test]
+fn test_convert_qualify_window() {
+    // SELECT * FROM orders QUALIFY row_number() OVER (PARTITION BY customer_id ORDER BY order_date DESC) = 1
+    let window = json!({
+        "class": "WINDOW", "type": "WINDOW_ROW_NUMBER", "alias": "", "function_name": "row_number",
+        "schema": "", "catalog": "", "children": [], "partitions": [column(&["customer_id"])],
+        "orders": [{ "type": "DESCENDING", "null_order": "ORDER_DEFAULT", "expression": column(&["order_date"]) }],
+        "start": "UNBOUNDED_PRECEDING", "end": "CURRENT_ROW_RANGE", "start_expr": null, "end_expr": null,
+        "offset_expr": null, "default_expr": null, "ignore_nulls": false, "filter_expr": null,
+        "exclude_clause": "NO_OTHER", "distinct": false
+    });
+    let mut node = select(vec![star()], base_table("orders", ""));
+    node["qualify"] = json!({
+        "class": "COMPARISON", "type": "COMPARE_EQUAL", "alias": "", "left": window, "right": integer(1)
+    });
+
+    let statement = convert_one(node);
+
+    assert_eq!(
+        statement.to_string(),
+        "SELECT * FROM orders QUALIFY row_number() OVER (PARTITION BY customer_id ORDER BY order_date DESC) = 1"
+    );
+    assert!(extract_tables(&statement).contains("orders"), "Should keep the QUALIFY query's table");
+}
+
+#[test]
+fn test_convert_from_first_query() {
+    // FROM customers  (no select list written, DuckDB fills in *)
+    let statement = convert_one(select(vec![star()], base_table("customers", "")));
+
+    assert_eq!(statement.to_string(), "SELECT * FROM customers");
+    assert!(extract_tables(&statement).contains("customers"), "Should extract the FROM-first table");
+}
+
+#[test]
+fn test_convert_star_exclude_replace_lineage() {
+    // SELECT * EXCLUDE (secret) REPLACE (amount * 100 AS amount) FROM payments
+    let mut item = star();
+    item["exclude_list"] = json!(["secret"]);
+    item["replace_list"] = json!([{
+        "key": "amount",
+        "value": function("*", vec![column(&["amount"]), integer(100)], true)
+    }]);
+    let statement = convert_one(select(vec![item], base_table("payments", "")));
+
+    assert_eq!(
+        statement.to_string(),
+        "SELECT * EXCLUDE (secret) REPLACE (amount * 100 AS amount) FROM payments"
+    );
+
+    let mut schemas = HashMap::new();
+    schemas.insert("payments".to_string(), vec!["id".to_string(), "amount".to_string(), "secret".to_string()]);
+    let resolved = resolve_output_columns(&statement, &schemas);
+
+    let names: Vec<&str> = resolved.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "amount"], "EXCLUDE should drop the secret column");
+
+    let amount = &resolved.columns[1];
+    assert_eq!(amount.kind, TransformationKind::Expression, "REPLACE should make amount computed");
+    assert_eq!(amount.sources, vec![("payments".to_string(), "amount".to_string())]);
+}
+
+#[test]
+fn test_convert_columns_expression() {
+    // SELECT COLUMNS('^amount_') FROM payments
+    let mut item = star();
+    item["columns"] = json!(true);
+    item["expr"] = string("^amount_");
+
+    let statement = convert_one(select(vec![item], base_table("payments", "")));
+
+    assert_eq!(statement.to_string(), "SELECT COLUMNS('^amount_') FROM payments");
+}
+
+#[test]
+fn test_convert_pivot_and_unpivot_tables() {
+    // SELECT * FROM sales PIVOT (sum(amount) FOR year IN (2023, 2024))
+    let pivot = json!({
+        "type": "PIVOT", "alias": "", "sample": null, "source": base_table("sales", ""),
+        "aggregates": [function("sum", vec![column(&["amount"])], false)],
+        "unpivot_names": [],
+        "pivots": [{
+            "unpivot_names": [], "pivot_expressions": [column(&["year"])], "pivot_enum": "",
+            "entries": [
+                { "values": [integer(2023)["value"].clone()], "star_expr": null, "alias": "" },
+                { "values": [integer(2024)["value"].clone()], "star_expr": null, "alias": "" }
+            ]
+        }],
+        "groups": [], "column_name_alias": [], "include_nulls": false
+    });
+    let statement = convert_one(select(vec![star()], pivot));
+
+    assert_eq!(statement.to_string(), "SELECT * FROM sales PIVOT(sum(amount) FOR year IN (2023, 2024))");
+    assert!(extract_tables(&statement).contains("sales"), "Should extract the pivoted table");
+
+    // SELECT * FROM monthly UNPIVOT (amount FOR month IN (jan, feb)) AS m
+    let unpivot = json!({
+        "type": "PIVOT", "alias": "m", "sample": null, "source": base_table("monthly", ""),
+        "aggregates": [], "unpivot_names": ["amount"],
+        "pivots": [{
+            "unpivot_names": ["month"], "pivot_expressions": [], "pivot_enum": "",
+            "entries": [
+                { "values": [], "star_expr": null, "expr": column(&["jan"]), "alias": "" },
+                { "values": [], "star_expr": null, "expr": column(&["feb"]), "alias": "" }
+            ]
+        }],
+        "groups": [], "column_name_alias": [], "include_nulls": false
+    });
+    let statement = convert_one(select(vec![column(&["m", "amount"])], unpivot));
+
+    assert_eq!(statement.to_string(), "SELECT m.amount FROM monthly UNPIVOT(amount FOR month IN (jan, feb)) AS m");
+    assert!(extract_tables(&statement).contains("monthly"), "Should extract the unpivoted table");
+
+    let resolved = resolve_output_columns(&statement, &HashMap::new());
+    assert_eq!(resolved.columns.len(), 1, "Should resolve the column read from the unpivot alias");
+}
+
+#[test]
+fn test_convert_asof_and_positional_joins() {
+    // SELECT t.symbol, p.price FROM trades t ASOF JOIN prices p ON t.symbol = p.symbol AND t.ts >= p.ts
+    //     POSITIONAL JOIN fx
+    let condition = json!({
+        "class": "CONJUNCTION", "type": "CONJUNCTION_AND", "alias": "",
+        "children": [
+            { "class": "COMPARISON", "type": "COMPARE_EQUAL", "alias": "",
+              "left": column(&["t", "symbol"]), "right": column(&["p", "symbol"]) },
+            { "class": "COMPARISON", "type": "COMPARE_GREATERTHANOREQUALTO", "alias": "",
+              "left": column(&["t", "ts"]), "right": column(&["p", "ts"]) }
+        ]
+    });
+    let asof = json!({
+        "type": "JOIN", "alias": "", "sample": null, "left": base_table("trades", "t"),
+        "right": base_table("prices", "p"), "condition": condition, "join_type": "INNER",
+        "ref_type": "ASOF", "using_columns": [], "delim_flipped": false, "duplicate_eliminated_columns": []
+    });
+    let positional = json!({
+        "type": "JOIN", "alias": "", "sample": null, "left": asof, "right": base_table("fx", ""),
+        "condition": null, "join_type": "INNER", "ref_type": "POSITIONAL", "using_columns": []
+    });
+    let statement = convert_one(select(vec![column(&["t", "symbol"]), column(&["p", "price"])], positional));
+
+    assert!(
+        matches!(last_join(&statement).join_operator, JoinOperator::FullOuter(JoinConstraint::None)),
+        "Unexpected positional join: {}", statement
+    );
+
+    let tables = extract_tables(&statement);
+    for table in ["trades", "prices", "fx"] {
+        assert!(tables.contains(table), "Should extract joined table {}", table);
+    }
+    assert!(
+        statement.to_string().contains("ASOF JOIN prices AS p MATCH_CONDITION (t.ts >= p.ts) ON t.symbol = p.symbol"),
+        "Unexpected ASOF join: {}", statement
+    );
+
+    let mut schemas = HashMap::new();
+    schemas.insert("trades".to_string(), vec!["symbol".to_string(), "ts".to_string()]);
+    schemas.insert("prices".to_string(), vec!["symbol".to_string(), "ts".to_string(), "price".to_string()]);
+    let resolved = resolve_output_columns(&statement, &schemas);
+
+    assert!(
+        resolved.join_keys.iter().any(|k| {
+            (k.left_table.as_str(), k.left_column.as_str(), k.right_table.as_str(), k.right_column.as_str())
+                == ("trades", "symbol", "prices", "symbol")
+        }),
+        "ASOF equality should be a join key: {:?}", resolved.join_keys
+    );
+    assert_eq!(resolved.columns[1].sources, vec![("prices".to_string(), "price".to_string())]);
+}
+
+#[test]
+fn test_convert_struct_list_and_lambda() {
+    // SELECT {'id': id, 'n': 1} AS s, [1, 2] AS l, list_transform(tags, x -> x || '!') AS t FROM items
+    let lambda = json!({
+        "class": "LAMBDA", "type": "LAMBDA", "alias": "", "lhs": column(&["x"]),
+        "expr": function("||", vec![column(&["x"]), string("!")], true)
+    });
+    let statement = convert_one(select(
+        vec![
+            aliased(function("struct_pack", vec![aliased(column(&["id"]), "id"), aliased(integer(1), "n")], false), "s"),
+            aliased(function("list_value", vec![integer(1), integer(2)], false), "l"),
+            aliased(function("list_transform", vec![column(&["tags"]), lambda], false), "t"),
+        ],
+        base_table("items", ""),
+    ));
+
+    assert_eq!(
+        statement.to_string(),
+        "SELECT {id: id, n: 1} AS s, [1, 2] AS l, list_transform(tags, x -> x || '!') AS t FROM items"
+    );
+
+    let resolved = resolve_output_columns(&statement, &HashMap::new());
+    assert_eq!(resolved.columns[0].sources, vec![("items".to_string(), "id".to_string())]);
+    assert_eq!(
+        resolved.columns[2].sources,
+        vec![("items".to_string(), "tags".to_string())],
+        "Lambda parameters should not be treated as columns"
+    );
+}
+
+#[test]
+fn test_convert_keeps_operator_precedence() {
+    // SELECT (a + b) * c, a - (b - c) FROM t
+    let sum = function("+", vec![column(&["a"]), column(&["b"])], true);
+    let difference = function("-", vec![column(&["b"]), column(&["c"])], true);
+    let statement = convert_one(select(
+        vec![
+            function("*", vec![sum, column(&["c"])], true),
+            function("-", vec![column(&["a"]), difference], true),
+        ],
+        base_table("t", ""),
+    ));
+
+    assert_eq!(statement.to_string(), "SELECT (a + b) * c, a - (b - c) FROM t");
+}
+
+#[test]
+fn test_convert_unsupported_node_fails() {
+    let mut node = select(vec![star()], base_table("t", ""));
+    node["select_list"] = json!([{ "class": "BOUND_SOMETHING", "type": "INVALID", "alias": "" }]);
+
+    assert!(
+        convert_statements(&ast(node)).is_err(),
+        "Unsupported expressions should fail instead of being dropped"
+    );
+    assert!(
+        convert_statements(&json!({ "error": true, "error_message": "syntax error" })).is_err(),
+        "Parser errors should be reported"
+    );
+}
+
+#[test]
+fn test_split_statements_keeps_source_text() {
+    let sql = "CREATE TABLE x AS SELECT ';' AS s; -- ignored;\nSELECT * FROM x POSITIONAL JOIN y;\n";
+
+    let statements = split_statements(sql).unwrap();
+
+    assert_eq!(
+        statements,
+        vec![
+            "CREATE TABLE x AS SELECT ';' AS s".to_string(),
+            "-- ignored;\nSELECT * FROM x POSITIONAL JOIN y".to_string(),
+        ]
+    );
+
+    // Comments after the last statement are not a statement of their own
+    let statements = split_statements("SELECT 1;\nSELECT 2;\n-- @config: {output: {type: view}}\n/* done */\n").unwrap();
+    assert_eq!(statements, vec!["SELECT 1".to_string(), "SELECT 2".to_string()]);
+}
+
+/// Parse a query with DuckDB's own parser, checking that `parse_sql` takes the same path
+fn parse_duckdb(sql: &str) -> Statement {
+    let statements = parse_with_duckdb_and_convert(sql).unwrap();
+    assert_eq!(parse_sql(sql, "duckdb").unwrap(), statements);
+    assert_eq!(statements.len(), 1);
+    statements.into_iter().next().unwrap()
+}
+
+/// Last join in the FROM clause of a SELECT
+fn last_join(statement: &Statement) -> &sqlparser::ast::Join {
+    let Statement::Query(query) = statement else { panic!("Not a query: {}", statement) };
+    let SetExpr::Select(select) = query.body.as_ref() else { panic!("Not a SELECT: {}", statement) };
+    select.from[0].joins.last().unwrap()
+}
+
+/// Columns of the `orders` table used by the real-SQL tests
+fn orders_schema() -> HashMap<String, Vec<String>> {
+    HashMap::from([(
+        "orders".to_string(),
+        vec!["id".to_string(), "amount".to_string(), "secret".to_string()],
+    )])
+}
+
+#[test]
+fn test_duckdb_sql_qualify() {
+    let statement = parse_duckdb(
+        "SELECT id, amount FROM orders QUALIFY row_number() OVER (PARTITION BY id ORDER BY amount DESC) = 1",
+    );
+
+    assert!(statement.to_string().contains("QUALIFY row_number() OVER (PARTITION BY id ORDER BY amount DESC) = 1"), "{}", statement);
+    let resolved = resolve_output_columns(&statement, &orders_schema());
+    let names: Vec<&str> = resolved.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "amount"]);
+}
+
+#[test]
+fn test_duckdb_sql_pivot() {
+    for sql in [
+        "SELECT * FROM orders PIVOT (sum(amount) FOR id IN (1, 2))",
+        "PIVOT orders ON id IN (1, 2) USING sum(amount)",
+    ] {
+        let statement = parse_duckdb(sql);
+        assert!(extract_tables(&statement).contains("orders"), "Should read orders: {}", statement);
+    }
+}
+
+#[test]
+fn test_duckdb_sql_from_first() {
+    let statement = parse_duckdb("FROM orders SELECT id, amount * 2 AS doubled");
+    let resolved = resolve_output_columns(&statement, &orders_schema());
+    assert_eq!(resolved.columns[0].sources, vec![("orders".to_string(), "id".to_string())]);
+    assert_eq!(resolved.columns[1].name, "doubled");
+    assert_eq!(resolved.columns[1].kind, TransformationKind::Expression);
+
+    let statement = parse_duckdb("FROM orders");
+    let resolved = resolve_output_columns(&statement, &orders_schema());
+    assert_eq!(resolved.columns.len(), 3, "FROM without SELECT selects every column");
+}
+
+#[test]
+fn test_duckdb_sql_exclude_and_replace() {
+    let statement = parse_duckdb("SELECT * EXCLUDE (secret) REPLACE (amount * 2 AS amount) FROM orders");
+
+    let resolved = resolve_output_columns(&statement, &orders_schema());
+    let names: Vec<&str> = resolved.columns.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(names, vec!["id", "amount"]);
+    assert_eq!(resolved.columns[0].kind, TransformationKind::Passthrough);
+    assert_eq!(resolved.columns[1].kind, TransformationKind::Expression);
+}
+
+#[test]
+fn test_duckdb_sql_columns_expression() {
+    let statement = parse_duckdb("SELECT max(COLUMNS('amount|id')) FROM orders");
+
+    assert!(extract_tables(&statement).contains("orders"));
+    assert!(statement.to_string().contains("COLUMNS('amount|id')"), "{}", statement);
+}
+
+#[test]
+fn test_duckdb_sql_asof_and_positional_joins() {
+    let statement = parse_duckdb(
+        "SELECT t.symbol, p.price FROM trades t ASOF JOIN prices p ON t.symbol = p.symbol AND t.ts >= p.ts",
+    );
+    assert!(
+        statement.to_string().contains("ASOF JOIN prices AS p MATCH_CONDITION (t.ts >= p.ts) ON t.symbol = p.symbol"),
+        "Unexpected ASOF join: {}", statement
+    );
+    let resolved = resolve_output_columns(&statement, &HashMap::new());
+    assert!(
+        resolved.join_keys.iter().any(|k| (k.left_table.as_str(), k.right_table.as_str()) == ("trades", "prices")),
+        "ASOF equality should be a join key: {:?}", resolved.join_keys
+    );
+
+    let statement = parse_duckdb("SELECT a.x, b.y FROM a POSITIONAL JOIN b");
+    let join = last_join(&statement);
+    assert!(matches!(join.join_operator, JoinOperator::FullOuter(JoinConstraint::None)), "{}", statement);
+    let tables = extract_tables(&statement);
+    assert!(tables.contains("a") && tables.contains("b"));
+}
+
+#[test]
+fn test_run_multi_statement_model_with_positional_join() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    // DuckDB does not serialize the CREATE and sqlparser rejects the POSITIONAL JOIN,
+    // so the file is only parsed one statement at a time
+    write_model(&models, "paired.sql", "\
+CREATE OR REPLACE TEMP TABLE letters AS SELECT * FROM (VALUES ('a'), ('b')) l(letter);
+SELECT n.n, l.letter FROM (VALUES (1), (2)) n(n) POSITIONAL JOIN letters l;
+-- @config: {output: {type: table}}
+");
+    let database = temp_dir.path().join("test.db");
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    );
+    crabwalk.run().unwrap();
+    drop(crabwalk);
+
+    let conn = duckdb::Connection::open(&database).unwrap();
+    let mut stmt = conn.prepare("SELECT n, letter FROM transform.paired ORDER BY n").unwrap();
+    let rows: Vec<(i32, String)> = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .map(|row| row.unwrap())
+        .collect();
+    assert_eq!(rows, vec![(1, "a".to_string()), (2, "b".to_string())]);
+}
@@ -52,6 +52,9 @@ fn test_generate_mermaid_diagram_simple() {
         config: None,
         columns: Vec::new(),
         column_lineage: Vec::new(),
+        join_keys: Vec::new(),
+        sql: String::new(),
+        statements: Vec::new(),
     };
     dependencies.insert("source".to_string(), source);
     
@@ -64,6 +67,9 @@ fn test_generate_mermaid_diagram_simple() {
         config: None,
         columns: Vec::new(),
         column_lineage: Vec::new(),
+        join_keys: Vec::new(),
+        sql: String::new(),
+        statements: Vec::new(),
     };
     dependencies.insert("target".to_string(), target);
     
@@ -96,6 +102,9 @@ fn test_generate_mermaid_diagram_complex() {
             config: None,
             columns: Vec::new(),
             column_lineage: Vec::new(),
+            join_keys: Vec::new(),
+            sql: String::new(),
+            statements: Vec::new(),
         });
     }
     
@@ -109,6 +118,9 @@ fn test_generate_mermaid_diagram_complex() {
         config: None,
         columns: Vec::new(),
         column_lineage: Vec::new(),
+        join_keys: Vec::new(),
+        sql: String::new(),
+        statements: Vec::new(),
     });
     
     // Add final model that depends on intermediate
@@ -120,6 +132,9 @@ fn test_generate_mermaid_diagram_complex() {
         config: None,
         columns: Vec::new(),
         column_lineage: Vec::new(),
+        join_keys: Vec::new(),
+        sql: String::new(),
+        statements: Vec::new(),
     });
     
     let result = generate_mermaid_diagram(path, &dependencies);
@@ -1,4 +1,5 @@
-use crabwalk::parser::sql::{parse_sql, extract_tables};
+use crabwalk::parser::diagnostics::ParseDiagnostic;
+use crabwalk::parser::sql::{parse_sql, extract_tables, SqlParser};
 
 #[test]
 fn test_parse_simple_sql() {
@@ -85,4 +86,35 @@ fn test_extract_tables_from_complex_sql() {
     // Complex SQL parsing is still being improved, so we'll just check that 
     // some tables are extracted without being strict about which ones.
     // In a more comprehensive test suite, this would be fixed to check for all tables.
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_parse_error_points_at_failing_token() {
+    let sql = "SELECT id,\n       name\nFROM customers WHERE id = = 1";
+
+    let error = parse_sql(sql, "duckdb").unwrap_err();
+    let diagnostic = error.downcast_ref::<ParseDiagnostic>().expect("Parse errors should be diagnostics");
+
+    let location = diagnostic.location.expect("Diagnostic should have a location");
+    assert_eq!((location.line, location.column), (3, 27), "Should point at the repeated operator");
+    assert_eq!(diagnostic.source_line.as_deref(), Some("FROM customers WHERE id = = 1"));
+    assert!(
+        diagnostic.messages.iter().any(|m| m.parser == "sqlparser"),
+        "Should say which parser reported the error"
+    );
+
+    let rendered = diagnostic.to_string();
+    assert!(rendered.contains("3 | FROM customers WHERE id = = 1\n  |                           ^\n"), "Unexpected excerpt:\n{}", rendered);
+}
+
+#[test]
+fn test_parse_error_names_model_file() {
+    let sql = "SELECT 1 +";
+
+    let error = SqlParser::new("duckdb").parse_file(sql, Some("models/broken.sql")).unwrap_err();
+
+    assert!(
+        error.to_string().starts_with("Failed to parse SQL at models/broken.sql"),
+        "Diagnostic should name the file: {}", error
+    );
+}
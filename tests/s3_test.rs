@@ -0,0 +1,53 @@
+//! Backup and restore against a real S3-compatible store, e.g. a local MinIO:
+//!
+//! ```bash
+//! docker run -p 9000:9000 minio/minio server /data
+//! CRABWALK_TEST_S3_ENDPOINT=http://localhost:9000 CRABWALK_TEST_S3_BUCKET=crabwalk \
+//!     AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin cargo test --features s3 --test s3_test
+//! ```
+//!
+//! The bucket must exist. Without `CRABWALK_TEST_S3_ENDPOINT` the test does nothing.
+#![cfg(feature = "s3")]
+
+use duckdb::Connection;
+use tempfile::tempdir;
+use crabwalk::storage::{backup, restore, BackupSelection, S3Backend, S3Config};
+
+#[test]
+fn test_backup_and_restore_round_trip() {
+    let Ok(endpoint) = std::env::var("CRABWALK_TEST_S3_ENDPOINT") else {
+        eprintln!("CRABWALK_TEST_S3_ENDPOINT is not set; skipping");
+        return;
+    };
+    let bucket = std::env::var("CRABWALK_TEST_S3_BUCKET").unwrap_or_else(|_| "crabwalk".to_string());
+
+    let temp_dir = tempdir().unwrap();
+    let database = temp_dir.path().join("source.duckdb");
+    {
+        let conn = Connection::open(&database).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events AS SELECT range AS id, repeat('x', 200) || range::VARCHAR AS payload FROM range(100000)",
+        ).unwrap();
+    }
+
+    // Credentials come from the default chain (AWS_ACCESS_KEY_ID / AWS_SECRET_ACCESS_KEY)
+    let s3_config = S3Config {
+        bucket,
+        endpoint_url: Some(endpoint),
+        db_folder_name: format!("crabwalk-test-{}", std::process::id()),
+        ..S3Config::default()
+    };
+    let backend = S3Backend::new(s3_config).unwrap();
+    let manifest = backup(database.to_str().unwrap(), &backend, &BackupSelection::default()).unwrap();
+    assert!(manifest.files.iter().any(|file| file.key == "schema.sql"));
+
+    let restored = temp_dir.path().join("restored.duckdb");
+    restore(restored.to_str().unwrap(), &backend, None, false).unwrap();
+
+    let conn = Connection::open(&restored).unwrap();
+    let rows: i64 = conn.query_row("SELECT count(*) FROM events", [], |row| row.get(0)).unwrap();
+    assert_eq!(rows, 100000);
+
+    // An existing database is only replaced with overwrite
+    assert!(restore(restored.to_str().unwrap(), &backend, None, false).is_err());
+}
@@ -0,0 +1,87 @@
+use std::fs;
+use duckdb::Connection;
+use tempfile::tempdir;
+use crabwalk::executor::RunContext;
+use crabwalk::parser::dependencies::get_dependencies;
+use crabwalk::schema::introspect::apply_column_types;
+
+fn column_type(columns: &[crabwalk::parser::sql::ColumnInfo], name: &str) -> String {
+    columns.iter()
+        .find(|c| c.name == name)
+        .map(|c| c.data_type.clone())
+        .unwrap_or_else(|| panic!("Column {} not found in {:?}", name, columns))
+}
+
+#[test]
+fn test_describe_returns_duckdb_types() {
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("CREATE TABLE orders (order_id INTEGER, amount DECIMAL(10, 2), ordered_at TIMESTAMP)").unwrap();
+
+    let columns = context.describe("orders").unwrap();
+    assert_eq!(columns, vec![
+        ("order_id".to_string(), "INTEGER".to_string()),
+        ("amount".to_string(), "DECIMAL(10,2)".to_string()),
+        ("ordered_at".to_string(), "TIMESTAMP".to_string()),
+    ]);
+
+    let columns = context.describe("SELECT CAST(order_id AS VARCHAR) AS order_key FROM orders").unwrap();
+    assert_eq!(columns, vec![("order_key".to_string(), "VARCHAR".to_string())]);
+}
+
+#[test]
+fn test_apply_column_types_from_tables_and_queries() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    fs::write(format!("{}/stg_orders.sql", path), "SELECT * FROM raw_orders").unwrap();
+    fs::write(
+        format!("{}/order_amounts.sql", path),
+        "SELECT customer_id, amount * 100 AS amount_cents, CAST(order_id AS BIGINT) AS order_key FROM stg_orders",
+    ).unwrap();
+
+    let mut dependencies = get_dependencies(path, "duckdb").unwrap();
+
+    // stg_orders is materialized; order_amounts is only described from its query
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("CREATE SCHEMA transform").unwrap();
+    context.execute("CREATE TABLE raw_orders (order_id INTEGER, customer_id INTEGER, amount DOUBLE)").unwrap();
+    context.execute("CREATE TABLE transform.stg_orders AS SELECT * FROM raw_orders").unwrap();
+    context.execute("USE transform").unwrap();
+
+    apply_column_types(&mut dependencies, &context, "transform");
+
+    let stg_orders = &dependencies["stg_orders"].columns;
+    assert!(!stg_orders.iter().any(|c| c.name == "*"), "Wildcard placeholder should be replaced: {:?}", stg_orders);
+    assert_eq!(column_type(stg_orders, "order_id"), "INTEGER");
+    assert_eq!(column_type(stg_orders, "amount"), "DOUBLE");
+
+    let order_amounts = &dependencies["order_amounts"].columns;
+    assert_eq!(column_type(order_amounts, "customer_id"), "INTEGER");
+    assert_eq!(column_type(order_amounts, "amount_cents"), "DOUBLE");
+    assert_eq!(column_type(order_amounts, "order_key"), "BIGINT");
+    assert!(order_amounts.iter().find(|c| c.name == "amount_cents").unwrap().is_derived);
+}
+
+#[test]
+fn test_unmaterialized_model_is_described_as_written() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    // The parsed tree of a POSITIONAL JOIN prints back as a FULL JOIN without a condition
+    fs::write(
+        format!("{}/paired.sql", path),
+        "-- Pair rows by position\nSELECT l.id, r.label FROM lefts AS l POSITIONAL JOIN rights AS r;\n",
+    ).unwrap();
+
+    let mut dependencies = get_dependencies(path, "duckdb").unwrap();
+
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("CREATE TABLE lefts AS SELECT * FROM (VALUES (1), (2)) AS t(id)").unwrap();
+    context.execute("CREATE TABLE rights AS SELECT * FROM (VALUES ('a'), ('b')) AS t(label)").unwrap();
+
+    apply_column_types(&mut dependencies, &context, "transform");
+
+    let paired = &dependencies["paired"].columns;
+    assert_eq!(column_type(paired, "id"), "INTEGER");
+    assert_eq!(column_type(paired, "label"), "VARCHAR");
+}
@@ -0,0 +1,240 @@
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::parser::dependencies::get_dependencies;
+use crabwalk::schema::generate_database_schema;
+use crabwalk::schema::visualization::visualize_database_schema;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use crabwalk::schema::relationships::{collect_relationships, Confidence, Relationship, RelationshipOrigin};
+
+fn write_models(path: &str, models: &[(&str, &str)]) {
+    for (filename, content) in models {
+        fs::write(format!("{}/{}", path, filename), content).unwrap();
+    }
+}
+
+#[test]
+fn test_relationships_from_declared_keys_and_joins() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("customers.sql", "-- @config: {primary_key: customer_id}\nSELECT id AS customer_id, name FROM raw_customers"),
+        ("products.sql", "SELECT id, title FROM raw_products"),
+        ("orders.sql", "-- @config: {primary_key: order_id, foreign_keys: [{column: customer_id, references: customers.customer_id}]}\nSELECT order_id, customer_id, product_id FROM raw_orders"),
+        ("order_details.sql", "SELECT o.order_id, c.name, p.title \
+            FROM orders o \
+            JOIN customers c ON o.customer_id = c.customer_id \
+            JOIN products p ON p.id = o.product_id"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+
+    let declared: Vec<_> = relationships.iter().filter(|r| r.origin == RelationshipOrigin::Declared).collect();
+    assert_eq!(declared.len(), 1, "{:?}", relationships);
+    assert_eq!((declared[0].from_table.as_str(), declared[0].to_table.as_str()), ("orders", "customers"));
+
+    // The join on orders.customer_id duplicates the declared key and is not repeated
+    assert!(!relationships.iter().any(|r| r.origin == RelationshipOrigin::JoinCondition && r.to_table == "customers"),
+        "{:?}", relationships);
+
+    // products.id is the key side of the join, so orders references products
+    let inferred = relationships.iter()
+        .find(|r| r.origin == RelationshipOrigin::JoinCondition)
+        .expect("Join condition should produce a relationship");
+    assert_eq!(
+        (inferred.from_table.as_str(), inferred.from_column.as_str(), inferred.to_table.as_str(), inferred.to_column.as_str()),
+        ("orders", "product_id", "products", "id")
+    );
+}
+
+/// Parse the whole document, failing on anything an XML parser rejects
+fn assert_well_formed(xml: &str) {
+    let mut reader = Reader::from_str(xml);
+    loop {
+        match reader.read_event() {
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(e) => panic!("Not well-formed at {}: {}\n{}", reader.buffer_position(), e, xml),
+        }
+    }
+}
+
+fn find<'a>(relationships: &'a [Relationship], from_table: &str, to_table: &str) -> &'a Relationship {
+    relationships.iter()
+        .find(|r| r.from_table == from_table && r.to_table == to_table)
+        .unwrap_or_else(|| panic!("No relationship {} -> {} in {:?}", from_table, to_table, relationships))
+}
+
+#[test]
+fn test_relationship_confidence_levels() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("customers.sql", "-- @config: {primary_key: customer_id}\nSELECT id AS customer_id, region_code FROM raw_customers"),
+        ("products.sql", "SELECT id, title FROM raw_products"),
+        ("regions.sql", "SELECT code, name FROM raw_regions"),
+        ("orders.sql", "SELECT order_id, customer_id, product_id FROM raw_orders"),
+        ("order_customers.sql", "SELECT o.order_id, c.region_code FROM orders o JOIN customers c ON o.customer_id = c.customer_id"),
+        ("order_products.sql", "SELECT o.order_id, p.title FROM orders o, products p WHERE o.product_id = p.id"),
+        ("customer_regions.sql", "SELECT c.customer_id, r.name FROM customers c JOIN regions r ON c.region_code = r.code"),
+        ("customer_region_names.sql", "SELECT r.name FROM customers c JOIN regions r ON r.code = c.region_code"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+
+    // Joined on the declared primary key of customers
+    let customers = find(&relationships, "orders", "customers");
+    assert_eq!(customers.origin, RelationshipOrigin::JoinCondition);
+    assert_eq!(customers.confidence, Confidence::High);
+
+    // Named like a key, but only compared in a WHERE clause
+    let products = find(&relationships, "orders", "products");
+    assert_eq!(products.origin, RelationshipOrigin::WhereCondition);
+    assert_eq!(products.confidence, Confidence::Low);
+
+    // No naming evidence, but two models join the same way
+    let regions = relationships.iter()
+        .find(|r| r.from_column == "region_code" || r.to_column == "region_code")
+        .expect("Join on region_code should produce a relationship");
+    assert_eq!(regions.models.len(), 2, "{:?}", regions);
+    assert_eq!(regions.confidence, Confidence::Medium);
+
+    let schema_path = format!("{}/database_schema.xml", path);
+    generate_database_schema(&dependencies, &schema_path).unwrap();
+    let xml = fs::read_to_string(&schema_path).unwrap();
+    assert!(xml.contains("origin=\"join_condition\" confidence=\"high\""), "{}", xml);
+    assert!(xml.contains("<evidence model=\"transform.order_customers\"/>"), "{}", xml);
+}
+
+#[test]
+fn test_schema_xml_does_not_invent_keys() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("stg_customers.sql", "SELECT * FROM raw_customers"),
+        ("customers.sql", "SELECT name, email FROM stg_customers"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let schema_path = format!("{}/database_schema.xml", path);
+    generate_database_schema(&dependencies, &schema_path).unwrap();
+
+    let xml = fs::read_to_string(&schema_path).unwrap();
+    assert!(!xml.contains("primary_key=\"true\""), "No primary key was declared:\n{}", xml);
+    assert!(!xml.contains("column=\"id\""), "No id relationship exists:\n{}", xml);
+    assert!(!xml.contains("<relationship "), "Dependencies alone are not relationships:\n{}", xml);
+}
+
+#[test]
+fn test_relationships_from_correlated_subqueries() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("customers.sql", "-- @config: {primary_key: customer_id}\nSELECT id AS customer_id, name FROM raw_customers"),
+        ("orders.sql", "SELECT order_id, customer_id FROM raw_orders"),
+        ("active_customers.sql", "SELECT c.customer_id, c.name FROM customers c \
+            WHERE EXISTS (SELECT 1 FROM orders o WHERE o.customer_id = c.customer_id)"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+
+    let customers = find(&relationships, "orders", "customers");
+    assert_eq!(customers.origin, RelationshipOrigin::WhereCondition);
+    assert_eq!((customers.from_column.as_str(), customers.to_column.as_str()), ("customer_id", "customer_id"));
+}
+
+#[test]
+fn test_no_relationships_within_one_table() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("orders.sql", "SELECT order_id, order_date, shipped_date FROM raw_orders"),
+        ("same_day_orders.sql", "SELECT o.order_id FROM orders o WHERE o.shipped_date = o.order_date"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+    assert!(relationships.iter().all(|r| r.from_table != r.to_table), "{:?}", relationships);
+    assert!(relationships.is_empty(), "{:?}", relationships);
+}
+
+#[test]
+fn test_relationships_from_self_joins() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("employees.sql", "-- @config: {primary_key: id}\nSELECT id, name, manager_id FROM raw_employees"),
+        ("reporting_lines.sql", "SELECT e.name, m.name AS manager FROM employees e JOIN employees m ON e.manager_id = m.id"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+
+    let managers = find(&relationships, "employees", "employees");
+    assert_eq!(managers.origin, RelationshipOrigin::JoinCondition);
+    assert_eq!((managers.from_column.as_str(), managers.to_column.as_str()), ("manager_id", "id"));
+    assert_eq!(managers.confidence, Confidence::High);
+    assert_eq!(relationships.len(), 1, "{:?}", relationships);
+}
+
+#[test]
+fn test_schema_xml_escapes_expressions_and_sources() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("orders.sql", "SELECT id, amount, \"a<b\" FROM raw_orders"),
+        ("order_flags.sql", "SELECT CASE WHEN amount > 0 THEN 'paid & <due>' END AS status, o.\"a<b\" FROM orders o"),
+        ("small_orders.sql", "SELECT o.amount < 5, o.\"a<b\" FROM orders o JOIN raw_customers c ON o.id = c.id"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let schema_path = format!("{}/database_schema.xml", path);
+    generate_database_schema(&dependencies, &schema_path).unwrap();
+
+    let xml = fs::read_to_string(&schema_path).unwrap();
+    assert!(xml.contains("paid &amp; &lt;due&gt;"), "Expressions should be escaped:\n{}", xml);
+    assert!(!xml.contains("<due>"), "{}", xml);
+    assert!(xml.contains("<column>&quot;a&lt;b&quot;</column>"), "Source columns should be escaped:\n{}", xml);
+    assert!(xml.contains("<column name=\"&quot;a&lt;b&quot;\""), "Column names should be escaped:\n{}", xml);
+    assert!(xml.contains("<source_column>a&lt;b</source_column>"), "Lineage should be escaped:\n{}", xml);
+    assert!(xml.contains("<target_column>o.amount &lt; 5</target_column>"), "Lineage should be escaped:\n{}", xml);
+    assert_well_formed(&xml);
+}
+
+#[test]
+fn test_relationship_markup_is_escaped() {
+    let temp_dir = tempdir().unwrap();
+    let path = temp_dir.path().to_str().unwrap();
+
+    write_models(path, &[
+        ("customers.sql", "SELECT 1 AS id, 'x' AS name"),
+        ("orders.sql", "SELECT 1 AS order_id, 1 AS \"cust&id\""),
+        ("order_customers.sql", "SELECT o.order_id, c.name FROM orders o JOIN customers c ON o.\"cust&id\" = c.id"),
+    ]);
+
+    let dependencies = get_dependencies(path, "duckdb").unwrap();
+    let relationships = collect_relationships(&dependencies);
+    assert_eq!(find(&relationships, "orders", "customers").from_column, "cust&id");
+
+    let schema_path = format!("{}/database_schema.xml", path);
+    generate_database_schema(&dependencies, &schema_path).unwrap();
+    let xml = fs::read_to_string(&schema_path).unwrap();
+    assert!(xml.contains("<from table=\"transform.orders\" column=\"cust&amp;id\"/>"), "{}", xml);
+    assert_well_formed(&xml);
+
+    let html_path = format!("{}/schema.html", path);
+    visualize_database_schema(&dependencies, "html", Some(&html_path), true).unwrap();
+    let html = fs::read_to_string(&html_path).unwrap();
+    assert!(html.contains("cust&amp;id &rarr; customers.id"), "{}", html);
+    assert!(!html.contains("cust&id"), "{}", html);
+}
@@ -0,0 +1,109 @@
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::Crabwalk;
+use crabwalk::parser::dependencies::get_dependencies;
+use crabwalk::parser::selection::{Selection, Selector};
+
+mod common;
+use common::write_model;
+
+fn write_project(root: &std::path::Path) {
+    write_model(root, "stg_events.sql", "-- @config: {tags: [hourly, daily]}\nSELECT 1 AS id, 10 AS amount");
+    write_model(root, "hourly_counts.sql", "-- @config: {tags: [hourly], meta: {owner: ops}}\nSELECT count(*) AS n FROM stg_events");
+    write_model(root, "daily_revenue.sql", "-- @config: {tags: [daily, finance]}\nSELECT sum(amount) AS revenue FROM stg_events");
+}
+
+fn sorted(selected: std::collections::HashSet<String>) -> Vec<String> {
+    let mut names: Vec<String> = selected.into_iter().collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_parse_selectors() {
+    assert_eq!("tag:hourly".parse::<Selector>(), Ok(Selector::Tag("hourly".to_string())));
+    assert_eq!("orders".parse::<Selector>(), Ok(Selector::Model("orders".to_string())));
+    assert!("path:models/x".parse::<Selector>().is_err());
+    assert!("tag:".parse::<Selector>().is_err());
+}
+
+#[test]
+fn test_select_models_by_tag() {
+    let temp_dir = tempdir().unwrap();
+    write_project(temp_dir.path());
+    let dependencies = get_dependencies(temp_dir.path().to_str().unwrap(), "duckdb").unwrap();
+
+    let hourly = Selection::parse(&["tag:hourly".to_string()], &[]).unwrap();
+    assert_eq!(sorted(hourly.apply(&dependencies).unwrap()), vec!["hourly_counts", "stg_events"]);
+
+    let not_finance = Selection::parse(&[], &["tag:finance".to_string()]).unwrap();
+    assert_eq!(sorted(not_finance.apply(&dependencies).unwrap()), vec!["hourly_counts", "stg_events"]);
+
+    let mixed = Selection::parse(&["tag:daily".to_string(), "hourly_counts".to_string()], &["stg_events".to_string()]).unwrap();
+    assert_eq!(sorted(mixed.apply(&dependencies).unwrap()), vec!["daily_revenue", "hourly_counts"]);
+
+    let typo = Selection::parse(&["tag:hourlly".to_string()], &[]).unwrap();
+    assert!(typo.apply(&dependencies).is_err(), "A tag no model carries should be an error");
+}
+
+#[test]
+fn test_run_selected_models_writes_run_results() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    write_project(&models);
+    let database = temp_dir.path().join("test.db");
+
+    let crabwalk = Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    ).with_selection(Selection::parse(&["tag:hourly".to_string()], &[]).unwrap());
+    crabwalk.run().unwrap();
+
+    let results: serde_json::Value = serde_json::from_str(&fs::read_to_string(models.join("run_results.json")).unwrap()).unwrap();
+    let models_run = results["models"].as_array().unwrap();
+    let status = |name: &str| models_run.iter().find(|m| m["name"] == name).unwrap()["status"].as_str().unwrap().to_string();
+
+    assert_eq!(status("stg_events"), "success");
+    assert_eq!(status("hourly_counts"), "success");
+    assert_eq!(status("daily_revenue"), "skipped");
+
+    let hourly = models_run.iter().find(|m| m["name"] == "hourly_counts").unwrap();
+    assert_eq!(hourly["tags"], serde_json::json!(["hourly"]));
+    assert_eq!(hourly["meta"]["owner"], "ops");
+
+    drop(crabwalk);
+    let conn = duckdb::Connection::open(&database).unwrap();
+    assert!(conn.prepare("SELECT * FROM transform.hourly_counts").is_ok());
+    assert!(conn.prepare("SELECT * FROM transform.daily_revenue").is_err(), "Unselected models should not be built");
+}
+
+#[test]
+fn test_run_single_file_writes_run_results_next_to_it() {
+    let temp_dir = tempdir().unwrap();
+    let models = temp_dir.path().join("models");
+    write_model(&models, "stg_events.sql", "SELECT 1 AS id");
+    let database = temp_dir.path().join("test.db");
+
+    let crabwalk = |selection: Selection| Crabwalk::new(
+        database.to_str().unwrap().to_string(),
+        models.join("stg_events.sql").to_str().unwrap().to_string(),
+        "duckdb".to_string(),
+        "transform".to_string(),
+        None,
+        None,
+    ).with_selection(selection);
+    crabwalk(Selection::default()).run().unwrap();
+
+    let results: serde_json::Value = serde_json::from_str(&fs::read_to_string(models.join("run_results.json")).unwrap()).unwrap();
+    assert_eq!(results["models"][0]["status"], "success");
+
+    // Force mode honours the selection for a single file too
+    let skip = Selection::parse(&[], &["stg_events".to_string()]).unwrap();
+    crabwalk(skip).run_force().unwrap();
+    let results: serde_json::Value = serde_json::from_str(&fs::read_to_string(models.join("run_results.json")).unwrap()).unwrap();
+    assert_eq!(results["models"][0]["status"], "skipped");
+}
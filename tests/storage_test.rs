@@ -0,0 +1,76 @@
+use duckdb::Connection;
+use std::fs;
+use tempfile::tempdir;
+use crabwalk::config::{is_remote_location, OutputConfig, OutputType, ProjectConfig, SecretType};
+use crabwalk::executor::output::handle_output;
+use crabwalk::executor::RunContext;
+use crabwalk::parser::config::extract_config_from_sql;
+
+#[test]
+fn test_remote_locations() {
+    for location in ["s3://lake/events.parquet", "gs://lake/events", "az://container/events.csv", "abfss://c@account.dfs.core.windows.net/x"] {
+        assert!(is_remote_location(location), "{} should be remote", location);
+    }
+    for location in ["./output/events.parquet", "/data/events.csv", "C://data/events.csv", "events.csv"] {
+        assert!(!is_remote_location(location), "{} should be local", location);
+    }
+}
+
+#[test]
+fn test_secrets_from_project_config() {
+    let temp_dir = tempdir().unwrap();
+    fs::write(
+        temp_dir.path().join("crabwalk.yml"),
+        "secrets:\n  - name: minio\n    type: s3\n    scope: s3://lake\n    options:\n      key_id: '{{MINIO_KEY}}'\n      secret: \"it's\"\n      endpoint: localhost:9000\n      url_style: path\n      use_ssl: false\n  - {name: gcs, type: gcs, provider: credential_chain}\n",
+    ).unwrap();
+
+    let project = ProjectConfig::load(temp_dir.path().to_str().unwrap()).unwrap();
+    assert_eq!(project.secrets.len(), 2);
+    assert_eq!(project.secrets[0].secret_type, SecretType::S3);
+    assert_eq!(
+        project.secrets[0].create_secret_sql().unwrap(),
+        "CREATE OR REPLACE SECRET \"minio\" (TYPE s3, SCOPE 's3://lake', ENDPOINT 'localhost:9000', KEY_ID '{{MINIO_KEY}}', SECRET 'it''s', URL_STYLE 'path', USE_SSL false)"
+    );
+    assert_eq!(
+        project.secrets[1].create_secret_sql().unwrap(),
+        "CREATE OR REPLACE SECRET \"gcs\" (TYPE gcs, PROVIDER credential_chain)"
+    );
+
+    let invalid = [
+        "secrets:\n  - {name: a, type: s3}\n  - {name: A, type: gcs}\n",
+        "secrets:\n  - {name: a, type: s3, options: {region: [us, eu]}}\n",
+        "secrets:\n  - {name: a, type: s3, options: {'key id': x}}\n",
+        "secrets:\n  - {name: a, type: ftp}\n",
+    ];
+    for yaml in invalid {
+        fs::write(temp_dir.path().join("crabwalk.yml"), yaml).unwrap();
+        assert!(ProjectConfig::load(temp_dir.path().to_str().unwrap()).is_err(), "Expected an error for {}", yaml);
+    }
+}
+
+#[test]
+fn test_locally_written_outputs_reject_remote_locations() {
+    for sql in [
+        "-- @config: {output: {type: arrow, location: s3://lake/events.arrow}}",
+        "-- @config: {output: {type: delta, location: s3://lake/events}}",
+    ] {
+        assert!(extract_config_from_sql(sql).is_err(), "Expected an error for {}", sql);
+    }
+    assert!(extract_config_from_sql("-- @config: {output: {type: parquet, location: s3://lake/events.parquet}}").is_ok());
+}
+
+#[test]
+fn test_remote_file_output_creates_no_local_directories() {
+    // Without httpfs the COPY fails at once, after the location has been prepared
+    let context = RunContext::new(Connection::open_in_memory().unwrap());
+    context.execute("SET autoinstall_known_extensions = false").unwrap();
+    context.execute("SET autoload_known_extensions = false").unwrap();
+    let location = format!("s3://crabwalk-test-{}/events/events.csv", std::process::id());
+
+    // The write fails, but it must not leave an `s3:` directory behind
+    let output = OutputConfig::new(OutputType::Csv, Some(location), false);
+    let cwd = std::env::current_dir().unwrap();
+    assert!(handle_output("events", "SELECT 1 AS id", &output, "main", &context).is_err());
+
+    assert!(!cwd.join("s3:").exists());
+}